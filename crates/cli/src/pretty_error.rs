@@ -23,11 +23,12 @@ impl ErrorHandler for PrettyErrorHandler {
     fn on_error(&self, err: CompilationError) {
         let mut files = SimpleFiles::new();
         let default_vue = files.add(&self.name, &self.source);
-        let diagnostic = Diagnostic::error().with_labels(vec![Label::primary(
-            default_vue,
-            err.location.clone(),
-        )
-        .with_message(format!("{}", err))]);
+        let primary = Label::primary(default_vue, err.location.clone());
+        let mut labels = vec![primary.with_message(format!("{}", err))];
+        for (label, loc) in &err.related {
+            labels.push(Label::secondary(default_vue, loc.clone()).with_message(*label));
+        }
+        let diagnostic = Diagnostic::error().with_labels(labels);
 
         let writer = StandardStream::stderr(ColorChoice::Always);
         let config = codespan_reporting::term::Config::default();
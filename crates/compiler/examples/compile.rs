@@ -0,0 +1,39 @@
+//! Compiles a template to render function code.
+//!
+//! `cargo run --example compile < App.html`
+use std::io::{self, Read};
+use std::rc::Rc;
+use vue_compiler_core as compiler;
+
+use compiler::compiler::{get_base_passes, BaseCompiler, CompileOption, TemplateCompiler};
+use compiler::error::{CompilationError, VecErrorHandler};
+use compiler::SFCInfo;
+
+/// Returns generated code and errors of all compilation phases.
+pub fn compile(source: &str) -> (String, Vec<CompilationError>) {
+    let sfc_info = SFCInfo::default();
+    let eh = Rc::new(VecErrorHandler::new());
+    let option = CompileOption {
+        error_handler: eh.clone(),
+        ..Default::default()
+    };
+    let compiler = BaseCompiler::new(Vec::new, get_base_passes, option);
+    let code = compiler
+        .compile(source, &sfc_info)
+        .expect("writing to Vec cannot fail");
+    let errors = std::mem::take(&mut *eh.error_mut());
+    (String::from_utf8(code).expect("code is utf8"), errors)
+}
+
+#[allow(dead_code)]
+fn main() {
+    let mut source = String::new();
+    io::stdin()
+        .read_to_string(&mut source)
+        .expect("cannot read stdin");
+    let (code, errors) = compile(&source);
+    for error in errors {
+        eprintln!("{}", error);
+    }
+    println!("{}", code);
+}
@@ -0,0 +1,38 @@
+//! Formats templates by printing the parsed AST back to source.
+//!
+//! `cargo run --example format < App.html`
+use std::io::{self, Read};
+use std::rc::Rc;
+use vue_compiler_core as compiler;
+
+use compiler::compiler::CompileOption;
+use compiler::error::VecErrorHandler;
+use compiler::parser::Parser;
+use compiler::printer::print_root;
+use compiler::scanner::Scanner;
+
+/// Prints source in the normalized form, or None if it has syntax errors,
+/// since error recovery may drop source that a formatter must not lose.
+pub fn format(source: &str) -> Option<String> {
+    let option = CompileOption::default();
+    let eh = Rc::new(VecErrorHandler::new());
+    let tokens = Scanner::new(option.scanning()).scan(source, eh.clone());
+    let root = Parser::new(option.parsing()).parse(tokens, eh.clone());
+    let has_error = eh.errors().iter().any(|e| !e.is_warning());
+    if has_error {
+        return None;
+    }
+    Some(print_root(&root, &option.printing()))
+}
+
+#[allow(dead_code)]
+fn main() {
+    let mut source = String::new();
+    io::stdin()
+        .read_to_string(&mut source)
+        .expect("cannot read stdin");
+    match format(&source) {
+        Some(formatted) => print!("{}", formatted),
+        None => eprintln!("template has syntax errors, left unformatted"),
+    }
+}
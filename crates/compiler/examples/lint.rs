@@ -0,0 +1,63 @@
+//! Lints templates with a custom rule on top of compiler diagnostics.
+//!
+//! `cargo run --example lint -- App.html Nav.html`
+use std::{env, fs, rc::Rc};
+use vue_compiler_core as compiler;
+
+use compiler::audit::audit_html_sinks;
+use compiler::compiler::CompileOption;
+use compiler::diagnostics::DiagnosticsSummary;
+use compiler::error::{CompilationError, ErrorKind, Severity, VecErrorHandler};
+use compiler::parser::{AstRoot, Element, Parser};
+use compiler::scanner::Scanner;
+use compiler::testing::sort_errors;
+use compiler::visit::{walk_element, walk_root, Ancestors, Visitor};
+
+/// `<img>` without `alt` is not accessible to screen readers.
+struct MissingAlt;
+impl ErrorKind for MissingAlt {
+    fn msg(&self) -> &'static str {
+        "<img> should have an alt attribute."
+    }
+    fn severity(&self) -> Severity {
+        Severity::Warning
+    }
+}
+
+#[derive(Default)]
+struct AltChecker(Vec<CompilationError>);
+impl<'b, 'a: 'b> Visitor<'b, 'a> for AltChecker {
+    fn visit_element(&mut self, e: &'b Element<'a>, ancestors: &mut Ancestors<'b, 'a>) {
+        if e.tag_name == "img" && e.find_prop("alt").is_none() {
+            let error = CompilationError::extended(MissingAlt).with_location(e.location.clone());
+            self.0.push(error);
+        }
+        walk_element(self, e, ancestors);
+    }
+}
+
+/// Returns AST and diagnostics in source order.
+pub fn lint(source: &str) -> (AstRoot<'_>, Vec<CompilationError>) {
+    let option = CompileOption::default();
+    let eh = Rc::new(VecErrorHandler::new());
+    let tokens = Scanner::new(option.scanning()).scan(source, eh.clone());
+    let root = Parser::new(option.parsing()).parse(tokens, eh.clone());
+    audit_html_sinks(&root).report(&*eh);
+    let mut checker = AltChecker::default();
+    walk_root(&root, &mut checker);
+    let mut errors = std::mem::take(&mut *eh.error_mut());
+    errors.extend(checker.0);
+    sort_errors(&mut errors);
+    (root, errors)
+}
+
+#[allow(dead_code)]
+fn main() {
+    let mut summary = DiagnosticsSummary::default();
+    for name in env::args().skip(1) {
+        let source = fs::read_to_string(&name).expect("cannot read template");
+        let (root, errors) = lint(&source);
+        summary.add_with_ast(&name, &root, errors);
+    }
+    print!("{}", summary);
+}
@@ -0,0 +1,314 @@
+/*!
+Owned mirror of the parser AST.
+
+Parser AST borrows from template source. The owned AST is detached from source
+so it can be cached, sent across threads or deserialized from other processes.
+String operations of VStr are applied eagerly on conversion, so text and
+attribute values hold decoded strings, the same as the serialized borrowed AST.
+
+With the serde feature, JSON of a borrowed AST deserializes into the owned AST.
+*/
+
+use crate::{
+    parser::{self, AstAlloc, ElementType, NodeId},
+    scanner::{self, QuoteKind},
+    SourceLocation,
+};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum AstNode {
+    Element(Element),
+    Text(TextNode),
+    Interpolation(SourceNode),
+    Comment(SourceNode),
+    ProcessingInstruction(SourceNode),
+}
+
+impl AstNode {
+    pub fn get_element(&self) -> Option<&Element> {
+        match self {
+            AstNode::Element(e) => Some(e),
+            _ => None,
+        }
+    }
+    pub fn get_location(&self) -> &SourceLocation {
+        match self {
+            Self::Element(e) => &e.location,
+            Self::Text(t) => &t.location,
+            Self::Interpolation(i) => &i.location,
+            Self::Comment(c) => &c.location,
+            Self::ProcessingInstruction(p) => &p.location,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SourceNode {
+    pub source: String,
+    pub location: SourceLocation,
+    pub id: NodeId,
+}
+
+/// Text parts are joined, in line with the serialized borrowed TextNode.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TextNode {
+    pub text: String,
+    pub location: SourceLocation,
+    pub id: NodeId,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ElemProp {
+    Attr(Attribute),
+    Dir(Directive),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Attribute {
+    pub name: String,
+    pub value: Option<AttributeValue>,
+    pub name_loc: SourceLocation,
+    pub location: SourceLocation,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct AttributeValue {
+    pub content: String,
+    pub location: SourceLocation,
+    pub quote: QuoteKind,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum DirectiveArg {
+    Static(String),
+    Dynamic(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Directive {
+    pub name: String,
+    pub argument: Option<DirectiveArg>,
+    pub modifiers: Vec<String>,
+    pub expression: Option<AttributeValue>,
+    pub head_loc: SourceLocation,
+    pub location: SourceLocation,
+    pub arg_loc: Option<SourceLocation>,
+    pub modifier_locs: Vec<SourceLocation>,
+}
+
+/// Owned crate::Namespace whose user defined name is not static.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Namespace {
+    Html,
+    Svg,
+    MathMl,
+    UserDefined(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Element {
+    pub tag_name: String,
+    pub tag_type: ElementType,
+    pub namespace: Namespace,
+    pub properties: Vec<ElemProp>,
+    pub children: Vec<AstNode>,
+    pub location: SourceLocation,
+    pub id: NodeId,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct AstRoot {
+    pub children: Vec<AstNode>,
+    pub location: SourceLocation,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub frontmatter: Option<SourceNode>,
+}
+
+impl<'a, A: AstAlloc<'a>> From<&parser::AstRoot<'a, A>> for AstRoot {
+    fn from(root: &parser::AstRoot<'a, A>) -> Self {
+        Self {
+            children: root.children.iter().map(From::from).collect(),
+            location: root.location.clone(),
+            frontmatter: root.frontmatter.as_ref().map(From::from),
+        }
+    }
+}
+
+impl<'a, A: AstAlloc<'a>> From<&parser::AstNode<'a, A>> for AstNode {
+    fn from(node: &parser::AstNode<'a, A>) -> Self {
+        use parser::AstNode as N;
+        match node {
+            N::Element(e) => Self::Element(e.into()),
+            N::Text(t) => Self::Text(t.into()),
+            N::Interpolation(i) => Self::Interpolation(i.into()),
+            N::Comment(c) => Self::Comment(c.into()),
+            N::ProcessingInstruction(p) => Self::ProcessingInstruction(p.into()),
+        }
+    }
+}
+
+impl<'a> From<&parser::SourceNode<'a>> for SourceNode {
+    fn from(node: &parser::SourceNode<'a>) -> Self {
+        Self {
+            source: node.source.into(),
+            location: node.location.clone(),
+            id: node.id,
+        }
+    }
+}
+
+impl<'a> From<&parser::TextNode<'a>> for TextNode {
+    fn from(node: &parser::TextNode<'a>) -> Self {
+        Self {
+            text: node.text.iter().map(|s| s.into_string()).collect(),
+            location: node.location.clone(),
+            id: node.id,
+        }
+    }
+}
+
+impl<'a, A: AstAlloc<'a>> From<&parser::Element<'a, A>> for Element {
+    fn from(e: &parser::Element<'a, A>) -> Self {
+        Self {
+            tag_name: e.tag_name.into(),
+            tag_type: e.tag_type,
+            namespace: e.namespace.into(),
+            properties: e.properties.iter().map(From::from).collect(),
+            children: e.children.iter().map(From::from).collect(),
+            location: e.location.clone(),
+            id: e.id,
+        }
+    }
+}
+
+impl From<crate::Namespace> for Namespace {
+    fn from(ns: crate::Namespace) -> Self {
+        use crate::Namespace as N;
+        match ns {
+            N::Html => Self::Html,
+            N::Svg => Self::Svg,
+            N::MathMl => Self::MathMl,
+            N::UserDefined(s) => Self::UserDefined(s.into()),
+        }
+    }
+}
+
+impl<'a> From<&parser::ElemProp<'a>> for ElemProp {
+    fn from(prop: &parser::ElemProp<'a>) -> Self {
+        match prop {
+            parser::ElemProp::Attr(a) => Self::Attr(a.into()),
+            parser::ElemProp::Dir(d) => Self::Dir(d.into()),
+        }
+    }
+}
+
+impl<'a> From<&scanner::Attribute<'a>> for Attribute {
+    fn from(attr: &scanner::Attribute<'a>) -> Self {
+        Self {
+            name: attr.name.into(),
+            value: attr.value.as_ref().map(From::from),
+            name_loc: attr.name_loc.clone(),
+            location: attr.location.clone(),
+        }
+    }
+}
+
+impl<'a> From<&scanner::AttributeValue<'a>> for AttributeValue {
+    fn from(val: &scanner::AttributeValue<'a>) -> Self {
+        Self {
+            content: val.content.into_string(),
+            location: val.location.clone(),
+            quote: val.quote,
+        }
+    }
+}
+
+impl<'a> From<&parser::Directive<'a>> for Directive {
+    fn from(dir: &parser::Directive<'a>) -> Self {
+        let argument = dir.argument.as_ref().map(|arg| match arg {
+            parser::DirectiveArg::Static(a) => DirectiveArg::Static((*a).into()),
+            parser::DirectiveArg::Dynamic(a) => DirectiveArg::Dynamic((*a).into()),
+        });
+        Self {
+            name: dir.name.into(),
+            argument,
+            modifiers: dir.modifiers.iter().map(|&m| m.into()).collect(),
+            expression: dir.expression.as_ref().map(From::from),
+            head_loc: dir.head_loc.clone(),
+            location: dir.location.clone(),
+            arg_loc: dir.arg_loc.clone(),
+            modifier_locs: dir.modifier_locs.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::test::base_parse;
+
+    #[test]
+    fn test_to_owned_ast() {
+        let ast = base_parse(r#"<div :a.b.c="d" e="&amp;">f &lt; {{ g }}<!--h--></div>"#);
+        let owned = ast.to_owned_ast();
+        let div = owned.children[0].get_element().unwrap();
+        assert_eq!(div.tag_type, ElementType::Plain);
+        match &div.properties[..] {
+            [ElemProp::Dir(d), ElemProp::Attr(a)] => {
+                assert_eq!(d.argument, Some(DirectiveArg::Static("a".into())));
+                assert_eq!(d.modifiers, ["b", "c"]);
+                assert_eq!(a.value.as_ref().unwrap().content, "&");
+            }
+            _ => panic!("unexpected props"),
+        }
+        match &div.children[0] {
+            AstNode::Text(t) => assert_eq!(t.text, "f < "),
+            _ => panic!("expect text"),
+        }
+        assert_eq!(div.children[1].get_location().start.offset, 33);
+    }
+
+    #[test]
+    fn test_send_owned_ast() {
+        let owned = base_parse("<p>{{ a }}</p>").to_owned_ast();
+        let handle = std::thread::spawn(move || {
+            let p = owned.children[0].get_element().unwrap();
+            match &p.children[0] {
+                AstNode::Interpolation(i) => i.source.clone(),
+                _ => panic!("expect interpolation"),
+            }
+        });
+        assert_eq!(handle.join().unwrap(), " a ");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() -> serde_json::Result<()> {
+        let ast = base_parse(
+            r#"<comp v-for="a in b" #[c]="{ d }" @e.stop="f">g<?xml h?>&gt;{{ i }}<!--j--></comp>
+<svg><path d="k"/></svg>"#,
+        );
+        let owned: AstRoot = serde_json::from_str(&serde_json::to_string(&ast)?)?;
+        assert_eq!(owned, ast.to_owned_ast());
+        // owned AST serializes identically
+        assert_eq!(serde_json::to_string(&owned)?, serde_json::to_string(&ast)?);
+        Ok(())
+    }
+}
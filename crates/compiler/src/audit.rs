@@ -0,0 +1,155 @@
+/*!
+Inventory of raw HTML sinks in a template.
+
+`v-html` sets `innerHTML` without escaping so every usage should be reviewed.
+The audit runs on AST only and does not require conversion or code generation.
+*/
+
+use crate::{
+    converter::v_on::is_member_expression,
+    error::{CompilationError, CompilationErrorKind as ErrorKind, ErrorHandler},
+    parser::{AstRoot, Directive},
+    util::{is_simple_identifier, rslint, VStr},
+    visit::{walk_root, Ancestors, Visitor},
+    SourceLocation,
+};
+
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+/// Shape of the expression bound to an HTML sink.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub enum HtmlSinkExprKind {
+    /// e.g. `v-html="content"`
+    Identifier,
+    /// e.g. `v-html="post.body"`
+    MemberPath,
+    /// e.g. `v-html="render(md)"`
+    Call,
+    Other,
+}
+
+impl HtmlSinkExprKind {
+    fn of(expr: &str) -> Self {
+        let v = VStr::raw(expr.trim());
+        if is_simple_identifier(v) {
+            Self::Identifier
+        } else if is_member_expression(v) {
+            Self::MemberPath
+        } else if rslint::is_call_expression(v.raw) {
+            Self::Call
+        } else {
+            Self::Other
+        }
+    }
+}
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct HtmlSink<'a> {
+    pub tag: &'a str,
+    /// raw expression text, empty if v-html has no value.
+    pub expression: &'a str,
+    pub location: SourceLocation,
+    pub kind: HtmlSinkExprKind,
+    /// The sink is rendered once per item of an enclosing v-for.
+    pub in_v_for: bool,
+}
+
+#[derive(Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct HtmlSinkReport<'a> {
+    pub sinks: Vec<HtmlSink<'a>>,
+}
+
+impl<'a> HtmlSinkReport<'a> {
+    /// Reports every sink as a warning, e.g. to fail CI builds on new usages.
+    pub fn report<EH: ErrorHandler + ?Sized>(&self, eh: &EH) {
+        for sink in &self.sinks {
+            let error =
+                CompilationError::new(ErrorKind::VHtmlSink).with_location(sink.location.clone());
+            eh.on_error(error);
+        }
+    }
+}
+
+pub fn audit_html_sinks<'a>(root: &AstRoot<'a>) -> HtmlSinkReport<'a> {
+    let mut report = HtmlSinkReport::default();
+    walk_root(root, &mut report);
+    report
+}
+
+impl<'b, 'a: 'b> Visitor<'b, 'a> for HtmlSinkReport<'a> {
+    fn visit_directive(&mut self, dir: &'b Directive<'a>, ancestors: &Ancestors<'b, 'a>) {
+        if dir.name != "html" {
+            return;
+        }
+        let owner = ancestors.parent().expect("directive must have owner");
+        // v-for on the owner element also repeats the sink
+        let in_v_for = ancestors.iter().any(|e| e.dirs().any(|d| d.name == "for"));
+        let expression = dir.expression.as_ref().map_or("", |v| v.content.raw);
+        self.sinks.push(HtmlSink {
+            tag: owner.tag_name,
+            expression,
+            location: dir.location.clone(),
+            kind: HtmlSinkExprKind::of(expression),
+            in_v_for,
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{error::VecErrorHandler, parser::test::base_parse};
+
+    fn audit(s: &str) -> Vec<(&str, &str, HtmlSinkExprKind, bool)> {
+        let root = base_parse(s);
+        let report = audit_html_sinks(&root);
+        report
+            .sinks
+            .into_iter()
+            .map(|s| (s.tag, s.expression, s.kind, s.in_v_for))
+            .collect()
+    }
+
+    #[test]
+    fn test_nested_v_for() {
+        let sinks = audit(
+            r#"<div v-html="a"/>
+<ul v-for="b in bs">
+  <li><template v-if="b.ok"><p v-html="b.body"/></template></li>
+</ul>
+<span v-for="c in cs" v-html="c"/>"#,
+        );
+        use HtmlSinkExprKind::*;
+        assert_eq!(
+            sinks,
+            [
+                ("div", "a", Identifier, false),
+                ("p", "b.body", MemberPath, true),
+                ("span", "c", Identifier, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_call_expression() {
+        let sinks = audit(r#"<p v-html="render(md)"/><p v-html="(a.b(c))"/><p v-html="a + b"/>"#);
+        let kinds: Vec<_> = sinks.iter().map(|s| s.2).collect();
+        use HtmlSinkExprKind::*;
+        assert_eq!(kinds, [Call, Call, Other]);
+    }
+
+    #[test]
+    fn test_report_warning() {
+        let root = base_parse(r#"<p v-html="a"/><p v-html="b"/>"#);
+        let eh = VecErrorHandler::default();
+        audit_html_sinks(&root).report(&eh);
+        let errors = eh.errors();
+        assert_eq!(errors.len(), 2);
+        assert!(errors.iter().all(|e| e.is_warning()));
+        assert_eq!(errors[1].location.start.offset, 18);
+    }
+}
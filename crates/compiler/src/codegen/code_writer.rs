@@ -462,7 +462,7 @@ impl<'a, T: ioWrite> CodeWriter<'a, T> {
         match hoist {
             H::FullElement(e) => self.generate_vnode(e),
             H::StaticProps(p) => self.generate_js_expr(p),
-            H::ChildrenArray(_) => todo!(),
+            H::ChildrenArray(c) => self.generate_children(c),
             H::DynamicPropsHint(d) => self.gen_dynamic_props(d),
         }
     }
@@ -1105,7 +1105,9 @@ mod test {
         assert!(s.contains("b: b,"), "{}", s);
         assert!(s.contains("PROPS"), "{}", s);
         let s = base_gen("<p :prop />");
-        assert!(s.contains(r#"prop: """#), "{}", s);
+        assert!(s.contains("prop: prop"), "{}", s);
+        let s = base_gen("<p :user-name />");
+        assert!(s.contains(r#""user-name": userName"#), "{}", s);
     }
     #[test]
     fn test_v_bind_dir() {
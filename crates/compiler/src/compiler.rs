@@ -1,16 +1,19 @@
 use super::{
     SFCInfo,
+    audit::audit_html_sinks,
     codegen::{CodeGenerateOption, CodeGenerator, CodeGen, ScriptMode, CodeGenInfo},
     converter::{
         no_op_directive_convert, BaseConvertInfo as BaseInfo, BaseConverter, BaseRoot,
-        ConvertOption, Converter, DirConvertFn, V_BIND, V_MODEL,
+        CompileConstants, ConvertOption, Converter, DirConvertFn, V_BIND, V_MODEL,
     },
     error::{NoopErrorHandler, RcErrHandle},
     flags::RuntimeHelper,
-    parser::{Element, ParseOption, Parser, WhitespaceStrategy, AstRoot},
-    scanner::{ScanOption, Scanner, TextMode, Tokens},
+    ir::IrDocument,
+    parser::{Element, ParseLimits, ParseOption, Parser, WhitespaceStrategy, AstRoot},
+    printer::PrintOption,
+    scanner::{FrontmatterMode, ScanOption, Scanner, Tag, TextMode, Tokens},
     transformer::{BaseTransformer, CorePass, TransformOption, Transformer},
-    util::{no, yes},
+    util::{is_reserved_binding, no, yes},
     Namespace,
     transformer::{
         collect_entities::EntityCollector,
@@ -36,6 +39,10 @@ pub struct CompileOption {
     /// e.g. elements that should preserve whitespace inside, e.g. `<pre>`
     pub is_pre_tag: fn(&str) -> bool,
 
+    /// e.g. elements rendered inline, e.g. `<span>`. Newline whitespace between
+    /// two inline elements is condensed to a space instead of being removed.
+    pub is_inline_tag: fn(&str) -> bool,
+
     /// Platform-specific built-in components e.g. `<Transition>`
     /// The pairing runtime provides additional built-in elements,
     /// Platform developer can use this to mark them as built-in
@@ -49,7 +56,7 @@ pub struct CompileOption {
     pub get_namespace: fn(&str, Option<&Element<'_>>) -> Namespace,
 
     /// Get text parsing mode for this element
-    pub get_text_mode: fn(&str) -> TextMode,
+    pub get_text_mode: fn(&Tag<'_>) -> TextMode,
 
     /// @default ['{{', '}}']
     pub delimiters: (String, String),
@@ -57,18 +64,83 @@ pub struct CompileOption {
     /// Whitespace handling strategy
     pub whitespace: WhitespaceStrategy,
 
+    /// Upper bounds against pathological templates
+    pub parse_limits: ParseLimits,
+
+    /// Inserts empty element for stray `</br>` and `</p>` like browsers do.
+    /// @default false
+    pub recover_stray_end_tag: bool,
+    /// Keeps `<?xml ?>` like processing instructions distinct from comments.
+    /// @default false
+    pub keep_processing_instructions: bool,
+    /// Keeps comments inside pre even if comments are not preserved.
+    /// @default false
+    pub preserve_all_in_pre: bool,
+    /// Reports the rule classifying each element as component or not, for debugging.
+    /// @default false
+    pub explain_classification: bool,
+    /// Warns `:fooBar` like arguments, which browsers lowercase in in-DOM templates.
+    /// @default false
+    pub v_bind_prop_case_check: bool,
+    /// Vue 2 compat: `is="comp"` without `vue:` prefix makes a component, with a warning.
+    /// @default false
+    pub v2_compat_is: bool,
+    /// Warns v-for elements without key.
+    /// @default false
+    pub warn_missing_vfor_key: bool,
+    /// Warns components shadowing native elements and misused built-in tags.
+    /// @default false
+    pub strict_components: bool,
+    /// Reports misused v-model at parse time. v-model converters report the
+    /// same errors, so enable it only for tools that do not convert.
+    /// @default false
+    pub check_v_model: bool,
+    /// Returns if a custom directive is registered. Unknown `v-` directives,
+    /// e.g. typo like `v-modle`, are warned only if this is set.
+    /// @default None
+    pub is_known_directive: Option<fn(&str) -> bool>,
+    /// Recognizes `---` fenced frontmatter before template markup.
+    /// @default FrontmatterMode::None
+    pub frontmatter: FrontmatterMode,
+    /// Decodes HTML entities like `&amp;` in text and attribute values, not in directive expressions.
+    /// Malformed references like `&foo;` or `&#0;` are warned only if this is set.
+    /// @default true
+    pub decode_entities: bool,
+
     /// platform speicific helper
     pub helper_strs: &'static [&'static str],
 
     /// Whether to keep comments in the templates AST.
     /// This defaults to `true` in development and `false` in production builds.
     pub preserve_comments: Option<bool>,
+    /// Keeps only comments whose text satisfies the filter, e.g. `<!--! license -->`.
+    /// It takes precedence over `preserve_comments` if set.
+    pub comment_filter: Option<fn(&str) -> bool>,
     /// Whether the output is dev build which includes v-if comment and dev patch flags.
     pub is_dev: bool,
 
     /// An object of { name: transform } to be applied to every directive attribute
     /// node found on element nodes.
     pub directive_converters: FxHashMap<&'static str, DirConvertFn>,
+    /// Identifiers known at build time mapped to their JS literal source.
+    /// `v-if` on them is folded and their usages are replaced by the literal.
+    /// e.g. `__FEATURE_X__` => `"false"`
+    pub constants: CompileConstants,
+    /// Binding names introduced by v-for/v-slot that should be warned.
+    /// Defaults to `$` prefixed names and `_` prefixed generated aliases.
+    pub is_reserved_binding: fn(&str) -> bool,
+    /// Warns interpolation directly inside these tags, e.g. `<textarea>{{ text }}</textarea>`
+    /// which should be written as `<textarea :value="text"/>`.
+    /// @default no tag is checked
+    pub lint_form_interpolation: fn(&str) -> bool,
+    /// Directive name prefixes reserved for registered converters, with the
+    /// message reported when an unregistered directive uses one, e.g. `("x-", "...")`.
+    /// @default empty
+    pub reserved_directive_prefixes: Vec<(&'static str, &'static str)>,
+    /// Warns every `v-html` usage so CI can enforce a review of raw HTML sinks.
+    /// See `audit::audit_html_sinks` for the full report.
+    /// @default false
+    pub warn_html_sinks: bool,
     /// Hoist static VNodes and props objects to `_hoisted_x` constants
     /// @default false
     pub hoist_static: bool,
@@ -132,16 +204,36 @@ impl Default for CompileOption {
             is_native_tag: yes,
             is_void_tag: no,
             is_pre_tag: no,
+            is_inline_tag: no,
             get_builtin_component: |_| None,
             is_custom_element: no,
             get_namespace: |_, _| Namespace::Html,
             get_text_mode: |_| TextMode::Data,
             delimiters: ("{{".into(), "}}".into()),
             whitespace: WhitespaceStrategy::Preserve,
+            parse_limits: ParseLimits::default(),
+            recover_stray_end_tag: false,
+            keep_processing_instructions: false,
+            preserve_all_in_pre: false,
+            explain_classification: false,
+            v_bind_prop_case_check: false,
+            v2_compat_is: false,
+            warn_missing_vfor_key: false,
+            strict_components: false,
+            check_v_model: false,
+            is_known_directive: None,
+            frontmatter: FrontmatterMode::None,
+            decode_entities: true,
             helper_strs: &[],
             preserve_comments: None,
+            comment_filter: None,
             is_dev: true,
             directive_converters,
+            is_reserved_binding,
+            constants: CompileConstants::default(),
+            lint_form_interpolation: no,
+            reserved_directive_prefixes: vec![],
+            warn_html_sinks: false,
             hoist_static: false,
             cache_handlers: false,
             mode: ScriptMode::Function {
@@ -160,19 +252,41 @@ impl CompileOption {
         ScanOption {
             delimiters: self.delimiters.clone(),
             get_text_mode: self.get_text_mode,
+            frontmatter: self.frontmatter,
+            decode_entities: self.decode_entities,
         }
     }
     pub fn parsing(&self) -> ParseOption {
         ParseOption {
             whitespace: self.whitespace.clone(),
-            preserve_comment: self.preserve_comments.unwrap_or(self.is_dev),
+            comment_filter: self.comment_filter().into(),
             get_namespace: self.get_namespace,
             get_text_mode: self.get_text_mode,
-            is_native_element: self.is_native_tag,
-            is_void_tag: self.is_void_tag,
-            is_pre_tag: self.is_pre_tag,
+            is_native_element: self.is_native_tag.into(),
+            is_void_tag: self.is_void_tag.into(),
+            is_pre_tag: self.is_pre_tag.into(),
+            is_inline_tag: self.is_inline_tag.into(),
             get_builtin_component: self.get_builtin_component,
-            is_custom_element: self.is_custom_element,
+            is_custom_element: self.is_custom_element.into(),
+            recover_stray_end_tag: self.recover_stray_end_tag,
+            keep_processing_instructions: self.keep_processing_instructions,
+            preserve_all_in_pre: self.preserve_all_in_pre,
+            explain_classification: self.explain_classification,
+            v_bind_prop_case_check: self.v_bind_prop_case_check,
+            v2_compat_is: self.v2_compat_is,
+            warn_missing_vfor_key: self.warn_missing_vfor_key,
+            strict_components: self.strict_components,
+            check_v_model: self.check_v_model,
+            is_known_directive: self.is_known_directive.map(Into::into),
+            frontmatter: self.frontmatter,
+            decode_entities: self.decode_entities,
+            limits: self.parse_limits.clone(),
+        }
+    }
+    pub fn printing(&self) -> PrintOption {
+        PrintOption {
+            delimiters: self.delimiters.clone(),
+            is_pre_tag: self.is_pre_tag.into(),
         }
     }
     pub fn converting(&self) -> ConvertOption {
@@ -181,17 +295,17 @@ impl CompileOption {
             is_dev: self.is_dev,
             directive_converters: self.directive_converters.clone(),
             need_reactivity: self.need_reactivity,
+            is_reserved_binding: self.is_reserved_binding,
+            prefix_identifier: self.prefix_identifier(),
+            constants: self.constants.clone(),
+            lint_form_interpolation: self.lint_form_interpolation,
+            reserved_directive_prefixes: self.reserved_directive_prefixes.clone(),
+            v2_compat_is: self.v2_compat_is,
         }
     }
     pub fn transforming(&self) -> TransformOption {
-        let prefix = match self.mode {
-            ScriptMode::Function {
-                prefix_identifier, ..
-            } => prefix_identifier,
-            ScriptMode::Module { .. } => true,
-        };
         TransformOption {
-            prefix_identifier: prefix,
+            prefix_identifier: self.prefix_identifier(),
             is_dev: self.is_dev,
         }
     }
@@ -203,6 +317,23 @@ impl CompileOption {
             helper_strs: self.helper_strs,
         }
     }
+    fn comment_filter(&self) -> fn(&str) -> bool {
+        if let Some(filter) = self.comment_filter {
+            filter
+        } else if self.preserve_comments.unwrap_or(self.is_dev) {
+            yes
+        } else {
+            no
+        }
+    }
+    fn prefix_identifier(&self) -> bool {
+        match self.mode {
+            ScriptMode::Function {
+                prefix_identifier, ..
+            } => prefix_identifier,
+            ScriptMode::Module { .. } => true,
+        }
+    }
 }
 
 // TODO: refactor this ownership usage
@@ -280,7 +411,11 @@ where
     }
 
     fn parse(&self, tokens: Tokens<'a>) -> AstRoot<'a> {
-        self.parser.parse(tokens, self.get_error_handler())
+        let ast = self.parser.parse(tokens, self.get_error_handler());
+        if self.option.warn_html_sinks {
+            audit_html_sinks(&ast).report(&*self.get_error_handler());
+        }
+        ast
     }
     fn convert(&self, ast: AstRoot<'a>, info: Self::Info) -> Self::IR {
         self.get_converter().convert_ir(ast, info)
@@ -305,6 +440,20 @@ where
     }
 }
 
+/// Compiles template to a serializable IR for backends not rendering with JS.
+/// Only scanning, parsing and conversion run. See `ir::document` for semantics.
+pub fn compile_to_ir(source: &str, option: &CompileOption) -> IrDocument {
+    let sfc_info = SFCInfo::default();
+    let eh = option.error_handler.clone();
+    let tokens = Scanner::new(option.scanning()).scan(source, eh.clone());
+    let ast = Parser::new(option.parsing()).parse(tokens, eh.clone());
+    if option.warn_html_sinks {
+        audit_html_sinks(&ast).report(&*eh);
+    }
+    let ir = BaseConverter::new(eh, option.converting()).convert_ir(ast, &sfc_info);
+    IrDocument::new(ir, source, option.helper_strs)
+}
+
 pub fn get_base_passes<'a>(
     sfc_info: &'a SFCInfo<'a>,
     opt: &CompileOption,
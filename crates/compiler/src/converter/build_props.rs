@@ -1,12 +1,15 @@
-use super::{BaseConversion as BC, CoreConversion, Element, VStr};
+use super::{BaseConversion as BC, CompilationError, CoreConversion, Element, VStr};
 use crate::{
+    error::{CompilationErrorKind as ErrorKind, RcErrHandle},
     flags::{self, PatchFlag, RuntimeHelper},
     ir::{JsExpr as Js, Prop},
-    parser::{Directive, ElemProp},
+    parser::{Directive, ElemProp, ElementType},
+    props::{merge_props, PropsBuilder},
     scanner::Attribute,
-    util::{self, is_bind_key, is_component_tag, is_reserved_prop},
+    util::{is_bind_key, is_component_tag, is_reserved_prop},
+    SourceLocation,
 };
-use rustc_hash::{FxHashMap, FxHashSet};
+use rustc_hash::FxHashSet;
 use std::iter::IntoIterator;
 use std::mem;
 
@@ -28,17 +31,39 @@ struct PropFlags {
     has_vnode_hook: bool,
 }
 
-#[derive(Default)]
 /// collecting props object for vnode call. e.g:
 /// <:prop="val" v-bind="obj"/> becomes {prop: val, ...obj}
 struct PropArgs<'a> {
     /// pending properties, e.g. (prop, val)
-    pending_props: Props<'a>,
+    pending_props: PropsBuilder<'a, 'a>,
     /// merged prop argument, e.g. obj
     merge_args: Args<'a>,
+    /// reports conflicting props of the element
+    err_handle: RcErrHandle,
+    location: SourceLocation,
+}
+
+impl<'a> PropArgs<'a> {
+    fn new(err_handle: RcErrHandle, location: SourceLocation) -> Self {
+        Self {
+            pending_props: new_props_builder(&err_handle, &location),
+            merge_args: vec![],
+            err_handle,
+            location,
+        }
+    }
+}
+
+// duplicate props other than class, style and handlers are dropped with a warning
+fn new_props_builder<'a>(eh: &RcErrHandle, location: &SourceLocation) -> PropsBuilder<'a, 'a> {
+    let eh = eh.clone();
+    let location = location.clone();
+    PropsBuilder::new().on_conflict(move |_, _| {
+        let error = CompilationError::new(ErrorKind::DuplicateProp).with_location(location.clone());
+        eh.on_error(error);
+    })
 }
 
-#[derive(Default)]
 struct CollectProps<'a> {
     prop_args: PropArgs<'a>,
     runtime_dirs: Dirs<'a>,
@@ -47,14 +72,20 @@ struct CollectProps<'a> {
 }
 
 impl<'a> CollectProps<'a> {
-    fn new(e: &Element<'a>) -> Self {
-        let mut s = Self::default();
-        s.prop_flags.is_component = e.is_component();
-        s
+    fn new(bc: &BC<'a>, e: &Element<'a>) -> Self {
+        let prop_flags = PropFlags {
+            is_component: e.is_component(),
+            ..Default::default()
+        };
+        Self {
+            prop_args: PropArgs::new(bc.err_handle.clone(), e.location.clone()),
+            runtime_dirs: vec![],
+            dynamic_props: FxHashSet::default(),
+            prop_flags,
+        }
     }
 }
 
-type Props<'a> = Vec<Prop<'a>>;
 type Args<'a> = Vec<Js<'a>>;
 type Dir<'a> = (Directive<'a>, Option<RuntimeHelper>);
 type Dirs<'a> = Vec<Dir<'a>>;
@@ -63,7 +94,7 @@ pub fn build_props<'a, T>(bc: &BC<'a>, e: &mut Element<'a>, elm_props: T) -> Bui
 where
     T: IntoIterator<Item = ElemProp<'a>>,
 {
-    let mut cp = CollectProps::new(e);
+    let mut cp = CollectProps::new(bc, e);
     elm_props.into_iter().for_each(|prop| match prop {
         ElemProp::Dir(dir) => collect_dir(bc, e, dir, &mut cp),
         ElemProp::Attr(attr) => collect_attr(bc, e, attr, &mut cp),
@@ -89,8 +120,10 @@ fn collect_attr<'a>(bc: &BC, e: &Element<'a>, attr: Attribute<'a>, cp: &mut Coll
         Some(v) => v.content,
         None => VStr::raw(""),
     };
-    // skip dynamic component is
-    if name == "is" && (is_component_tag(e.tag_name) || val.starts_with("vue:")) {
+    // skip dynamic component is. In Vue 2 compat, only if it made a component
+    let is_compat_is = bc.option.v2_compat_is && e.tag_type == ElementType::Component;
+    let is_component_is = is_component_tag(e.tag_name) || val.starts_with("vue:") || is_compat_is;
+    if name == "is" && is_component_is {
         return;
     }
     let mut value_expr = Js::StrLit(val);
@@ -100,9 +133,8 @@ fn collect_attr<'a>(bc: &BC, e: &Element<'a>, attr: Attribute<'a>, cp: &mut Coll
             value_expr = process_inline_ref(val);
         }
     }
-    cp.prop_args
-        .pending_props
-        .push((Js::str_lit(name), value_expr));
+    let prop = (Js::str_lit(name), value_expr);
+    merge_props(&mut cp.prop_args.pending_props, prop);
 }
 
 #[inline]
@@ -156,55 +188,21 @@ fn flush_pending_props(prop_args: &mut PropArgs) {
     if prop_args.pending_props.is_empty() {
         return;
     }
-    let arg = mem::take(&mut prop_args.pending_props);
-    let arg = dedupe_properties(arg);
-    prop_args.merge_args.push(Js::Props(arg));
+    let builder = new_props_builder(&prop_args.err_handle, &prop_args.location);
+    let arg = mem::replace(&mut prop_args.pending_props, builder);
+    prop_args.merge_args.push(Js::Props(arg.into_props()));
 }
 
 fn process_inline_ref(_val: VStr) -> Js {
     todo!("setup binding is pending")
 }
 
-fn dedupe_properties(props: Props) -> Props {
-    let mut known_props = FxHashMap::default();
-    let mut ret = vec![];
-    for (key, val) in props {
-        let name = match &key {
-            Js::StrLit(name) => name,
-            _ => {
-                ret.push((key, val));
-                continue;
-            }
-        };
-        if let Some(&i) = known_props.get(name) {
-            if util::is_mergeable_prop(name) {
-                merge_as_array(&mut ret[i], val);
-            }
-            // TODO: should remove by parser
-        } else {
-            known_props.insert(*name, ret.len());
-            ret.push((key, val));
-        }
-    }
-    ret
-}
-
-fn merge_as_array<'a>(existing: &mut Prop<'a>, incoming: Js<'a>) {
-    let val = &mut existing.1;
-    if let Js::Array(arr) = val {
-        arr.push(incoming);
-    } else {
-        let v = mem::replace(val, Js::Src(""));
-        let mut arr = Js::Array(vec![v]);
-        mem::swap(val, &mut arr);
-    }
-}
-
 fn compute_prop_expr(mut prop_args: PropArgs) -> Option<Js> {
     flush_pending_props(&mut prop_args);
     let PropArgs {
         pending_props,
         merge_args,
+        ..
     } = prop_args;
     debug_assert!(pending_props.is_empty());
     if merge_args.len() <= 1 {
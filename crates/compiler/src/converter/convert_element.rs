@@ -9,7 +9,10 @@ use crate::{
     ir::{IRNode, JsExpr as Js, RuntimeDir, VNodeIR},
     parser::{AstNode, Directive, ElemProp, ElementType},
     scanner::Attribute,
-    util::{find_dir, get_core_component, is_builtin_symbol, is_component_tag, prop_finder},
+    util::{
+        find_dir, find_dir_empty, get_core_component, is_builtin_symbol, is_component_tag,
+        prop_finder,
+    },
     BindingMetadata, BindingTypes, SourceLocation,
 };
 use std::{iter, mem};
@@ -59,7 +62,7 @@ pub fn convert_template<'a>(bc: &BC<'a>, mut e: Element<'a>) -> BaseIR<'a> {
     let child_count = e
         .children
         .iter()
-        .filter(|c| !matches!(c, AstNode::Comment(_)))
+        .filter(|c| !matches!(c, AstNode::Comment(_) | AstNode::ProcessingInstruction(_)))
         .count();
     // only build key for props
     let props = |e: &mut Element<'a>| {
@@ -91,7 +94,7 @@ pub fn resolve_element_tag<'a>(bc: &BC<'a>, e: &Element<'a>) -> Js<'a> {
     }
     let is_explicit_dynamic = is_component_tag(e.tag_name);
     // 1. resolve dynamic component
-    let tag = match resolve_dynamic_component(e, is_explicit_dynamic) {
+    let tag = match resolve_dynamic_component(bc, e, is_explicit_dynamic) {
         Ok(call_expr) => return call_expr,
         Err(tag_name) => tag_name,
     };
@@ -130,6 +133,7 @@ pub fn resolve_element_tag<'a>(bc: &BC<'a>, e: &Element<'a>) -> Js<'a> {
 const MUST_NON_EMPTY: &str = "find_prop must return prop with non-empty value";
 /// Returns Ok if resolved as dynamic component call, Err if resolved as static string tag
 fn resolve_dynamic_component<'a>(
+    bc: &BC<'a>,
     e: &Element<'a>,
     is_explicit_dynamic: bool,
 ) -> Result<Js<'a>, &'a str> {
@@ -159,10 +163,13 @@ fn resolve_dynamic_component<'a>(
     }) = prop.get_ref()
     {
         // if not <component>, e.g. <button is="vue:xxx">
-        // only `is` value that starts with "vue:" will be
-        // treated as component by the parse phase and reach here
-        debug_assert!(val.content.starts_with("vue:"));
-        return Err(&val.content.raw[4..]); // strip vue:
+        if let Some(name) = val.content.raw.strip_prefix("vue:") {
+            return Err(name);
+        }
+        // Vue 2 compat: <button is="xxx"> is also a component
+        if bc.option.v2_compat_is {
+            return Err(val.content.raw);
+        }
     }
     Err(e.tag_name)
 }
@@ -265,10 +272,8 @@ fn build_children<'a>(
     e: &mut Element<'a>,
     tag: &Js<'a>,
 ) -> (Vec<BaseIR<'a>>, PatchFlag) {
-    // check slot should precede return
-    if !e.is_component() {
-        v_slot::check_wrong_slot(bc, e, ErrorKind::VSlotMisplaced);
-    }
+    // misplaced v-slot is reported by parser
+    check_form_interpolation(bc, e);
     let mut more_flag = PatchFlag::empty();
     if e.children.is_empty() {
         return (vec![], more_flag);
@@ -372,11 +377,39 @@ fn get_variety_from_binding<'a: 'b, 'b>(
     }
 }
 
+/// e.g. <textarea>{{ text }}</textarea> should be <textarea :value="text"/>
+fn check_form_interpolation<'a>(bc: &BC<'a>, e: &Element<'a>) {
+    if !(bc.option.lint_form_interpolation)(e.tag_name) {
+        return;
+    }
+    // v-model already controls the value
+    if find_dir_empty(e, "model").is_some() {
+        return;
+    }
+    let suggestion = if e.tag_name == "textarea" {
+        r#" Use v-model or :value="..." on <textarea> instead."#
+    } else {
+        r#" Use :value="..." on the element instead."#
+    };
+    for child in &e.children {
+        if let AstNode::Interpolation(i) = child {
+            let error = CompilationError::new(ErrorKind::InterpolationInFormElement)
+                .with_location(i.location.clone())
+                .with_additional_message(suggestion);
+            bc.emit_error(error);
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use super::super::test::base_convert;
+    use super::super::test::{base_convert, SFC_INFO};
     use super::*;
     use crate::cast;
+    use crate::converter::{BaseConverter, ConvertOption, Converter};
+    use crate::error::VecErrorHandler;
+    use crate::parser::test::base_parse;
+    use std::rc::Rc;
     #[test]
     fn test_component_basic() {
         let mut body = base_convert("<comp/>").body;
@@ -386,4 +419,82 @@ mod test {
         assert_eq!(tag.into_string(), "_component_comp");
         assert!(vn.is_component);
     }
+
+    fn lint_form_with(s: &str, tags: fn(&str) -> bool) -> Vec<(usize, String)> {
+        let handler = Rc::new(VecErrorHandler::new());
+        let option = ConvertOption {
+            lint_form_interpolation: tags,
+            ..Default::default()
+        };
+        let bc = BaseConverter::new(handler.clone(), option);
+        bc.convert_ir(base_parse(s), &SFC_INFO);
+        let errors = handler.errors();
+        errors
+            .iter()
+            .filter(|e| matches!(e.kind, ErrorKind::InterpolationInFormElement))
+            .map(|e| (e.location.start.offset, e.to_string()))
+            .collect()
+    }
+    fn lint_form(s: &str) -> Vec<(usize, String)> {
+        lint_form_with(s, |t| matches!(t, "textarea" | "option"))
+    }
+
+    #[test]
+    fn test_form_interpolation() {
+        let errors = lint_form("<textarea>a {{ text }}</textarea>");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].0, 12);
+        assert!(errors[0].1.contains(":value"));
+        let errors = lint_form("<select><option>{{ a }}{{ b }}</option></select>");
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[1].0, 23);
+        // v-model suppresses the lint
+        assert!(lint_form(r#"<textarea v-model="t">{{ t }}</textarea>"#).is_empty());
+        // only configured tags are linted
+        assert!(lint_form("<p>{{ a }}</p>").is_empty());
+        // opt-in
+        let default = ConvertOption::default().lint_form_interpolation;
+        assert!(lint_form_with("<textarea>{{ a }}</textarea>", default).is_empty());
+    }
+
+    #[test]
+    fn test_v2_compat_is_prop() {
+        use crate::parser::{ParseOption, Parser};
+        use crate::scanner::test::base_scan;
+        let props = |s: &str| {
+            let parse_option = ParseOption {
+                is_custom_element: (|t: &str| t == "my-el").into(),
+                v2_compat_is: true,
+                ..Default::default()
+            };
+            let eh = Rc::new(VecErrorHandler::new());
+            let ast = Parser::new(parse_option).parse(base_scan(s), eh.clone());
+            let option = ConvertOption {
+                v2_compat_is: true,
+                ..Default::default()
+            };
+            let mut body = BaseConverter::new(eh, option)
+                .convert_ir(ast, &SFC_INFO)
+                .body;
+            let vn = cast!(body.remove(0), IRNode::VNodeCall);
+            let keys = match vn.props {
+                Some(Js::Props(props)) => props.into_iter().map(|(k, _)| k).collect(),
+                None => vec![],
+                _ => panic!("props should be static"),
+            };
+            let keys: Vec<_> = keys
+                .into_iter()
+                .map(|k| cast!(k, Js::StrLit).into_string())
+                .collect();
+            (vn.is_component, keys)
+        };
+        // converted element drops is
+        assert_eq!(
+            props(r#"<tr is="my-row" a="b"/>"#),
+            (true, vec!["a".into()])
+        );
+        // custom element is kept plain and keeps is
+        let expected = (false, vec!["is".into(), "a".into()]);
+        assert_eq!(props(r#"<my-el is="my-row" a="b"/>"#), expected);
+    }
 }
@@ -5,9 +5,8 @@ use super::{
 use crate::{
     error::{CompilationError, CompilationErrorKind::VSlotUnexpectedDirectiveOnSlotOutlet},
     ir::RenderSlotIR,
-    parser::{DirectiveArg, ElemProp},
+    parser::{is_slot_name_prop, DirectiveArg, ElemProp, SlotName},
     scanner::Attribute,
-    util::is_bind_key,
 };
 use std::mem;
 
@@ -34,18 +33,16 @@ pub fn convert_slot_outlet<'a>(bc: &BC<'a>, mut e: Element<'a>) -> BaseIR<'a> {
 type NameAndProps<'a> = (Js<'a>, Option<Js<'a>>);
 
 fn process_slot_outlet<'a>(bc: &BC<'a>, e: &mut Element<'a>) -> NameAndProps<'a> {
-    let mut slot_name = Js::str_lit("default");
-    let mapper = |mut prop| {
-        match &mut prop {
-            ElemProp::Dir(dir @ Directive { name: "bind", .. })
-                if is_bind_key(&dir.argument, "name") =>
-            {
-                if !dir.has_empty_expr() {
-                    let content = dir.expression.as_ref().unwrap().content;
-                    slot_name = Js::simple(content);
-                }
-                None
-            }
+    let slot_name = match e.slot_outlet_name() {
+        SlotName::Static(name) => Js::StrLit(name),
+        SlotName::Dynamic(expr) => Js::simple(expr.content),
+        SlotName::Default => Js::str_lit("default"),
+    };
+    let mapper = |prop| {
+        if is_slot_name_prop(&prop) {
+            return None;
+        }
+        match &prop {
             ElemProp::Dir(Directive {
                 name: "bind",
                 argument: Some(arg),
@@ -58,16 +55,9 @@ fn process_slot_outlet<'a>(bc: &BC<'a>, e: &mut Element<'a>) -> NameAndProps<'a>
                 Some(prop)
             }
             ElemProp::Dir(_) => Some(prop),
-            ElemProp::Attr(Attribute {
-                name,
-                value: Some(v),
-                ..
-            }) => {
+            ElemProp::Attr(Attribute { value: Some(v), .. }) => {
                 if v.content.is_empty() {
                     None
-                } else if *name == "name" {
-                    slot_name = Js::StrLit(v.content);
-                    None
                 } else {
                     // TODO: handle camelize
                     // name.camelize();
@@ -37,17 +37,23 @@ use crate::{
     flags::{HelperCollector, RuntimeHelper},
     ir::{ConvertInfo, IRNode, IRRoot, JsExpr, TextIR, VNodeIR},
     parser::{SourceNode, TextNode},
-    util::{get_core_component, VStr},
-    SFCInfo,
+    util::{
+        get_core_component, is_generated_alias, is_reserved_binding, is_simple_identifier, no,
+        rslint, VStr,
+    },
+    SFCInfo, SourceLocation,
 };
 pub use v_bind::V_BIND;
 pub use v_model::V_MODEL;
 
+use crate::error::CompilationErrorKind as ErrorKind;
 pub use crate::error::{CompilationError, ErrorHandler, RcErrHandle};
 pub use crate::parser::{AstNode, AstRoot, Directive, Element};
+use crate::scanner::AttributeValue;
 use rustc_hash::{FxHashMap, FxHashSet};
 use smallvec::{smallvec, SmallVec};
 use std::marker::PhantomData;
+use std::ops::Range;
 use std::rc::Rc;
 
 #[cfg(feature = "serde")]
@@ -77,7 +83,9 @@ pub trait CoreConversion<'a, T: ConvertInfo> {
             top_scope: T::TopType::default(),
         }
     }
-    fn convert_children(&self, children: Vec<AstNode<'a>>) -> Vec<IRNode<T>> {
+    fn convert_children(&self, mut children: Vec<AstNode<'a>>) -> Vec<IRNode<T>> {
+        // pre trims its leading newline and may leave an empty text
+        children.retain(|n| !matches!(n, AstNode::Text(t) if t.text.is_empty()));
         let mut key = 0;
         // pre group adjacent v-if here to avoid access siblings
         pre_group_v_if(children)
@@ -98,6 +106,8 @@ pub trait CoreConversion<'a, T: ConvertInfo> {
             AstNode::Text(t) => self.convert_text(t),
             AstNode::Interpolation(i) => self.convert_interpolation(i),
             AstNode::Comment(c) => self.convert_comment(c),
+            // runtime has no processing instruction node
+            AstNode::ProcessingInstruction(p) => self.convert_comment(p),
             // all element like node needs pre-convert structural dirs
             AstNode::Element(e) => self.pre_convert_element(e),
         }
@@ -105,7 +115,7 @@ pub trait CoreConversion<'a, T: ConvertInfo> {
     fn pre_convert_element(&self, mut e: Element<'a>) -> IRNode<T> {
         // in non reactive build, we can skip cache related dir
         if !self.is_reactive_build() {
-            let vfor = pre_convert_for(self, &mut e);
+            let vfor = pre_convert_for(&mut e);
             let mut n = self.dispatch_element(e);
             if let Some(d) = vfor {
                 n = self.convert_for(d, n);
@@ -114,7 +124,7 @@ pub trait CoreConversion<'a, T: ConvertInfo> {
         }
         // order is defined as @vue/compiler-core/src/compile.ts
         let once = pre_convert_once(&mut e);
-        let vfor = pre_convert_for(self, &mut e);
+        let vfor = pre_convert_for(&mut e);
         let memo = pre_convert_memo(&mut e);
         let mut n = self.dispatch_element(e);
         if let Some(d) = memo {
@@ -249,6 +259,8 @@ pub type CoreDirConvRet<'a> = DirectiveConvertResult<JsExpr<'a>>;
 pub type DirConvertFn =
     for<'a> fn(&mut Directive<'a>, &Element<'a>, &dyn ErrorHandler) -> CoreDirConvRet<'a>;
 pub type DirectiveConverter = (&'static str, DirConvertFn);
+/// Maps identifier to JS literal source known at compile time, e.g. `__FEATURE_X__: "true"`.
+pub type CompileConstants = FxHashMap<&'static str, &'static str>;
 
 #[derive(Clone)]
 pub struct ConvertOption {
@@ -258,6 +270,20 @@ pub struct ConvertOption {
     pub directive_converters: FxHashMap<&'static str, DirConvertFn>,
     pub is_dev: bool,
     pub need_reactivity: bool,
+    /// Checks binding names introduced by v-for alias and v-slot props.
+    pub is_reserved_binding: fn(&str) -> bool,
+    /// Reserved bindings colliding with helper alias are errors in prefix mode.
+    pub prefix_identifier: bool,
+    /// v-if on a constant or its negation is folded and dead branches are removed.
+    /// Interpolations and v-bind values that are exactly a constant become its literal.
+    pub constants: CompileConstants,
+    /// Form tags whose interpolation children should be warned, e.g. textarea.
+    pub lint_form_interpolation: fn(&str) -> bool,
+    /// (prefix, message) pairs. Prefix is matched against directive name without `v-`.
+    /// Directives with the prefix but without converter are errors with the message.
+    pub reserved_directive_prefixes: Vec<(&'static str, &'static str)>,
+    /// Vue 2 compat: `is="comp"` on plain elements resolves the component dynamically.
+    pub v2_compat_is: bool,
 }
 
 impl Default for ConvertOption {
@@ -267,6 +293,12 @@ impl Default for ConvertOption {
             is_dev: true,
             need_reactivity: true,
             directive_converters: FxHashMap::default(),
+            is_reserved_binding,
+            prefix_identifier: false,
+            constants: CompileConstants::default(),
+            lint_form_interpolation: no,
+            reserved_directive_prefixes: vec![],
+            v2_compat_is: false,
         }
     }
 }
@@ -323,10 +355,23 @@ impl<'a> CoreConversion<'a, BaseConvertInfo<'a>> for BaseConversion<'a> {
         e: &mut Element<'a>,
     ) -> CoreDirConvRet<'a> {
         if let Some(convert) = self.option.directive_converters.get(dir.name) {
-            convert(dir, e, self.err_handle.as_ref())
-        } else {
-            DirectiveConvertResult::Preserve
+            let mut ret = convert(dir, e, self.err_handle.as_ref());
+            if let DirectiveConvertResult::Converted { value, .. } = &mut ret {
+                if dir.name == "bind" {
+                    self.fold_bind_value(value);
+                }
+            }
+            return ret;
         }
+        let reserved = &self.option.reserved_directive_prefixes;
+        if let Some((_, msg)) = reserved.iter().find(|(p, _)| dir.name.starts_with(p)) {
+            let error = CompilationError::new(ErrorKind::ReservedDirectivePrefix)
+                .with_location(dir.head_loc.clone())
+                .with_additional_message(msg);
+            self.emit_error(error);
+            return DirectiveConvertResult::Dropped;
+        }
+        DirectiveConvertResult::Preserve
     }
     fn convert_if(&self, elems: Vec<Element<'a>>, key: usize) -> BaseIR<'a> {
         v_if::convert_if(self, elems, key)
@@ -365,7 +410,8 @@ impl<'a> CoreConversion<'a, BaseConvertInfo<'a>> for BaseConversion<'a> {
         })
     }
     fn convert_interpolation(&self, interp: SourceNode<'a>) -> BaseIR<'a> {
-        let expr = JsExpr::simple(interp.source);
+        let mut expr = JsExpr::simple(interp.source);
+        self.fold_constant(&mut expr);
         let call = JsExpr::Call(RuntimeHelper::TO_DISPLAY_STRING, vec![expr]);
         IRNode::TextCall(TextIR {
             fast_path: false,
@@ -385,6 +431,69 @@ impl<'a> BaseConversion<'a> {
     fn no_slotted(&self) -> bool {
         self.sfc_info.scope_id.is_some() && !self.sfc_info.slotted
     }
+    /// Replaces an expression that is exactly a compile-time constant with its literal.
+    fn fold_constant(&self, expr: &mut JsExpr<'a>) {
+        if let JsExpr::Simple(v, _) = expr {
+            if let Some(&lit) = self.option.constants.get(v.raw.trim()) {
+                *expr = JsExpr::Src(lit);
+            }
+        }
+    }
+    /// Only v-bind values are folded. v-model and v-on need assignable or callable
+    /// expressions, and v-for source is not converted here.
+    fn fold_bind_value(&self, value: &mut JsExpr<'a>) {
+        if let JsExpr::Props(props) = value {
+            props.iter_mut().for_each(|(_, v)| self.fold_constant(v));
+        } else {
+            self.fold_constant(value);
+        }
+    }
+    /// Reports reserved names introduced by v-for alias or v-slot props.
+    /// `param` must be a sub slice of `val`'s content.
+    fn check_reserved_bindings(&self, param: &str, val: &AttributeValue) {
+        let is_reserved = self.option.is_reserved_binding;
+        let mut names = vec![];
+        if is_simple_identifier(VStr::raw(param)) {
+            names.push(0..param.len());
+        } else if let Some(list) = rslint::parse_fn_param(param) {
+            // range is offset by -1 due to the wrapping parens when parsed
+            let offset = if param.starts_with('(') { 0 } else { 1 };
+            rslint::walk_param_and_default_arg(list, |range, is_param| {
+                if is_param {
+                    names.push(range.start - offset..range.end - offset);
+                }
+            });
+        }
+        for range in names {
+            let name = &param[range.clone()];
+            if !is_reserved(name) {
+                continue;
+            }
+            let kind = if self.option.prefix_identifier && is_generated_alias(name) {
+                ErrorKind::HelperAliasCollision
+            } else {
+                ErrorKind::ReservedBindingName
+            };
+            let location = sub_location(val, param, range);
+            let error = CompilationError::new(kind).with_location(location);
+            self.emit_error(error);
+        }
+    }
+}
+
+/// Locates `range` of `param`, a slice of directive expression's content.
+/// NB: expression location starts at its trimmed content.
+fn sub_location(val: &AttributeValue, param: &str, range: Range<usize>) -> SourceLocation {
+    let content = val.content.raw;
+    let param_start = (param.as_ptr() as usize)
+        .checked_sub(content.as_ptr() as usize)
+        .filter(|start| start + param.len() <= content.len())
+        .expect("param must be a slice of directive content");
+    let mut start = val.location.start.clone();
+    start.advance(&content[..param_start + range.start]);
+    let mut end = start.clone();
+    end.advance(&param[range]);
+    SourceLocation { start, end }
 }
 
 #[cfg(test)]
@@ -397,7 +506,7 @@ pub mod test {
     use lazy_static::lazy_static;
 
     lazy_static! {
-        static ref SFC_INFO: SFCInfo<'static> = SFCInfo::default();
+        pub static ref SFC_INFO: SFCInfo<'static> = SFCInfo::default();
     }
 
     pub fn base_convert(s: &str) -> BaseRoot {
@@ -459,4 +568,147 @@ pub mod test {
     fn test_abort() {
         base_convert("hello <p/> {{world}}");
     }
+
+    #[test]
+    fn test_reserved_binding_in_prefix_mode() {
+        use crate::error::{CompilationErrorKind as Kind, VecErrorHandler};
+        let handler = Rc::new(VecErrorHandler::new());
+        let option = ConvertOption {
+            prefix_identifier: true,
+            ..Default::default()
+        };
+        let bc = BC {
+            err_handle: handler.clone(),
+            option: Rc::new(option),
+        };
+        let ast = base_parse(r#"<p v-for="(_ctx, $key, _renderList) in xs"/>"#);
+        bc.convert_ir(ast, &SFC_INFO);
+        let errors = handler.errors();
+        assert_eq!(errors.len(), 3);
+        assert!(matches!(errors[0].kind, Kind::HelperAliasCollision));
+        assert!(!errors[0].is_warning());
+        assert!(errors[1].is_warning());
+        assert!(matches!(errors[2].kind, Kind::HelperAliasCollision));
+    }
+
+    #[test]
+    fn test_reserved_directive_prefix() {
+        use crate::error::{CompilationErrorKind as Kind, VecErrorHandler};
+        let handler = Rc::new(VecErrorHandler::new());
+        let mut convs = FxHashMap::default();
+        convs.insert("x-known", no_op_directive_convert as DirConvertFn);
+        let option = ConvertOption {
+            directive_converters: convs,
+            reserved_directive_prefixes: vec![("x-", "Owned by the platform team.")],
+            ..Default::default()
+        };
+        let bc = BC {
+            err_handle: handler.clone(),
+            option: Rc::new(option),
+        };
+        let ast = base_parse(r#"<p v-x-known="a" v-x-unknown:b="c" v-custom="d"/>"#);
+        let ir = bc.convert_ir(ast, &SFC_INFO);
+        let errors = handler.errors();
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(errors[0].kind, Kind::ReservedDirectivePrefix));
+        assert!(errors[0]
+            .to_string()
+            .ends_with("Owned by the platform team."));
+        assert_eq!(errors[0].location.start.offset, 17);
+        assert_eq!(errors[0].location.end.offset, 30);
+        // unknown directive without reserved prefix is still a runtime directive
+        let vn = cast!(&ir.body[0], IRNode::VNodeCall);
+        assert_eq!(vn.directives.len(), 1);
+    }
+
+    #[test]
+    fn test_spread_directive() {
+        use crate::error::{CompilationErrorKind as Kind, VecErrorHandler};
+        let errors = |s| {
+            let handler = Rc::new(VecErrorHandler::new());
+            let option = ConvertOption {
+                directive_converters: vec![v_bind::V_BIND, v_on::V_ON].into_iter().collect(),
+                ..Default::default()
+            };
+            let bc = BC {
+                err_handle: handler.clone(),
+                option: Rc::new(option),
+            };
+            bc.convert_ir(base_parse(s), &SFC_INFO);
+            let errors = std::mem::take(&mut *handler.error_mut());
+            errors
+        };
+        assert!(errors(r#"<p v-bind="obj" v-on="obj"/>"#).is_empty());
+        let bare = errors("<p v-bind/>");
+        assert_eq!(bare.len(), 1);
+        assert!(matches!(bare[0].kind, Kind::VBindNoExpression));
+        for case in [r#"<p v-bind.sync="obj"/>"#, r#"<p v-on.once="obj"/>"#] {
+            let errors = errors(case);
+            assert_eq!(errors.len(), 1, "{}", case);
+            assert!(matches!(errors[0].kind, Kind::UnexpectedSpreadModifier));
+            assert_eq!(errors[0].location.start.offset, 3);
+        }
+    }
+
+    #[test]
+    fn test_duplicate_prop() {
+        use crate::error::{CompilationErrorKind as Kind, VecErrorHandler};
+        let errors = |s| {
+            let handler = Rc::new(VecErrorHandler::new());
+            let option = ConvertOption {
+                directive_converters: vec![v_bind::V_BIND, v_on::V_ON].into_iter().collect(),
+                ..Default::default()
+            };
+            let bc = BC {
+                err_handle: handler.clone(),
+                option: Rc::new(option),
+            };
+            let ir = bc.convert_ir(base_parse(s), &SFC_INFO);
+            let errors = std::mem::take(&mut *handler.error_mut());
+            (ir, errors)
+        };
+        let (ir, dup) = errors(r#"<p id="a" :id="b"/>"#);
+        assert_eq!(dup.len(), 1);
+        assert!(matches!(dup[0].kind, Kind::DuplicateProp));
+        assert_eq!(dup[0].location.start.offset, 0);
+        // the first one wins
+        let vn = cast!(&ir.body[0], IRNode::VNodeCall);
+        let props = cast!(vn.props.as_ref().unwrap(), Js::Props);
+        assert_eq!(props.len(), 1);
+        assert!(matches!(props[0].1, Js::StrLit(v) if v.raw == "a"));
+        // mergeable props and props split by spread do not conflict
+        let (_, dup) = errors(r#"<p class="a" :class="b" @click="c" v-on:click="d"/>"#);
+        assert!(dup.is_empty());
+        let (_, dup) = errors(r#"<p id="a" v-bind="obj" :id="b"/>"#);
+        assert!(dup.is_empty());
+    }
+
+    #[test]
+    fn test_fold_constants() {
+        let convs = vec![
+            v_bind::V_BIND,
+            v_on::V_ON,
+            ("model", v_model::convert_v_model_event),
+        ];
+        let option = ConvertOption {
+            directive_converters: convs.into_iter().collect(),
+            constants: [("__A__", "true")].iter().copied().collect(),
+            ..Default::default()
+        };
+        let bc = BC {
+            err_handle: Rc::new(TestErrorHandler),
+            option: Rc::new(option),
+        };
+        let case =
+            r#"{{ __A__ }}<p :a="__A__" @b="__A__" v-model="__A__"/><p v-for="i in __A__"/>"#;
+        let ir = bc.convert_ir(base_parse(case), &SFC_INFO);
+        let text = cast!(&ir.body[0], IRNode::TextCall);
+        assert!(matches!(&text.texts[0], Js::Call(_, args) if matches!(args[0], Js::Src("true"))));
+        let vn = cast!(&ir.body[1], IRNode::VNodeCall);
+        let props = cast!(vn.props.as_ref().unwrap(), Js::Props);
+        assert!(matches!(props[0].1, Js::Src("true")));
+        assert!(props[1..].iter().all(|(_, v)| !matches!(v, Js::Src(_))));
+        let for_ = cast!(&ir.body[2], IRNode::For);
+        assert!(matches!(for_.source, Js::Simple(..)));
+    }
 }
@@ -1,4 +1,4 @@
-use crate::error::CompilationErrorKind as ErrorKind;
+use crate::error::{CompilationError, CompilationErrorKind as ErrorKind};
 use crate::flags::RuntimeHelper;
 
 use super::{
@@ -28,6 +28,11 @@ pub fn convert_v_bind<'a>(
             .expect("dir without value should be dropped");
         Js::simple(expr.content)
     };
+    if dir.is_spread() && !dir.modifiers.is_empty() {
+        let error = CompilationError::new(ErrorKind::UnexpectedSpreadModifier)
+            .with_location(dir.head_loc.clone());
+        eh.on_error(error);
+    }
     let Directive {
         modifiers,
         argument,
@@ -1,26 +1,21 @@
 use super::{
-    BaseConvertInfo, BaseConversion, BaseIR, CompilationError, ConvertInfo, CoreConversion,
-    Directive, Element,
+    BaseConvertInfo, BaseConversion, BaseIR, CompilationError, CoreConversion, Directive, Element,
 };
 use crate::{
     error::CompilationErrorKind as ErrorKind,
     flags::PatchFlag,
     ir::{ForNodeIR, ForParseResult, IRNode, JsExpr as Js},
-    parser::ElementType,
-    util::{find_dir_empty, find_prop, VStr},
+    parser::split_v_for,
+    util::{find_dir_empty, VStr},
+    Position,
 };
-use smallvec::SmallVec;
 
 /// Pre converts v-if or v-for like structural dir
-pub fn pre_convert_for<'a, T: ConvertInfo, C: CoreConversion<'a, T> + ?Sized>(
-    bc: &C,
-    elem: &mut Element<'a>,
-) -> Option<Directive<'a>> {
+pub fn pre_convert_for<'a>(elem: &mut Element<'a>) -> Option<Directive<'a>> {
     // convert v-for, v-if is converted elsewhere
     let dir = find_dir_empty(&mut *elem, "for")?;
     let b = dir.take();
     debug_assert!(find_dir_empty(&mut *elem, "for").is_none());
-    check_template_v_for_key(bc, elem);
     Some(b)
 }
 
@@ -40,6 +35,12 @@ pub fn convert_for<'a>(bc: &BaseConversion, d: Directive<'a>, n: BaseIR<'a>) ->
             return n;
         }
     };
+    let ForParseResult { value, key, index } = &parse_result;
+    for param in std::iter::once(value).chain(key).chain(index) {
+        if let Js::Param(p) = param {
+            bc.check_reserved_bindings(p, &expr);
+        }
+    }
     let n = convert_memo_in_v_for(n, || parse_result.key.clone());
     IRNode::For(ForNodeIR {
         source,
@@ -53,48 +54,18 @@ pub fn convert_for<'a>(bc: &BaseConversion, d: Directive<'a>, n: BaseIR<'a>) ->
 
 type ParsedFor<'a> = (Js<'a>, ForParseResult<BaseConvertInfo<'a>>);
 
-const PARENS: &[char] = &['(', ')'];
 fn parse_for_expr(expr: VStr) -> Option<ParsedFor> {
-    // split source and binding
-    let (lhs, rhs) = expr
-        .raw
-        .split_once(" in ")
-        .or_else(|| expr.raw.split_once(" of "))
-        .map(|(l, r)| (l.trim_matches(PARENS), r.trim()))?;
-    if rhs.is_empty() {
-        return None;
-    }
-    // split iterator by ,
-    let (val, key, idx) = split_v_for_iter(lhs);
+    let parts = split_v_for(expr.raw, &Position::default())?;
     Some((
-        Js::simple(rhs.trim()),
+        Js::simple(parts.source.content),
         ForParseResult {
-            value: Js::Param(val),
-            key: key.map(Js::Param),
-            index: idx.map(Js::Param),
+            value: Js::Param(parts.value.content),
+            key: parts.key.map(|k| Js::Param(k.content)),
+            index: parts.index.map(|i| Js::Param(i.content)),
         },
     ))
 }
 
-const DESTRUCTING: &[char] = &['}', ']'];
-fn split_v_for_iter(mut lhs: &str) -> (&str, Option<&str>, Option<&str>) {
-    let mut split = SmallVec::<[&str; 3]>::new();
-    while let Some((pre, post)) = lhs.rsplit_once(',') {
-        if post.contains(DESTRUCTING) || split.len() == 2 {
-            break;
-        }
-        lhs = pre;
-        split.push(post.trim());
-    }
-    split.push(lhs.trim());
-    split.reverse();
-    match split.len() {
-        2 => (split[0], Some(split[1]), None),
-        3 => (split[0], Some(split[1]), Some(split[2])),
-        _ => (split[0], None, None),
-    }
-}
-
 fn convert_memo_in_v_for<'a, F>(n: BaseIR<'a>, get_key: F) -> BaseIR<'a>
 where
     F: Fn() -> Option<Js<'a>>,
@@ -118,27 +89,6 @@ where
     CacheNode(CacheIR { kind, child })
 }
 
-// check <template v-for> key placement
-fn check_template_v_for_key<'a, T: ConvertInfo, C: CoreConversion<'a, T> + ?Sized>(
-    bc: &C,
-    elem: &Element,
-) {
-    if elem.tag_type != ElementType::Template {
-        return;
-    }
-    let first_wrong = elem
-        .children
-        .iter()
-        .filter_map(|child| child.get_element())
-        .find_map(|child| find_prop(child, "key"));
-    if let Some(wrong) = first_wrong {
-        let key_loc = wrong.get_ref().get_location().clone();
-        let error =
-            CompilationError::new(ErrorKind::VForTemplateKeyPlacement).with_location(key_loc);
-        bc.emit_error(error);
-    }
-}
-
 #[cfg(test)]
 mod test {
     use super::*;
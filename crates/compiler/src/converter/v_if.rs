@@ -7,7 +7,7 @@ use crate::{
     error::CompilationErrorKind as ErrorKind,
     ir::{IfBranch, IfNodeIR},
     scanner::Attribute,
-    util::{find_dir_empty, find_prop, VStr},
+    util::{find_dir_empty, find_prop, literal_truthiness, VStr},
 };
 use rustc_hash::FxHashSet;
 use std::{iter::Peekable, vec::IntoIter};
@@ -75,7 +75,7 @@ impl<'a> Iterator for PreGroupIter<'a> {
                 }
                 // skip whitespace when v-if precedes
                 self.inner.next().unwrap();
-            } else if matches!(n, &AstNode::Comment(_)) {
+            } else if matches!(n, AstNode::Comment(_) | AstNode::ProcessingInstruction(_)) {
                 // ignore comments for now. #3619
                 return self.next_standalone();
             } else {
@@ -105,22 +105,79 @@ pub fn pre_group_v_if(children: Vec<AstNode>) -> impl Iterator<Item = PreGroup>
 pub fn convert_if<'a>(c: &BC<'a>, elems: Vec<Element<'a>>, key: usize) -> BaseIR<'a> {
     debug_assert!(!elems.is_empty());
     check_v_if_group(c, &elems);
-    let branches: Vec<_> = elems
-        .into_iter()
-        .enumerate()
-        .map(|(i, n)| convert_if_branch(c, n, key + i))
-        .collect();
-    IRNode::If(IfNodeIR { branches })
+    if c.option.constants.is_empty() {
+        let branches: Vec<_> = elems
+            .into_iter()
+            .enumerate()
+            .map(|(i, n)| convert_if_branch(c, n, key + i))
+            .collect();
+        return IRNode::If(IfNodeIR { branches });
+    }
+    fold_if(c, elems, key)
 }
 
-pub fn report_dangling_else<'a>(c: &BC<'a>, elem: &Element<'a>) {
-    debug_assert!(find_dir_empty(elem, "if").is_none());
-    let loc = find_dir_empty(elem, ["else-if", "else"])
-        .expect("must have other v-if dir")
-        .get_ref()
-        .location
-        .clone();
-    let error = CompilationError::new(ErrorKind::VElseNoAdjacentIf).with_location(loc);
+/// Removes branches whose condition is a falsy compile-time constant.
+/// The first truthy branch becomes the last one, or the only child if no branch precedes it.
+fn fold_if<'a>(c: &BC<'a>, elems: Vec<Element<'a>>, key: usize) -> BaseIR<'a> {
+    let mut branches = vec![];
+    let mut eliminated = false;
+    let mut elems = elems.into_iter().enumerate();
+    while let Some((i, mut e)) = elems.next() {
+        let folded = match fold_condition(c, &e) {
+            Some(b) => Some(b),
+            // v-else is always taken if a preceding branch was eliminated
+            None if eliminated && find_dir_empty(&e, "else").is_some() => Some(true),
+            None => None,
+        };
+        match folded {
+            None => branches.push(convert_if_branch(c, e, key + i)),
+            Some(false) => {
+                eliminated = true;
+                report_eliminated(c, &e);
+            }
+            Some(true) => {
+                find_dir_empty(&mut e, ["if", "else-if", "else"])
+                    .expect("the element must have v-if directives")
+                    .take();
+                report_duplicate_v_if(c, &mut e);
+                for (_, rest) in elems {
+                    report_eliminated(c, &rest);
+                }
+                if branches.is_empty() {
+                    return c.pre_convert_element(e);
+                }
+                branches.push(IfBranch {
+                    child: Box::new(c.pre_convert_element(e)),
+                    condition: None,
+                    info: key + i,
+                });
+                break;
+            }
+        }
+    }
+    if branches.is_empty() {
+        IRNode::CommentCall("v-if")
+    } else {
+        IRNode::If(IfNodeIR { branches })
+    }
+}
+
+/// Returns the truthiness of v-if/v-else-if whose value is a constant or its negation.
+fn fold_condition<'a>(c: &BC<'a>, e: &Element<'a>) -> Option<bool> {
+    let dir = find_dir_empty(e, ["if", "else-if"])?;
+    let expr = dir.get_ref().expression.as_ref()?;
+    let raw = expr.content.raw.trim();
+    let (negated, name) = match raw.strip_prefix('!') {
+        Some(rest) => (true, rest.trim()),
+        None => (false, raw),
+    };
+    let lit = c.option.constants.get(name)?;
+    literal_truthiness(lit).map(|b| b != negated)
+}
+
+fn report_eliminated<'a>(c: &BC<'a>, e: &Element<'a>) {
+    let error =
+        CompilationError::new(ErrorKind::VIfBranchEliminated).with_location(e.location.clone());
     c.emit_error(error);
 }
 
@@ -157,20 +214,16 @@ fn check_duplicate_key<'a>(
 }
 
 fn check_v_if_group<'a>(c: &BC<'a>, elems: &[Element<'a>]) {
-    // 1. check dangling else
-    if find_dir_empty(&elems[0], "if").is_none() {
-        report_dangling_else(c, &elems[0]);
-    }
+    // NB: dangling else is reported by parser as VElseNoAdjacentIf.
     if !c.option.is_dev {
         return;
     }
-    // 2. check duplicate v-if key in dev build
+    // check duplicate v-if key in dev build
     let mut dirs = FxHashSet::default();
     let mut attrs = FxHashSet::default();
     let mut has_else = false;
     for child in elems {
         if has_else {
-            report_dangling_else(c, child);
             continue;
         }
         let prop = find_prop(child, "key");
@@ -225,6 +278,10 @@ mod test {
     use super::super::test::*;
     use super::*;
     use crate::cast;
+    use crate::converter::{BaseConverter, BaseRoot, ConvertOption, Converter};
+    use crate::error::VecErrorHandler;
+    use crate::parser::test::base_parse;
+    use std::rc::Rc;
 
     fn test_no_panic() {
         let cases = [
@@ -251,4 +308,62 @@ mod test {
         let cond = cast!(condition, Js::Simple);
         assert_eq!(cond.into_string(), "true");
     }
+
+    fn constant_convert(s: &str) -> (BaseRoot<'_>, usize) {
+        let handler = Rc::new(VecErrorHandler::new());
+        let option = ConvertOption {
+            constants: [
+                ("__A__", "true"),
+                ("__B__", "0"),
+                ("__C__", "'x'"),
+                ("__D__", "'a' === 'b'"),
+            ]
+            .iter()
+            .copied()
+            .collect(),
+            ..Default::default()
+        };
+        let bc = BaseConverter::new(handler.clone(), option);
+        let ir = bc.convert_ir(base_parse(s), &SFC_INFO);
+        let eliminated = handler
+            .errors()
+            .iter()
+            .filter(|e| matches!(e.kind, ErrorKind::VIfBranchEliminated))
+            .count();
+        (ir, eliminated)
+    }
+
+    #[test]
+    fn test_fold_constant_if() {
+        // truthy v-if is unwrapped
+        let (ir, n) = constant_convert("<p v-if='__A__'/><p v-else/>");
+        assert_eq!(n, 1);
+        cast!(&ir.body[0], IRNode::VNodeCall);
+        // falsy v-if falls through to v-else
+        let (ir, n) = constant_convert("<p v-if='!__C__'/><p v-else/>");
+        assert_eq!(n, 1);
+        cast!(&ir.body[0], IRNode::VNodeCall);
+        // all branches eliminated
+        let (ir, n) = constant_convert("<p v-if='__B__'/>");
+        assert_eq!(n, 1);
+        cast!(&ir.body[0], IRNode::CommentCall);
+        // unknown condition is kept
+        let (ir, n) = constant_convert("<p v-if='a'/><p v-else-if='!__B__'/><p v-else/>");
+        assert_eq!(n, 1);
+        let v_if = cast!(&ir.body[0], IRNode::If);
+        assert_eq!(v_if.branches.len(), 2);
+        assert!(v_if.branches[1].condition.is_none());
+        assert_eq!(v_if.branches[1].info, 1);
+        // eliminated branch before unknown
+        let (ir, n) = constant_convert("<p v-if='__B__'/><p v-else-if='b'/><p v-else/>");
+        assert_eq!(n, 1);
+        let v_if = cast!(&ir.body[0], IRNode::If);
+        assert_eq!(v_if.branches.len(), 2);
+        assert!(v_if.branches[0].condition.is_some());
+        // expression of string literals is not a constant
+        let (ir, n) = constant_convert("<p v-if='__D__'/><p v-else/>");
+        assert_eq!(n, 0);
+        let v_if = cast!(&ir.body[0], IRNode::If);
+        assert_eq!(v_if.branches.len(), 2);
+    }
 }
@@ -2,12 +2,12 @@ use super::{
     CoreDirConvRet, Directive, DirectiveConvertResult, DirectiveConverter, Element, ErrorHandler,
 };
 use crate::{
-    error::CompilationErrorKind as ErrorKind,
+    error::{CompilationError, CompilationErrorKind as ErrorKind},
     flags::RuntimeHelper,
     ir::{HandlerType, JsExpr as Js},
     parser::DirectiveArg,
     scanner::AttributeValue,
-    util::{is_simple_identifier, not_js_identifier, rslint, VStr},
+    util::{is_fn_exp, is_simple_identifier, rslint, VStr},
 };
 
 // this module process v-on without arg and with arg.
@@ -27,6 +27,11 @@ pub fn convert_v_on<'a>(
             eh.on_error(error);
         }
     }
+    if dir.is_spread() && !dir.modifiers.is_empty() {
+        let error = CompilationError::new(ErrorKind::UnexpectedSpreadModifier)
+            .with_location(dir.head_loc.clone());
+        eh.on_error(error);
+    }
     let Directive {
         expression,
         argument,
@@ -64,50 +69,14 @@ pub fn convert_v_on_expr<'a>(expr: Option<&AttributeValue<'a>>) -> Js<'a> {
     Js::func(val)
 }
 
-fn is_js_identifier(c: char) -> bool {
-    !not_js_identifier(c)
-}
-
-// equivalent to this JS regexp
-// /^\s*([\w$_]+|(async\s*)?\([^)]*?\))\s*=>|^\s*(async\s+)?function(?:\s+[\w$]+)?\s*\(/
-fn is_fn_exp(raw: &str) -> bool {
-    // 0. strip whitespace
-    let mut raw = raw.trim_start();
-    // 1. strip potential async
-    raw = raw.trim_start_matches("async ").trim_start();
-    // 2.a  async => 123
-    if raw.starts_with("=>") {
-        return true;
-    }
-    // 2.b function keyword
-    if raw.starts_with("function ") {
-        raw = raw.trim_start_matches("function ").trim_start();
-        // 3. trim function name
-        raw = raw.trim_start_matches(is_js_identifier).trim_start();
-        return raw.starts_with('(');
-    }
-    // 2.c arrow func shorthand. e.g: argName => expr
-    if raw.starts_with(is_js_identifier) {
-        // 3. strip argName
-        raw = raw.trim_start_matches(is_js_identifier).trim_start();
-        return raw.starts_with("=>");
-    }
-    // 2.d arrow func full (arg, arg, ...arg) => expr
-    if raw.starts_with('(') {
-        raw = raw[1..] // skip (
-            .trim_start_matches(|c| c != ')') // skip inside paren
-            [1..] // skip )
-            .trim_start();
-        return raw.starts_with("=>");
-    }
-    false
-}
-
 pub fn is_member_expression(expr: VStr) -> bool {
     if VStr::has_affix(&expr) {
         return false;
     }
-    if !expr.raw.starts_with(char::is_alphabetic) {
+    if !expr
+        .raw
+        .starts_with(|c: char| c.is_alphabetic() || c == '_' || c == '$')
+    {
         return false;
     }
     is_simple_identifier(expr) || rslint::is_member_expression(&expr)
@@ -124,30 +93,3 @@ pub fn get_handler_type(val: VStr) -> HandlerType {
 }
 
 pub const V_ON: DirectiveConverter = ("on", convert_v_on);
-
-#[cfg(test)]
-mod test {
-    use super::*;
-    #[test]
-    fn test_is_fn_expr() {
-        let positive_cases = [
-            "() => 123",
-            "([a,b,c]) => 123",
-            "(arg) => 123",
-            "async => 123",
-            "async arg => 123",
-            "async (arg) => 123",
-            "function (arg) {}",
-            "function (arg) {}",
-            "async    function   (  arg)  {}",
-            "    function   (  arg)  {}",
-        ];
-        for case in positive_cases {
-            assert!(is_fn_exp(case), "{}", case);
-        }
-        let negative_cases = ["a", "a.b.c", "call()"];
-        for case in negative_cases {
-            assert!(!is_fn_exp(case), "{}", case);
-        }
-    }
-}
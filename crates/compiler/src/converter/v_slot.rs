@@ -11,6 +11,7 @@ use crate::{
     flags::{RuntimeHelper, SlotFlag},
     ir::{IRNode, IfBranch, JsExpr as Js, Slot, VSlotIR},
     parser::{DirectiveArg, ElementType},
+    scanner::AttributeValue,
     util::dir_finder,
 };
 
@@ -91,7 +92,7 @@ fn convert_on_component_slot<'a>(bc: &BC<'a>, e: &mut Element<'a>) -> Option<Bas
     });
     let slot = Slot {
         name: slot_name,
-        param: expression.map(|v| Js::Param(v.content.raw)),
+        param: expression.map(|v| slot_param(bc, v)),
         body: bc.convert_children(children.collect()),
     };
     let v_slot_ir = VSlotIR {
@@ -169,7 +170,7 @@ fn build_stable_slot<'a>(
         }
         seen.insert(n.raw);
     }
-    let param = expression.map(|v| Js::Param(v.content.raw));
+    let param = expression.map(|v| slot_param(bc, v));
     let body = bc.convert_children(t.children);
     Some(Slot { name, param, body })
 }
@@ -179,6 +180,9 @@ fn build_alterable_slots<'a>(bc: &BC<'a>, mut templates: Vec<Element<'a>>) -> Ve
         .iter_mut()
         .map(get_slot_dir)
         .collect::<VecDeque<_>>();
+    for v in dirs.iter().filter_map(|d| d.expression.as_ref()) {
+        bc.check_reserved_bindings(v.content.raw, v);
+    }
     let templates = templates.into_iter().map(AstNode::Element);
     let mut ir_nodes = bc.convert_children(templates.collect());
     // re-assign name to slot
@@ -219,6 +223,11 @@ where
     }
 }
 
+fn slot_param<'a>(bc: &BC<'a>, v: AttributeValue<'a>) -> Js<'a> {
+    bc.check_reserved_bindings(v.content.raw, &v);
+    Js::Param(v.content.raw)
+}
+
 fn get_slot_dir<'a>(t: &mut Element<'a>) -> Directive<'a> {
     dir_finder(t, "slot").allow_empty().find().unwrap().take()
 }
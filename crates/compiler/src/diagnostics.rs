@@ -0,0 +1,279 @@
+/*!
+Diagnostics grouped across templates, e.g. for CI annotations.
+
+Every diagnostic gets a fingerprint that stays the same as long as the
+diagnostic is reported on the same node. The fingerprint hashes the error code,
+the source name and an anchor instead of raw offsets:
+* with AST, the anchor is the NodePath of the innermost enclosing element.
+* without AST, the anchor is the trimmed text of the line of the diagnostic.
+
+Diagnostics with the same anchor and code are told apart by their order.
+So reformatting does not churn fingerprints but moving the element does.
+*/
+
+use crate::{
+    error::{CompilationError, Severity},
+    node_path::NodePath,
+    parser::{AstNode, AstRoot},
+    SourceLocation,
+};
+use std::{collections::BTreeMap, fmt};
+
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Diagnostic {
+    pub source_name: String,
+    pub code: u32,
+    pub severity: Severity,
+    pub message: String,
+    pub location: SourceLocation,
+    /// Labeled secondary locations, see CompilationError::related.
+    pub related: Vec<(String, SourceLocation)>,
+    /// 16 hex digits, stable across whitespace only edits.
+    pub fingerprint: String,
+}
+
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct DiagnosticsSummary {
+    pub by_code: BTreeMap<u32, usize>,
+    pub by_file: BTreeMap<String, usize>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl DiagnosticsSummary {
+    /// Adds errors of one template, fingerprinted by the enclosing element path.
+    pub fn add_with_ast(
+        &mut self,
+        source_name: &str,
+        root: &AstRoot,
+        errors: Vec<CompilationError>,
+    ) {
+        self.add_by(source_name, errors, |loc| {
+            match innermost_element(&root.children, loc.start.offset) {
+                Some(node) => NodePath::of(root, node).map_or_else(String::new, |p| p.to_string()),
+                None => String::new(),
+            }
+        })
+    }
+
+    /// Adds errors of one template, fingerprinted by the line of the error.
+    /// Used when AST is not available, e.g. the template fails to parse.
+    pub fn add_with_source(
+        &mut self,
+        source_name: &str,
+        source: &str,
+        errors: Vec<CompilationError>,
+    ) {
+        self.add_by(source_name, errors, |loc| {
+            line_at(source, loc.start.offset).trim().to_string()
+        })
+    }
+
+    fn add_by<F>(&mut self, source_name: &str, errors: Vec<CompilationError>, anchor: F)
+    where
+        F: Fn(&SourceLocation) -> String,
+    {
+        let mut occurrences = BTreeMap::new();
+        for error in errors {
+            let code = error.code();
+            let anchor = anchor(&error.location);
+            let nth = occurrences.entry((code, anchor.clone())).or_insert(0usize);
+            let fingerprint =
+                fingerprint(&[&code.to_string(), source_name, &anchor, &nth.to_string()]);
+            *nth += 1;
+            *self.by_code.entry(code).or_default() += 1;
+            *self.by_file.entry(source_name.to_string()).or_default() += 1;
+            self.diagnostics.push(Diagnostic {
+                source_name: source_name.to_string(),
+                code,
+                severity: error.severity(),
+                message: error.to_string(),
+                location: error.location,
+                related: error
+                    .related
+                    .into_iter()
+                    .map(|(label, loc)| (label.to_string(), loc))
+                    .collect(),
+                fingerprint,
+            });
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+}
+
+/// Renders one line per diagnostic followed by counts, e.g.
+/// `App.vue:3:5: warning[58] message (fingerprint)`.
+impl fmt::Display for DiagnosticsSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for d in &self.diagnostics {
+            let severity = match d.severity {
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+                Severity::Info => "info",
+            };
+            let start = &d.location.start;
+            writeln!(
+                f,
+                "{}:{}:{}: {}[{}] {} ({})",
+                d.source_name, start.line, start.column, severity, d.code, d.message, d.fingerprint
+            )?;
+        }
+        writeln!(f, "{} diagnostic(s)", self.diagnostics.len())?;
+        for (code, count) in &self.by_code {
+            writeln!(f, "  code {}: {}", code, count)?;
+        }
+        for (file, count) in &self.by_file {
+            writeln!(f, "  {}: {}", file, count)?;
+        }
+        Ok(())
+    }
+}
+
+fn innermost_element<'r, 'a>(
+    children: &'r [AstNode<'a>],
+    offset: usize,
+) -> Option<&'r AstNode<'a>> {
+    let node = children.iter().find(|n| {
+        let loc = n.get_location();
+        n.get_element().is_some() && loc.start.offset <= offset && offset < loc.end.offset
+    })?;
+    let e = node.get_element()?;
+    innermost_element(&e.children, offset).or(Some(node))
+}
+
+fn line_at(source: &str, offset: usize) -> &str {
+    let offset = offset.min(source.len());
+    let start = source[..offset].rfind('\n').map_or(0, |i| i + 1);
+    let end = source[offset..]
+        .find('\n')
+        .map_or(source.len(), |i| offset + i);
+    &source[start..end]
+}
+
+// FNV-1a is used since std hashers are not stable across Rust releases.
+fn fingerprint(parts: &[&str]) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for part in parts {
+        for b in part.bytes().chain(Some(0)) {
+            hash ^= u64::from(b);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    format!("{:016x}", hash)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{audit::audit_html_sinks, error::VecErrorHandler, parser::test::base_parse};
+
+    fn add(summary: &mut DiagnosticsSummary, name: &str, source: &str) {
+        let root = base_parse(source);
+        let eh = VecErrorHandler::default();
+        audit_html_sinks(&root).report(&eh);
+        let errors = std::mem::take(&mut *eh.error_mut());
+        summary.add_with_ast(name, &root, errors);
+    }
+
+    fn summarize(name: &str, source: &str) -> DiagnosticsSummary {
+        let mut summary = DiagnosticsSummary::default();
+        add(&mut summary, name, source);
+        summary
+    }
+
+    fn fingerprints(s: &DiagnosticsSummary) -> Vec<&str> {
+        s.diagnostics
+            .iter()
+            .map(|d| d.fingerprint.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_stable_fingerprint() {
+        let before = summarize(
+            "a.vue",
+            r#"<div><p><i v-html="a"/><i v-html="b"/></p></div>"#,
+        );
+        let after = summarize(
+            "a.vue",
+            "\n\n<div>\n  <p>\n    <i v-html=\"a\"/>\n    <i v-html=\"b\"/>\n  </p>\n</div>",
+        );
+        assert_eq!(before.diagnostics.len(), 2);
+        assert_ne!(
+            before.diagnostics[0].location,
+            after.diagnostics[0].location
+        );
+        assert_eq!(fingerprints(&before), fingerprints(&after));
+        assert_ne!(fingerprints(&before)[0], fingerprints(&before)[1]);
+        // element moved out of <p>
+        let moved = summarize(
+            "a.vue",
+            r#"<div><p><i v-html="a"/></p><i v-html="b"/></div>"#,
+        );
+        assert_eq!(fingerprints(&before)[0], fingerprints(&moved)[0]);
+        assert_ne!(fingerprints(&before)[1], fingerprints(&moved)[1]);
+        // source name is part of fingerprint
+        let renamed = summarize(
+            "b.vue",
+            r#"<div><p><i v-html="a"/><i v-html="b"/></p></div>"#,
+        );
+        assert_ne!(fingerprints(&before)[0], fingerprints(&renamed)[0]);
+    }
+
+    #[test]
+    fn test_line_fallback() {
+        let source = "<p>\n  <i v-html=\"a\"/>\n</p>";
+        let root = base_parse(source);
+        let eh = VecErrorHandler::default();
+        audit_html_sinks(&root).report(&eh);
+        let errors = std::mem::take(&mut *eh.error_mut());
+        let mut summary = DiagnosticsSummary::default();
+        summary.add_with_source("a.vue", source, errors);
+        let expected = fingerprint(&[
+            &summary.diagnostics[0].code.to_string(),
+            "a.vue",
+            "<i v-html=\"a\"/>",
+            "0",
+        ]);
+        assert_eq!(summary.diagnostics[0].fingerprint, expected);
+    }
+
+    #[test]
+    fn test_counts_and_render() {
+        let mut summary = summarize("a.vue", r#"<p v-html="a"/><p v-html="b"/>"#);
+        add(&mut summary, "b.vue", r#"<p v-html="c"/>"#);
+        add(&mut summary, "c.vue", "<p/>");
+        let code = crate::error::CompilationErrorKind::VHtmlSink;
+        let code = CompilationError::new(code).code();
+        assert_eq!(summary.by_code[&code], 3);
+        assert_eq!(summary.by_file["a.vue"], 2);
+        assert_eq!(summary.by_file["b.vue"], 1);
+        assert!(!summary.by_file.contains_key("c.vue"));
+        let text = summary.to_string();
+        assert!(text.starts_with(&format!("a.vue:1:4: warning[{}] v-html", code)));
+        assert!(text.contains("3 diagnostic(s)"));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serialize() {
+        let summary = summarize("a.vue", r#"<p v-html="a"/>"#);
+        let json = serde_json::to_value(&summary).unwrap();
+        assert_eq!(json["by_file"]["a.vue"], 1);
+        assert_eq!(json["diagnostics"][0]["severity"], "Warning");
+        assert_eq!(
+            json["diagnostics"][0]["fingerprint"]
+                .as_str()
+                .unwrap()
+                .len(),
+            16
+        );
+    }
+}
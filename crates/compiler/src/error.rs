@@ -5,6 +5,24 @@ use std::rc::Rc;
 
 pub trait ErrorKind {
     fn msg(&self) -> &'static str;
+    fn severity(&self) -> Severity {
+        Severity::Error
+    }
+    /// Higher-order compilers should start their codes from EXTEND_POINT_CODE.
+    fn code(&self) -> u32 {
+        EXTEND_POINT_CODE
+    }
+}
+
+pub const EXTEND_POINT_CODE: u32 = 1000;
+
+/// Warnings do not stop compilation but are still reported to ErrorHandler.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
 }
 
 pub enum CompilationErrorKind {
@@ -38,21 +56,45 @@ pub enum CompilationErrorKind {
     MissingInterpolationEnd,
     MissingDynamicDirectiveArgumentEnd,
     UnexpectedContentAfterDynamicDirective,
+    InvalidDynamicArgument,
     MissingDirectiveName,
     MissingDirectiveArg,
     MissingDirectiveMod,
     InvalidVSlotModifier,
+    TextSegmentLimitExceeded,
+    MissingFrontmatterEnd,
+    ElementClassified,
+    UnknownDirective,
+    MaxNestingDepthExceeded,
+    UppercaseInDomAttribute,
+    CompatIsAttribute,
+    TemplateNoSpecialDirective,
+    MissingVForKey,
+    ComponentShadowsNativeTag,
+    BuiltinTagMisuse,
+    UnknownNamedCharacterReference,
+    MissingSemicolonAfterCharacterReference,
+    AbsenceOfDigitsInNumericCharacterReference,
+    NullCharacterReference,
+    CharacterReferenceOutsideUnicodeRange,
+    SurrogateCharacterReference,
+    VBindShorthandReservedWord,
+    VModelValueNotWritable,
 
     // transform errors
     VIfNoExpression,
     VIfSameKey,
     VIfDuplicateDir,
+    VIfBranchEliminated,
     VElseNoAdjacentIf,
+    VIfWithVFor,
     VForNoExpression,
     VForMalformedExpression,
     VForTemplateKeyPlacement,
     VBindNoExpression,
     VOnNoExpression,
+    UnexpectedSpreadModifier,
+    DuplicateProp,
     VSlotUnexpectedDirectiveOnSlotOutlet,
     VSlotMixedSlotUsage,
     VSlotTemplateMisplaced,
@@ -64,6 +106,11 @@ pub enum CompilationErrorKind {
     VModelMalformedExpression,
     VModelOnScopeVariable,
     InvalidExpression,
+    InterpolationInFormElement,
+    VHtmlSink,
+    ReservedBindingName,
+    HelperAliasCollision,
+    ReservedDirectivePrefix,
 
     UnexpectedDirExpression,
     KeepAliveInvalidChildren,
@@ -89,6 +136,8 @@ pub struct CompilationError {
     pub kind: CompilationErrorKind,
     pub additional_message: Option<&'static str>,
     pub location: SourceLocation,
+    /// Secondary locations with labels, e.g. where an element is implicitly closed.
+    pub related: Vec<(&'static str, SourceLocation)>,
 }
 
 impl CompilationError {
@@ -97,6 +146,7 @@ impl CompilationError {
             kind,
             additional_message: None,
             location: Default::default(),
+            related: vec![],
         }
     }
     #[must_use]
@@ -110,17 +160,32 @@ impl CompilationError {
         self
     }
     #[must_use]
+    pub fn with_related(mut self, label: &'static str, loc: SourceLocation) -> Self {
+        self.related.push((label, loc));
+        self
+    }
+    #[must_use]
     pub fn extended<K: ErrorKind + 'static>(kind: K) -> Self {
         Self {
             kind: CompilationErrorKind::ExtendPoint(Box::new(kind)),
             additional_message: None,
             location: Default::default(),
+            related: vec![],
         }
     }
 
     pub fn msg(&self) -> &'static str {
         msg(&self.kind)
     }
+    pub fn severity(&self) -> Severity {
+        self.kind.severity()
+    }
+    pub fn code(&self) -> u32 {
+        self.kind.code()
+    }
+    pub fn is_warning(&self) -> bool {
+        self.severity() == Severity::Warning
+    }
 }
 
 #[cold]
@@ -162,21 +227,64 @@ fn msg(kind: &CompilationErrorKind) -> &'static str {
             "End bracket for dynamic directive argument was not found. Note that dynamic directive argument cannot contain spaces.",
         UnexpectedContentAfterDynamicDirective =>
             "Unexpected content was found after a closed dynamic argument. Add a dot as separator if it is a modifier.",
+        InvalidDynamicArgument =>
+            "Dynamic argument cannot contain whitespace. Use a computed property instead.",
         MissingDirectiveName => "Legal directive name was expected.",
         MissingDirectiveArg => "Directive argument was expected.",
         MissingDirectiveMod => "Directive modifier was expected.",
         InvalidVSlotModifier => "v-slot does not take modifier.",
+        TextSegmentLimitExceeded => "Text is too long and is split into several text nodes.",
+        MissingFrontmatterEnd =>
+            "Frontmatter closing fence `---` was not found. It is parsed as template instead.",
+        ElementClassified => "Element type is decided by rule: ",
+        UnknownDirective => "Directive is neither built-in nor registered. Is it a typo?",
+        MaxNestingDepthExceeded =>
+            "Elements are nested too deeply. Deeper elements are parsed as self-closing.",
+        UppercaseInDomAttribute =>
+            "Browsers lowercase attribute names in in-DOM templates. Use kebab-case instead.",
+        CompatIsAttribute =>
+            r#"`is` on plain elements is deprecated in Vue 3. Use `is="vue:comp"` instead."#,
+        TemplateNoSpecialDirective =>
+            "<template> without v-if, v-for or v-slot is rendered as a native element.",
+        MissingVForKey =>
+            "Elements in v-for should have a key.",
+        ComponentShadowsNativeTag =>
+            "Component tag shadows a native element of the same name. Rename the component.",
+        BuiltinTagMisuse =>
+            "Built-in tag is used with a misleading prop.",
+        UnknownNamedCharacterReference =>
+            "Unknown named character reference is kept as is.",
+        MissingSemicolonAfterCharacterReference =>
+            "Character reference should end with a semicolon.",
+        AbsenceOfDigitsInNumericCharacterReference =>
+            "Numeric character reference has no digits and is kept as is.",
+        NullCharacterReference =>
+            "Null character reference is replaced with U+FFFD.",
+        CharacterReferenceOutsideUnicodeRange =>
+            "Character reference outside Unicode range is replaced with U+FFFD.",
+        SurrogateCharacterReference =>
+            "Surrogate character reference is replaced with U+FFFD.",
+        VBindShorthandReservedWord =>
+            "v-bind same-name shorthand cannot expand to a JavaScript reserved word. Add a value.",
+        VModelValueNotWritable =>
+            "v-model value must be a variable or a member expression that can be assigned.",
 
         // transform errors
         VIfNoExpression => "v-if/v-else-if is missing expression.",
         VIfSameKey => "v-if/else branches must use unique keys.",
         VIfDuplicateDir => "Duplicate v-if/else-if/else. Use v-else-if instead.",
+        VIfBranchEliminated => "v-if branch is eliminated by compile-time constant.",
         VElseNoAdjacentIf => "v-else/v-else-if has no adjacent v-if.",
+        VIfWithVFor =>
+            "v-if and v-for are on the same element. v-if has higher priority in Vue 3 so it cannot access v-for variables. Move v-if to a wrapper <template>.",
         VForNoExpression => "v-for is missing expression.",
         VForMalformedExpression => "v-for has invalid expression.",
         VForTemplateKeyPlacement => "<template v-for> key should be placed on the <template> tag.",
         VBindNoExpression => "v-bind is missing expression.",
         VOnNoExpression => "v-on is missing expression.",
+        UnexpectedSpreadModifier =>
+            "v-bind/v-on without argument spreads an object and does not take modifiers.",
+        DuplicateProp => "Duplicate prop is ignored. The first one takes effect.",
         VSlotUnexpectedDirectiveOnSlotOutlet => "Unexpected custom directive on <slot> outlet.",
         VSlotMixedSlotUsage =>
             "Mixed v-slot usage on both the component and nested <template>. When there are multiple named slots, all slots should use <template> syntax to avoid scope ambiguity.",
@@ -191,6 +299,14 @@ fn msg(kind: &CompilationErrorKind) -> &'static str {
         VModelOnScopeVariable =>
             "v-model cannot be used on v-for or v-slot scope variables because they are not writable.",
         InvalidExpression => "Error parsing JavaScript expression: ",
+        InterpolationInFormElement =>
+            "Interpolation inside form element does not update its value as expected.",
+        VHtmlSink => "v-html renders unescaped HTML and may lead to XSS attacks.",
+        ReservedBindingName =>
+            "Binding name is reserved by Vue runtime or compiler generated code and may be shadowed.",
+        HelperAliasCollision =>
+            "Binding name collides with a helper alias generated in prefixIdentifiers mode.",
+        ReservedDirectivePrefix => "Directive name uses a reserved prefix but is not registered.",
         UnexpectedDirExpression => "This directive does not accept any epxression.",
         KeepAliveInvalidChildren => "<KeepAlive> expects exactly one child component.",
 
@@ -209,6 +325,133 @@ impl ErrorKind for CompilationErrorKind {
     fn msg(&self) -> &'static str {
         msg(self)
     }
+    fn severity(&self) -> Severity {
+        use CompilationErrorKind::*;
+        match self {
+            ReservedBindingName
+            | InterpolationInFormElement
+            | VHtmlSink
+            | InvalidDynamicArgument
+            | MissingFrontmatterEnd
+            | UnknownDirective
+            | UppercaseInDomAttribute
+            | CompatIsAttribute
+            | TemplateNoSpecialDirective
+            | MissingVForKey
+            | ComponentShadowsNativeTag
+            | UnknownNamedCharacterReference
+            | MissingSemicolonAfterCharacterReference
+            | AbsenceOfDigitsInNumericCharacterReference
+            | NullCharacterReference
+            | CharacterReferenceOutsideUnicodeRange
+            | SurrogateCharacterReference
+            | VIfWithVFor
+            | DuplicateProp => Severity::Warning,
+            TextSegmentLimitExceeded | VIfBranchEliminated | ElementClassified => Severity::Info,
+            ExtendPoint(err) => err.severity(),
+            _ => Severity::Error,
+        }
+    }
+    /// Numeric code. The initial codes follow this enum's declaration order, so they
+    /// do not match Vue's ErrorCodes. Codes are append-only: a new kind takes the
+    /// next unused code below EXTEND_POINT_CODE wherever it is declared, and
+    /// existing codes never change or get reused.
+    fn code(&self) -> u32 {
+        use CompilationErrorKind::*;
+        match self {
+            AbruptClosingOfEmptyComment => 0,
+            CDataInHtmlContent => 1,
+            DuplicateAttribute => 2,
+            EndTagWithAttributes => 3,
+            EndTagWithTrailingSolidus => 4,
+            EofBeforeTagName => 5,
+            EofInCdata => 6,
+            EofInComment => 7,
+            EofInScriptHtmlCommentLikeText => 8,
+            EofInTag => 9,
+            IncorrectlyClosedComment => 10,
+            IncorrectlyOpenedComment => 11,
+            InvalidFirstCharacterOfTagName => 12,
+            MissingAttributeValue => 13,
+            MissingEndTagName => 14,
+            MissingWhitespaceBetweenAttributes => 15,
+            NestedComment => 16,
+            UnexpectedEqualsSignBeforeAttributeName => 17,
+            UnexpectedCharacterInAttributeName => 18,
+            UnexpectedCharacterInUnquotedAttributeValue => 19,
+            UnexpectedNullCharacter => 20,
+            UnexpectedQuestionMarkInsteadOfTagName => 21,
+            UnexpectedSolidusInTag => 22,
+            InvalidEndTag => 23,
+            MissingEndTag => 24,
+            MissingInterpolationEnd => 25,
+            MissingDynamicDirectiveArgumentEnd => 26,
+            UnexpectedContentAfterDynamicDirective => 27,
+            MissingDirectiveName => 28,
+            MissingDirectiveArg => 29,
+            MissingDirectiveMod => 30,
+            InvalidVSlotModifier => 31,
+            TextSegmentLimitExceeded => 32,
+            MissingFrontmatterEnd => 33,
+            ElementClassified => 34,
+            VIfNoExpression => 35,
+            VIfSameKey => 36,
+            VIfDuplicateDir => 37,
+            VIfBranchEliminated => 38,
+            VElseNoAdjacentIf => 39,
+            VForNoExpression => 40,
+            VForMalformedExpression => 41,
+            VForTemplateKeyPlacement => 42,
+            VBindNoExpression => 43,
+            VOnNoExpression => 44,
+            VSlotUnexpectedDirectiveOnSlotOutlet => 45,
+            VSlotMixedSlotUsage => 46,
+            VSlotTemplateMisplaced => 47,
+            VSlotDuplicateSlotNames => 48,
+            VSlotExtraneousDefaultSlotChildren => 49,
+            VSlotMisplaced => 50,
+            VMemoNoExpression => 51,
+            VModelNoExpression => 52,
+            VModelMalformedExpression => 53,
+            VModelOnScopeVariable => 54,
+            InvalidExpression => 55,
+            InterpolationInFormElement => 56,
+            VHtmlSink => 57,
+            ReservedBindingName => 58,
+            HelperAliasCollision => 59,
+            UnexpectedDirExpression => 60,
+            KeepAliveInvalidChildren => 61,
+            PrefixIdNotSupported => 62,
+            ModuleModeNotSupported => 63,
+            CacheHandlerNotSupported => 64,
+            ScopeIdNotSupported => 65,
+            // appended after the initial numbering
+            InvalidDynamicArgument => 66,
+            ReservedDirectivePrefix => 67,
+            UnknownDirective => 68,
+            UnexpectedSpreadModifier => 69,
+            MaxNestingDepthExceeded => 70,
+            VIfWithVFor => 71,
+            UppercaseInDomAttribute => 72,
+            CompatIsAttribute => 73,
+            TemplateNoSpecialDirective => 74,
+            // 75 is retired, it was a duplicate of VForTemplateKeyPlacement
+            MissingVForKey => 76,
+            ComponentShadowsNativeTag => 77,
+            BuiltinTagMisuse => 78,
+            UnknownNamedCharacterReference => 79,
+            MissingSemicolonAfterCharacterReference => 80,
+            AbsenceOfDigitsInNumericCharacterReference => 81,
+            NullCharacterReference => 82,
+            CharacterReferenceOutsideUnicodeRange => 83,
+            SurrogateCharacterReference => 84,
+            VBindShorthandReservedWord => 85,
+            // 86-88 are retired, v-model on plain elements is checked by DomError
+            VModelValueNotWritable => 89,
+            DuplicateProp => 90,
+            ExtendPoint(err) => err.code(),
+        }
+    }
 }
 
 impl fmt::Display for CompilationError {
@@ -273,4 +516,22 @@ pub mod test {
     #[derive(Clone)]
     pub struct TestErrorHandler;
     impl ErrorHandler for TestErrorHandler {}
+
+    #[test]
+    fn test_stable_codes() {
+        use super::{CompilationErrorKind as Kind, ErrorKind, EXTEND_POINT_CODE};
+        assert_eq!(Kind::AbruptClosingOfEmptyComment.code(), 0);
+        assert_eq!(Kind::MissingDirectiveName.code(), 28);
+        assert_eq!(Kind::VIfNoExpression.code(), 35);
+        assert_eq!(Kind::ScopeIdNotSupported.code(), 65);
+        assert_eq!(Kind::InvalidDynamicArgument.code(), 66);
+        assert_eq!(Kind::SurrogateCharacterReference.code(), 84);
+        // kinds declared among older ones keep their appended codes
+        assert_eq!(Kind::VForTemplateKeyPlacement.code(), 42);
+        assert_eq!(Kind::MaxNestingDepthExceeded.code(), 70);
+        assert_eq!(Kind::MissingVForKey.code(), 76);
+        assert_eq!(Kind::VModelValueNotWritable.code(), 89);
+        assert_eq!(Kind::DuplicateProp.code(), 90);
+        assert_eq!(EXTEND_POINT_CODE, 1000);
+    }
 }
@@ -182,8 +182,17 @@ impl RuntimeHelper {
     pub const IS_REF: RH = RH(37);
     pub const WITH_MEMO: RH = RH(38);
     pub const IS_MEMO_SAME: RH = RH(39);
+    // exported by runtime-dom, but resolved by ParseOption::dom() in core
+    pub const TRANSITION: RH = RH(40);
+    pub const TRANSITION_GROUP: RH = RH(41);
 
-    pub const INTERNAL_MAX: u8 = 40;
+    pub const INTERNAL_MAX: u8 = 42;
+
+    /// Iterates over all runtime helpers defined in core.
+    pub fn core_helpers() -> impl Iterator<Item = RH> {
+        // NB: RH(10) is not assigned
+        (0..Self::INTERNAL_MAX).filter(|&i| i != 10).map(RH)
+    }
 
     pub fn helper_str(&self, map: &[&'static str]) -> &'static str {
         match *self {
@@ -226,6 +235,8 @@ impl RuntimeHelper {
             RH::IS_REF => "isRef",
             RH::WITH_MEMO => "withMemo",
             RH::IS_MEMO_SAME => "isMemoSame",
+            RH::TRANSITION => "Transition",
+            RH::TRANSITION_GROUP => "TransitionGroup",
             RH(s) => map[(s - RH::INTERNAL_MAX) as usize],
         }
     }
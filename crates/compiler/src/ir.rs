@@ -6,6 +6,9 @@ use crate::{
 use rustc_hash::FxHashSet;
 use std::hash::Hash;
 
+pub mod document;
+pub use document::{IrDocument, IR_VERSION};
+
 #[cfg(feature = "serde")]
 use serde::Serialize;
 
@@ -227,7 +230,7 @@ pub type JsExpression<T> = <T as ConvertInfo>::JsExpression;
 pub type CommentType<T> = <T as ConvertInfo>::CommentType;
 
 #[cfg_attr(feature = "serde", derive(Serialize))]
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 /// Records how v-on handler is written in the template.
 /// Variants will be compiled differently (also depends on `cache_handlers`).
 pub enum HandlerType {
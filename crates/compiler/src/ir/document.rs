@@ -0,0 +1,508 @@
+/*!
+Serializable IR for backends that do not render with JS, e.g. native renderers.
+
+The document describes converted IR: v-if/v-for/v-slot/v-model are already
+resolved into control flow and props, but no transform pass has run yet so
+there is no hoisting, patch flag, block or caching decision in it.
+Expressions are opaque source strings which the host evaluates in its own way.
+
+Node kinds, tagged by `kind`:
+* `Text`: concatenation of parts. `Literal` parts are static text and any
+  other part is an interpolated expression converted to display string.
+* `Comment`: comment text, only present if comments are preserved.
+* `Element`: a native element, component, builtin or fragment. Props are
+  merged in order, later entries override earlier ones except that `class`,
+  `style` and `onXxx` values are already concatenated into arrays.
+  Component children are always described by `slots`, never by `children`.
+* `If`: the first branch whose condition is truthy renders its body.
+  A branch without condition is `v-else`.
+* `For`: renders body for every item of source, with value/key/index aliases
+  bound in body.
+* `Slot`: a slot declared under v-if/v-for, only found in `slots.dynamic`.
+* `SlotOutlet`: renders the slot of the given name passed by parent,
+  or fallback if parent does not provide one.
+* `Once`: body is rendered once and never updated.
+* `Memo`: body is updated only when any value in the `deps` array changes.
+
+Expression kinds, tagged by `kind`:
+* `Literal`: string value, already decoded.
+* `Number`: integer literal.
+* `Code`: JS expression source. Identifiers refer to the render context.
+* `Handler`: event handler source, either a function or statements using `$event`.
+* `Compound`: JS source made by concatenating all parts.
+* `Object`, `Array`: containers of expressions.
+* `Call`: call to a runtime helper by name, e.g. `toDisplayString`.
+* `Symbol`: a runtime value by name, e.g. builtin component `KeepAlive`.
+
+Expressions written in template carry their location so the host can report
+errors in template coordinates. `version` bumps on incompatible changes.
+*/
+
+use super::{
+    CacheIR, CacheKind, ForNodeIR, IRNode, IfNodeIR, JsExpr as Js, RenderSlotIR, RuntimeDir,
+    Slot as SlotIR, VNodeIR, VSlotIR,
+};
+use crate::{
+    converter::{BaseConvertInfo, BaseRoot},
+    flags::RuntimeHelper,
+    SourceLocation, SourceMapper,
+};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+pub const IR_VERSION: u32 = 1;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct IrDocument {
+    pub version: u32,
+    pub body: Vec<Node>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "kind"))]
+pub enum Node {
+    Text { parts: Vec<Expr> },
+    Comment { text: String },
+    Element(Element),
+    If { branches: Vec<IfBranch> },
+    For(ForNode),
+    Slot(Slot),
+    SlotOutlet(SlotOutlet),
+    Once { body: Box<Node> },
+    Memo(MemoNode),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Element {
+    pub tag: Tag,
+    pub props: Vec<PropEntry>,
+    pub directives: Vec<Directive>,
+    pub children: Vec<Node>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub slots: Option<Slots>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "kind"))]
+pub enum Tag {
+    /// platform element, e.g. `div`
+    Native { name: String },
+    /// user component resolved by name as written in template
+    Component { name: String },
+    /// component provided by runtime, e.g. `KeepAlive`
+    Builtin { name: String },
+    /// component given by expression value, e.g. `<component :is="comp">`
+    Dynamic { is: Expr },
+    /// `<template>` with directives, rendering children only
+    Fragment,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "kind"))]
+pub enum PropEntry {
+    /// a prop whose key evaluates to a string
+    Prop { key: Expr, value: Expr },
+    /// an object whose entries are all props, e.g. `v-bind="obj"`
+    Spread { value: Expr },
+}
+
+/// Directive to be applied by runtime after props are set.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Directive {
+    /// name without `v-`, or the runtime name if it is builtin, e.g. `vShow`
+    pub name: String,
+    pub value: Option<Expr>,
+    pub arg: Option<Expr>,
+    pub modifiers: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Slots {
+    /// slots always passed to the component
+    pub stable: Vec<Slot>,
+    /// If/For nodes whose leaves are Slot nodes
+    pub dynamic: Vec<Node>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Slot {
+    pub name: Expr,
+    /// parameter pattern binding slot props in body, e.g. `{ item }`
+    pub param: Option<Expr>,
+    pub body: Vec<Node>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct IfBranch {
+    pub condition: Option<Expr>,
+    pub body: Node,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ForNode {
+    pub source: Expr,
+    pub value: Expr,
+    pub key: Option<Expr>,
+    pub index: Option<Expr>,
+    pub body: Box<Node>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SlotOutlet {
+    pub name: Expr,
+    /// props passed to the slot
+    pub props: Vec<PropEntry>,
+    pub fallback: Vec<Node>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MemoNode {
+    pub deps: Expr,
+    /// key alias of the enclosing v-for, if v-memo is used with v-for
+    pub for_key: Option<Expr>,
+    pub body: Box<Node>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "kind"))]
+pub enum Expr {
+    Literal {
+        value: String,
+        #[cfg_attr(
+            feature = "serde",
+            serde(default, skip_serializing_if = "Option::is_none")
+        )]
+        location: Option<SourceLocation>,
+    },
+    Number {
+        value: usize,
+    },
+    Code {
+        code: String,
+        #[cfg_attr(
+            feature = "serde",
+            serde(default, skip_serializing_if = "Option::is_none")
+        )]
+        location: Option<SourceLocation>,
+    },
+    Handler {
+        code: String,
+        #[cfg_attr(
+            feature = "serde",
+            serde(default, skip_serializing_if = "Option::is_none")
+        )]
+        location: Option<SourceLocation>,
+    },
+    Compound {
+        parts: Vec<Expr>,
+    },
+    Object {
+        props: Vec<ObjectProp>,
+    },
+    Array {
+        items: Vec<Expr>,
+    },
+    Call {
+        helper: String,
+        args: Vec<Expr>,
+    },
+    Symbol {
+        name: String,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ObjectProp {
+    pub key: Expr,
+    pub value: Expr,
+}
+
+type Info<'a> = BaseConvertInfo<'a>;
+
+impl IrDocument {
+    /// Describes IR converted from source. Transformed IR is not accepted
+    /// since hoisting and expression processing are JS codegen decisions.
+    pub(crate) fn new(root: BaseRoot<'_>, source: &str, helper_strs: &[&'static str]) -> Self {
+        let builder = Builder {
+            source,
+            mapper: SourceMapper::new(source),
+            helper_strs,
+        };
+        Self {
+            version: IR_VERSION,
+            body: builder.nodes(root.body),
+        }
+    }
+}
+
+struct Builder<'s> {
+    source: &'s str,
+    mapper: SourceMapper<'s>,
+    helper_strs: &'s [&'static str],
+}
+
+// NB: Hoisted, FuncCompound and setup bindings are produced by transform
+// passes or SFCInfo bindings, neither of which compile_to_ir involves.
+const NOT_CONVERTED: &str = "IR document only describes converted IR";
+
+impl<'s> Builder<'s> {
+    fn nodes<'a>(&self, nodes: Vec<IRNode<Info<'a>>>) -> Vec<Node> {
+        nodes.into_iter().map(|n| self.node(n)).collect()
+    }
+
+    fn node<'a>(&self, node: IRNode<Info<'a>>) -> Node {
+        match node {
+            IRNode::TextCall(t) => Node::Text {
+                parts: t.texts.into_iter().map(|t| self.text_part(t)).collect(),
+            },
+            IRNode::CommentCall(c) => Node::Comment { text: c.into() },
+            IRNode::VNodeCall(v) => Node::Element(self.element(v)),
+            IRNode::If(i) => self.if_node(i),
+            IRNode::For(f) => Node::For(self.for_node(f)),
+            IRNode::AlterableSlot(s) => Node::Slot(self.slot(s)),
+            IRNode::RenderSlotCall(r) => Node::SlotOutlet(self.slot_outlet(r)),
+            IRNode::CacheNode(c) => self.cache(c),
+            IRNode::VSlotUse(_) | IRNode::Hoisted(_) => unreachable!("{}", NOT_CONVERTED),
+        }
+    }
+
+    fn text_part<'a>(&self, text: Js<'a>) -> Expr {
+        match text {
+            Js::Call(RuntimeHelper::TO_DISPLAY_STRING, mut args) if args.len() == 1 => {
+                self.expr(args.pop().unwrap())
+            }
+            t => self.expr(t),
+        }
+    }
+
+    fn element<'a>(&self, v: VNodeIR<Info<'a>>) -> Element {
+        let mut slots = None;
+        let mut children = vec![];
+        for child in v.children {
+            match child {
+                IRNode::VSlotUse(s) => slots = Some(self.slots(s)),
+                c => children.push(self.node(c)),
+            }
+        }
+        Element {
+            tag: self.tag(v.tag, v.is_component),
+            props: self.prop_entries(v.props),
+            directives: v
+                .directives
+                .into_iter()
+                .map(|d| self.directive(d))
+                .collect(),
+            children,
+            slots,
+        }
+    }
+
+    fn tag<'a>(&self, tag: Js<'a>, is_component: bool) -> Tag {
+        match tag {
+            Js::StrLit(name) if !is_component => Tag::Native {
+                name: name.into_string(),
+            },
+            // the raw is the tag name as written, before resolving ops
+            Js::Simple(name, _) if is_component => Tag::Component {
+                name: name.raw.into(),
+            },
+            Js::Symbol(RuntimeHelper::FRAGMENT) => Tag::Fragment,
+            Js::Symbol(rh) => Tag::Builtin {
+                name: self.helper(rh),
+            },
+            Js::Call(RuntimeHelper::RESOLVE_DYNAMIC_COMPONENT, mut args) if args.len() == 1 => {
+                Tag::Dynamic {
+                    is: self.expr(args.pop().unwrap()),
+                }
+            }
+            t => Tag::Dynamic { is: self.expr(t) },
+        }
+    }
+
+    fn prop_entries<'a>(&self, props: Option<Js<'a>>) -> Vec<PropEntry> {
+        let mut entries = vec![];
+        let args = match props {
+            None => return entries,
+            Some(Js::Call(RuntimeHelper::MERGE_PROPS, args)) => args,
+            Some(p) => vec![p],
+        };
+        for arg in args {
+            match arg {
+                Js::Props(ps) => entries.extend(ps.into_iter().map(|(k, v)| PropEntry::Prop {
+                    key: self.expr(k),
+                    value: self.expr(v),
+                })),
+                // placeholder for renderSlot's positional arguments
+                Js::Src("{}") => (),
+                a => entries.push(PropEntry::Spread {
+                    value: self.expr(a),
+                }),
+            }
+        }
+        entries
+    }
+
+    fn directive<'a>(&self, dir: RuntimeDir<Info<'a>>) -> Directive {
+        let name = match dir.name {
+            Js::Symbol(rh) => self.helper(rh),
+            Js::Simple(name, _) => name.raw.into(),
+            _ => unreachable!("{}", NOT_CONVERTED),
+        };
+        let modifiers = match dir.mods {
+            Some(Js::Props(ps)) => ps.into_iter().filter_map(|(k, _)| raw_of(k)).collect(),
+            _ => vec![],
+        };
+        Directive {
+            name,
+            value: dir.expr.map(|e| self.expr(e)),
+            arg: dir.arg.map(|e| self.expr(e)),
+            modifiers,
+        }
+    }
+
+    fn slots<'a>(&self, s: VSlotIR<Info<'a>>) -> Slots {
+        Slots {
+            stable: s.stable_slots.into_iter().map(|s| self.slot(s)).collect(),
+            dynamic: self.nodes(s.alterable_slots),
+        }
+    }
+
+    fn slot<'a>(&self, s: SlotIR<Info<'a>>) -> Slot {
+        Slot {
+            name: self.expr(s.name),
+            param: s.param.map(|p| self.expr(p)),
+            body: self.nodes(s.body),
+        }
+    }
+
+    fn if_node<'a>(&self, i: IfNodeIR<Info<'a>>) -> Node {
+        let branches = i.branches.into_iter().map(|b| IfBranch {
+            condition: b.condition.map(|c| self.expr(c)),
+            body: self.node(*b.child),
+        });
+        Node::If {
+            branches: branches.collect(),
+        }
+    }
+
+    fn for_node<'a>(&self, f: ForNodeIR<Info<'a>>) -> ForNode {
+        let aliases = f.parse_result;
+        ForNode {
+            source: self.expr(f.source),
+            value: self.expr(aliases.value),
+            key: aliases.key.map(|k| self.expr(k)),
+            index: aliases.index.map(|i| self.expr(i)),
+            body: Box::new(self.node(*f.child)),
+        }
+    }
+
+    fn slot_outlet<'a>(&self, r: RenderSlotIR<Info<'a>>) -> SlotOutlet {
+        SlotOutlet {
+            name: self.expr(r.slot_name),
+            props: self.prop_entries(r.slot_props),
+            fallback: self.nodes(r.fallbacks),
+        }
+    }
+
+    fn cache<'a>(&self, c: CacheIR<Info<'a>>) -> Node {
+        let body = Box::new(self.node(*c.child));
+        let (deps, for_key) = match c.kind {
+            CacheKind::Once => return Node::Once { body },
+            CacheKind::Memo(deps) => (deps, None),
+            CacheKind::MemoInVFor { v_for_key, expr } => (expr, v_for_key),
+        };
+        Node::Memo(MemoNode {
+            deps: self.expr(deps),
+            for_key: for_key.map(|k| self.expr(k)),
+            body,
+        })
+    }
+
+    fn expr<'a>(&self, js: Js<'a>) -> Expr {
+        match js {
+            Js::StrLit(v) => Expr::Literal {
+                location: self.location_of(v.raw),
+                value: v.into_string(),
+            },
+            Js::Num(n) => Expr::Number { value: n },
+            Js::Simple(v, _) => Expr::Code {
+                location: self.location_of(v.raw),
+                code: v.into_string(),
+            },
+            Js::Src(s) | Js::Param(s) => Expr::Code {
+                location: self.location_of(s),
+                code: s.into(),
+            },
+            Js::FuncSimple { src, .. } => Expr::Handler {
+                location: self.location_of(src.raw),
+                code: src.into_string(),
+            },
+            Js::Compound(parts) => Expr::Compound {
+                parts: parts.into_iter().map(|p| self.expr(p)).collect(),
+            },
+            Js::Props(ps) => Expr::Object {
+                props: ps
+                    .into_iter()
+                    .map(|(k, v)| ObjectProp {
+                        key: self.expr(k),
+                        value: self.expr(v),
+                    })
+                    .collect(),
+            },
+            Js::Array(items) => Expr::Array {
+                items: items.into_iter().map(|i| self.expr(i)).collect(),
+            },
+            Js::Call(rh, args) => Expr::Call {
+                helper: self.helper(rh),
+                args: args.into_iter().map(|a| self.expr(a)).collect(),
+            },
+            Js::Symbol(rh) => Expr::Symbol {
+                name: self.helper(rh),
+            },
+            Js::FuncCompound { .. } => unreachable!("{}", NOT_CONVERTED),
+        }
+    }
+
+    fn helper(&self, rh: RuntimeHelper) -> String {
+        rh.helper_str(self.helper_strs).into()
+    }
+
+    /// Locates str borrowed from template source. Static strs have no location.
+    fn location_of(&self, s: &str) -> Option<SourceLocation> {
+        let base = self.source.as_ptr() as usize;
+        let start = (s.as_ptr() as usize).checked_sub(base)?;
+        let end = start + s.len();
+        if end > self.source.len() {
+            return None;
+        }
+        Some(SourceLocation {
+            start: self.mapper.position_of(start),
+            end: self.mapper.position_of(end),
+        })
+    }
+}
+
+fn raw_of(js: Js) -> Option<String> {
+    match js {
+        Js::StrLit(v) | Js::Simple(v, _) => Some(v.raw.into()),
+        _ => None,
+    }
+}
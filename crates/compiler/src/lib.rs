@@ -4,15 +4,24 @@
 // TODO: reorg pub
 #[macro_use]
 pub mod util;
+pub mod ast_owned;
+pub mod audit;
 pub mod codegen;
 pub mod compiler;
 pub mod converter;
+pub mod diagnostics;
 pub mod error;
 pub mod flags;
 pub mod ir;
+pub mod node_path;
 pub mod parser;
+pub mod printer;
+pub mod props;
 pub mod scanner;
+pub mod testing;
 pub mod transformer;
+pub mod visit;
+pub mod whitespace_impact;
 
 use flags::StaticLevel;
 pub use ir::JsExpr as Js;
@@ -23,7 +32,7 @@ pub use transformer::{pass::Chain, process_expression::ExpressionProcessor};
 use util::VStr;
 
 #[cfg(feature = "serde")]
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 // use plain &str here for now
 // may change to tendril
@@ -31,11 +40,12 @@ pub type Name<'a> = &'a str;
 
 #[derive(PartialEq, Eq, Clone, Debug)]
 pub struct Position {
-    /// the 0-indexed offset in the source str modulo newline
+    /// the 0-indexed byte offset in the source str.
+    /// `&source[start.offset..end.offset]` is the text of a location.
     pub offset: usize,
     /// the line number in the source code
     pub line: u32,
-    /// the column number in the source code
+    /// the column number in the source code, counted in char
     pub column: u32,
 }
 
@@ -54,6 +64,48 @@ impl Serialize for Position {
     }
 }
 
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Position {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        use serde::de::Error;
+        let s = String::deserialize(deserializer)?;
+        let parse = || {
+            let mut nums = s
+                .strip_prefix("Pos: ")?
+                .splitn(3, ", ")
+                .zip(["", "Ln: ", "Col: "])
+                .map(|(n, prefix)| n.strip_prefix(prefix));
+            let offset = nums.next()??.parse().ok()?;
+            let line = nums.next()??.parse().ok()?;
+            let column = nums.next()??.parse().ok()?;
+            Some(Position {
+                offset,
+                line,
+                column,
+            })
+        };
+        parse().ok_or_else(|| D::Error::custom(format!("invalid position: {}", s)))
+    }
+}
+
+impl Position {
+    /// Moves the position past `text`, which must directly follow it in the source.
+    pub fn advance(&mut self, text: &str) {
+        // NB: offset is counted in u8 but column in char
+        self.offset += text.len();
+        match text.rfind('\n') {
+            Some(i) => {
+                self.line += text.matches('\n').count() as u32;
+                self.column = text[i..].chars().count() as u32;
+            }
+            None => self.column += text.chars().count() as u32,
+        }
+    }
+}
+
 impl Default for Position {
     fn default() -> Self {
         Self {
@@ -64,13 +116,62 @@ impl Default for Position {
     }
 }
 
-#[derive(Default, PartialEq, Eq, Clone)]
-#[cfg_attr(feature = "serde", derive(Serialize))]
+#[derive(Default, PartialEq, Eq, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct SourceLocation {
     pub start: Position,
     pub end: Position,
 }
 
+/// Converts between byte offsets and line/column of the original source.
+/// Lines are split by `\n` so `\r\n` counts as one line break.
+pub struct SourceMapper<'a> {
+    source: &'a str,
+    /// byte offsets where each line starts
+    line_starts: Vec<usize>,
+}
+
+impl<'a> SourceMapper<'a> {
+    pub fn new(source: &'a str) -> Self {
+        let newlines = source.match_indices('\n').map(|(i, _)| i + 1);
+        let line_starts = std::iter::once(0).chain(newlines).collect();
+        Self {
+            source,
+            line_starts,
+        }
+    }
+    /// Returns the position of a byte offset. Offset must be on char boundary.
+    pub fn position_of(&self, offset: usize) -> Position {
+        debug_assert!(self.source.is_char_boundary(offset));
+        let line = self.line_starts.partition_point(|&s| s <= offset) - 1;
+        let line_start = self.line_starts[line];
+        let column = self.source[line_start..offset].chars().count() + 1;
+        Position {
+            offset,
+            line: line as u32 + 1,
+            column: column as u32,
+        }
+    }
+    /// Returns the byte offset of 1-based line and column, if any.
+    pub fn offset_of(&self, line: u32, column: u32) -> Option<usize> {
+        let line_start = *self.line_starts.get((line as usize).checked_sub(1)?)?;
+        let skip = (column as usize).checked_sub(1)?;
+        let rest = &self.source[line_start..];
+        if skip == 0 {
+            return Some(line_start);
+        }
+        let (i, c) = rest.char_indices().nth(skip - 1)?;
+        if c == '\n' {
+            return None;
+        }
+        Some(line_start + i + c.len_utf8())
+    }
+    /// Returns the source text of the location.
+    pub fn slice(&self, location: &SourceLocation) -> &'a str {
+        &self.source[location.start.offset..location.end.offset]
+    }
+}
+
 impl From<SourceLocation> for Range<usize> {
     fn from(location: SourceLocation) -> Self {
         location.start.offset..location.end.offset
@@ -186,4 +287,20 @@ mod test {
     fn test_source_size() {
         assert_eq!(std::mem::size_of::<Position>(), 16);
     }
+
+    #[test]
+    fn test_source_mapper() {
+        let src = "ab\r\nいろは\nc";
+        let mapper = SourceMapper::new(src);
+        for (offset, line, column) in [(0, 1, 1), (4, 2, 1), (7, 2, 2), (13, 2, 4), (14, 3, 1)] {
+            let pos = mapper.position_of(offset);
+            assert_eq!((pos.line, pos.column), (line, column));
+            assert_eq!(mapper.offset_of(line, column), Some(offset));
+        }
+        assert_eq!(mapper.offset_of(2, 5), None);
+        assert_eq!(mapper.offset_of(4, 1), None);
+        let mut pos = Position::default();
+        pos.advance(&src[..7]);
+        assert_eq!(pos, mapper.position_of(7));
+    }
 }
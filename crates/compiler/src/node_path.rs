@@ -0,0 +1,205 @@
+/*!
+Stable addressing of AST nodes.
+
+A NodePath is the sequence of child indexes from AstRoot to a node.
+Every step also records the tag name of the element being indexed into
+so that resolving a path against an edited tree fails instead of
+silently pointing to an unrelated node.
+
+The compact string form joins steps by `/`, e.g. `0/div.2/span.0` is
+the first child of the third child of a root level `<div>`, which is a `<span>`.
+*/
+
+use crate::parser::{AstNode, AstRoot};
+use std::{fmt, str::FromStr};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PathStep {
+    /// tag name of the parent element, None for root children.
+    pub tag: Option<String>,
+    pub index: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NodePath {
+    steps: Vec<PathStep>,
+}
+
+impl NodePath {
+    /// Finds the path of a node in the tree by identity.
+    /// Returns None if the node does not belong to the root.
+    pub fn of<'a>(root: &AstRoot<'a>, node: &AstNode<'a>) -> Option<Self> {
+        let mut steps = vec![];
+        if find_steps(&root.children, None, node, &mut steps) {
+            Some(Self { steps })
+        } else {
+            None
+        }
+    }
+
+    /// Returns None if the tree no longer matches the path.
+    pub fn resolve<'r, 'a>(&self, root: &'r AstRoot<'a>) -> Option<&'r AstNode<'a>> {
+        let (first, rest) = self.steps.split_first()?;
+        if first.tag.is_some() {
+            return None;
+        }
+        let mut node = root.children.get(first.index)?;
+        for step in rest {
+            let e = node.get_element()?;
+            if step.tag.as_deref() != Some(e.tag_name) {
+                return None;
+            }
+            node = e.children.get(step.index)?;
+        }
+        Some(node)
+    }
+
+    pub fn steps(&self) -> &[PathStep] {
+        &self.steps
+    }
+}
+
+fn find_steps<'a>(
+    children: &[AstNode<'a>],
+    tag: Option<&str>,
+    target: &AstNode<'a>,
+    steps: &mut Vec<PathStep>,
+) -> bool {
+    for (index, child) in children.iter().enumerate() {
+        steps.push(PathStep {
+            tag: tag.map(String::from),
+            index,
+        });
+        if std::ptr::eq(child, target) {
+            return true;
+        }
+        if let AstNode::Element(e) = child {
+            if find_steps(&e.children, Some(e.tag_name), target, steps) {
+                return true;
+            }
+        }
+        steps.pop();
+    }
+    false
+}
+
+impl fmt::Display for NodePath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, step) in self.steps.iter().enumerate() {
+            if i > 0 {
+                f.write_str("/")?;
+            }
+            if let Some(tag) = &step.tag {
+                write!(f, "{}.", tag)?;
+            }
+            write!(f, "{}", step.index)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct InvalidNodePath;
+
+impl fmt::Display for InvalidNodePath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("invalid node path")
+    }
+}
+
+impl FromStr for NodePath {
+    type Err = InvalidNodePath;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err(InvalidNodePath);
+        }
+        let steps = s
+            .split('/')
+            .map(|seg| {
+                // tag name may contain dot, e.g. <Foo.Bar>
+                let (tag, index) = match seg.rsplit_once('.') {
+                    Some(("", _)) => return Err(InvalidNodePath),
+                    Some((tag, index)) => (Some(tag.to_string()), index),
+                    None => (None, seg),
+                };
+                let index = index.parse().map_err(|_| InvalidNodePath)?;
+                Ok(PathStep { tag, index })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { steps })
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for NodePath {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for NodePath {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::test::base_parse;
+
+    #[test]
+    fn test_node_path() {
+        let root = base_parse("<div>a<p/><span><b/></span></div>");
+        let div = root.children[0].get_element().unwrap();
+        let span = div.children[2].get_element().unwrap();
+        let b = &span.children[0];
+        let path = NodePath::of(&root, b).unwrap();
+        assert_eq!(path.to_string(), "0/div.2/span.0");
+        assert!(std::ptr::eq(path.resolve(&root).unwrap(), b));
+        let other = base_parse("<div>a<p/><span><b/></span></div>");
+        assert!(NodePath::of(&root, &other.children[0]).is_none());
+    }
+
+    #[test]
+    fn test_resolve_after_edit() {
+        let path: NodePath = "0/div.2/span.0".parse().unwrap();
+        // tag changed
+        let root = base_parse("<div>a<p/><i><b/></i></div>");
+        assert!(path.resolve(&root).is_none());
+        // index out of bound
+        let root = base_parse("<div>a<span><b/></span></div>");
+        assert!(path.resolve(&root).is_none());
+        // text is not element
+        let root = base_parse("<div>a<p/>text</div>");
+        assert!(path.resolve(&root).is_none());
+    }
+
+    #[test]
+    fn test_parse_path() {
+        let path: NodePath = "1/Foo.Bar.3".parse().unwrap();
+        assert_eq!(path.steps()[1].tag.as_deref(), Some("Foo.Bar"));
+        assert_eq!(path.steps()[1].index, 3);
+        for invalid in ["", "a", "0//1", "0/.1", "0/div.x"] {
+            assert_eq!(invalid.parse::<NodePath>(), Err(InvalidNodePath));
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        use serde::de::{value::Error, IntoDeserializer};
+        let path: NodePath = "0/div.2/span.0".parse().unwrap();
+        let serialized = serde_json::to_string(&path).unwrap();
+        assert_eq!(serialized, r#""0/div.2/span.0""#);
+        let de = NodePath::deserialize("0/div.2/span.0".into_deserializer());
+        assert_eq!(de, Ok::<_, Error>(path));
+        let de: Result<_, Error> = NodePath::deserialize("0/div.x".into_deserializer());
+        assert!(de.is_err());
+    }
+}
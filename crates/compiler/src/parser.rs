@@ -15,40 +15,73 @@
 // Instead, we use a simple stack to construct AST.
 
 use super::{
+    ast_owned,
     error::{CompilationError, CompilationErrorKind as ErrorKind, RcErrHandle},
     flags::RuntimeHelper,
-    scanner::{Attribute, AttributeValue, Tag, TextMode, Token, TokenSource},
-    util::{find_dir, is_core_component, no, non_whitespace, yes, VStr},
-    Name, Namespace, SourceLocation,
+    ir::HandlerType,
+    scanner::{
+        Attribute, AttributeValue, FrontmatterMode, QuoteKind, Tag, TextMode, Token, TokenSource,
+    },
+    util::{
+        classify_handler, dir_finder, find_prop, is_bind_key, is_component_tag, is_core_component,
+        is_js_reserved_word, is_simple_identifier, no, non_whitespace, prop_finder, rslint, yes,
+        PropFound, StrOps, VStr,
+    },
+    Name, Namespace, Position, SourceLocation,
 };
+use rustc_hash::FxHashMap;
 use smallvec::{smallvec, SmallVec};
-use std::ops::Deref;
+use std::{
+    borrow::Cow,
+    fmt,
+    hash::{Hash, Hasher},
+};
+
+mod alloc;
+mod builder;
+pub mod preset;
+mod reparse;
+mod v_for;
+#[cfg(feature = "bumpalo")]
+pub use alloc::Arena;
+pub use alloc::{AstAlloc, AstVec, Heap};
+pub use builder::{ParseOptionBuilder, StrPredicate};
+pub use reparse::TextEdit;
+pub use v_for::{ExprSlice, VForParts};
+pub(crate) use v_for::split_v_for;
 
 #[cfg(feature = "serde")]
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-#[cfg_attr(feature = "serde", derive(Serialize))]
-pub enum AstNode<'a> {
-    Element(Element<'a>),
+#[cfg_attr(feature = "serde", derive(Serialize), serde(bound = ""))]
+pub enum AstNode<'a, A: AstAlloc<'a> = Heap> {
+    Element(Element<'a, A>),
     Text(TextNode<'a>),
     Interpolation(SourceNode<'a>),
     Comment(SourceNode<'a>),
+    /// Only if ParseOption::keep_processing_instructions is set.
+    /// Source is the content between `<?` and `>` as a bogus comment ends at
+    /// the first `>`, e.g. `xml version="1.0"?`.
+    ProcessingInstruction(SourceNode<'a>),
 }
 
-impl<'a> AstNode<'a> {
-    pub fn get_element(&self) -> Option<&Element<'a>> {
+impl<'a, A: AstAlloc<'a>> AstNode<'a, A> {
+    pub fn to_owned_ast(&self) -> ast_owned::AstNode {
+        self.into()
+    }
+    pub fn get_element(&self) -> Option<&Element<'a, A>> {
         match self {
             AstNode::Element(e) => Some(e),
             _ => None,
         }
     }
-    pub fn get_element_mut(&mut self) -> Option<&mut Element<'a>> {
+    pub fn get_element_mut(&mut self) -> Option<&mut Element<'a, A>> {
         match self {
             AstNode::Element(e) => Some(e),
             _ => None,
         }
     }
-    pub fn into_element(self) -> Element<'a> {
+    pub fn into_element(self) -> Element<'a, A> {
         match self {
             AstNode::Element(e) => e,
             _ => panic!("call into_element on non-element AstNode"),
@@ -60,19 +93,41 @@ impl<'a> AstNode<'a> {
             Self::Text(t) => &t.location,
             Self::Interpolation(i) => &i.location,
             Self::Comment(c) => &c.location,
+            Self::ProcessingInstruction(p) => &p.location,
+        }
+    }
+    pub fn id(&self) -> NodeId {
+        match self {
+            Self::Element(e) => e.id,
+            Self::Text(t) => t.id,
+            Self::Interpolation(i) => i.id,
+            Self::Comment(c) => c.id,
+            Self::ProcessingInstruction(p) => p.id,
         }
     }
 }
 
+/// Index of a node in document order, i.e. pre-order of the tree.
+/// Parser assigns ids after the tree is built, so removed whitespace
+/// and comments leave no gap. Frontmatter is not in the tree and keeps 0.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct NodeId(pub u32);
+
 #[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct SourceNode<'a> {
     pub source: &'a str,
     pub location: SourceLocation,
+    pub id: NodeId,
 }
 
 pub struct TextNode<'a> {
     pub text: SmallVec<[VStr<'a>; 1]>,
     pub location: SourceLocation,
+    pub id: NodeId,
+    /// Text from a CDATA section in foreign content. It is kept verbatim,
+    /// i.e. neither decoded nor condensed.
+    pub is_cdata: bool,
 }
 #[cfg(feature = "serde")]
 impl<'a> Serialize for TextNode<'a> {
@@ -81,37 +136,65 @@ impl<'a> Serialize for TextNode<'a> {
         S: serde::Serializer,
     {
         use serde::ser::SerializeStruct;
-        let mut state = serializer.serialize_struct("TextNode", 2)?;
-        let s = self.text.iter().map(|&s| s.into_string());
-        let s: String = s.collect();
-        state.serialize_field("text", &s)?;
+        let mut state = serializer.serialize_struct("TextNode", 3)?;
+        state.serialize_field("text", &self.full_text())?;
         state.serialize_field("location", &self.location)?;
+        state.serialize_field("id", &self.id)?;
         state.end()
     }
 }
 
-impl<'a> Deref for TextNode<'a> {
-    type Target = str;
-    fn deref(&self) -> &Self::Target {
-        debug_assert!(self.text.len() == 1);
-        &self.text[0]
+// Text ops are often no-op, e.g. decoding text without `&`.
+fn ops_change_nothing(s: &VStr) -> bool {
+    let mut ops = s.ops;
+    if !s.raw.contains('&') {
+        ops.remove(StrOps::DECODE_ENTITY | StrOps::DECODE_ATTR);
+    }
+    let other_space = |c: char| c.is_ascii_whitespace() && c != ' ';
+    if !s.raw.contains(other_space) && !s.raw.contains("  ") {
+        ops.remove(StrOps::COMPRESS_WHITESPACE);
+    }
+    ops.is_empty()
+}
+
+impl<'a> fmt::Display for TextNode<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for s in &self.text {
+            s.write_to(&mut *f)?;
+        }
+        Ok(())
     }
 }
 
 impl<'a> TextNode<'a> {
+    /// Concatenates all segments with their ops applied, e.g. decoded entities.
+    /// Borrows source if there is only one segment whose ops change nothing.
+    pub fn full_text(&self) -> Cow<'a, str> {
+        match &self.text[..] {
+            [] => Cow::Borrowed(""),
+            [s] if ops_change_nothing(s) => Cow::Borrowed(s.raw),
+            _ => Cow::Owned(self.to_string()),
+        }
+    }
     /// if TextNode contains only whitespaces. In HTML it means empty node.
     pub fn is_all_whitespace(&self) -> bool {
         self.text.iter().all(|s| !s.chars().any(non_whitespace))
     }
+    /// text may have several parts, e.g. when comments in between are removed
+    pub fn contains_newline(&self) -> bool {
+        self.text.iter().any(|s| s.contains(&['\r', '\n'][..]))
+    }
+    /// Removes one leading `\n`, `\r\n` or `\r`. Whitespace after it is kept.
+    /// Location still covers the newline so it can be restored, e.g. by printer.
     pub fn trim_leading_newline(&mut self) {
         if self.text.is_empty() {
             return;
         }
         let first = &self.text[0];
-        let offset = if first.starts_with('\n') {
-            1
-        } else if first.starts_with("\r\n") {
+        let offset = if first.starts_with("\r\n") {
             2
+        } else if first.starts_with(&['\n', '\r'][..]) {
+            1
         } else {
             return;
         };
@@ -139,16 +222,36 @@ impl<'a> ElemProp<'a> {
             Self::Dir(d) => &d.location,
         }
     }
-    fn attr(mut a: Attribute<'a>) -> Self {
-        if let Some(val) = a.value.as_mut() {
+    /// Source text of the whole prop, e.g. `:a = "b"`.
+    pub fn raw_source<'s>(&self, source: &'s str) -> &'s str {
+        let loc = self.get_location();
+        &source[loc.start.offset..loc.end.offset]
+    }
+    fn attr(mut a: Attribute<'a>, decode: bool) -> Self {
+        if let Some(val) = a.value.as_mut().filter(|_| decode) {
             val.content.decode(true);
         }
         Self::Attr(a)
     }
 }
 
-#[derive(PartialEq, Eq)]
-#[cfg_attr(feature = "serde", derive(Serialize))]
+/// See Element::slot_outlet_name.
+pub enum SlotName<'a, 'e> {
+    Static(VStr<'a>),
+    Dynamic(&'e AttributeValue<'a>),
+    Default,
+}
+
+/// `name`, `:name` or `v-bind:name`, even if its value is empty.
+pub fn is_slot_name_prop(prop: &ElemProp) -> bool {
+    match prop {
+        ElemProp::Attr(attr) => attr.name == "name",
+        ElemProp::Dir(dir) => dir.name == "bind" && is_bind_key(&dir.argument, "name"),
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum ElementType {
     Plain,
     Component,
@@ -156,21 +259,106 @@ pub enum ElementType {
     SlotOutlet,
 }
 
-#[cfg_attr(feature = "serde", derive(Serialize))]
-pub struct Element<'a> {
+#[cfg_attr(feature = "serde", derive(Serialize), serde(bound = ""))]
+pub struct Element<'a, A: AstAlloc<'a> = Heap> {
     pub tag_name: Name<'a>,
     pub tag_type: ElementType,
     pub namespace: Namespace,
     pub properties: Vec<ElemProp<'a>>,
-    pub children: Vec<AstNode<'a>>,
+    #[cfg_attr(feature = "serde", serde(serialize_with = "alloc::serialize_slice"))]
+    pub children: A::Vec<AstNode<'a, A>>,
     pub location: SourceLocation,
+    pub id: NodeId,
 }
 
-impl<'a> Element<'a> {
+impl<'a, A: AstAlloc<'a>> Element<'a, A> {
     #[inline]
     pub fn is_component(&self) -> bool {
         self.tag_type == ElementType::Component
     }
+    /// Finds static attribute or v-bind with static argument.
+    /// e.g. `id`, `:id` and `v-bind:id` all match "id".
+    /// Unlike util::find_prop, prop without value is also found.
+    pub fn find_prop<'e>(&'e self, name: &str) -> Option<PropFound<'a, &'e Self, ElemProp<'a>, A>> {
+        prop_finder(self, name).allow_empty().find()
+    }
+    /// Removes the first prop matched by find_prop, keeping the order of the rest.
+    pub fn remove_prop(&mut self, name: &str) -> Option<ElemProp<'a>> {
+        prop_finder(self, name)
+            .allow_empty()
+            .find()
+            .map(|p| p.take())
+    }
+    /// Name of `<slot>` outlet from `name="a"`, `:name="a"` or `v-bind:name="a"`.
+    /// The last non-empty one wins. Returns Default if this is not a slot outlet,
+    /// e.g. `<slot>` inside v-pre.
+    pub fn slot_outlet_name(&self) -> SlotName<'a, '_> {
+        let mut name = SlotName::Default;
+        if self.tag_type != ElementType::SlotOutlet {
+            return name;
+        }
+        for prop in &self.properties {
+            match prop {
+                ElemProp::Attr(Attribute {
+                    name: "name",
+                    value: Some(v),
+                    ..
+                }) if !v.content.is_empty() => name = SlotName::Static(v.content),
+                ElemProp::Dir(dir) if is_slot_name_prop(prop) && !dir.has_empty_expr() => {
+                    name = SlotName::Dynamic(dir.expression.as_ref().unwrap());
+                }
+                _ => (),
+            }
+        }
+        name
+    }
+    /// Props of `<slot>` outlet forwarded as slot props, i.e. all but name.
+    pub fn slot_outlet_props(&self) -> impl Iterator<Item = &ElemProp<'a>> {
+        self.properties.iter().filter(|p| !is_slot_name_prop(p))
+    }
+    /// Source text of the start tag, from `<` to `>` inclusive.
+    /// `source` must be the template this element is parsed from.
+    /// NB: the end is found by source since AST does not record it.
+    pub fn start_tag_source<'s>(&self, source: &'s str) -> &'s str {
+        let start = self.location.start.offset;
+        let after_props = self
+            .properties
+            .iter()
+            .map(|p| p.get_location().end.offset)
+            .max()
+            .unwrap_or(start + 1 + self.tag_name.len());
+        // skip quoted values of duplicate attributes removed from props
+        let mut quote = None;
+        let rest = &source[after_props..];
+        let len = rest
+            .char_indices()
+            .find(|&(_, c)| match quote {
+                Some(q) if q == c => {
+                    quote = None;
+                    false
+                }
+                Some(_) => false,
+                None if c == '"' || c == '\'' => {
+                    quote = Some(c);
+                    false
+                }
+                None => c == '>',
+            })
+            .map_or(rest.len(), |(i, _)| i + 1);
+        &source[start..after_props + len]
+    }
+    pub fn dirs(&self) -> impl Iterator<Item = &Directive<'a>> {
+        self.properties.iter().filter_map(|p| match p {
+            ElemProp::Dir(dir) => Some(dir),
+            _ => None,
+        })
+    }
+    pub fn dirs_mut(&mut self) -> impl Iterator<Item = &mut Directive<'a>> {
+        self.properties.iter_mut().filter_map(|p| match p {
+            ElemProp::Dir(dir) => Some(dir),
+            _ => None,
+        })
+    }
 }
 
 /// Directive supports two forms
@@ -193,6 +381,11 @@ pub struct Directive<'a> {
     pub expression: Option<AttributeValue<'a>>,
     pub head_loc: SourceLocation,
     pub location: SourceLocation,
+    /// Location of the argument in head, including brackets of dynamic argument.
+    pub arg_loc: Option<SourceLocation>,
+    /// Locations of modifiers, parallel to `modifiers`. The `prop` modifier
+    /// implied by `.` shorthand is located at the dot.
+    pub modifier_locs: Vec<SourceLocation>,
 }
 
 impl<'a> Directive<'a> {
@@ -201,6 +394,27 @@ impl<'a> Directive<'a> {
             .as_ref()
             .map_or(true, |v| !v.content.contains(non_whitespace))
     }
+    /// `:user-name` without value, whose expression is the camelized argument.
+    pub fn is_same_name_shorthand(&self) -> bool {
+        match (&self.expression, &self.arg_loc) {
+            (Some(v), Some(loc)) => v.location == *loc,
+            _ => false,
+        }
+    }
+    /// `v-bind="obj"` or `v-on="obj"`, which spreads object as props or handlers.
+    pub fn is_spread(&self) -> bool {
+        (self.name == "bind" || self.name == "on") && self.argument.is_none()
+    }
+    /// Returns how v-on handler is written, or None if it is not v-on or has no expression.
+    /// See `util::classify_handler`.
+    pub fn handler_type(&self) -> Option<HandlerType> {
+        if self.name != "on" || self.has_empty_expr() {
+            return None;
+        }
+        self.expression
+            .as_ref()
+            .map(|v| classify_handler(v.content.raw))
+    }
     /// Returns the error if expression is empty
     pub fn check_empty_expr(&self, kind: ErrorKind) -> Option<CompilationError> {
         if !self.has_empty_expr() {
@@ -214,10 +428,87 @@ impl<'a> Directive<'a> {
     }
 }
 
-#[cfg_attr(feature = "serde", derive(Serialize))]
-pub struct AstRoot<'a> {
-    pub children: Vec<AstNode<'a>>,
+#[cfg_attr(feature = "serde", derive(Serialize), serde(bound = ""))]
+pub struct AstRoot<'a, A: AstAlloc<'a> = Heap> {
+    #[cfg_attr(feature = "serde", serde(serialize_with = "alloc::serialize_slice"))]
+    pub children: A::Vec<AstNode<'a, A>>,
     pub location: SourceLocation,
+    /// Content between `---` fences, only if FrontmatterMode::Capture.
+    /// Location covers the fences.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub frontmatter: Option<SourceNode<'a>>,
+}
+
+impl<'a, A: AstAlloc<'a>> AstRoot<'a, A> {
+    pub fn to_owned_ast(&self) -> ast_owned::AstRoot {
+        self.into()
+    }
+    /// Number of nodes in the tree, excluding frontmatter.
+    pub fn node_count(&self) -> usize {
+        let mut count = 0;
+        let mut stack: Vec<&[AstNode<'a, A>]> = vec![&self.children];
+        while let Some(nodes) = stack.pop() {
+            count += nodes.len();
+            stack.extend(
+                nodes
+                    .iter()
+                    .filter_map(|n| Some(&n.get_element()?.children[..])),
+            );
+        }
+        count
+    }
+    /// Finds the node by a depth first traversal, so it still works after
+    /// transforms reorder nodes as long as ids are not reassigned.
+    pub fn find_by_id(&self, id: NodeId) -> Option<&AstNode<'a, A>> {
+        let mut stack: Vec<_> = self.children.iter().rev().collect();
+        while let Some(node) = stack.pop() {
+            if node.id() == id {
+                return Some(node);
+            }
+            if let AstNode::Element(e) = node {
+                stack.extend(e.children.iter().rev());
+            }
+        }
+        None
+    }
+    /// Elements directly under root, including ones with v-if.
+    pub fn root_elements(&self) -> impl Iterator<Item = &Element<'a, A>> {
+        self.children.iter().filter_map(AstNode::get_element)
+    }
+    /// Returns the only root element if comments and whitespace are ignored.
+    /// e.g. for scoped attribute inheritance and `<Transition>` validation.
+    pub fn single_root(&self) -> Option<&Element<'a, A>> {
+        let i = self.single_root_index()?;
+        self.children[i].get_element()
+    }
+    pub fn single_root_mut(&mut self) -> Option<&mut Element<'a, A>> {
+        let i = self.single_root_index()?;
+        self.children[i].get_element_mut()
+    }
+    /// If the template renders more than one root node.
+    pub fn is_fragment(&self) -> bool {
+        self.children
+            .iter()
+            .filter(|n| renders_root(n))
+            .nth(1)
+            .is_some()
+    }
+    fn single_root_index(&self) -> Option<usize> {
+        let mut roots = (0..self.children.len()).filter(|&i| renders_root(&self.children[i]));
+        let i = roots.next()?;
+        if roots.next().is_some() {
+            return None;
+        }
+        Some(i)
+    }
+}
+
+fn renders_root<'a, A: AstAlloc<'a>>(node: &AstNode<'a, A>) -> bool {
+    match node {
+        AstNode::Comment(_) | AstNode::ProcessingInstruction(_) => false,
+        AstNode::Text(t) => !t.is_all_whitespace(),
+        AstNode::Element(_) | AstNode::Interpolation(_) => true,
+    }
 }
 
 #[derive(Clone, Default)]
@@ -227,44 +518,138 @@ pub enum WhitespaceStrategy {
     Condense,
 }
 
+/// Upper bounds for pathological inputs.
+#[derive(Clone)]
+pub struct ParseLimits {
+    /// Max text tokens merged into one TextNode. Exceeding text starts a new TextNode,
+    /// so two consecutive TextNodes can appear only under this limit.
+    pub max_text_segments: usize,
+    /// Max depth of open elements, None for no limit. Deeper elements are parsed
+    /// as self-closing so that recursive AST consumers do not overflow the stack.
+    /// NB: converter, transformer and codegen recurse per nesting level, so a
+    /// template at the default limit needs more than 8MiB stack in debug build.
+    pub max_nesting_depth: Option<usize>,
+}
+
+impl Default for ParseLimits {
+    fn default() -> Self {
+        Self {
+            max_text_segments: u16::MAX as usize,
+            max_nesting_depth: Some(4096),
+        }
+    }
+}
+
 // `is_xxx` methods in ParseOption targets different audience.
 // Please refer to project README for more details.
 #[derive(Clone)]
 pub struct ParseOption {
     pub whitespace: WhitespaceStrategy,
-    pub preserve_comment: bool,
+    /// Returns if a comment should be kept in AST, given the comment text.
+    pub comment_filter: StrPredicate,
+    /// Returns the namespace of a tag, given its open parent element.
+    /// NB: the parent has no children if parsed by `Parser::parse_in`.
     pub get_namespace: fn(&str, Option<&Element<'_>>) -> Namespace,
-    pub get_text_mode: fn(&str) -> TextMode,
+    /// Should be the same as ScanOption::get_text_mode.
+    pub get_text_mode: fn(&Tag<'_>) -> TextMode,
     /// Returns if a tag is self closing.
-    pub is_void_tag: fn(&str) -> bool,
-    // probably we don't need configure pre tag?
-    // in original Vue this is only used for parsing SFC.
-    pub is_pre_tag: fn(&str) -> bool,
+    pub is_void_tag: StrPredicate,
+    /// Pre tags keep whitespace and trim one leading newline, e.g. `pre` and
+    /// `textarea` in HTML. SFC parsing regards every tag as pre.
+    pub is_pre_tag: StrPredicate,
+    /// Returns if a tag renders inline, e.g. `span`. In Condense mode, newline
+    /// whitespace between two inline elements is kept as a single space.
+    pub is_inline_tag: StrPredicate,
     /// Exposed to end user for customization like importing web-component from React.
-    pub is_custom_element: fn(&str) -> bool,
+    pub is_custom_element: StrPredicate,
     /// For platform developers. Registers platform specific components written in JS.
     /// e.g. transition, transition-group. Components that require code in Vue runtime.
     pub get_builtin_component: fn(&str) -> Option<RuntimeHelper>,
     /// For platform developer. Registers platform components written in host language like C++.
-    pub is_native_element: fn(&str) -> bool,
+    pub is_native_element: StrPredicate,
+    /// Follows browser behavior for stray `</br>` and `</p>`: insert an empty element.
+    pub recover_stray_end_tag: bool,
+    /// Keeps `<?xml ?>` like processing instructions as distinct AST nodes
+    /// instead of bogus comments.
+    pub keep_processing_instructions: bool,
+    /// Keeps comments inside pre regardless of comment_filter.
+    pub preserve_all_in_pre: bool,
+    /// Reports why an element is or is not a component as Info diagnostic.
+    pub explain_classification: bool,
+    /// Warns uppercase static arguments of v-bind and v-on. Set it for
+    /// in-DOM templates, whose attribute names are lowercased by browsers.
+    pub v_bind_prop_case_check: bool,
+    /// Vue 2 compat: `is="comp"` on plain elements without `vue:` prefix
+    /// also makes a component, with a deprecation warning.
+    pub v2_compat_is: bool,
+    /// Warns v-for elements without key. Keys on children of `<template v-for>`
+    /// are always warned.
+    pub warn_missing_vfor_key: bool,
+    /// Warns components like `<Div>` that shadow native elements, judged by
+    /// is_native_element, and reports misused `<component>`, `<slot>` or `<template>`.
+    pub strict_components: bool,
+    /// Reports v-model values that cannot be assigned, e.g. `a + b`. Converters
+    /// report them on compile, so it is for tools that only parse.
+    pub check_v_model: bool,
+    /// Returns if a custom directive name is registered. If set, directives that
+    /// are neither built-in nor registered are warned as UnknownDirective.
+    pub is_known_directive: Option<StrPredicate>,
+    /// Keeps frontmatter in AstRoot if Capture. Scanner should use the same mode.
+    pub frontmatter: FrontmatterMode,
+    /// Decodes HTML entities in text and attribute values. Directive expressions
+    /// are JS source and are never decoded. Raw text like `<style>` and interpolations are never decoded.
+    /// Platforms without HTML entities can disable it to keep raw source text.
+    pub decode_entities: bool,
+    pub limits: ParseLimits,
 }
 
 impl Default for ParseOption {
     fn default() -> Self {
         Self {
             whitespace: WhitespaceStrategy::Condense,
-            preserve_comment: true,
+            comment_filter: yes.into(),
             get_namespace: |_, _| Namespace::Html,
             get_text_mode: |_| TextMode::Data,
-            is_void_tag: no,
-            is_pre_tag: |s| s == "pre",
-            is_custom_element: no,
+            is_void_tag: no.into(),
+            is_pre_tag: (|s: &str| s == "pre").into(),
+            is_inline_tag: no.into(),
+            is_custom_element: no.into(),
             get_builtin_component: |_| None,
-            is_native_element: yes,
+            is_native_element: yes.into(),
+            recover_stray_end_tag: false,
+            keep_processing_instructions: false,
+            preserve_all_in_pre: false,
+            explain_classification: false,
+            v_bind_prop_case_check: false,
+            v2_compat_is: false,
+            warn_missing_vfor_key: false,
+            strict_components: false,
+            check_v_model: false,
+            is_known_directive: None,
+            frontmatter: FrontmatterMode::None,
+            decode_entities: true,
+            limits: ParseLimits::default(),
+        }
+    }
+}
+
+impl ParseOption {
+    /// Keeps either all comments or none of them.
+    pub fn with_preserve_comment(preserve_comment: bool) -> Self {
+        Self {
+            comment_filter: if preserve_comment { yes } else { no }.into(),
+            ..Default::default()
         }
     }
 }
 
+/// Parser builds template AST from a token source.
+/// NB: Parser is `Send + Sync` by construction: it holds only ParseOption,
+/// whose hooks are fn pointers or `Send + Sync` predicates, and all per-parse state lives in AstBuilder.
+/// So a long-lived parser can be shared by `Arc<Parser>` across threads.
+/// Scanner gives the same guarantee. Converter and Transformer do not since
+/// they own an `RcErrHandle`, so they should be constructed per thread.
+/// New option fields or caches must keep the guarantee (use Arc/Mutex/RwLock, not Rc/RefCell).
 pub struct Parser {
     option: ParseOption,
 }
@@ -277,49 +662,102 @@ impl Parser {
     pub fn parse<'a, Ts>(&self, tokens: Ts, err_handle: RcErrHandle) -> AstRoot<'a>
     where
         Ts: TokenSource<'a>,
+    {
+        self.build(tokens, err_handle, Heap)
+    }
+
+    /// Same as parse, but children of root and elements are allocated in the arena.
+    /// Dropping the AST frees nothing, the arena frees it all at once.
+    /// Use `to_owned_ast` or `parse` if the AST is compiled by converters.
+    #[cfg(feature = "bumpalo")]
+    pub fn parse_in<'a, Ts>(
+        &self,
+        tokens: Ts,
+        err_handle: RcErrHandle,
+        arena: &'a bumpalo::Bump,
+    ) -> AstRoot<'a, Arena<'a>>
+    where
+        Ts: TokenSource<'a>,
+    {
+        self.build(tokens, err_handle, Arena(arena))
+    }
+
+    fn build<'a, Ts, A>(&self, tokens: Ts, err_handle: RcErrHandle, alloc: A) -> AstRoot<'a, A>
+    where
+        Ts: TokenSource<'a>,
+        A: AstAlloc<'a>,
     {
         let need_flag_namespace = tokens.need_flag_hint();
         AstBuilder {
             tokens,
             err_handle,
             option: self.option.clone(),
+            alloc,
             open_elems: vec![],
-            root_nodes: vec![],
+            open_text_modes: vec![],
+            root_nodes: alloc.new_vec(),
+            frontmatter: None,
             pre_count: 0,
             v_pre_index: None,
             need_flag_namespace,
+            text_limit_reported: false,
+            force_closed: vec![],
+            reached_eof: false,
+            too_deep: vec![],
+            open_index: FxHashMap::default(),
         }
         .build_ast()
     }
 }
 
 // TODO: remove Eh as generic
-struct AstBuilder<'a, Ts>
+struct AstBuilder<'a, Ts, A>
 where
     Ts: TokenSource<'a>,
+    A: AstAlloc<'a>,
 {
     tokens: Ts,
     err_handle: RcErrHandle,
     option: ParseOption,
-    open_elems: Vec<Element<'a>>,
-    root_nodes: Vec<AstNode<'a>>,
+    alloc: A,
+    open_elems: Vec<Element<'a, A>>,
+    // text mode of each element in open_elems, decided by its start tag
+    open_text_modes: Vec<TextMode>,
+    root_nodes: A::Vec<AstNode<'a, A>>,
+    frontmatter: Option<SourceNode<'a>>,
     // how many <pre> already met
     pre_count: usize,
-    // the idx of v-pre boundary in open_elems
-    // NB: idx is enough since v-pre does not nest
+    // the idx of the outermost v-pre boundary in open_elems
+    // NB: idx is enough since nested v-pre is parsed as plain attribute
     v_pre_index: Option<usize>,
     need_flag_namespace: bool,
+    // report exceeding text segments only once
+    text_limit_reported: bool,
+    // tag names and namespaces of elements closed without end tag by the last
+    // end tag. their end tags appearing later are not reported twice.
+    force_closed: Vec<(&'a str, Namespace)>,
+    // elements closed after all tokens are consumed are closed by EOF
+    reached_eof: bool,
+    // tag names and namespaces of elements beyond max_nesting_depth.
+    // they are parsed as self-closing and their end tags are swallowed.
+    too_deep: Vec<(&'a str, Namespace)>,
+    // indexes in open_elems keyed by case-insensitive tag name, for end tag matching.
+    open_index: FxHashMap<TagKey<'a>, SmallVec<[usize; 2]>>,
 }
 
 // utility method
-impl<'a, Ts> AstBuilder<'a, Ts>
+impl<'a, Ts, A> AstBuilder<'a, Ts, A>
 where
     Ts: TokenSource<'a>,
+    A: AstAlloc<'a>,
 {
     // Insert node into current insertion point.
     // It's the last open element's children if open_elems is not empty.
     // Otherwise it is root_nodes.
-    fn insert_node(&mut self, node: AstNode<'a>) {
+    fn insert_node(&mut self, node: AstNode<'a, A>) {
+        if let AstNode::Element(elem) = &node {
+            self.check_dangling_else(elem);
+        }
         if let Some(elem) = self.open_elems.last_mut() {
             elem.children.push(node);
         } else {
@@ -327,34 +765,75 @@ where
         }
     }
 
+    // v-else/v-else-if must follow v-if/v-else-if. Comments and whitespace in between are fine.
+    fn check_dangling_else(&self, elem: &Element<'a, A>) {
+        let dir = match dir_finder(elem, ["else-if", "else"]).allow_empty().find() {
+            Some(dir) => dir,
+            None => return,
+        };
+        // v-if with v-else starts a new chain, reported as VIfDuplicateDir
+        if dir_finder(elem, "if").allow_empty().find().is_some() {
+            return;
+        }
+        let siblings = match self.open_elems.last() {
+            Some(parent) => &parent.children,
+            None => &self.root_nodes,
+        };
+        let prev = siblings.iter().rev().find(|n| renders_root(n));
+        let follows_if = prev.and_then(|n| n.get_element()).map(|e| {
+            dir_finder(e, ["if", "else-if"])
+                .allow_empty()
+                .find()
+                .is_some()
+        });
+        if follows_if != Some(true) {
+            let loc = dir.get_ref().head_loc.clone();
+            self.emit_error(ErrorKind::VElseNoAdjacentIf, loc);
+        }
+    }
+
     fn emit_error(&self, kind: ErrorKind, loc: SourceLocation) {
         let error = CompilationError::new(kind).with_location(loc);
         self.err_handle.on_error(error)
     }
+
+    fn get_namespace(&mut self, tag_name: &str) -> Namespace {
+        let get_namespace = self.option.get_namespace;
+        match self.open_elems.last_mut() {
+            Some(parent) => A::with_heap_element(parent, |p| get_namespace(tag_name, Some(p))),
+            None => get_namespace(tag_name, None),
+        }
+    }
 }
 
 // parse logic
-impl<'a, Ts> AstBuilder<'a, Ts>
+impl<'a, Ts, A> AstBuilder<'a, Ts, A>
 where
     Ts: TokenSource<'a>,
+    A: AstAlloc<'a>,
 {
-    fn build_ast(mut self) -> AstRoot<'a> {
+    fn build_ast(mut self) -> AstRoot<'a, A> {
         let start = self.tokens.current_position();
         while let Some(token) = self.tokens.next() {
             self.parse_token(token);
         }
         self.report_unclosed_script_comment();
+        self.reached_eof = true;
         for _ in 0..self.open_elems.len() {
             self.close_element(/*has_matched_end*/ false);
         }
-        debug_assert_eq!(self.pre_count, 0);
-        debug_assert!(self.v_pre_index.is_none());
-        let need_condense = self.need_condense();
-        compress_whitespaces(&mut self.root_nodes, need_condense);
+        // every open element is closed above, so both should be reset already
+        self.pre_count = 0;
+        if self.v_pre_index.take().is_some() {
+            self.tokens.set_is_in_v_pre(false);
+        }
+        compress_whitespaces(&mut self.root_nodes, &self.option);
+        assign_node_ids(&mut self.root_nodes);
         let location = self.tokens.get_location_from(start);
         AstRoot {
             children: self.root_nodes,
             location,
+            frontmatter: self.frontmatter,
         }
     }
 
@@ -365,69 +844,128 @@ where
             Token::Text(text) => self.parse_text(text),
             Token::StartTag(tag) => self.parse_open_tag(tag),
             Token::Comment(c) => self.parse_comment(c),
+            Token::ProcessingInstruction(p) => self.parse_processing_instruction(p),
             Token::Interpolation(i) => self.parse_interpolation(i),
+            Token::CData(c) => self.parse_cdata(c),
+            Token::Frontmatter(f) => self.parse_frontmatter(f),
         };
     }
     fn parse_open_tag(&mut self, tag: Tag<'a>) {
+        self.force_closed.clear();
+        let text_mode = (self.option.get_text_mode)(&tag);
         let Tag {
             name,
             self_closing,
             attributes,
         } = tag;
         let props = self.parse_attributes(attributes);
-        let ns = (self.option.get_namespace)(name, self.open_elems.last());
+        self.check_v_if_with_v_for(&props);
+        if self.option.v_bind_prop_case_check {
+            self.check_prop_case(&props);
+        }
+        let ns = self.get_namespace(name);
         let elem = Element {
             tag_name: name,
             tag_type: ElementType::Plain,
             namespace: ns,
             properties: props,
-            children: vec![],
+            children: self.alloc.new_vec(),
             location: SourceLocation {
                 start: self.tokens.last_position(),
                 end: self.tokens.current_position(),
             },
+            id: Default::default(),
         };
         if self_closing || (self.option.is_void_tag)(name) {
             let node = self.parse_element(elem);
             self.insert_node(node);
+        } else if matches!(
+            self.option.limits.max_nesting_depth,
+            Some(max) if self.open_elems.len() >= max
+        ) {
+            if self.too_deep.is_empty() {
+                let loc = elem.location.clone();
+                self.emit_error(ErrorKind::MaxNestingDepthExceeded, loc);
+            }
+            self.too_deep.push((name, ns));
+            let node = self.parse_element(elem);
+            self.insert_node(node);
         } else {
             // only element with childen needs set pre/v-pre.
             // self-closing element cancels out pre itself.
+            // NB: its own attributes are already kept by parse_attributes.
             self.handle_pre_like(&elem);
+            let indexes = self.open_index.entry(TagKey(name)).or_default();
+            indexes.push(self.open_elems.len());
             self.open_elems.push(elem);
+            self.open_text_modes.push(text_mode);
             self.set_scanner_flag();
         }
     }
-    fn parse_attributes(&mut self, mut attrs: Vec<Attribute<'a>>) -> Vec<ElemProp<'a>> {
+    fn parse_attributes(&mut self, attrs: Vec<Attribute<'a>>) -> Vec<ElemProp<'a>> {
         // in v-pre, parse no directive
         if self.v_pre_index.is_some() {
             return attrs.into_iter().map(ElemProp::Attr).collect();
         }
-        let mut dir_parser = DirectiveParser::new(&self.err_handle);
-        // v-pre precedes any other directives
-        for i in 0..attrs.len() {
-            if attrs[i].name != "v-pre" {
-                continue;
-            }
-            let dir = dir_parser.parse(attrs.remove(i));
-            let mut ret = vec![ElemProp::Dir(dir)];
-            ret.extend(attrs.into_iter().map(ElemProp::attr));
-            return ret;
+        let decode = self.option.decode_entities;
+        let is_known = self.option.is_known_directive.as_ref();
+        let mut dir_parser = DirectiveParser::new(&self.err_handle, is_known);
+        // v-pre precedes any other directives, but keeps its source position
+        if attrs.iter().any(|a| a.name == "v-pre") {
+            return attrs
+                .into_iter()
+                .map(|a| {
+                    if a.name == "v-pre" {
+                        ElemProp::Dir(dir_parser.parse(a))
+                    } else {
+                        ElemProp::attr(a, decode)
+                    }
+                })
+                .collect();
         }
-        attrs
+        let splits: SmallVec<[bool; 8]> = (0..attrs.len())
+            .map(|i| attrs[i].value.is_none() && has_rest_of_split_arg(&attrs[i + 1..]))
+            .collect();
+        let props: Vec<_> = attrs
             .into_iter()
-            .map(|attr| {
+            .zip(splits)
+            .map(|(attr, split)| {
                 if dir_parser.detect_directive(&attr) {
                     // TODO: report duplicate prop by is_mergeable_prop
+                    dir_parser.maybe_split = split;
                     ElemProp::Dir(dir_parser.parse(attr))
                 } else {
-                    ElemProp::attr(attr)
+                    ElemProp::attr(attr, decode)
                 }
             })
-            .collect()
+            .collect();
+        self.report_duplicate_slots(&props);
+        props
+    }
+    // e.g. <comp #default v-slot:default/>. dynamic slot names are not comparable.
+    fn report_duplicate_slots(&self, props: &[ElemProp<'a>]) {
+        let mut seen = vec![];
+        let slots = props.iter().filter_map(|p| match p {
+            ElemProp::Dir(dir) if dir.name == "slot" => Some(dir),
+            _ => None,
+        });
+        for dir in slots {
+            let name = match &dir.argument {
+                None => "default",
+                Some(DirectiveArg::Static(name)) => name,
+                Some(DirectiveArg::Dynamic(_)) => continue,
+            };
+            if seen.contains(&name) {
+                let error = CompilationError::new(ErrorKind::VSlotDuplicateSlotNames)
+                    .with_location(dir.head_loc.clone());
+                self.err_handle.on_error(error);
+            } else {
+                seen.push(name);
+            }
+        }
     }
 
-    fn handle_pre_like(&mut self, elem: &Element) {
+    fn handle_pre_like(&mut self, elem: &Element<'a, A>) {
         debug_assert!(
             self.open_elems
                 .last()
@@ -438,35 +976,83 @@ where
         if (self.option.is_pre_tag)(elem.tag_name) {
             self.pre_count += 1;
         }
-        // open_v_pre
-        if is_v_pre_boundary(elem) {
-            debug_assert!(self.v_pre_index.is_none());
+        // open_v_pre, inner v-pre is not a boundary
+        if self.v_pre_index.is_none() && is_v_pre_boundary(elem) {
             self.v_pre_index = Some(self.open_elems.len());
+            self.tokens.set_is_in_v_pre(true);
         }
     }
     fn parse_end_tag(&mut self, end_tag: &'a str) {
-        // rfind is good since only mismatch will traverse stack
-        let index = self
-            .open_elems
-            .iter()
-            .enumerate()
-            .rfind(|p| element_matches_end_tag(p.1, end_tag))
-            .map(|p| p.0);
+        if let Some((name, ns)) = self.too_deep.last() {
+            if tag_matches(name, ns, end_tag) {
+                self.too_deep.pop();
+                return;
+            }
+        }
+        // candidates share the lowercase name. foreign content may still mismatch case
+        let index = self.open_index.get(&TagKey(end_tag)).and_then(|indexes| {
+            let open_elems = &self.open_elems;
+            let matches = |&i: &usize| element_matches_end_tag(&open_elems[i], end_tag);
+            indexes.iter().rev().copied().find(matches)
+        });
         if let Some(i) = index {
+            self.force_closed.clear();
+            self.too_deep.clear();
             let mut to_close = self.open_elems.len() - i;
             while to_close > 0 {
                 to_close -= 1;
+                if to_close > 0 {
+                    let e = self.open_elems.last().unwrap();
+                    self.force_closed.push((e.tag_name, e.namespace));
+                }
                 self.close_element(to_close == 0);
             }
             debug_assert_eq!(self.open_elems.len(), i);
-        } else {
-            let start = self.tokens.last_position();
-            let loc = self.tokens.get_location_from(start);
-            self.emit_error(ErrorKind::InvalidEndTag, loc);
+            return;
         }
+        // e.g. <div><span></div></span>, MissingEndTag is already reported on span
+        let closed = self
+            .force_closed
+            .iter()
+            .rposition(|(name, ns)| tag_matches(name, ns, end_tag));
+        if let Some(i) = closed {
+            self.force_closed.remove(i);
+            return;
+        }
+        let start = self.tokens.last_position();
+        let loc = self.tokens.get_location_from(start);
+        self.emit_error(ErrorKind::InvalidEndTag, loc.clone());
+        let is_br_or_p = end_tag.eq_ignore_ascii_case("br") || end_tag.eq_ignore_ascii_case("p");
+        if self.option.recover_stray_end_tag && is_br_or_p {
+            self.insert_stray_element(end_tag, loc);
+        }
+    }
+    // https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-inbody
+    // An end tag whose tag name is "br" or "p" with no open p
+    fn insert_stray_element(&mut self, tag_name: &'a str, location: SourceLocation) {
+        let namespace = self.get_namespace(tag_name);
+        let elem = Element {
+            tag_name,
+            tag_type: ElementType::Plain,
+            namespace,
+            properties: vec![],
+            children: self.alloc.new_vec(),
+            location,
+            id: Default::default(),
+        };
+        let node = self.parse_element(elem);
+        self.insert_node(node);
     }
     fn close_element(&mut self, has_matched_end: bool) {
         let mut elem = self.open_elems.pop().unwrap();
+        let key = TagKey(elem.tag_name);
+        if let Some(indexes) = self.open_index.get_mut(&key) {
+            indexes.pop();
+            if indexes.is_empty() {
+                self.open_index.remove(&key);
+            }
+        }
+        let text_mode = self.open_text_modes.pop().unwrap();
         self.set_scanner_flag();
         let start = elem.location.start;
         if !has_matched_end {
@@ -475,31 +1061,44 @@ where
                 start: start.clone(),
                 end: start.clone(),
             };
-            self.emit_error(ErrorKind::MissingEndTag, err_location);
+            // EOF, or the start of the ancestor end tag being parsed
+            let closed_at = if self.reached_eof {
+                self.tokens.current_position()
+            } else {
+                self.tokens.last_position()
+            };
+            let closed_location = SourceLocation {
+                start: closed_at.clone(),
+                end: closed_at,
+            };
+            let error = CompilationError::new(ErrorKind::MissingEndTag)
+                .with_location(err_location)
+                .with_related("element was implicitly closed here", closed_location);
+            self.err_handle.on_error(error);
         }
         let location = self.tokens.get_location_from(start);
         elem.location = location;
-        if self.pre_count > 0 {
+        // pre is decremented on every close path: matched end tag, force
+        // close by an outer end tag and close at EOF all come here.
+        if (self.option.is_pre_tag)(elem.tag_name) {
             self.decrement_pre(&mut elem)
-        } else if (self.option.get_text_mode)(elem.tag_name) == TextMode::Data {
-            // skip compress in pre or RAWTEXT/RCDATA
-            compress_whitespaces(&mut elem.children, self.need_condense());
+        } else {
+            match text_mode {
+                // skip compress in pre or RAWTEXT/RCDATA
+                TextMode::Data if self.pre_count == 0 => {
+                    compress_whitespaces(&mut elem.children, &self.option)
+                }
+                _ => (),
+            }
         }
         let node = self.parse_element(elem);
         self.insert_node(node);
     }
-    fn decrement_pre(&mut self, elem: &mut Element) {
-        debug_assert!(self.pre_count > 0);
-        let pre_boundary = (self.option.is_pre_tag)(elem.tag_name);
+    fn decrement_pre(&mut self, elem: &mut Element<'a, A>) {
         // trim pre tag's leading new line
         // https://html.spec.whatwg.org/multipage/syntax.html#element-restrictions
-        if !pre_boundary {
-            return;
-        }
-        if let Some(AstNode::Text(tn)) = elem.children.last_mut() {
-            tn.trim_leading_newline();
-        }
-        self.pre_count -= 1;
+        trim_leading_newline(elem);
+        self.pre_count = self.pre_count.saturating_sub(1);
     }
     fn close_v_pre(&mut self) {
         let idx = self.v_pre_index.unwrap();
@@ -507,11 +1106,13 @@ where
         // met v-pre boundary, switch back
         if idx == self.open_elems.len() {
             self.v_pre_index = None;
+            self.tokens.set_is_in_v_pre(false);
         }
     }
-    fn parse_element(&mut self, mut elem: Element<'a>) -> AstNode<'a> {
+    fn parse_element(&mut self, mut elem: Element<'a, A>) -> AstNode<'a, A> {
         debug_assert!(elem.tag_type == ElementType::Plain);
-        if self.v_pre_index.is_some() {
+        let in_v_pre = self.v_pre_index.is_some();
+        if in_v_pre {
             debug_assert!({
                 let i = *self.v_pre_index.as_ref().unwrap();
                 i != self.open_elems.len() || is_v_pre_boundary(&elem)
@@ -522,53 +1123,301 @@ where
             elem.tag_type = ElementType::SlotOutlet;
         } else if is_template_element(&elem) {
             elem.tag_type = ElementType::Template;
-        } else if self.is_component(&elem) {
-            elem.tag_type = ElementType::Component;
+        } else {
+            let classified = classify_element(&elem, &self.option);
+            if self.option.explain_classification {
+                let error = CompilationError::new(ErrorKind::ElementClassified)
+                    .with_location(elem.location.clone())
+                    .with_additional_message(classified.rule.as_str());
+                self.err_handle.on_error(error);
+            }
+            if let ClassificationRule::IsAttrCompat = classified.rule {
+                let attr = find_is_attr(&elem).expect("compat rule must have is attr");
+                self.emit_error(ErrorKind::CompatIsAttribute, attr.location.clone());
+            }
+            elem.tag_type = classified.tag_type;
+            self.check_plain_template(&elem);
+            self.check_misplaced_slot(&elem);
+        }
+        if elem.tag_type == ElementType::SlotOutlet {
+            self.check_misplaced_slot(&elem);
+        }
+        self.check_v_for_key(&elem);
+        if self.option.strict_components && !in_v_pre {
+            self.check_strict_component(&elem);
+        }
+        if self.option.check_v_model && !in_v_pre {
+            self.check_v_model(&elem);
         }
         AstNode::Element(elem)
     }
+    // argument and file input on plain elements are platform errors, see DomError
+    fn check_v_model(&self, elem: &Element<'a, A>) {
+        let dir = match elem.dirs().find(|d| d.name == "model") {
+            Some(dir) => dir,
+            None => return,
+        };
+        let val = match &dir.expression {
+            Some(val) if !dir.has_empty_expr() => val,
+            _ => return,
+        };
+        let raw = val.content.raw.trim();
+        if !is_simple_identifier(VStr::raw(raw)) && !rslint::is_member_expression(raw) {
+            self.emit_error(ErrorKind::VModelValueNotWritable, val.location.clone());
+        }
+    }
+    fn check_strict_component(&self, elem: &Element<'a, A>) {
+        let tag = elem.tag_name;
+        let shadowed = elem.tag_type == ElementType::Component
+            && !is_component_tag(tag)
+            && tag.starts_with(|c: char| c.is_uppercase())
+            && !(self.option.is_custom_element)(tag)
+            && {
+                let lower = tag.to_ascii_lowercase();
+                lower == "slot" || lower == "template" || (self.option.is_native_element)(&lower)
+            };
+        if shadowed {
+            self.emit_error(ErrorKind::ComponentShadowsNativeTag, elem.location.clone());
+            return;
+        }
+        let has_is = find_prop(elem, "is").is_some() || elem.dirs().any(|d| d.name == "is");
+        let misuse = match tag {
+            "component" | "Component" if !has_is => " <component> needs `is` to render anything.",
+            "slot" if has_is => " <slot> does not take `is`. Use <component :is> instead.",
+            "template" if find_prop(elem, "name").is_some() => {
+                " <template name> does not declare a slot. Use v-slot:name instead."
+            }
+            _ => return,
+        };
+        let error = CompilationError::new(ErrorKind::BuiltinTagMisuse)
+            .with_location(elem.location.clone())
+            .with_additional_message(misuse);
+        self.err_handle.on_error(error);
+    }
+    // <template v-for> takes the key, not its children as in Vue 2.
+    fn check_v_for_key(&self, elem: &Element<'a, A>) {
+        let v_for = match elem.dirs().find(|d| d.name == "for") {
+            Some(dir) => dir,
+            None => return,
+        };
+        let has_key = find_prop(elem, "key").is_some();
+        let mut child_key = false;
+        if elem.tag_type == ElementType::Template {
+            let children = elem.children.iter().filter_map(|n| n.get_element());
+            for key in children.filter_map(|c| find_prop(c, "key")) {
+                child_key = true;
+                let loc = key.get_ref().get_location().clone();
+                self.emit_error(ErrorKind::VForTemplateKeyPlacement, loc);
+            }
+        }
+        if self.option.warn_missing_vfor_key && !has_key && !child_key {
+            self.emit_error(ErrorKind::MissingVForKey, v_for.location.clone());
+        }
+    }
+    // v-if is evaluated before v-for in Vue 3, unlike Vue 2.
+    fn check_v_if_with_v_for(&self, props: &[ElemProp<'a>]) {
+        let dirs = || {
+            props.iter().filter_map(|p| match p {
+                ElemProp::Dir(dir) => Some(dir),
+                _ => None,
+            })
+        };
+        if !dirs().any(|d| d.name == "for") {
+            return;
+        }
+        if let Some(dir) = dirs().find(|d| d.name == "if" || d.name == "else-if") {
+            let loc = dir.head_loc.clone();
+            self.emit_error(ErrorKind::VIfWithVFor, loc);
+        }
+    }
+    // e.g. `:fooBar` in in-DOM template is `:foobar`. `.camel` is written for this.
+    fn check_prop_case(&self, props: &[ElemProp<'a>]) {
+        for prop in props {
+            let dir = match prop {
+                ElemProp::Dir(dir) if dir.name == "bind" || dir.name == "on" => dir,
+                _ => continue,
+            };
+            let arg = match &dir.argument {
+                Some(DirectiveArg::Static(arg)) => arg,
+                _ => continue,
+            };
+            if dir.modifiers.contains(&"camel") || !arg.contains(|c: char| c.is_ascii_uppercase()) {
+                continue;
+            }
+            let loc = dir.arg_loc.clone().unwrap_or_else(|| dir.head_loc.clone());
+            self.emit_error(ErrorKind::UppercaseInDomAttribute, loc);
+        }
+    }
+    // Root <template> is a common wrapper, e.g. SFC template block.
+    // <template> is legit content in SVG, e.g. <svg><template/></svg>
+    fn check_plain_template(&self, elem: &Element<'a, A>) {
+        if elem.tag_name != "template"
+            || elem.tag_type != ElementType::Plain
+            || elem.namespace != Namespace::Html
+            || self.open_elems.is_empty()
+        {
+            return;
+        }
+        self.emit_error(ErrorKind::TemplateNoSpecialDirective, elem.location.clone());
+    }
+    // v-slot is only allowed on <template> or component, e.g. <div #foo/> is wrong.
+    fn check_misplaced_slot(&self, elem: &Element<'a, A>) {
+        if elem.is_component() {
+            return;
+        }
+        if let Some(dir) = elem.dirs().find(|d| d.name == "slot") {
+            let error = CompilationError::new(ErrorKind::VSlotMisplaced)
+                .with_location(dir.location.clone());
+            self.err_handle.on_error(error);
+        }
+    }
     fn parse_text(&mut self, text: VStr<'a>) {
-        let mut text = smallvec![text];
-        let mut next_token = None;
-        let start = self.tokens.last_position();
-        for token in &mut self.tokens {
-            if let Token::Text(ds) = token {
-                text.push(ds);
-            } else {
-                next_token = Some(token);
-                break;
+        self.parse_text_segments(smallvec![text]);
+    }
+    fn parse_text_segments(&mut self, mut text: SmallVec<[VStr<'a>; 1]>) {
+        let mut start = self.tokens.last_position();
+        let comment_filter = self.option.comment_filter.clone();
+        let keep_all = self.keeps_all_comments();
+        let comment_filter = |c: &str| keep_all || comment_filter(c);
+        let keep_pi = self.option.keep_processing_instructions;
+        // outside pre, removed comment is a placeholder until whitespaces are compressed
+        let merge_removed = self.pre_count > 0;
+        let max_segments = self.option.limits.max_text_segments;
+        let decode = self.option.decode_entities;
+        // NB: loop instead of recursion since text may be split many times
+        loop {
+            loop {
+                // interpolation in v-pre takes three segments
+                let v_pre_room = self.v_pre_index.is_some() && text.len() + 3 <= max_segments;
+                match self.tokens.peek() {
+                    Some(Token::Text(_)) if text.len() < max_segments => {}
+                    Some(Token::Interpolation(_)) if v_pre_room => {}
+                    // merge texts around removed comment as if it never existed
+                    Some(Token::Comment(c)) if merge_removed && !comment_filter(c) => {}
+                    Some(Token::ProcessingInstruction(p))
+                        if merge_removed && !keep_pi && !comment_filter(p) => {}
+                    _ => break,
+                }
+                match self.tokens.next() {
+                    Some(Token::Text(ds)) => text.push(ds),
+                    Some(Token::Interpolation(src)) => text.extend(interpolation_as_text(src)),
+                    _ => (),
+                }
+            }
+            // peeking does not move current_position past the end of text
+            let end = self.tokens.current_position();
+            let location = SourceLocation {
+                start,
+                end: end.clone(),
+            };
+            if !decode {
+                // scanner decodes text in Data and RcData mode
+                for s in text.iter_mut() {
+                    s.ops.remove(StrOps::DECODE_ENTITY);
+                }
+            }
+            let text_node = TextNode {
+                text: std::mem::take(&mut text),
+                location,
+                id: Default::default(),
+                is_cdata: false,
+            };
+            self.insert_node(AstNode::Text(text_node));
+            // other tokens are left to build_ast
+            let in_v_pre = self.v_pre_index.is_some();
+            match self.tokens.peek() {
+                Some(Token::Text(_)) => (),
+                Some(Token::Interpolation(_)) if in_v_pre => (),
+                _ => return,
             }
+            self.report_text_limit(&end);
+            start = end;
         }
-        let end = self.tokens.last_position();
-        let location = SourceLocation { start, end };
-        let text_node = TextNode { text, location };
-        self.insert_node(AstNode::Text(text_node));
-        // NB: token must not be dropped
-        if let Some(token) = next_token {
-            self.parse_token(token);
+    }
+    fn keeps_all_comments(&self) -> bool {
+        self.option.preserve_all_in_pre && self.pre_count > 0
+    }
+    fn report_text_limit(&mut self, pos: &Position) {
+        if self.text_limit_reported {
+            return;
         }
+        self.text_limit_reported = true;
+        let loc = SourceLocation {
+            start: pos.clone(),
+            end: pos.clone(),
+        };
+        self.emit_error(ErrorKind::TextSegmentLimitExceeded, loc);
     }
     fn parse_comment(&mut self, c: &'a str) {
-        // Remove comments if desired by configuration.
-        if !self.option.preserve_comment {
+        // Remove comments if desired by configuration. Outside pre they are
+        // removed after compress_whitespaces so that condensing is the same
+        // whether comments are kept or not.
+        let removed = !self.keeps_all_comments() && !(self.option.comment_filter)(c);
+        if removed && self.pre_count > 0 {
             return;
         }
         let pos = self.tokens.last_position();
         let source_node = SourceNode {
             source: c,
             location: self.tokens.get_location_from(pos),
+            id: Default::default(),
         };
         self.insert_node(AstNode::Comment(source_node));
     }
+    fn parse_processing_instruction(&mut self, p: &'a str) {
+        if !self.option.keep_processing_instructions {
+            return self.parse_comment(p);
+        }
+        let pos = self.tokens.last_position();
+        let content = p.strip_prefix('?').unwrap_or(p);
+        let source_node = SourceNode {
+            source: content,
+            location: self.tokens.get_location_from(pos),
+            id: Default::default(),
+        };
+        self.insert_node(AstNode::ProcessingInstruction(source_node));
+    }
+    fn parse_frontmatter(&mut self, src: &'a str) {
+        if self.option.frontmatter != FrontmatterMode::Capture {
+            return;
+        }
+        let pos = self.tokens.last_position();
+        self.frontmatter = Some(SourceNode {
+            source: src,
+            location: self.tokens.get_location_from(pos),
+            id: Default::default(),
+        });
+    }
     fn parse_interpolation(&mut self, src: &'a str) {
+        // FlagVPre makes scanner emit interpolation in v-pre as text,
+        // but other token sources may not respect the flag.
+        if self.v_pre_index.is_some() {
+            return self.parse_text_segments(interpolation_as_text(src).iter().copied().collect());
+        }
         let pos = self.tokens.last_position();
         let source_node = SourceNode {
             source: src,
             location: self.tokens.get_location_from(pos),
+            id: Default::default(),
         };
         self.insert_node(AstNode::Interpolation(source_node));
     }
 
+    fn parse_cdata(&mut self, src: &'a str) {
+        // scanner only emits CDATA outside HTML namespace, see set_scanner_flag
+        if src.is_empty() {
+            return;
+        }
+        let pos = self.tokens.last_position();
+        let text_node = TextNode {
+            text: smallvec![VStr::raw(src)],
+            location: self.tokens.get_location_from(pos),
+            id: Default::default(),
+            is_cdata: true,
+        };
+        self.insert_node(AstNode::Text(text_node));
+    }
+
     // https://html.spec.whatwg.org/multipage/parsing.html#parse-error-eof-in-script-html-comment-like-text
     fn report_unclosed_script_comment(&mut self) {
         debug_assert!(self.tokens.next().is_none());
@@ -586,6 +1435,8 @@ where
         // Netscape's legacy from 1995 when JS is nascent.
         // Even 4 years before Bizarre Summer(?v=UztXN2rKQNc).
         // https://stackoverflow.com/questions/808816/
+        // script text may arrive in several segments, e.g. a<!--b
+        let text = text.full_text();
         if text.contains("<!--") && !text.contains("-->") {
             let loc = SourceLocation {
                 start: self.tokens.last_position(),
@@ -598,7 +1449,7 @@ where
     // must call this when handle CDATA
     #[inline]
     fn set_scanner_flag(&mut self) {
-        if self.need_flag_namespace {
+        if !self.need_flag_namespace {
             return;
         }
         // TODO: we can set flag only when namespace changes
@@ -608,36 +1459,7 @@ where
             .map_or(true, |e| e.namespace == Namespace::Html);
         self.tokens.set_is_in_html(in_html)
     }
-
-    fn is_component(&self, e: &Element) -> bool {
-        let opt = &self.option;
-        let tag_name = e.tag_name;
-        if (opt.is_custom_element)(tag_name) {
-            return false;
-        }
-        if tag_name == "component"
-            || tag_name.starts_with(|c: char| c.is_ascii_uppercase())
-            || is_core_component(tag_name)
-            || (opt.get_builtin_component)(tag_name).is_some()
-            || !(opt.is_native_element)(tag_name)
-        {
-            return true;
-        }
-        e.properties.iter().any(|prop| match prop {
-            ElemProp::Dir(Directive { name: "is", .. }) => true,
-            ElemProp::Attr(Attribute {
-                name: "is",
-                value: Some(v),
-                ..
-            }) => v.content.starts_with("vue:"),
-            _ => false,
-        })
-    }
-
-    fn need_condense(&self) -> bool {
-        matches!(self.option.whitespace, WhitespaceStrategy::Condense)
-    }
-}
+}
 
 const BIND_CHAR: char = ':';
 const MOD_CHAR: char = '.';
@@ -646,27 +1468,52 @@ const SLOT_CHAR: char = '#';
 const SEP_BYTES: &[u8] = &[BIND_CHAR as u8, MOD_CHAR as u8];
 const SHORTHANDS: &[char] = &[BIND_CHAR, ON_CHAR, SLOT_CHAR, MOD_CHAR];
 const DIR_MARK: &str = "v-";
+const CORE_DIRECTIVES: &[&str] = &[
+    "bind", "on", "slot", "if", "else", "else-if", "for", "model", "show", "html", "text", "pre",
+    "once", "memo", "cloak",
+];
 
 type StrPair<'a> = (&'a str, &'a str);
 struct DirectiveParser<'a, 'b> {
     eh: &'b RcErrHandle,
+    is_known: Option<&'b StrPredicate>,
+    attr_name: &'a str,
     name_loc: SourceLocation,
     location: SourceLocation,
     cached: Option<StrPair<'a>>,
+    /// following attributes may be the rest of a dynamic arg split by whitespace.
+    maybe_split: bool,
 }
 impl<'a, 'b> DirectiveParser<'a, 'b> {
-    fn new(eh: &'b RcErrHandle) -> Self {
+    fn new(eh: &'b RcErrHandle, is_known: Option<&'b StrPredicate>) -> Self {
         Self {
             eh,
+            is_known,
+            attr_name: "",
             name_loc: Default::default(),
             location: Default::default(),
             cached: None,
+            maybe_split: false,
         }
     }
     fn attr_name_err(&self, kind: ErrorKind) {
         let error = CompilationError::new(kind).with_location(self.name_loc.clone());
         self.eh.on_error(error);
     }
+    // Reports error at `s`, a slice of the attribute name.
+    fn name_slice_err(&self, kind: ErrorKind, s: &str) {
+        let error = CompilationError::new(kind).with_location(self.name_slice_loc(s));
+        self.eh.on_error(error);
+    }
+    fn name_slice_loc(&self, s: &str) -> SourceLocation {
+        let offset = s.as_ptr() as usize - self.attr_name.as_ptr() as usize;
+        debug_assert!(offset + s.len() <= self.attr_name.len());
+        let mut start = self.name_loc.start.clone();
+        start.advance(&self.attr_name[..offset]);
+        let mut end = start.clone();
+        end.advance(s);
+        SourceLocation { start, end }
+    }
     fn detect_directive(&mut self, attr: &Attribute<'a>) -> bool {
         debug_assert!(self.cached.is_none());
         self.cached = self.detect_dir_name(attr);
@@ -674,6 +1521,7 @@ impl<'a, 'b> DirectiveParser<'a, 'b> {
     }
     fn set_location(&mut self, attr: &Attribute<'a>) {
         self.location = attr.location.clone();
+        self.attr_name = attr.name;
         self.name_loc = attr.name_loc.clone();
     }
 
@@ -686,9 +1534,24 @@ impl<'a, 'b> DirectiveParser<'a, 'b> {
         let is_v_slot = name == "slot";
         let (arg_str, mods_str) = self.split_arg_and_mods(prefixed, is_v_slot, is_prop);
         let argument = self.parse_directive_arg(arg_str);
-        let modifiers = self.parse_directive_mods(mods_str, is_prop);
+        let arg_loc = argument.as_ref().map(|_| self.name_slice_loc(arg_str));
+        // .prop shorthand without argument is reported and has no implicit prop
+        let has_prop = is_prop && argument.is_some();
+        let (modifiers, modifier_locs) = self.parse_directive_mods(mods_str, has_prop);
         self.cached = None; // cleanup
-        let expression = Self::trim_attr_value(attr.value);
+        self.maybe_split = false;
+        let mut expression = Self::trim_attr_value(attr.value);
+        if expression.is_none() && name == "bind" {
+            expression = same_name_shorthand(&argument, &arg_loc);
+            if let Some(val) = &expression {
+                if is_js_reserved_word(&val.content.into_string()) {
+                    let loc = val.location.clone();
+                    let error = CompilationError::new(ErrorKind::VBindShorthandReservedWord);
+                    self.eh.on_error(error.with_location(loc));
+                }
+            }
+        }
+        self.check_unknown(name, &attr.name_loc);
         Directive {
             name,
             argument,
@@ -696,7 +1559,21 @@ impl<'a, 'b> DirectiveParser<'a, 'b> {
             expression,
             head_loc: attr.name_loc,
             location: attr.location,
+            arg_loc,
+            modifier_locs,
+        }
+    }
+    fn check_unknown(&self, name: &str, head_loc: &SourceLocation) {
+        let is_known = match self.is_known {
+            Some(is_known) => is_known,
+            None => return,
+        };
+        if CORE_DIRECTIVES.contains(&name) || is_known(name) {
+            return;
         }
+        let error =
+            CompilationError::new(ErrorKind::UnknownDirective).with_location(head_loc.clone());
+        self.eh.on_error(error);
     }
     // NB: this function sets self's location so it's mut.
     fn detect_dir_name(&mut self, attr: &Attribute<'a>) -> Option<StrPair<'a>> {
@@ -735,7 +1612,7 @@ impl<'a, 'b> DirectiveParser<'a, 'b> {
             return ("", "");
         }
         if prefixed.len() == 1 {
-            self.attr_name_err(ErrorKind::MissingDirectiveArg);
+            self.name_slice_err(ErrorKind::MissingDirectiveArg, prefixed);
             return ("", "");
         }
         let remain = &prefixed[1..];
@@ -744,7 +1621,7 @@ impl<'a, 'b> DirectiveParser<'a, 'b> {
         if is_v_slot {
             if prefixed.starts_with(MOD_CHAR) {
                 // only . can end dir_name, e.g. v-slot.error
-                self.attr_name_err(ErrorKind::InvalidVSlotModifier);
+                self.name_slice_err(ErrorKind::InvalidVSlotModifier, prefixed);
                 ("", prefixed)
             } else {
                 debug_assert!(prefixed.starts_with(&[SLOT_CHAR, BIND_CHAR][..]));
@@ -758,24 +1635,27 @@ impl<'a, 'b> DirectiveParser<'a, 'b> {
         } else {
             debug_assert!(!prefixed.starts_with(SLOT_CHAR));
             // handle .prop shorthand elsewhere
-            remain
+            let (arg, mods) = remain
                 .bytes()
                 .position(|u| u == MOD_CHAR as u8)
                 .map(|i| remain.split_at(i))
-                .unwrap_or((remain, ""))
+                .unwrap_or((remain, ""));
+            // e.g. `..foo`, the first dot expects an argument
+            if arg.is_empty() {
+                self.name_slice_err(ErrorKind::MissingDirectiveArg, &prefixed[..1]);
+            }
+            (arg, mods)
         }
     }
     fn split_dynamic_arg(&self, remain: &'a str) -> (&'a str, &'a str) {
-        // dynamic arg
-        let bytes = remain.as_bytes();
-        let end = bytes
-            .iter()
-            .position(|b| *b == b']')
-            .map_or(bytes.len(), |i| i + 1);
+        // dynamic arg, unbalanced arg is reported in parse_directive_arg
+        let end = dynamic_arg_end(remain).map_or(remain.len(), |i| i + 1);
         let (arg, mut mods) = remain.split_at(end);
         if mods.starts_with(|c| c != MOD_CHAR) {
-            self.attr_name_err(ErrorKind::UnexpectedContentAfterDynamicDirective);
-            mods = mods.trim_start_matches(|c| c != MOD_CHAR);
+            let rest = mods.trim_start_matches(|c| c != MOD_CHAR);
+            let content = &mods[..mods.len() - rest.len()];
+            self.name_slice_err(ErrorKind::UnexpectedContentAfterDynamicDirective, content);
+            mods = rest;
         }
         (arg, mods)
     }
@@ -785,42 +1665,75 @@ impl<'a, 'b> DirectiveParser<'a, 'b> {
         }
         Some(if !arg.starts_with('[') {
             DirectiveArg::Static(arg)
-        } else if let Some(i) = arg.chars().position(|c| c == ']') {
-            debug_assert!(i == arg.len() - 1);
-            DirectiveArg::Dynamic(&arg[1..i])
+        } else if let Some(i) = dynamic_arg_end(arg) {
+            // v-slot arg is not split by split_dynamic_arg, e.g. #[a]b
+            if i != arg.len() - 1 {
+                let kind = ErrorKind::UnexpectedContentAfterDynamicDirective;
+                self.name_slice_err(kind, &arg[i + 1..]);
+            }
+            let content = &arg[1..i];
+            // e.g. :[]="x". The directive is kept as if no argument is given.
+            if content.trim().is_empty() {
+                self.name_slice_err(ErrorKind::MissingDirectiveArg, &arg[..=i]);
+                return None;
+            }
+            // quotes are fine as long as they enclose string literals, e.g. :['a]b']
+            if content.contains(char::is_whitespace) {
+                self.name_slice_err(ErrorKind::InvalidDynamicArgument, &arg[..=i]);
+            }
+            DirectiveArg::Dynamic(content)
+        } else if self.maybe_split {
+            // the rest of the arg is parsed as following attributes
+            let error = CompilationError::new(ErrorKind::InvalidDynamicArgument)
+                .with_location(self.name_slice_loc(arg))
+                .with_additional_message(
+                    " The attribute name is split at whitespace and the rest of the argument is parsed as another attribute.",
+                );
+            self.eh.on_error(error);
+            DirectiveArg::Dynamic(&arg[1..])
         } else {
-            self.attr_name_err(ErrorKind::MissingDynamicDirectiveArgumentEnd);
+            self.name_slice_err(ErrorKind::MissingDynamicDirectiveArgumentEnd, arg);
             DirectiveArg::Dynamic(&arg[1..])
         })
     }
     // TODO: check duplicate modifiers
-    fn parse_directive_mods(&self, mods: &'a str, is_prop: bool) -> Vec<&'a str> {
+    fn parse_directive_mods(
+        &self,
+        mods: &'a str,
+        is_prop: bool,
+    ) -> (Vec<&'a str>, Vec<SourceLocation>) {
         debug_assert!(mods.is_empty() || mods.starts_with(MOD_CHAR));
-        let report_missing_mod = |s: &&str| {
-            if s.is_empty() {
-                self.attr_name_err(ErrorKind::MissingDirectiveMod);
-            }
-        };
-        let mut ret = if mods.is_empty() {
+        let mut ret: Vec<_> = if mods.is_empty() {
             vec![]
         } else {
-            mods[1..]
-                .as_bytes()
-                .split(|b| *b == b'.')
-                .map(std::str::from_utf8) // use unsafe if too slow
-                .map(Result::unwrap)
-                .inspect(report_missing_mod)
-                .collect()
+            mods[1..].split(MOD_CHAR).collect()
         };
+        let mut locs: Vec<_> = ret.iter().map(|m| self.name_slice_loc(m)).collect();
+        for (m, loc) in ret.iter().zip(&locs) {
+            if m.is_empty() {
+                let error = CompilationError::new(ErrorKind::MissingDirectiveMod)
+                    .with_location(loc.clone());
+                self.eh.on_error(error);
+            }
+        }
         if is_prop {
-            ret.push("prop")
+            ret.push("prop");
+            locs.push(self.name_slice_loc(&self.attr_name[..1]));
         }
-        ret
+        (ret, locs)
     }
 
-    fn trim_attr_value(attr_val: Option<AttributeValue>) -> Option<AttributeValue> {
+    fn trim_attr_value(attr_val: Option<AttributeValue<'a>>) -> Option<AttributeValue<'a>> {
         if let Some(mut val) = attr_val {
-            val.content.raw = val.content.raw.trim();
+            let raw = val.content.raw;
+            let trimmed = raw.trim();
+            // empty expression keeps the full value span for error report
+            if !trimmed.is_empty() {
+                val.location = trimmed_location(&val.location, raw, trimmed);
+            }
+            // expression is JS source that is prefixed and validated by slices,
+            // so it is kept undecoded regardless of ParseOption::decode_entities
+            val.content.raw = trimmed;
             Some(val)
         } else {
             None
@@ -828,64 +1741,302 @@ impl<'a, 'b> DirectiveParser<'a, 'b> {
     }
 }
 
-fn compress_whitespaces(nodes: &mut Vec<AstNode>, need_condense: bool) {
-    // no two consecutive Text node, ensured by parse_text
-    debug_assert!({
-        let no_consecutive_text = |last_is_text, is_text| {
-            if last_is_text && is_text {
-                None
-            } else {
-                Some(is_text)
+/// `:user-name` is short for `:user-name="userName"` since Vue 3.4.
+/// Only static argument can be expanded.
+fn same_name_shorthand<'a>(
+    arg: &Option<DirectiveArg<'a>>,
+    arg_loc: &Option<SourceLocation>,
+) -> Option<AttributeValue<'a>> {
+    let (arg, location) = match (arg, arg_loc) {
+        (Some(DirectiveArg::Static(arg)), Some(loc)) => (arg, loc.clone()),
+        _ => return None,
+    };
+    let mut content = VStr::raw(arg);
+    content.camelize();
+    Some(AttributeValue {
+        content,
+        location,
+        quote: QuoteKind::Unquoted,
+    })
+}
+
+/// Parses a single attribute into Directive without scanning a template,
+/// e.g. `v-on:click.prevent` and `do()`. Returns None if name is not a
+/// directive. Value is trimmed as the parser does, and
+/// regarded as double quoted.
+/// Errors are located at `name_loc`. The directive's location spans from
+/// `name_loc` to `val_loc`, or is `name_loc` if there is no value.
+/// Expression location is `val_loc` narrowed to the trimmed value.
+pub fn parse_directive<'a>(
+    name: &'a str,
+    value: Option<&'a str>,
+    name_loc: SourceLocation,
+    val_loc: SourceLocation,
+) -> Option<Result<Directive<'a>, Vec<CompilationError>>> {
+    use crate::error::VecErrorHandler;
+    let location = SourceLocation {
+        start: name_loc.start.clone(),
+        end: if value.is_some() {
+            val_loc.end.clone()
+        } else {
+            name_loc.end.clone()
+        },
+    };
+    let attr = Attribute {
+        name,
+        value: value.map(|v| AttributeValue {
+            content: VStr::raw(v),
+            location: val_loc,
+            quote: QuoteKind::Double,
+        }),
+        name_loc,
+        location,
+    };
+    let eh = std::rc::Rc::new(VecErrorHandler::new());
+    let rc_eh: RcErrHandle = eh.clone();
+    let mut dir_parser = DirectiveParser::new(&rc_eh, None);
+    let dir = if dir_parser.detect_directive(&attr) {
+        Some(dir_parser.parse(attr))
+    } else {
+        None
+    };
+    let errors = std::mem::take(&mut *eh.error_mut());
+    match dir {
+        _ if !errors.is_empty() => Some(Err(errors)),
+        Some(dir) => Some(Ok(dir)),
+        None => None,
+    }
+}
+
+/// Locates `trimmed`, a slice of `raw`, given `loc` of the raw value.
+fn trimmed_location(loc: &SourceLocation, raw: &str, trimmed: &str) -> SourceLocation {
+    let mut start = loc.start.clone();
+    let leading = trimmed.as_ptr() as usize - raw.as_ptr() as usize;
+    start.advance(&raw[..leading]);
+    let mut end = start.clone();
+    end.advance(trimmed);
+    SourceLocation { start, end }
+}
+
+// e.g. `:[a + b]="c"` is scanned as `:[a`, `+` and `b]="c"`.
+fn has_rest_of_split_arg(following: &[Attribute]) -> bool {
+    for attr in following {
+        if attr.name.contains(']') {
+            return true;
+        }
+        if attr.value.is_some() {
+            return false;
+        }
+    }
+    false
+}
+
+/// Returns index of the `]` matching the leading `[` of a dynamic argument.
+/// Nested brackets must balance and brackets in string literals are skipped.
+fn dynamic_arg_end(arg: &str) -> Option<usize> {
+    debug_assert!(arg.starts_with('['));
+    let mut depth = 0;
+    let mut quote = None;
+    for (i, b) in arg.bytes().enumerate() {
+        match (quote, b) {
+            (Some(q), _) if q == b => quote = None,
+            (Some(_), _) => (),
+            (None, b'\'' | b'"' | b'`') => quote = Some(b),
+            (None, b'[') => depth += 1,
+            (None, b']') => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
             }
-        };
-        nodes
-            .iter()
-            .map(|n| matches!(n, AstNode::Text(_)))
-            .try_fold(false, no_consecutive_text)
-            .is_some()
-    });
-    let mut i = 0;
-    while i < nodes.len() {
-        let should_remove = if let AstNode::Text(child) = &nodes[i] {
-            use AstNode as A;
-            if !child.is_all_whitespace() {
+            _ => (),
+        }
+    }
+    None
+}
+
+// Token sources do not tell delimiters, so the default ones are assumed.
+fn interpolation_as_text(src: &str) -> [VStr<'_>; 3] {
+    [VStr::raw("{{"), VStr::raw(src), VStr::raw("}}")]
+}
+
+fn compress_whitespaces<'a, A, V>(nodes: &mut V, option: &ParseOption)
+where
+    A: AstAlloc<'a>,
+    V: AstVec<AstNode<'a, A>>,
+{
+    let need_condense = matches!(option.whitespace, WhitespaceStrategy::Condense);
+    // no two consecutive Text node, ensured by parse_text
+    // unless the former one reaches ParseLimits::max_text_segments or is CDATA
+    debug_assert!(nodes.windows(2).all(|w| match w {
+        [AstNode::Text(prev), AstNode::Text(next)] => {
+            prev.text.len() >= option.limits.max_text_segments || prev.is_cdata || next.is_cdata
+        }
+        _ => true,
+    }));
+    // Compacts kept nodes to the front in one pass. Decisions only look at
+    // the last kept node and the next unvisited node, same as removing in place.
+    let len = nodes.len();
+    let mut kept = 0;
+    for i in 0..len {
+        let (should_remove, should_compress) = if let AstNode::Text(child) = &nodes[i] {
+            if child.is_cdata {
+                // CDATA is literal content
+                (false, false)
+            } else if !child.is_all_whitespace() {
                 // non empty text node
-                if need_condense {
-                    compress_text_node(&mut nodes[i]);
-                }
-                false
-            } else if i == nodes.len() - 1 || i == 0 {
+                (false, need_condense)
+            } else if i == len - 1 || kept == 0 {
                 // Remove the leading/trailing whitespace
-                true
+                (true, false)
             } else if !need_condense {
-                false
+                (false, false)
             } else {
-                // Condense mode remove whitespaces between comment and
-                // whitespaces with contains newline between two elements
-                let prev = &nodes[i - 1];
+                // Condense mode remove whitespaces next to comment, unless the
+                // other side is text, and whitespaces with newline between two elements
+                // unless both are inline, where whitespace renders as a space.
+                // Whitespaces next to interpolation always render as a space.
+                let prev = &nodes[kept - 1];
                 let next = &nodes[i + 1];
-                match (prev, next) {
-                    (A::Comment(_), A::Comment(_)) => true,
-                    _ => is_element(prev) && is_element(next) && child.contains(&['\r', '\n'][..]),
+                if is_interpolation(prev) || is_interpolation(next) {
+                    (false, true)
+                } else if is_comment_like(prev) && (is_comment_like(next) || is_element(next))
+                    || is_element(prev) && is_comment_like(next)
+                {
+                    (true, false)
+                } else if is_element(prev) && is_element(next) && child.contains_newline() {
+                    let is_inline = |n: &AstNode<'a, A>| {
+                        let e = n.get_element().unwrap();
+                        (option.is_inline_tag)(e.tag_name)
+                    };
+                    let both_inline = is_inline(prev) && is_inline(next);
+                    (!both_inline, both_inline)
+                } else {
+                    (false, false)
                 }
             }
         } else {
-            false
+            (false, false)
         };
         if should_remove {
-            nodes.remove(i);
-        } else {
-            i += 1;
+            continue;
+        }
+        if should_compress {
+            compress_text_node(&mut nodes[i]);
+        }
+        nodes.swap(kept, i);
+        kept += 1;
+    }
+    nodes.truncate(kept);
+    remove_filtered_comments(nodes, option);
+}
+
+// Removes comment placeholders left by parse_comment and merges
+// the texts around them as if the comments never existed.
+fn remove_filtered_comments<'a, A, V>(nodes: &mut V, option: &ParseOption)
+where
+    A: AstAlloc<'a>,
+    V: AstVec<AstNode<'a, A>>,
+{
+    let max_segments = option.limits.max_text_segments;
+    let need_condense = matches!(option.whitespace, WhitespaceStrategy::Condense);
+    let mut kept = 0;
+    let mut removed = false;
+    for i in 0..nodes.len() {
+        if let AstNode::Comment(c) = &nodes[i] {
+            if !(option.comment_filter)(c.source) {
+                removed = true;
+                continue;
+            }
+        }
+        let after_removed = std::mem::take(&mut removed);
+        if kept > 0 {
+            let (head, tail) = nodes.split_at_mut(i);
+            if let (AstNode::Text(prev), AstNode::Text(next)) = (&mut head[kept - 1], &mut tail[0])
+            {
+                if prev.text.len() + next.text.len() <= max_segments {
+                    // whitespaces on both sides of the comment condense to one
+                    let ends_with_space = prev
+                        .text
+                        .last()
+                        .is_some_and(|s| !s.ends_with(non_whitespace));
+                    if need_condense && after_removed && ends_with_space && !next.is_cdata {
+                        trim_start_whitespace(&mut next.text);
+                    }
+                    prev.text.extend(next.text.drain(..));
+                    prev.location.end = next.location.end.clone();
+                    continue;
+                }
+            }
+        }
+        nodes.swap(kept, i);
+        kept += 1;
+    }
+    nodes.truncate(kept);
+}
+
+fn trim_start_whitespace(text: &mut SmallVec<[VStr; 1]>) {
+    while let Some(first) = text.first_mut() {
+        let trimmed = first.raw.trim_start_matches(|c: char| !non_whitespace(c));
+        if !trimmed.is_empty() {
+            first.raw = trimmed;
+            return;
+        }
+        text.remove(0);
+    }
+}
+
+// Numbers nodes in pre-order. NB: iterative since nesting can be deep.
+fn assign_node_ids<'a, A: AstAlloc<'a>>(nodes: &mut [AstNode<'a, A>]) {
+    let mut next = 0;
+    let mut stack = vec![nodes.iter_mut()];
+    while let Some(iter) = stack.last_mut() {
+        let node = match iter.next() {
+            Some(node) => node,
+            None => {
+                stack.pop();
+                continue;
+            }
+        };
+        let id = NodeId(next);
+        next += 1;
+        match node {
+            AstNode::Element(e) => {
+                e.id = id;
+                stack.push(e.children.iter_mut());
+            }
+            AstNode::Text(t) => t.id = id,
+            AstNode::Interpolation(n) | AstNode::Comment(n) | AstNode::ProcessingInstruction(n) => {
+                n.id = id
+            }
         }
     }
 }
 
+// A text node of only the newline is kept empty, as Vue does, so that
+// its location still records the newline for printing.
+fn trim_leading_newline<'a, A: AstAlloc<'a>>(elem: &mut Element<'a, A>) {
+    if let Some(AstNode::Text(tn)) = elem.children.first_mut() {
+        tn.trim_leading_newline();
+    }
+}
+
 #[inline]
-fn is_element(n: &AstNode) -> bool {
+fn is_element<'a, A: AstAlloc<'a>>(n: &AstNode<'a, A>) -> bool {
     n.get_element().is_some()
 }
 
-fn compress_text_node(n: &mut AstNode) {
+#[inline]
+fn is_interpolation<'a, A: AstAlloc<'a>>(n: &AstNode<'a, A>) -> bool {
+    matches!(n, AstNode::Interpolation(_))
+}
+
+#[inline]
+fn is_comment_like<'a, A: AstAlloc<'a>>(n: &AstNode<'a, A>) -> bool {
+    matches!(n, AstNode::Comment(_) | AstNode::ProcessingInstruction(_))
+}
+
+fn compress_text_node<'a, A: AstAlloc<'a>>(n: &mut AstNode<'a, A>) {
     if let AstNode::Text(src) = n {
         for s in src.text.iter_mut() {
             s.compress_whitespace();
@@ -895,6 +2046,112 @@ fn compress_text_node(n: &mut AstNode) {
     }
 }
 
+/// The rule deciding if an element is a component, in the order of precedence.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub enum ClassificationRule {
+    /// ParseOption::is_custom_element. Plain element.
+    CustomElement,
+    /// `<component>`
+    ExplicitComponentTag,
+    /// e.g. `<MyComp>`, `<Éditeur>` or `<My-Comp>`. Unicode uppercase counts.
+    UppercaseTag,
+    /// e.g. `<Teleport>`, `<keep-alive>`
+    CoreComponent,
+    /// ParseOption::get_builtin_component, e.g. `<transition>` in DOM.
+    BuiltinComponent,
+    /// ParseOption::is_native_element returns false.
+    NotNativeElement,
+    /// `v-is` or `is="vue:comp"`.
+    IsAttrVuePrefix,
+    /// `is="comp"` with ParseOption::v2_compat_is.
+    IsAttrCompat,
+    /// None of the above. Plain element.
+    DefaultPlain,
+}
+
+impl ClassificationRule {
+    pub fn as_str(&self) -> &'static str {
+        use ClassificationRule::*;
+        match self {
+            CustomElement => "CustomElement",
+            ExplicitComponentTag => "ExplicitComponentTag",
+            UppercaseTag => "UppercaseTag",
+            CoreComponent => "CoreComponent",
+            BuiltinComponent => "BuiltinComponent",
+            NotNativeElement => "NotNativeElement",
+            IsAttrVuePrefix => "IsAttrVuePrefix",
+            IsAttrCompat => "IsAttrCompat",
+            DefaultPlain => "DefaultPlain",
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct ElementClassification {
+    pub tag_type: ElementType,
+    pub rule: ClassificationRule,
+}
+
+/// Decides if an element is a component or a plain element.
+/// NB: v-pre, `<slot>` and `<template>` are resolved before this by the parser.
+pub fn classify_element<'a, A: AstAlloc<'a>>(
+    e: &Element<'a, A>,
+    opt: &ParseOption,
+) -> ElementClassification {
+    use ClassificationRule as R;
+    let tag_name = e.tag_name;
+    let rule = if (opt.is_custom_element)(tag_name) {
+        R::CustomElement
+    } else if tag_name == "component" {
+        R::ExplicitComponentTag
+    } else if tag_name.starts_with(|c: char| c.is_uppercase()) {
+        R::UppercaseTag
+    } else if is_core_component(tag_name) {
+        R::CoreComponent
+    } else if (opt.get_builtin_component)(tag_name).is_some() {
+        R::BuiltinComponent
+    } else if !(opt.is_native_element)(tag_name) {
+        R::NotNativeElement
+    } else if e.properties.iter().any(is_vue_is_prop) {
+        R::IsAttrVuePrefix
+    } else if opt.v2_compat_is && find_is_attr(e).is_some() {
+        R::IsAttrCompat
+    } else {
+        R::DefaultPlain
+    };
+    let tag_type = match rule {
+        R::CustomElement | R::DefaultPlain => ElementType::Plain,
+        _ => ElementType::Component,
+    };
+    ElementClassification { tag_type, rule }
+}
+
+fn is_vue_is_prop(prop: &ElemProp) -> bool {
+    match prop {
+        ElemProp::Dir(Directive { name: "is", .. }) => true,
+        ElemProp::Attr(Attribute {
+            name: "is",
+            value: Some(v),
+            ..
+        }) => v.content.starts_with("vue:"),
+        _ => false,
+    }
+}
+
+fn find_is_attr<'a, 'b, A: AstAlloc<'a>>(e: &'b Element<'a, A>) -> Option<&'b Attribute<'a>> {
+    e.properties.iter().find_map(|p| match p {
+        ElemProp::Attr(
+            attr @ Attribute {
+                name: "is",
+                value: Some(_),
+                ..
+            },
+        ) => Some(attr),
+        _ => None,
+    })
+}
+
 fn is_special_template_directive(n: &str) -> bool {
     // we only have 5 elements to compare. == takes 2ns while phf takes 26ns
     match n.len() {
@@ -906,22 +2163,60 @@ fn is_special_template_directive(n: &str) -> bool {
     }
 }
 
-fn is_template_element(e: &Element) -> bool {
-    e.tag_name == "template" && find_dir(e, is_special_template_directive).is_some()
+fn is_template_element<'a, A: AstAlloc<'a>>(e: &Element<'a, A>) -> bool {
+    // directives like v-else or #slot have no expression
+    e.tag_name == "template"
+        && dir_finder(e, is_special_template_directive)
+            .allow_empty()
+            .find()
+            .is_some()
+}
+
+// ASCII case-insensitive tag name, hashed without allocating a lowercase copy.
+#[derive(Clone, Copy)]
+struct TagKey<'a>(&'a str);
+
+impl<'a> PartialEq for TagKey<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.eq_ignore_ascii_case(other.0)
+    }
+}
+impl<'a> Eq for TagKey<'a> {}
+impl<'a> Hash for TagKey<'a> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for b in self.0.bytes() {
+            state.write_u8(b.to_ascii_lowercase());
+        }
+        state.write_u8(0xff);
+    }
+}
+
+fn element_matches_end_tag<'a, A: AstAlloc<'a>>(e: &Element<'a, A>, tag: &str) -> bool {
+    tag_matches(e.tag_name, &e.namespace, tag)
 }
 
-fn element_matches_end_tag(e: &Element, tag: &str) -> bool {
-    e.tag_name.eq_ignore_ascii_case(tag)
+// tag names are case-sensitive in foreign content like SVG, e.g. clipPath.
+fn tag_matches(name: &str, ns: &Namespace, tag: &str) -> bool {
+    if *ns == Namespace::Html {
+        name.eq_ignore_ascii_case(tag)
+    } else {
+        name == tag
+    }
 }
 
-fn is_v_pre_boundary(elem: &Element) -> bool {
-    find_dir(elem, "pre").is_some()
+fn is_v_pre_boundary<'a, A: AstAlloc<'a>>(elem: &Element<'a, A>) -> bool {
+    // v-pre has no expression
+    dir_finder(elem, "pre").allow_empty().find().is_some()
 }
 
 #[cfg(test)]
 pub mod test {
     use super::*;
-    use crate::{cast, error::test::TestErrorHandler, scanner::test::base_scan};
+    use crate::{
+        cast,
+        error::test::TestErrorHandler,
+        scanner::{test::base_scan, Locatable, Peekable, ScanOption, Scanner, VecTokenSource},
+    };
 
     #[test]
     fn test_parse_text() {
@@ -939,6 +2234,34 @@ pub mod test {
         assert_eq!(v.source, "world");
     }
     #[test]
+    fn test_no_decode_entities() {
+        let case = r#"<p decode='&amp;' :a="b &amp;&amp; c"/>"#;
+        let parser = Parser::new(ParseOption {
+            decode_entities: false,
+            ..Default::default()
+        });
+        let eh = std::rc::Rc::new(TestErrorHandler);
+        let ast = parser.parse(base_scan(case), eh);
+        let p = ast.children[0].get_element().unwrap();
+        let attr = cast!(&p.properties[0], ElemProp::Attr);
+        let val = attr.value.as_ref().unwrap();
+        assert_eq!(val.content.into_string(), "&amp;");
+        assert!(!val.is_decoded());
+        assert_eq!(val.decoded(), "&");
+        let dir = cast!(&p.properties[1], ElemProp::Dir);
+        let exp = dir.expression.as_ref().unwrap();
+        assert_eq!(exp.content.into_string(), "b &amp;&amp; c");
+        // attribute is decoded by default, but expression is never decoded
+        let p = mock_element(case);
+        let attr = cast!(&p.properties[0], ElemProp::Attr);
+        assert!(attr.value.as_ref().unwrap().is_decoded());
+        let dir = cast!(&p.properties[1], ElemProp::Dir);
+        let exp = dir.expression.as_ref().unwrap();
+        assert!(!exp.is_decoded());
+        assert_eq!(exp.content.into_string(), "b &amp;&amp; c");
+        assert_eq!(exp.decoded(), "b && c");
+    }
+    #[test]
     fn test_decode_attr() {
         let case = "<p decode='&amp;' />";
         let ast = base_parse(case);
@@ -950,19 +2273,1963 @@ pub mod test {
         let val = decode.value.unwrap().content;
         assert_eq!(val.into_string(), "&");
     }
+    #[test]
+    fn test_decode_numeric_ref() {
+        let case = "<p title='&#60;&#x3E;&#;'>&#x1F600;&#38&#x110000;</p>";
+        let p = mock_element(case);
+        let title = cast!(&p.properties[0], ElemProp::Attr);
+        let val = title.value.as_ref().unwrap();
+        assert_eq!(val.content.into_string(), "<>&#;");
+        let text = cast!(&p.children[0], AstNode::Text);
+        assert_eq!(text.full_text(), "😀&\u{fffd}");
+    }
+    #[test]
+    fn test_decode_text() {
+        let texts = |case: &str, decode_entities| {
+            let scanner = Scanner::new(ScanOption {
+                get_text_mode: preset::dom_get_text_mode,
+                decode_entities,
+                ..Default::default()
+            });
+            let parser = Parser::new(ParseOption {
+                decode_entities,
+                ..Default::default()
+            });
+            let eh = std::rc::Rc::new(TestErrorHandler);
+            let root = parser.parse(scanner.scan(case, eh.clone()), eh);
+            let p = root.children[0].get_element().unwrap();
+            p.children
+                .iter()
+                .filter_map(|c| match c {
+                    AstNode::Text(t) => Some(t.full_text().into_owned()),
+                    AstNode::Interpolation(i) => Some(format!("{{{{{}}}}}", i.source)),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        // Data
+        assert_eq!(texts("<p>Fish &amp; Chips</p>", true), ["Fish & Chips"]);
+        assert_eq!(
+            texts("<p>Fish &amp; Chips</p>", false),
+            ["Fish &amp; Chips"]
+        );
+        let case = "<p>{{ a &amp;&amp; b }}&lt;</p>";
+        assert_eq!(texts(case, true), ["{{ a &amp;&amp; b }}", "<"]);
+        // RcData
+        assert_eq!(texts("<textarea>&lt;b&gt;</textarea>", true), ["<b>"]);
+        assert_eq!(
+            texts("<textarea>&lt;b&gt;</textarea>", false),
+            ["&lt;b&gt;"]
+        );
+        // RawText
+        assert_eq!(texts("<style>a &amp; b</style>", true), ["a &amp; b"]);
+        // whitespace is condensed after decoding, but &nbsp; is not whitespace
+        assert_eq!(texts("<p>a&#32;&#32;b</p>", true), ["a b"]);
+        assert_eq!(
+            texts("<p> &nbsp;&nbsp; \n b</p>", true),
+            [" \u{a0}\u{a0} b"]
+        );
+        let nodes = texts("<p><b/>&nbsp;<b/>\n<b/></p>", true);
+        assert_eq!(nodes, ["\u{a0}"]);
+    }
 
-    pub fn base_parse(s: &str) -> AstRoot {
-        let tokens = base_scan(s);
+    #[test]
+    fn test_comment_filter() {
         let parser = Parser::new(ParseOption {
-            is_native_element: |s| s != "comp",
+            comment_filter: (|c: &str| c.starts_with('!')).into(),
             ..Default::default()
         });
+        let case = "<p/>\n<!-- drop -->\n<p/><!--! keep -->a<!-- drop -->b<p/>";
         let eh = std::rc::Rc::new(TestErrorHandler);
-        parser.parse(tokens, eh)
+        let mut children = parser.parse(base_scan(case), eh).children;
+        // whitespace between p is condensed as if the comment never existed
+        assert_eq!(children.len(), 5);
+        children.pop();
+        let ab = cast!(children.pop().unwrap(), AstNode::Text);
+        assert_eq!(ab.text.len(), 2);
+        assert_eq!(ab.location.start.offset, 37);
+        assert_eq!(ab.location.end.offset, case.len() - 4);
+        let keep = cast!(children.pop().unwrap(), AstNode::Comment);
+        assert_eq!(keep.source, "! keep ");
+        assert_eq!(keep.location.start.offset, 23);
+        assert!(children.into_iter().all(|n| n.get_element().is_some()));
+    }
+    #[test]
+    fn test_frontmatter() {
+        let parse = |s, mode| {
+            let option = ParseOption {
+                frontmatter: mode,
+                ..Default::default()
+            };
+            let opt = ScanOption {
+                frontmatter: mode,
+                ..Default::default()
+            };
+            let (root, errors) = scan_parse_with_errors(s, opt, option);
+            (root, errors.len())
+        };
+        let case = "---\ntitle: a < b\ntpl: '{{ c }}'\n---\n<p>{{ d }}</p>";
+        let (root, errors) = parse(case, FrontmatterMode::Capture);
+        assert_eq!(errors, 0);
+        let fm = root.frontmatter.unwrap();
+        assert_eq!(fm.source, "title: a < b\ntpl: '{{ c }}'");
+        assert_eq!(fm.location.start.offset, 0);
+        assert_eq!(fm.location.end.offset, 36);
+        assert_eq!(root.children.len(), 1);
+        let p = root.children[0].get_element().unwrap();
+        assert_eq!(p.location.start.offset, 36);
+        assert_eq!(p.location.start.line, 5);
+        assert_eq!(p.location.start.column, 1);
+        assert_eq!(p.children[0].get_location().start.offset, 39);
+        let (root, _) = parse(case, FrontmatterMode::Strip);
+        assert!(root.frontmatter.is_none());
+        assert_eq!(root.children.len(), 1);
+        let (root, _) = parse(case, FrontmatterMode::None);
+        assert!(root.children.len() > 1);
+        // unclosed fence falls back to text
+        let (root, errors) = parse("---\na: <b/>", FrontmatterMode::Capture);
+        assert_eq!(errors, 1);
+        assert!(root.frontmatter.is_none());
+        assert_eq!(root.children.len(), 2);
+    }
+    #[test]
+    fn test_preserve_comment_compat() {
+        let parser = Parser::new(ParseOption::with_preserve_comment(false));
+        let eh = std::rc::Rc::new(TestErrorHandler);
+        let children = parser.parse(base_scan("a<!--x-->"), eh).children;
+        assert_eq!(children.len(), 1);
+        let parser = Parser::new(ParseOption::with_preserve_comment(true));
+        let eh = std::rc::Rc::new(TestErrorHandler);
+        let children = parser.parse(base_scan("a<!--x-->"), eh).children;
+        assert_eq!(children.len(), 2);
+    }
+    #[test]
+    fn test_comment_agnostic_whitespace() {
+        let non_comments = |case, preserve| {
+            let parser = Parser::new(ParseOption::with_preserve_comment(preserve));
+            let eh = std::rc::Rc::new(TestErrorHandler);
+            let root = parser.parse(base_scan(case), eh);
+            let div = root.children[0].get_element().unwrap();
+            div.children
+                .iter()
+                .filter_map(|n| match n {
+                    AstNode::Comment(_) => None,
+                    AstNode::Text(t) => Some(t.text.iter().map(|s| s.into_string()).collect()),
+                    AstNode::Element(e) => Some(format!("<{}>", e.tag_name)),
+                    AstNode::Interpolation(i) => Some(i.source.to_string()),
+                    _ => panic!("unexpected node"),
+                })
+                .collect::<Vec<String>>()
+        };
+        for case in [
+            "<div><a/> <!-- c --> <span/></div>",
+            "<div><a/>\n  <!-- c -->\n  <span/></div>",
+            "<div>foo <!-- c --> <span/></div>",
+            "<div><a/> <!-- c -->{{ x }} bar</div>",
+            "<div>a <!-- c --> </div>",
+        ] {
+            assert_eq!(
+                non_comments(case, true),
+                non_comments(case, false),
+                "{}",
+                case
+            );
+        }
+        assert_eq!(
+            non_comments("<div><a/> <!-- c --> <span/></div>", false),
+            ["<a>", "<span>"]
+        );
+        // condensed across the removed comment
+        assert_eq!(non_comments("<div>a <!-- c --> b</div>", false), ["a b"]);
+        assert_eq!(non_comments("<div>a\n<!-- c -->\n b</div>", false), ["a b"]);
+        assert_eq!(non_comments("<div>a<!-- c --> b</div>", false), ["a b"]);
+    }
+    #[test]
+    fn test_text_around_removed_comment() {
+        let texts = |case, preserve| {
+            let parser = Parser::new(ParseOption::with_preserve_comment(preserve));
+            let eh = std::rc::Rc::new(TestErrorHandler);
+            let root = parser.parse(base_scan(case), eh);
+            root.children
+                .iter()
+                .map(|n| match n {
+                    AstNode::Text(t) => t.to_string(),
+                    AstNode::Comment(c) => format!("<!--{}-->", c.source),
+                    _ => panic!("unexpected node"),
+                })
+                .collect::<Vec<_>>()
+        };
+        let case = "a<!-- x -->b";
+        assert_eq!(texts(case, false), ["ab"]);
+        assert_eq!(texts(case, true), ["a", "<!-- x -->", "b"]);
+        let root = Parser::new(ParseOption::with_preserve_comment(false))
+            .parse(base_scan(case), std::rc::Rc::new(TestErrorHandler));
+        let text = cast!(&root.children[0], AstNode::Text);
+        assert_eq!(text.location.start.offset, 0);
+        assert_eq!(text.location.end.offset, case.len());
+    }
+    #[test]
+    fn test_html_document() {
+        use crate::error::ErrorKind as _;
+        let case = "<!DOCTYPE html>
+<html lang=\"en\">
+<head><meta charset=\"utf-8\"><title>T</title></head>
+<body><div>{{ a }}<![CDATA[ b > c ]]></div></body>
+</html>";
+        let parse = |preserve| {
+            let option = ParseOption {
+                comment_filter: if preserve { yes } else { no }.into(),
+                ..ParseOption::dom()
+            };
+            let scan = ScanOption {
+                get_text_mode: preset::dom_get_text_mode,
+                ..Default::default()
+            };
+            let (root, errors) = scan_parse_with_errors(case, scan, option);
+            let codes: Vec<_> = errors.iter().map(|e| e.code()).collect();
+            (root, codes)
+        };
+        let cdata = ErrorKind::CDataInHtmlContent.code();
+        let (root, codes) = parse(false);
+        assert_eq!(codes, [cdata]);
+        assert_eq!(root.children.len(), 1);
+        let html = root.children[0].get_element().unwrap();
+        let body = html.children[1].get_element().unwrap();
+        let div = body.children[0].get_element().unwrap();
+        assert_eq!(div.children.len(), 1);
+        let (root, codes) = parse(true);
+        assert_eq!(codes, [cdata]);
+        let doctype = cast!(&root.children[0], AstNode::Comment);
+        assert_eq!(doctype.source, "DOCTYPE html");
     }
+    #[test]
+    fn test_processing_instruction() {
+        let parse = |s, keep| {
+            let parser = Parser::new(ParseOption {
+                keep_processing_instructions: keep,
+                ..Default::default()
+            });
+            let eh = std::rc::Rc::new(TestErrorHandler);
+            parser.parse(base_scan(s), eh).children
+        };
+        let xml = "<?xml version=\"1.0\"?>\n<p/>";
+        let mut children = parse(xml, true);
+        // whitespace between comment-like node and element is removed
+        assert_eq!(children.len(), 2);
+        let pi = cast!(children.remove(0), AstNode::ProcessingInstruction);
+        assert_eq!(pi.source, "xml version=\"1.0\"?");
+        assert_eq!(pi.location.end.offset, 21);
+        // fallback to bogus comment as before
+        let mut children = parse(xml, false);
+        let c = cast!(children.remove(0), AstNode::Comment);
+        assert_eq!(c.source, "?xml version=\"1.0\"?");
 
-    pub fn mock_element(s: &str) -> Element {
-        let mut m = base_parse(s).children;
-        m.pop().unwrap().into_element()
+        let php = "<p/><?php echo 1 ?>\n<?php echo 2 ?><p/>";
+        let children = parse(php, true);
+        // whitespace between comment-like nodes is removed
+        assert_eq!(children.len(), 4);
+        let pi = cast!(&children[1], AstNode::ProcessingInstruction);
+        assert_eq!(pi.source, "php echo 1 ?");
+        assert_eq!(pi.location.start.offset, 4);
+    }
+
+    #[test]
+    fn test_text_segment_limit() {
+        use crate::error::Severity;
+        let option = ParseOption {
+            limits: ParseLimits {
+                max_text_segments: 4,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        // every lone { starts a new text token
+        let case = format!("<p>{}</p>", "{ &amp; ".repeat(10));
+        let (root, errors) = parse_tokens_with_errors(base_scan(&case), option);
+        let mut children = root.children;
+        let p = cast!(children.remove(0), AstNode::Element);
+        let segments: Vec<_> = p
+            .children
+            .iter()
+            .map(|n| cast!(n, AstNode::Text).text.len())
+            .collect();
+        assert_eq!(segments, [4, 4, 2]);
+        let rendered: String = p
+            .children
+            .iter()
+            .flat_map(|n| cast!(n, AstNode::Text).text.iter())
+            .map(|s| s.into_string())
+            .collect();
+        assert_eq!(rendered, "{ & ".repeat(10));
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].severity(), Severity::Info);
+    }
+    #[test]
+    fn test_recover_stray_end_tag() {
+        let parser = Parser::new(ParseOption {
+            recover_stray_end_tag: true,
+            ..Default::default()
+        });
+        let eh = std::rc::Rc::new(TestErrorHandler);
+        let children = parser
+            .parse(base_scan("<div></br></p></i></div>"), eh)
+            .children;
+        let div = children[0].get_element().unwrap();
+        let tags: Vec<_> = div
+            .children
+            .iter()
+            .map(|n| n.get_element().unwrap().tag_name)
+            .collect();
+        assert_eq!(tags, ["br", "p"]);
+        let eh = std::rc::Rc::new(TestErrorHandler);
+        let children = Parser::new(ParseOption::default())
+            .parse(base_scan("<div></br></p></div>"), eh)
+            .children;
+        assert!(children[0].get_element().unwrap().children.is_empty());
+    }
+    #[test]
+    fn test_v_pre_self_closing() {
+        let case = r#"<span v-pre :foo="bar" @click="a" #default="b" v-if="c"/>{{ d }}"#;
+        let mut children = base_parse(case).children;
+        assert_eq!(children.len(), 2);
+        cast!(children.pop().unwrap(), AstNode::Interpolation);
+        let span = cast!(children.pop().unwrap(), AstNode::Element);
+        let mut props = span.properties.into_iter();
+        let pre = cast!(props.next().unwrap(), ElemProp::Dir);
+        assert_eq!(pre.name, "pre");
+        let names: Vec<_> = props.map(|p| cast!(p, ElemProp::Attr).name).collect();
+        assert_eq!(names, [":foo", "@click", "#default", "v-if"]);
+    }
+
+    #[test]
+    fn test_v_pre_keeps_prop_order() {
+        let case = r#"<div class="a" v-pre id="b"></div>"#;
+        let div = base_parse(case).children.pop().unwrap().into_element();
+        let mut props = div.properties.into_iter();
+        let class = cast!(props.next().unwrap(), ElemProp::Attr);
+        let pre = cast!(props.next().unwrap(), ElemProp::Dir);
+        let id = cast!(props.next().unwrap(), ElemProp::Attr);
+        assert!(props.next().is_none());
+        let source = |l: &SourceLocation| &case[l.start.offset..l.end.offset];
+        assert_eq!(source(&class.location), r#"class="a""#);
+        assert_eq!(pre.name, "pre");
+        assert_eq!(source(&pre.location), "v-pre");
+        assert_eq!(source(&id.location), r#"id="b""#);
+    }
+
+    #[test]
+    fn test_v_pre_interpolation_text() {
+        let div = base_parse("<div v-pre>a {{ b }} c</div>{{ d }}").children;
+        let mut children = div.into_iter();
+        let div = children.next().unwrap().into_element();
+        assert_eq!(div.children.len(), 1);
+        let text = cast!(div.children.into_iter().next().unwrap(), AstNode::Text);
+        let raw: String = text.text.iter().map(|s| s.raw).collect();
+        assert_eq!(raw, "a {{ b }} c");
+        cast!(children.next().unwrap(), AstNode::Interpolation);
+    }
+
+    #[test]
+    fn test_element_find_prop() {
+        for case in ["<p id=a/>", "<p :id=a/>", "<p v-bind:id=a/>", "<p id/>"] {
+            let e = mock_element(case);
+            assert!(e.find_prop("id").is_some(), "{}", case);
+        }
+        let e = mock_element("<p :[id]=a v-on:id=b/>");
+        assert!(e.find_prop("id").is_none());
+    }
+
+    #[test]
+    fn test_element_remove_prop() {
+        let mut e = mock_element("<p a=1 :id=b c=2 id=d v-if=e/>");
+        let id = e.remove_prop("id").unwrap();
+        assert!(matches!(id, ElemProp::Dir(Directive { name: "bind", .. })));
+        let id = e.remove_prop("id").unwrap();
+        assert_eq!(cast!(id, ElemProp::Attr).name, "id");
+        assert!(e.remove_prop("id").is_none());
+        assert_eq!(e.properties.len(), 3);
+        assert_eq!(cast!(&e.properties[0], ElemProp::Attr).name, "a");
+        assert_eq!(cast!(&e.properties[1], ElemProp::Attr).name, "c");
+        assert_eq!(cast!(&e.properties[2], ElemProp::Dir).name, "if");
+    }
+
+    #[test]
+    fn test_element_dirs() {
+        let mut e = mock_element("<p @a=b id=c v-on:d=e v-if=f/>");
+        let names: Vec<_> = e.dirs().map(|d| d.name).collect();
+        assert_eq!(names, ["on", "on", "if"]);
+        e.dirs_mut()
+            .filter(|d| d.name == "on")
+            .for_each(|d| d.modifiers.push("stop"));
+        assert_eq!(e.dirs().filter(|d| !d.modifiers.is_empty()).count(), 2);
+    }
+
+    #[test]
+    fn test_nested_dynamic_arg() {
+        let cases = [
+            (":[keys[0]]", "keys[0]", vec![]),
+            (":[a[b[c]]].sync", "a[b[c]]", vec!["sync"]),
+            (":['a]b']", "'a]b'", vec![]),
+            (r#"@[a["]"]].stop.once"#, r#"a["]"]"#, vec!["stop", "once"]),
+        ];
+        for (name, arg, mods) in cases {
+            let case = format!("<p {}=v />", name);
+            let (root, errors) = parse_with_errors(&case, ParseOption::default());
+            let p = root.children[0].get_element().unwrap();
+            let dir = cast!(&p.properties[0], ElemProp::Dir);
+            let dyn_arg = cast!(dir.argument.as_ref().unwrap(), DirectiveArg::Dynamic);
+            assert_eq!(*dyn_arg, arg, "{}", name);
+            assert_eq!(dir.modifiers, mods);
+            // quotes are only reported by scanner
+            assert!(errors.is_empty(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_unbalanced_dynamic_arg() {
+        let cases = [
+            (
+                ":[a[b]",
+                "a[b]",
+                ErrorKind::MissingDynamicDirectiveArgumentEnd,
+            ),
+            (
+                ":[a]b]",
+                "a",
+                ErrorKind::UnexpectedContentAfterDynamicDirective,
+            ),
+            (
+                ":['a]",
+                "'a]",
+                ErrorKind::MissingDynamicDirectiveArgumentEnd,
+            ),
+        ];
+        for (name, arg, kind) in cases {
+            let case = format!("<p {}=v />", name);
+            let (root, errors) = parse_with_errors(&case, ParseOption::default());
+            let p = root.children[0].get_element().unwrap();
+            let dir = cast!(&p.properties[0], ElemProp::Dir);
+            let dyn_arg = cast!(dir.argument.as_ref().unwrap(), DirectiveArg::Dynamic);
+            assert_eq!(*dyn_arg, arg, "{}", name);
+            let kind = std::mem::discriminant(&kind);
+            let has_error = errors
+                .iter()
+                .any(|(k, _)| std::mem::discriminant(k) == kind);
+            assert!(has_error, "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_dynamic_arg_split_by_whitespace() {
+        let case = r#"<p :[a + b]="c" :[d e="f"/>"#;
+        let (_, errors) = parse_tokens_with_errors(base_scan(case), ParseOption::default());
+        let kinds: Vec<_> = errors.iter().map(|e| e.code()).collect();
+        let split = CompilationError::new(ErrorKind::InvalidDynamicArgument);
+        let missing_end = CompilationError::new(ErrorKind::MissingDynamicDirectiveArgumentEnd);
+        assert_eq!(kinds, [split.code(), missing_end.code()]);
+        assert!(errors[0].to_string().contains("split at whitespace"));
+        // located at the argument
+        assert_eq!(errors[0].location.start.offset, 4);
+    }
+
+    #[test]
+    fn test_duplicate_slot_dir() {
+        let cases = [
+            (r#"<comp #default="a" v-slot:default="b"/>"#, vec![19]),
+            (r#"<comp v-slot="a" #default/>"#, vec![17]),
+            (
+                r#"<comp #foo v-slot:bar #[foo] v-slot:foo #bar/>"#,
+                vec![29, 40],
+            ),
+            // identical name is removed by scanner as DuplicateAttribute
+            (r#"<comp #foo #foo/>"#, vec![]),
+            (r#"<comp #[a] #[a]/>"#, vec![]),
+        ];
+        for (case, offsets) in cases {
+            let (_, errors) = parse_tokens_with_errors(base_scan(case), ParseOption::default());
+            let dup = CompilationError::new(ErrorKind::VSlotDuplicateSlotNames).code();
+            let found: Vec<_> = errors
+                .iter()
+                .filter(|e| e.code() == dup)
+                .map(|e| e.location.start.offset)
+                .collect();
+            assert_eq!(found, offsets, "{}", case);
+        }
+    }
+
+    #[test]
+    fn test_misplaced_slot() {
+        let cases = [
+            (r#"<comp><div v-slot:foo="a"/></comp>"#, vec![11]),
+            (r#"<slot #foo/>"#, vec![6]),
+            (r#"<comp><template #foo>a</template></comp>"#, vec![]),
+            (r#"<comp #default="a"/><Comp v-slot/>"#, vec![]),
+            (r#"<div v-pre><p #foo/></div>"#, vec![]),
+        ];
+        for (case, offsets) in cases {
+            let option = ParseOption {
+                is_native_element: (|s: &str| s != "comp").into(),
+                ..Default::default()
+            };
+            let (_, errors) = parse_tokens_with_errors(base_scan(case), option);
+            let misplaced = CompilationError::new(ErrorKind::VSlotMisplaced).code();
+            let found: Vec<_> = errors
+                .iter()
+                .filter(|e| e.code() == misplaced)
+                .map(|e| e.location.start.offset)
+                .collect();
+            assert_eq!(found, offsets, "{}", case);
+        }
+    }
+
+    #[test]
+    fn test_start_tag_source() {
+        let cases = [
+            ("<p>a</p>", "<p>"),
+            ("<p/>", "<p/>"),
+            ("<p\n  :a = 'b'\n  c  />", "<p\n  :a = 'b'\n  c  />"),
+            (r#"<p a="1" a="x>y" >z</p>"#, r#"<p a="1" a="x>y" >"#),
+        ];
+        for (src, expected) in cases {
+            let e = mock_element(src);
+            assert_eq!(e.start_tag_source(src), expected);
+        }
+        let src = "<p\n  :a = 'b'\n  c  />";
+        let e = mock_element(src);
+        let raw: Vec<_> = e.properties.iter().map(|p| p.raw_source(src)).collect();
+        assert_eq!(raw, [":a = 'b'", "c"]);
+    }
+
+    #[test]
+    fn test_classify_element() {
+        use ClassificationRule as R;
+        let opt = ParseOption {
+            is_custom_element: (|t: &str| t == "my-el").into(),
+            get_builtin_component: |t| {
+                (t == "transition").then_some(RuntimeHelper::BASE_TRANSITION)
+            },
+            is_native_element: (|t: &str| t != "foo-bar").into(),
+            ..Default::default()
+        };
+        let cases = [
+            ("<my-el/>", R::CustomElement),
+            ("<component/>", R::ExplicitComponentTag),
+            ("<MyComp/>", R::UppercaseTag),
+            ("<My-Comp/>", R::UppercaseTag),
+            ("<my-Comp/>", R::DefaultPlain),
+            ("<keep-alive/>", R::CoreComponent),
+            ("<transition/>", R::BuiltinComponent),
+            ("<foo-bar/>", R::NotNativeElement),
+            (r#"<tr is="vue:my-row"/>"#, R::IsAttrVuePrefix),
+            (r#"<tr is="my-row"/>"#, R::DefaultPlain),
+        ];
+        // scanner only starts tags with ASCII letters, so rename a parsed element
+        let unicode_cases = [
+            ("Éditeur", R::UppercaseTag),
+            ("Ωmega", R::UppercaseTag),
+            ("éditeur", R::DefaultPlain),
+        ];
+        for (tag_name, rule) in unicode_cases {
+            let mut elem = mock_element("<tr/>");
+            elem.tag_name = tag_name;
+            assert_eq!(classify_element(&elem, &opt).rule, rule, "{}", tag_name);
+        }
+        let compat = ParseOption {
+            v2_compat_is: true,
+            ..Default::default()
+        };
+        let classified = classify_element(&mock_element(r#"<tr is="my-row"/>"#), &compat);
+        assert_eq!(classified.rule, R::IsAttrCompat);
+        for (case, rule) in cases {
+            let classified = classify_element(&mock_element(case), &opt);
+            assert_eq!(classified.rule, rule, "{}", case);
+            let is_plain = matches!(rule, R::CustomElement | R::DefaultPlain);
+            assert_eq!(classified.tag_type == ElementType::Plain, is_plain);
+        }
+    }
+
+    #[test]
+    fn test_explain_classification() {
+        use crate::error::{CompilationErrorKind as Kind, Severity};
+        let option = ParseOption {
+            explain_classification: true,
+            ..Default::default()
+        };
+        let case = "<div><Comp/></div><slot/>";
+        let (root, errors) = parse_tokens_with_errors(base_scan(case), option);
+        assert!(root.children[0].get_element().unwrap().children[0]
+            .get_element()
+            .unwrap()
+            .is_component());
+        // slot outlet is not classified
+        assert_eq!(errors.len(), 2);
+        assert!(errors
+            .iter()
+            .all(|e| matches!(e.kind, Kind::ElementClassified)));
+        assert_eq!(errors[0].severity(), Severity::Info);
+        assert!(errors[0].to_string().ends_with("UppercaseTag"));
+        assert!(errors[1].to_string().ends_with("DefaultPlain"));
+    }
+
+    #[test]
+    fn test_single_root() {
+        let mut root = base_parse("<!--license-->\n  <div>a</div>\n");
+        assert!(!root.is_fragment());
+        assert_eq!(root.single_root().unwrap().tag_name, "div");
+        assert_eq!(root.root_elements().count(), 1);
+        root.single_root_mut().unwrap().tag_name = "p";
+        assert_eq!(root.root_elements().next().unwrap().tag_name, "p");
+        let root = base_parse("<div/> text <p/>");
+        assert!(root.is_fragment());
+        assert!(root.single_root().is_none());
+        assert_eq!(root.root_elements().count(), 2);
+        let root = base_parse("{{ a }}");
+        assert!(!root.is_fragment());
+        assert!(root.single_root().is_none());
+    }
+
+    #[test]
+    fn test_inline_tag_whitespace() {
+        let parse = |case| {
+            let option = ParseOption {
+                is_inline_tag: (|t: &str| t == "span" || t == "b").into(),
+                ..Default::default()
+            };
+            parse_with_errors(case, option).0
+        };
+        let root = parse("<p><span>a</span>\n  <b>b</b></p>");
+        let children = &root.children[0].get_element().unwrap().children;
+        assert_eq!(children.len(), 3);
+        match &children[1] {
+            AstNode::Text(t) => assert_eq!(t.text[0].into_string(), " "),
+            _ => panic!("whitespace between inline elements should be kept"),
+        }
+        let root = parse("<p><div>a</div>\n<p>b</p></p>");
+        assert_eq!(root.children[0].get_element().unwrap().children.len(), 2);
+        let root = parse("<p><span>a</span>\n<div>b</div></p>");
+        assert_eq!(root.children[0].get_element().unwrap().children.len(), 2);
+        // default hook keeps removing
+        let root = base_parse("<p><span>a</span>\n<span>b</span></p>");
+        assert_eq!(root.children[0].get_element().unwrap().children.len(), 2);
+    }
+
+    #[test]
+    fn test_end_tag_many_siblings() {
+        let case = format!("<div>{}</div>", "<p>a</P><SPAN>b</span>".repeat(50_000));
+        let root = base_parse(&case);
+        let div = root.children[0].get_element().unwrap();
+        assert_eq!(div.children.len(), 100_000);
+        for (i, child) in div.children.iter().enumerate() {
+            let e = child.get_element().unwrap();
+            assert_eq!(e.tag_name, if i % 2 == 0 { "p" } else { "SPAN" });
+            assert_eq!(e.children.len(), 1);
+        }
+    }
+
+    #[test]
+    fn test_end_tag_matches_innermost() {
+        // end tags close the innermost open element a linear scan would find
+        fn shape(nodes: &[AstNode]) -> String {
+            let elems = nodes.iter().filter_map(|n| n.get_element());
+            let shapes: Vec<_> = elems
+                .map(|e| format!("{}({})", e.tag_name, shape(&e.children)))
+                .collect();
+            shapes.join(",")
+        }
+        let cases = [
+            ("<DIV><div></DIV></div>", "DIV(div())"),
+            ("<div><p><DIV></div>a</div>", "div(p(DIV()))"),
+            ("<a><b><A></a></B></a>", "a(b(A()))"),
+            (
+                "<svg><clipPath><clippath></clipPath></svg>",
+                "svg(clipPath(clippath()))",
+            ),
+            (
+                "<svg><clippath><clipPath></clippath></svg>",
+                "svg(clippath(clipPath()))",
+            ),
+            ("<svg><g><G></g></G></svg>", "svg(g(G()))"),
+            ("<div><svg><div></DIV></svg></div>", "div(svg(div()))"),
+        ];
+        for (case, expected) in cases {
+            let (root, _) = parse_with_errors(case, ParseOption::dom());
+            assert_eq!(shape(&root.children), expected, "{}", case);
+        }
+    }
+
+    #[test]
+    fn test_end_tag_deep_nesting() {
+        let depth = 4000;
+        // every other end tag is missing and closed by its parent's end tag
+        let mut case = String::new();
+        for i in 0..depth {
+            case.push_str(if i % 2 == 0 { "<div>" } else { "<span>" });
+        }
+        for i in (0..depth).rev() {
+            if i % 2 == 0 {
+                case.push_str("</DIV>");
+            }
+        }
+        let option = ParseOption {
+            limits: ParseLimits {
+                max_nesting_depth: Some(depth),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let root = Parser::new(option).parse(base_scan(&case), std::rc::Rc::new(TestErrorHandler));
+        let mut node = &root.children[0];
+        for i in 0..depth {
+            let e = node.get_element().unwrap();
+            assert_eq!(e.tag_name, if i % 2 == 0 { "div" } else { "span" });
+            if i + 1 == depth {
+                assert!(e.children.is_empty());
+            } else {
+                assert_eq!(e.children.len(), 1);
+                node = &e.children[0];
+            }
+        }
+    }
+
+    #[test]
+    fn test_compress_many_children() {
+        // quadratic node removal or comment scanning takes a minute here
+        let case = format!("<ul>{}</ul>", "\n  <li>a</li> <!--c-->".repeat(50_000));
+        let root = base_parse(&case);
+        let ul = root.children[0].get_element().unwrap();
+        assert_eq!(ul.children.len(), 100_000);
+        assert!(ul.children.iter().all(|n| !matches!(n, AstNode::Text(_))));
+    }
+
+    #[test]
+    fn test_interpolation_whitespace() {
+        let children = |case| {
+            let root = base_parse(case);
+            let div = root.children[0].get_element().unwrap();
+            div.children
+                .iter()
+                .map(|n| match n {
+                    AstNode::Text(t) => t.text.iter().map(|s| s.into_string()).collect(),
+                    AstNode::Interpolation(i) => i.source.trim().to_string(),
+                    _ => "_".to_string(),
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            children("<div>\n  {{ a }}\n  {{ b }}\n</div>"),
+            ["a", " ", "b"]
+        );
+        assert_eq!(
+            children("<div>{{ a }}\n<span/>\n{{ b }}</div>"),
+            ["a", " ", "_", " ", "b"]
+        );
+        assert_eq!(children("<div><!--c-->\n  {{ a }}</div>"), ["_", " ", "a"]);
+    }
+
+    #[test]
+    fn test_trim_leading_newline() {
+        let option = ParseOption {
+            get_text_mode: |t| match t.name {
+                "textarea" => TextMode::RcData,
+                _ => TextMode::Data,
+            },
+            is_pre_tag: preset::is_pre_tag.into(),
+            ..Default::default()
+        };
+        let parse = |case| parse_with_errors(case, option.clone()).0;
+        for (case, newline) in [
+            ("<pre>\n  a</pre>", "\n"),
+            ("<pre>\r\n  a</pre>", "\r\n"),
+            ("<pre>\r  a</pre>", "\r"),
+            ("<textarea>\n  a</textarea>", "\n"),
+        ] {
+            let root = parse(case);
+            let e = root.children[0].get_element().unwrap();
+            let text = match &e.children[0] {
+                AstNode::Text(t) => t,
+                _ => panic!("pre should have text"),
+            };
+            let joined: String = text.text.iter().map(|s| s.into_string()).collect();
+            assert_eq!(joined, "  a", "{:?}", case);
+            let location = &text.location;
+            let source = &case[location.start.offset..location.end.offset];
+            assert_eq!(source, format!("{}  a", newline));
+        }
+        // newline only text is kept empty with its location
+        let case = "<pre>\n</pre><textarea>\r\n</textarea><div><pre>\n</pre></div>";
+        let root = parse(case);
+        for e in root.children.iter().map(|n| n.get_element().unwrap()) {
+            let e = match e.tag_name {
+                "div" => e.children[0].get_element().unwrap(),
+                _ => e,
+            };
+            let t = match &e.children[..] {
+                [AstNode::Text(t)] => t,
+                _ => panic!("newline only text should be kept"),
+            };
+            assert!(t.text.is_empty());
+            let location = &t.location;
+            assert!(case[location.start.offset..location.end.offset].ends_with('\n'));
+        }
+        // textarea is pre only if the platform says so
+        let option = ParseOption {
+            get_text_mode: preset::dom_get_text_mode,
+            ..Default::default()
+        };
+        let (root, _) = parse_with_errors("<textarea>\na</textarea>", option);
+        let e = root.children[0].get_element().unwrap();
+        assert!(matches!(&e.children[0], AstNode::Text(t) if t.text[0].raw == "\na"));
+        // only one newline is trimmed
+        let root = parse("<pre>\n\na</pre>");
+        let e = root.children[0].get_element().unwrap();
+        match &e.children[0] {
+            AstNode::Text(t) => assert_eq!(t.text[0].raw, "\na"),
+            _ => panic!("pre should have text"),
+        }
+    }
+
+    #[test]
+    fn test_raw_text_children() {
+        let option = ParseOption {
+            get_text_mode: preset::dom_get_text_mode,
+            is_pre_tag: preset::is_pre_tag.into(),
+            ..Default::default()
+        };
+        let children = |case| {
+            let scan = ScanOption {
+                get_text_mode: option.get_text_mode,
+                ..Default::default()
+            };
+            let (mut root, errors) = scan_parse_with_errors(case, scan, option.clone());
+            assert!(errors.is_empty(), "{:?}", case);
+            let e = root.children.pop().unwrap().into_element();
+            let e = match e.children.first() {
+                Some(AstNode::Element(_)) => e.children.into_iter().next().unwrap().into_element(),
+                _ => e,
+            };
+            let children: Vec<_> = e
+                .children
+                .iter()
+                .map(|c| {
+                    let kind = match c {
+                        AstNode::Text(_) => "text",
+                        AstNode::Interpolation(_) => "interpolation",
+                        _ => panic!("raw element has only text or interpolation"),
+                    };
+                    let l = c.get_location();
+                    (kind, &case[l.start.offset..l.end.offset])
+                })
+                .collect();
+            children
+        };
+        let script = "<script>\nlet a = '</scr' + 'ipt>'\n</script>";
+        assert_eq!(children(script), [("text", "\nlet a = '</scr' + 'ipt>'\n")]);
+        assert_eq!(children("<style>\n  a {}</style>"), [("text", "\n  a {}")]);
+        assert!(children("<style></style>").is_empty());
+        assert!(children("<textarea></textarea>").is_empty());
+        assert_eq!(
+            children("<title>a {{ x }}</title>"),
+            [("text", "a "), ("interpolation", "{{ x }}")]
+        );
+        // the newline only text is kept empty, location of the rest is exact
+        assert_eq!(
+            children("<textarea>\n{{ x }} a</textarea>"),
+            [("text", "\n"), ("interpolation", "{{ x }}"), ("text", " a")]
+        );
+        let nested = "<pre><textarea>\n{{ x }}</textarea></pre>";
+        assert_eq!(
+            children(nested),
+            [("text", "\n"), ("interpolation", "{{ x }}")]
+        );
+        // tags are text in RCDATA, but interpolation still works
+        assert_eq!(
+            children("<textarea>a {{ draft }}<div></div></textarea>"),
+            [
+                ("text", "a "),
+                ("interpolation", "{{ draft }}"),
+                ("text", "<div></div>")
+            ]
+        );
+        assert_eq!(children("<style>{{ a }}</style>"), [("text", "{{ a }}")]);
+    }
+
+    #[test]
+    fn test_unknown_directive() {
+        let case = r#"<div v-modle="a" v-focus v-if="b" @click="c"/><p v-pre><i v-fi/></p>"#;
+        use crate::error::{ErrorKind as _, Severity};
+        assert!(parse_errors(case, ParseOption::default()).is_empty());
+        let option = ParseOption {
+            is_known_directive: Some((|name: &str| name == "focus").into()),
+            ..Default::default()
+        };
+        let errors = parse_errors(case, option);
+        assert_eq!(errors.len(), 1);
+        let (kind, src) = &errors[0];
+        assert!(matches!(kind, ErrorKind::UnknownDirective));
+        assert_eq!(kind.severity(), Severity::Warning);
+        assert_eq!(*src, "v-modle");
+    }
+
+    #[test]
+    fn test_foreign_end_tag_case() {
+        use crate::error::ErrorKind as _;
+        let option = ParseOption {
+            get_namespace: |tag, parent| match parent {
+                Some(p) if p.namespace == Namespace::Svg => Namespace::Svg,
+                _ if tag == "svg" => Namespace::Svg,
+                _ => Namespace::Html,
+            },
+            ..Default::default()
+        };
+        let parse = |case| {
+            let (root, errors) = parse_with_errors(case, option.clone());
+            let kinds: Vec<_> = errors.iter().map(|(k, _)| k.code()).collect();
+            (root, kinds)
+        };
+        let (root, kinds) = parse("<svg><clipPath><rect/></clipPath><g/></svg><DIV></div>");
+        assert!(kinds.is_empty());
+        let svg = root.children[0].get_element().unwrap();
+        assert_eq!(svg.children.len(), 2);
+        // lowercase end tag does not close clipPath
+        let (root, kinds) = parse("<svg><clipPath></clippath><g/></svg>");
+        let invalid = ErrorKind::InvalidEndTag.code();
+        let missing = ErrorKind::MissingEndTag.code();
+        assert_eq!(kinds, [invalid, missing]);
+        let svg = root.children[0].get_element().unwrap();
+        let clip_path = svg.children[0].get_element().unwrap();
+        assert_eq!(clip_path.children.len(), 1);
+        // force closed clipPath only swallows its exact end tag
+        let (_, kinds) = parse("<svg><clipPath></svg></clippath></clipPath>");
+        assert_eq!(kinds, [missing, invalid]);
+    }
+
+    #[test]
+    fn test_missing_end_tag_related() {
+        let related = |case| {
+            let (_, errors) = parse_tokens_with_errors(base_scan(case), ParseOption::default());
+            assert_eq!(errors.len(), 1);
+            let error = &errors[0];
+            assert!(matches!(error.kind, ErrorKind::MissingEndTag));
+            assert_eq!(error.related.len(), 1);
+            let (label, loc) = &error.related[0];
+            assert_eq!(*label, "element was implicitly closed here");
+            assert_eq!(loc.start.offset, loc.end.offset);
+            (
+                error.location.start.offset,
+                loc.start.offset,
+                error.to_string(),
+            )
+        };
+        // closed by EOF
+        let (start, closed, msg) = related("<div>\n<span>a</span>");
+        assert_eq!((start, closed), (0, 20));
+        assert_eq!(msg, "Element is missing end tag.");
+        // force closed by the outer end tag
+        let (start, closed, _) = related("<div><span>a</div>");
+        assert_eq!((start, closed), (5, 12));
+    }
+
+    #[test]
+    fn test_max_nesting_depth() {
+        let depth = 10_000;
+        let case = "<div>".repeat(depth) + "a" + &"</div>".repeat(depth);
+        let (root, errors) = parse_tokens_with_errors(base_scan(&case), ParseOption::default());
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(errors[0].kind, ErrorKind::MaxNestingDepthExceeded));
+        assert_eq!(errors[0].location.start.offset, 4096 * 5);
+        let mut elem = root.children[0].get_element().unwrap();
+        let mut nested = 1;
+        while let Some(AstNode::Element(child)) = elem.children.first() {
+            nested += 1;
+            elem = child;
+        }
+        assert_eq!(nested, 4097);
+        // the rest are siblings of the first too deep element
+        assert_eq!(elem.children.len(), 0);
+        let option = ParseOption {
+            limits: ParseLimits {
+                max_nesting_depth: Some(1),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let case = "<div><p><b>a</b></p><span/></div><i></i>";
+        let (root, errors) = parse_with_errors(case, option);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(root.children.len(), 2);
+        let div = root.children[0].get_element().unwrap();
+        let tags: Vec<_> = div
+            .children
+            .iter()
+            .map(|n| match n {
+                AstNode::Element(e) => e.tag_name,
+                AstNode::Text(_) => "text",
+                _ => panic!("unexpected node"),
+            })
+            .collect();
+        assert_eq!(tags, ["p", "b", "text", "span"]);
+        // no limit
+        let option = ParseOption {
+            limits: ParseLimits {
+                max_nesting_depth: None,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let case = "<div>".repeat(5000) + &"</div>".repeat(5000);
+        let (_, errors) = parse_with_errors(&case, option);
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn test_node_id() {
+        use crate::visit::{walk_element, walk_root, Ancestors, Visitor};
+        // records id and address of every visited node in visiting order
+        struct Collect(Vec<(NodeId, usize)>);
+        impl<'b, 'a: 'b> Visitor<'b, 'a> for Collect {
+            fn visit_element(&mut self, e: &'b Element<'a>, ancestors: &mut Ancestors<'b, 'a>) {
+                self.0.push((e.id, e as *const _ as usize));
+                walk_element(self, e, ancestors);
+            }
+            fn visit_text(&mut self, t: &'b TextNode<'a>, _: &Ancestors<'b, 'a>) {
+                self.0.push((t.id, t as *const _ as usize));
+            }
+            fn visit_interpolation(&mut self, i: &'b SourceNode<'a>, _: &Ancestors<'b, 'a>) {
+                self.0.push((i.id, i as *const _ as usize));
+            }
+            fn visit_comment(&mut self, c: &'b SourceNode<'a>, _: &Ancestors<'b, 'a>) {
+                self.0.push((c.id, c as *const _ as usize));
+            }
+        }
+        let root = base_parse("<div>\n  a<!--b-->\n  <p>{{ c }}</p>\n</div><span/>");
+        let mut collect = Collect(vec![]);
+        walk_root(&root, &mut collect);
+        let ids: Vec<_> = collect.0.iter().map(|p| p.0).collect();
+        assert_eq!(ids, (0..6).map(NodeId).collect::<Vec<_>>());
+        assert_eq!(root.node_count(), 6);
+        let div = root.children[0].get_element().unwrap();
+        assert_eq!(div.children[2].id(), NodeId(3));
+        assert_eq!(root.children[1].id(), NodeId(5));
+        for (id, addr) in collect.0 {
+            let found = match root.find_by_id(id).unwrap() {
+                AstNode::Element(e) => e as *const _ as usize,
+                AstNode::Text(t) => t as *const _ as usize,
+                AstNode::Interpolation(n) | AstNode::Comment(n) => n as *const _ as usize,
+                AstNode::ProcessingInstruction(_) => panic!("no processing instruction"),
+            };
+            assert_eq!(found, addr);
+        }
+        assert!(root.find_by_id(NodeId(6)).is_none());
+    }
+
+    #[test]
+    fn test_v_if_with_v_for() {
+        let warnings = |case: &'static str| {
+            let errors = parse_errors(case, ParseOption::default());
+            let errors = errors.into_iter();
+            let warned = errors.filter(|(kind, _)| matches!(kind, ErrorKind::VIfWithVFor));
+            warned.map(|(_, src)| src).collect::<Vec<_>>()
+        };
+        assert_eq!(warnings(r#"<p v-if="a" v-for="b in c"/>"#), ["v-if"]);
+        assert_eq!(warnings(r#"<p v-for="b in c" v-if="a"/>"#), ["v-if"]);
+        assert_eq!(
+            warnings(r#"<i v-if/><p v-for="b in c" v-else-if="a"/>"#),
+            ["v-else-if"]
+        );
+        assert_eq!(
+            warnings(r#"<template v-for="b in c" v-if="a"></template>"#),
+            ["v-if"]
+        );
+        assert!(warnings(r#"<p v-for="b in c"><i v-if="b"/></p>"#).is_empty());
+        assert!(warnings(r#"<template v-if="a"><p v-for="b in c"/></template>"#).is_empty());
+        assert!(warnings(r#"<p v-pre v-if="a" v-for="b in c"/>"#).is_empty());
+    }
+
+    #[test]
+    fn test_prop_case_check() {
+        let warnings = |case: &'static str, check| {
+            let option = ParseOption {
+                v_bind_prop_case_check: check,
+                ..Default::default()
+            };
+            let errors = parse_errors(case, option).into_iter();
+            let warned = errors.filter(|(k, _)| matches!(k, ErrorKind::UppercaseInDomAttribute));
+            warned.map(|(_, src)| src).collect::<Vec<_>>()
+        };
+        let case = r#"<p :fooBar="a" @myEvent="b" :foo-bar="c" :[dynArg]="d" fooBar/>"#;
+        assert_eq!(warnings(case, true), ["fooBar", "myEvent"]);
+        assert!(warnings(case, false).is_empty());
+        assert!(warnings(r#"<p :fooBar.camel="a" v-bind:viewBox.camel="b"/>"#, true).is_empty());
+        assert_eq!(warnings(r#"<p v-bind:viewBox="a"/>"#, true), ["viewBox"]);
+    }
+
+    #[test]
+    fn test_check_v_model() {
+        let errors = |case: &'static str| {
+            let option = ParseOption {
+                check_v_model: true,
+                is_native_element: (|s: &str| s != "comp").into(),
+                ..Default::default()
+            };
+            let errors = parse_errors(case, option).into_iter();
+            errors
+                .map(|(kind, src)| {
+                    assert!(matches!(kind, ErrorKind::VModelValueNotWritable));
+                    src
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(errors(r#"<input v-model="a + b"/>"#), ["a + b"]);
+        assert_eq!(errors(r#"<span v-model="foo.bar()"/>"#), ["foo.bar()"]);
+        assert_eq!(errors(r#"<comp v-model:value="1"/>"#), ["1"]);
+        // platform errors are left to platform converters
+        assert!(errors(r#"<input v-model:value.foo="a.b[c]" type="file"/>"#).is_empty());
+        assert!(errors(r#"<input type="text" v-model.lazy="a['b']"/><input v-model/>"#).is_empty());
+        assert!(errors(r#"<div v-pre><input v-model:a="b + 1"/></div>"#).is_empty());
+    }
+
+    #[test]
+    fn test_same_name_shorthand() {
+        let case =
+            r#"<p :user-name :id.camel .text-content :foo.prop v-bind:bar-baz :[dyn] @click/>"#;
+        let elem = mock_element(case);
+        let exprs: Vec<_> = elem
+            .dirs()
+            .map(|d| {
+                d.expression.as_ref().map(|e| {
+                    let loc = &e.location;
+                    let src = &case[loc.start.offset..loc.end.offset];
+                    (e.content.into_string(), src)
+                })
+            })
+            .collect();
+        let expected = [
+            Some(("userName".to_string(), "user-name")),
+            Some(("id".to_string(), "id")),
+            Some(("textContent".to_string(), "text-content")),
+            Some(("foo".to_string(), "foo")),
+            Some(("barBaz".to_string(), "bar-baz")),
+            None,
+            None,
+        ];
+        assert_eq!(exprs, expected);
+        let root = base_parse(r#"<div v-pre><p :user-name/></div>"#);
+        let elem = cast!(&root.children[0], AstNode::Element);
+        let elem = cast!(&elem.children[0], AstNode::Element);
+        assert!(matches!(elem.properties[0], ElemProp::Attr(_)));
+        let elem = mock_element(r#"<p :user-name :a="userName"/>"#);
+        let shorthand: Vec<_> = elem.dirs().map(|d| d.is_same_name_shorthand()).collect();
+        assert_eq!(shorthand, [true, false]);
+        let case = r#"<p :class :for="a" :default/>"#;
+        let errors: Vec<_> = parse_errors(case, ParseOption::default())
+            .into_iter()
+            .filter(|(kind, _)| matches!(kind, ErrorKind::VBindShorthandReservedWord))
+            .map(|(_, src)| src)
+            .collect();
+        assert_eq!(errors, ["class", "default"]);
+    }
+
+    #[test]
+    fn test_template_element() {
+        let cases = [
+            "<template #default/>",
+            "<template v-slot/>",
+            "<template v-slot:name/>",
+            "<template #[name]/>",
+        ];
+        for case in cases {
+            let elem = mock_element(case);
+            assert!(elem.tag_type == ElementType::Template, "{}", case);
+        }
+        let warnings = |case: &'static str| {
+            let option = ParseOption {
+                get_namespace: |tag, parent| match parent {
+                    Some(p) => p.namespace,
+                    None if tag == "svg" => Namespace::Svg,
+                    None => Namespace::Html,
+                },
+                ..Default::default()
+            };
+            let errors = parse_errors(case, option).into_iter();
+            let warned = errors.filter(|(k, _)| matches!(k, ErrorKind::TemplateNoSpecialDirective));
+            warned.map(|(_, src)| src).collect::<Vec<_>>()
+        };
+        assert!(warnings("<template><p/></template>").is_empty());
+        assert_eq!(
+            warnings("<div><template><p/></template></div>"),
+            ["<template><p/></template>"]
+        );
+        assert!(warnings("<div><template v-if='a'/><template v-else/></div>").is_empty());
+        assert!(warnings("<div v-pre><template/></div>").is_empty());
+        assert!(warnings("<svg><template/></svg>").is_empty());
+    }
+
+    #[test]
+    fn test_v_for_key() {
+        let warnings = |case: &'static str, warn_missing| {
+            let option = ParseOption {
+                warn_missing_vfor_key: warn_missing,
+                ..Default::default()
+            };
+            let errors = parse_errors(case, option).into_iter();
+            errors
+                .filter_map(|(kind, src)| {
+                    let kind = match kind {
+                        ErrorKind::VForTemplateKeyPlacement => "child",
+                        ErrorKind::MissingVForKey => "missing",
+                        _ => return None,
+                    };
+                    Some((kind, src))
+                })
+                .collect::<Vec<_>>()
+        };
+        let case = r#"<template v-for="i in a" :key="i"><p/><p/></template>"#;
+        assert!(warnings(case, true).is_empty());
+        let case = r#"<template v-for="i in a"><p :key="i"/>text<p key="b"/></template>"#;
+        let expected = [("child", r#":key="i""#), ("child", r#"key="b""#)];
+        assert_eq!(warnings(case, true), expected);
+        assert_eq!(warnings(case, false), expected);
+        let case = r#"<template v-for="i in a"><p/><p/></template>"#;
+        let expected = [("missing", r#"v-for="i in a""#)];
+        assert_eq!(warnings(case, true), expected);
+        assert!(warnings(case, false).is_empty());
+        let case = r#"<p v-for="i in a"/><p v-for="i in a" :key="i"/>"#;
+        assert_eq!(warnings(case, true), expected);
+        let case = r#"<div><p :key="a"/></div><template v-if="a"><p :key="a"/></template>"#;
+        assert!(warnings(case, true).is_empty());
+    }
+
+    #[test]
+    fn test_strict_components() {
+        let errors = |case: &'static str, strict| {
+            let option = ParseOption {
+                strict_components: strict,
+                is_native_element: (|s: &str| s == "div" || s == "p").into(),
+                ..Default::default()
+            };
+            let errors = parse_errors(case, option).into_iter();
+            errors
+                .filter_map(|(kind, src)| {
+                    let kind = match kind {
+                        ErrorKind::ComponentShadowsNativeTag => "shadow",
+                        ErrorKind::BuiltinTagMisuse => "misuse",
+                        _ => return None,
+                    };
+                    Some((kind, src))
+                })
+                .collect::<Vec<_>>()
+        };
+        let shadow = |s| vec![("shadow", s)];
+        let misuse = |s| vec![("misuse", s)];
+        assert_eq!(errors("<Div/>", true), shadow("<Div/>"));
+        assert!(errors("<Div/>", false).is_empty());
+        assert!(errors("<MyComp/><Component :is='a'/><div/>", true).is_empty());
+        assert_eq!(
+            errors(r#"<Slot name="a"/>"#, true),
+            shadow(r#"<Slot name="a"/>"#)
+        );
+        assert_eq!(errors("<component/>", true), misuse("<component/>"));
+        assert_eq!(errors("<Component></Component>", true).len(), 1);
+        assert!(errors("<component/>", false).is_empty());
+        let case = r#"<component is="a"/><component :is="b"/><component v-is="c"/>"#;
+        assert!(errors(case, true).is_empty());
+        let case = r#"<slot is="a"/>"#;
+        assert_eq!(errors(case, true), misuse(case));
+        assert!(errors(r#"<slot name="a"/>"#, true).is_empty());
+        let case = r#"<div><template name="a">b</template></div>"#;
+        assert_eq!(
+            errors(case, true),
+            misuse(r#"<template name="a">b</template>"#)
+        );
+        assert!(errors(r#"<div v-pre><component/><Div/></div>"#, true).is_empty());
+        let option = ParseOption {
+            strict_components: true,
+            ..Default::default()
+        };
+        let (_, errors) = parse_tokens_with_errors(base_scan("<component/>"), option);
+        assert_eq!(
+            errors[0].to_string(),
+            "Built-in tag is used with a misleading prop. <component> needs `is` to render anything."
+        );
+    }
+
+    #[test]
+    fn test_v2_compat_is() {
+        let parse = |case: &'static str, compat| {
+            let option = ParseOption {
+                is_native_element: (|_: &str| true).into(),
+                v2_compat_is: compat,
+                ..Default::default()
+            };
+            let (root, errors) = parse_with_errors(case, option);
+            let elem = cast!(&root.children[0], AstNode::Element);
+            let warned: Vec<_> = errors
+                .into_iter()
+                .filter(|(kind, _)| matches!(kind, ErrorKind::CompatIsAttribute))
+                .map(|(_, src)| src)
+                .collect();
+            (elem.tag_type == ElementType::Component, warned)
+        };
+        for compat in [true, false] {
+            let (is_comp, warned) = parse(r#"<tr is="vue:my-row"/>"#, compat);
+            assert!(is_comp);
+            assert!(warned.is_empty());
+            let (is_comp, warned) = parse(r#"<tr :is="row"/>"#, compat);
+            assert!(!is_comp);
+            assert!(warned.is_empty());
+        }
+        let (is_comp, warned) = parse(r#"<tr is="my-row"/>"#, true);
+        assert!(is_comp);
+        assert_eq!(warned, [r#"is="my-row""#]);
+        let (is_comp, warned) = parse(r#"<tr is="my-row"/>"#, false);
+        assert!(!is_comp);
+        assert!(warned.is_empty());
+    }
+
+    #[test]
+    fn test_empty_dynamic_arg() {
+        use crate::error::ErrorKind as _;
+        let parse = |case: &'static str| {
+            let (root, errors) = parse_with_errors(case, ParseOption::default());
+            let errors: Vec<_> = errors
+                .into_iter()
+                .map(|(kind, src)| (kind.code(), src))
+                .collect();
+            (
+                root.children[0].get_element().unwrap().dirs().count(),
+                errors,
+            )
+        };
+        let missing = ErrorKind::MissingDirectiveArg.code();
+        let (dirs, errors) = parse(r#"<Comp v-bind:[]="a" :[]="b" @[]="c" #[]="d"/>"#);
+        assert_eq!(dirs, 4);
+        assert_eq!(
+            errors,
+            [
+                (missing, "[]"),
+                (missing, "[]"),
+                (missing, "[]"),
+                (missing, "[]")
+            ]
+        );
+        let e = mock_element(r#"<p :[]="a" @[]="b"/>"#);
+        assert!(e
+            .dirs()
+            .all(|d| d.argument.is_none() && d.arg_loc.is_none()));
+        let (_, errors) = parse(r#"<p :[foo="a"/>"#);
+        let no_end = ErrorKind::MissingDynamicDirectiveArgumentEnd.code();
+        assert_eq!(errors, [(no_end, "[foo")]);
+    }
+
+    #[test]
+    fn test_prop_shorthand_edge() {
+        use crate::error::ErrorKind as _;
+        let parse = |case: &'static str| {
+            let (root, errors) = parse_with_errors(case, ParseOption::default());
+            let elem = root.children[0].get_element().unwrap();
+            let dir = elem.dirs().next().unwrap();
+            let arg = match &dir.argument {
+                Some(DirectiveArg::Static(a)) | Some(DirectiveArg::Dynamic(a)) => Some(*a),
+                None => None,
+            };
+            let errors: Vec<_> = errors
+                .into_iter()
+                .map(|(kind, src)| (kind.code(), src))
+                .collect();
+            (arg, dir.modifiers.clone(), errors)
+        };
+        let (arg, mods, errors) = parse(r#"<p .foo="v"/>"#);
+        assert_eq!((arg, mods), (Some("foo"), vec!["prop"]));
+        assert!(errors.is_empty());
+        let (arg, mods, errors) = parse(r#"<p .[x]="v"/>"#);
+        assert_eq!((arg, mods), (Some("x"), vec!["prop"]));
+        assert!(errors.is_empty());
+        let (arg, mods, errors) = parse(r#"<p .foo.camel="v"/>"#);
+        assert_eq!((arg, mods), (Some("foo"), vec!["camel", "prop"]));
+        assert!(errors.is_empty());
+        let missing = ErrorKind::MissingDirectiveArg.code();
+        let (arg, mods, errors) = parse(r#"<p .="v"/>"#);
+        assert_eq!((arg, mods), (None, vec![]));
+        assert_eq!(errors, [(missing, ".")]);
+        let (arg, mods, errors) = parse(r#"<p ..foo="v"/>"#);
+        assert_eq!((arg, mods), (None, vec!["foo"]));
+        assert_eq!(errors, [(missing, ".")]);
+        let (arg, mods, errors) = parse(r#"<p .[]="v"/>"#);
+        assert_eq!((arg, mods), (None, vec![]));
+        assert_eq!(errors, [(missing, "[]")]);
+    }
+
+    #[test]
+    fn test_unquoted_value_with_slash() {
+        let root = base_parse("<img src=foo/bar.png><a :href=base/path>t</a>");
+        let img = root.children[0].get_element().unwrap();
+        let src = match &img.properties[0] {
+            ElemProp::Attr(a) => a.value.as_ref().unwrap(),
+            _ => panic!("expect attribute"),
+        };
+        assert_eq!(src.content.into_string(), "foo/bar.png");
+        // img is not void in default option, so a is its child
+        let a = img.children[0].get_element().unwrap();
+        let href = a.dirs().next().unwrap();
+        assert_eq!(href.expression.as_ref().unwrap().content.raw, "base/path");
+        assert_eq!(a.children.len(), 1);
+        // per spec, slash before > is also in the value and does not self-close
+        let root = base_parse("<div a=b/>t</div>");
+        let div = root.children[0].get_element().unwrap();
+        assert_eq!(div.children.len(), 1);
+    }
+
+    #[test]
+    fn test_slot_outlet_name() {
+        let name = |case| {
+            let e = mock_element(case);
+            match e.slot_outlet_name() {
+                SlotName::Static(s) => format!("static {}", s.raw),
+                SlotName::Dynamic(v) => format!("dynamic {}", v.content.raw),
+                SlotName::Default => "default".to_string(),
+            }
+        };
+        assert_eq!(name(r#"<slot name="a" b="c"/>"#), "static a");
+        assert_eq!(name(r#"<slot :name="a"/>"#), "dynamic a");
+        assert_eq!(name(r#"<slot v-bind:name="a"/>"#), "dynamic a");
+        assert_eq!(name(r#"<slot name="a" :name="b"/>"#), "dynamic b");
+        assert_eq!(name(r#"<slot name="" :b="c"/>"#), "default");
+        assert_eq!(name(r#"<slot/>"#), "default");
+        let e = mock_element(r#"<slot name="a" :b="c" d :name="e"/>"#);
+        let props: Vec<_> = e.slot_outlet_props().map(|p| p.get_location()).collect();
+        assert_eq!(props.len(), 2);
+        let e = base_parse(r#"<div v-pre><slot name="a"/></div>"#)
+            .children
+            .pop()
+            .unwrap()
+            .into_element();
+        let slot = e.children[0].get_element().unwrap();
+        assert_eq!(slot.tag_type, ElementType::Plain);
+        assert!(matches!(slot.slot_outlet_name(), SlotName::Default));
+    }
+
+    #[test]
+    fn test_directive_is_spread() {
+        let e = mock_element(r#"<p v-bind="a" v-on="b" :c="d" @e="f" v-g/>"#);
+        let spread: Vec<_> = e.dirs().map(|d| d.is_spread()).collect();
+        assert_eq!(spread, [true, true, false, false, false]);
+    }
+
+    #[test]
+    fn test_directive_handler_type() {
+        let e = mock_element(r#"<p @click="a.b" v-on:input="() => c" @blur v-bind:d="e"/>"#);
+        let types: Vec<_> = e.dirs().map(|d| d.handler_type()).collect();
+        assert_eq!(
+            types,
+            [
+                Some(HandlerType::MemberExpr),
+                Some(HandlerType::FuncExpr),
+                None,
+                None
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dangling_else() {
+        let head_locs = |case: &'static str| {
+            let errors = parse_errors(case, ParseOption::default()).into_iter();
+            errors
+                .map(|(kind, src)| {
+                    assert!(matches!(kind, ErrorKind::VElseNoAdjacentIf));
+                    src
+                })
+                .collect::<Vec<_>>()
+        };
+        let valid = r#"<p v-if="a"/> <!-- b --> <p v-else-if="b"/>
+            <p v-else/><div><p v-if="c"/><!---->
+<p v-else/></div>"#;
+        assert!(head_locs(valid).is_empty());
+        assert_eq!(head_locs(r#"<p v-if="a"/><p/><p v-else/>"#), ["v-else"]);
+        assert_eq!(head_locs(r#"<p v-else-if="a"/>"#), ["v-else-if"]);
+        assert_eq!(head_locs(r#"<p v-if="a"/>b<p v-else/>"#), ["v-else"]);
+        let nested = r#"<div><p v-if="a"/><p v-else/><p v-else/></div>"#;
+        assert_eq!(head_locs(nested), ["v-else"]);
+        assert!(head_locs(r#"<div v-pre><p v-else/></div>"#).is_empty());
+    }
+
+    #[test]
+    fn test_text_mode_by_attribute() {
+        let get_text_mode = |tag: &Tag| {
+            let is_pug =
+                |a: &Attribute| a.name == "lang" && a.value.as_ref().unwrap().raw() == "pug";
+            if tag.name == "template" && tag.attributes.iter().any(is_pug) {
+                TextMode::RawText
+            } else {
+                TextMode::Data
+            }
+        };
+        let option = ParseOption {
+            get_text_mode,
+            ..Default::default()
+        };
+        let scanner = crate::scanner::Scanner::new(crate::scanner::ScanOption {
+            get_text_mode,
+            ..Default::default()
+        });
+        let parse = |case| {
+            let tokens = scanner.scan(case, std::rc::Rc::new(TestErrorHandler));
+            let root =
+                Parser::new(option.clone()).parse(tokens, std::rc::Rc::new(TestErrorHandler));
+            root.children.into_iter().next().unwrap().into_element()
+        };
+        let pug = parse(r#"<template lang="pug">#app<p>a</p></template>"#);
+        assert_eq!(pug.children.len(), 1);
+        match &pug.children[0] {
+            AstNode::Text(t) => assert_eq!(t.text[0].raw, "#app<p>a</p>"),
+            _ => panic!("pug template should have raw text"),
+        }
+        let html = parse(r#"<template lang="html">#app<p>a</p></template>"#);
+        assert_eq!(html.children.len(), 2);
+    }
+
+    #[test]
+    fn test_force_closed_pre() {
+        let case = "<div><pre>\n a  b</div><p> a  b </p><div><pre><pre>\n</span></div>x  y";
+        let root = base_parse(case);
+        let text_of = |n: &AstNode| match n {
+            AstNode::Text(t) => t.text.iter().map(|s| s.into_string()).collect::<String>(),
+            _ => panic!("should be text"),
+        };
+        let div = root.children[0].get_element().unwrap();
+        let pre = div.children[0].get_element().unwrap();
+        assert_eq!(text_of(&pre.children[0]), " a  b");
+        // pre is closed, so whitespace after it is condensed again
+        let p = root.children[1].get_element().unwrap();
+        assert_eq!(text_of(&p.children[0]), " a b ");
+        assert_eq!(text_of(&root.children[3]), "x y");
+    }
+
+    #[test]
+    fn test_preserve_in_pre() {
+        let text_of = |n: &AstNode| match n {
+            AstNode::Text(t) => t.text.iter().map(|s| s.into_string()).collect::<String>(),
+            _ => panic!("should be text"),
+        };
+        // nested element and element as the first child
+        let root = base_parse("<pre><code>\n  x   y\n</code>  <b> </b>\n</pre>");
+        let pre = root.children[0].get_element().unwrap();
+        assert_eq!(pre.children.len(), 4);
+        let code = pre.children[0].get_element().unwrap();
+        assert_eq!(text_of(&code.children[0]), "\n  x   y\n");
+        assert_eq!(text_of(&pre.children[1]), "  ");
+        assert_eq!(text_of(&pre.children[3]), "\n");
+        // unclosed pre at EOF
+        let root = base_parse("<p>a</p>\n<pre>\n a  <i> b  </i>\n\n");
+        let pre = root.children[1].get_element().unwrap();
+        assert_eq!(text_of(&pre.children[0]), " a  ");
+        let i = pre.children[1].get_element().unwrap();
+        assert_eq!(text_of(&i.children[0]), " b  ");
+        assert_eq!(text_of(&pre.children[2]), "\n\n");
+        // comments
+        let parse = |preserve_all_in_pre| {
+            let option = ParseOption {
+                preserve_all_in_pre,
+                ..ParseOption::with_preserve_comment(false)
+            };
+            let case = "<!--a--><pre>x<!--b-->y<!--c--></pre><!--d-->";
+            let eh = std::rc::Rc::new(TestErrorHandler);
+            let root = Parser::new(option).parse(base_scan(case), eh);
+            let pre = root.children[0].get_element().unwrap();
+            (root.children.len(), pre.children.len())
+        };
+        assert_eq!(parse(false), (1, 1));
+        assert_eq!(parse(true), (1, 4));
+    }
+
+    #[test]
+    fn test_trimmed_expression_location() {
+        let expr_loc = |case: &str| {
+            let elem = mock_element(case);
+            let dir = elem.dirs().next().unwrap();
+            let loc = dir.expression.as_ref().unwrap().location.clone();
+            let error = dir.check_empty_expr(ErrorKind::VIfNoExpression);
+            let slice = &case[loc.start.offset..loc.end.offset];
+            (slice.to_string(), loc.start.column, error.is_some())
+        };
+        assert_eq!(expr_loc(r#"<p :x="  foo  "/>"#), ("foo".into(), 10, false));
+        assert_eq!(
+            expr_loc("<p :x='a\n  + b\n'/>"),
+            ("a\n  + b".into(), 8, false)
+        );
+        assert_eq!(expr_loc("<p :x=\n  a />"), ("a".into(), 3, false));
+        // empty expression keeps the whole value for error report
+        assert_eq!(expr_loc(r#"<p v-if="   "/>"#), ("   ".into(), 10, true));
+        assert_eq!(expr_loc("<p v-if=''/>"), ("".into(), 10, true));
+    }
+
+    #[test]
+    fn test_parse_directive() {
+        let loc = |start: usize, end: usize| {
+            let pos = |offset| Position {
+                offset,
+                line: 1,
+                column: offset as u32 + 1,
+            };
+            SourceLocation {
+                start: pos(start),
+                end: pos(end),
+            }
+        };
+        let parse = |name, value| match parse_directive(name, value, loc(0, 5), loc(7, 12)) {
+            Some(Ok(dir)) => dir,
+            _ => panic!("{} should be a valid directive", name),
+        };
+        let dir = parse("v-on:click.prevent.stop", Some(" do() "));
+        assert_eq!(dir.name, "on");
+        assert!(matches!(dir.argument, Some(DirectiveArg::Static("click"))));
+        assert_eq!(dir.modifiers, ["prevent", "stop"]);
+        assert_eq!(dir.expression.as_ref().unwrap().content.raw, "do()");
+        assert_eq!(dir.head_loc, loc(0, 5));
+        assert_eq!(dir.location, loc(0, 12));
+        assert_eq!(dir.expression.unwrap().location, loc(8, 12));
+        for (name, dir_name, arg) in [
+            ("@click", "on", "click"),
+            (":id", "bind", "id"),
+            ("#item", "slot", "item"),
+        ] {
+            let dir = parse(name, None);
+            assert_eq!(dir.name, dir_name);
+            assert!(matches!(dir.argument, Some(DirectiveArg::Static(a)) if a == arg));
+            assert_eq!(dir.location, loc(0, 5));
+        }
+        let dir = parse(".text-content", Some("a"));
+        assert_eq!(dir.modifiers, ["prop"]);
+        let dir = parse(":[key]", Some("a"));
+        assert!(matches!(dir.argument, Some(DirectiveArg::Dynamic("key"))));
+        assert!(parse_directive("class", Some("a"), loc(0, 5), loc(7, 12)).is_none());
+        // returns the error and the part of name it locates
+        let error = |name: &'static str| {
+            let name_loc = loc(0, name.len());
+            match parse_directive(name, None, name_loc, loc(0, 0)) {
+                Some(Err(errors)) => {
+                    assert_eq!(errors.len(), 1);
+                    let l = &errors[0].location;
+                    let part = &name[l.start.offset..l.end.offset];
+                    (errors.into_iter().next().unwrap().kind, part)
+                }
+                _ => panic!("{} should have error", name),
+            }
+        };
+        use ErrorKind as K;
+        assert!(matches!(error("v-"), (K::MissingDirectiveName, "v-")));
+        assert!(matches!(error("v-on:"), (K::MissingDirectiveArg, ":")));
+        assert!(matches!(error("@click..a"), (K::MissingDirectiveMod, "")));
+        let end = error(":[a.b");
+        assert!(matches!(
+            end,
+            (K::MissingDynamicDirectiveArgumentEnd, "[a.b")
+        ));
+        assert!(matches!(
+            error(":[a b]"),
+            (K::InvalidDynamicArgument, "[a b]")
+        ));
+        let content = error(":[a]bc.d");
+        assert!(matches!(
+            content,
+            (K::UnexpectedContentAfterDynamicDirective, "bc")
+        ));
+        let content = error("#[a]bc");
+        assert!(matches!(
+            content,
+            (K::UnexpectedContentAfterDynamicDirective, "bc")
+        ));
+        assert!(matches!(error("v-slot.a"), (K::InvalidVSlotModifier, ".a")));
+        // argument and modifier locations
+        let dir = parse("@[evt].stop.prevent", None);
+        let part = |l: &SourceLocation| &"@[evt].stop.prevent"[l.start.offset..l.end.offset];
+        assert_eq!(part(dir.arg_loc.as_ref().unwrap()), "[evt]");
+        let mods: Vec<_> = dir.modifier_locs.iter().map(part).collect();
+        assert_eq!(mods, ["stop", "prevent"]);
+        let dir = parse(".a.camel", None);
+        let mods: Vec<_> = dir.modifier_locs.iter().map(|l| l.start.offset).collect();
+        assert_eq!((dir.modifiers, mods), (vec!["camel", "prop"], vec![3, 0]));
+        assert!(parse("v-if", None).arg_loc.is_none());
+    }
+
+    #[test]
+    fn test_parser_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<ParseOption>();
+        assert_send_sync::<Parser>();
+        assert_send_sync::<crate::scanner::Scanner>();
+    }
+
+    // feeds tokens a scanner would not produce, e.g. split text
+    // each token spans [start, end) on the first line
+    fn synthetic<'a>(tokens: Vec<(Token<'a>, usize, usize)>) -> VecTokenSource<'a> {
+        let pos = |offset: usize| Position {
+            offset,
+            line: 1,
+            column: offset as u32 + 1,
+        };
+        let tokens = tokens
+            .into_iter()
+            .map(|(t, start, end)| {
+                let (start, end) = (pos(start), pos(end));
+                (t, SourceLocation { start, end })
+            })
+            .collect();
+        VecTokenSource::new(tokens)
+    }
+
+    #[test]
+    fn test_text_node_full_text() {
+        let tag = Tag {
+            name: "script",
+            attributes: vec![],
+            self_closing: false,
+        };
+        let tokens = vec![
+            (Token::StartTag(tag), 0, 8),
+            (Token::from("a<!"), 8, 11),
+            (Token::from("--b"), 11, 14),
+        ];
+        let (root, errors) = parse_tokens_with_errors(synthetic(tokens), ParseOption::default());
+        let script = root.children[0].get_element().unwrap();
+        let text = cast!(&script.children[0], AstNode::Text);
+        assert_eq!(text.text.len(), 2);
+        assert_eq!(text.full_text(), "a<!--b");
+        assert_eq!(text.to_string(), "a<!--b");
+        assert!(errors
+            .iter()
+            .any(|e| matches!(e.kind, ErrorKind::EofInScriptHtmlCommentLikeText)));
+
+        let root = base_parse("<p>a&amp;b</p>");
+        let p = root.children[0].get_element().unwrap();
+        let text = cast!(&p.children[0], AstNode::Text);
+        assert_eq!(text.full_text(), "a&b");
+        let root = base_parse("<p>ab</p>");
+        let p = root.children[0].get_element().unwrap();
+        let text = cast!(&p.children[0], AstNode::Text);
+        assert!(matches!(text.full_text(), Cow::Borrowed("ab")));
+    }
+
+    #[test]
+    fn test_cdata() {
+        fn parse(case: &str) -> (AstRoot<'_>, Vec<&'static str>) {
+            let option = ParseOption {
+                get_namespace: preset::get_namespace,
+                ..Default::default()
+            };
+            let (root, errors) = scan_parse_with_errors(case, ScanOption::default(), option);
+            let errors: Vec<_> = errors
+                .iter()
+                .map(|e| match e.kind {
+                    ErrorKind::CDataInHtmlContent => "html",
+                    ErrorKind::EofInCdata => "eof",
+                    _ => "other",
+                })
+                .collect();
+            (root, errors)
+        }
+        let (root, errors) = parse("<svg><![CDATA[ raw &amp; <text> ]]></svg>");
+        assert!(errors.is_empty());
+        let svg = root.children[0].get_element().unwrap();
+        let text = cast!(&svg.children[0], AstNode::Text);
+        assert!(text.is_cdata);
+        assert_eq!(text.full_text(), " raw &amp; <text> ");
+        // CDATA is exempt from whitespace condensing
+        let (root, _) = parse("<svg><g/><![CDATA[ \n ]]><g/></svg>");
+        let svg = root.children[0].get_element().unwrap();
+        assert_eq!(svg.children.len(), 3);
+        let text = cast!(&svg.children[1], AstNode::Text);
+        assert_eq!(text.full_text(), " \n ");
+        // scanner flag is reset after leaving foreign content
+        let (root, errors) = parse("<svg></svg><div><![CDATA[x]]></div>");
+        assert_eq!(errors, ["html"]);
+        let div = root.children[1].get_element().unwrap();
+        let comment = cast!(&div.children[0], AstNode::Comment);
+        assert_eq!(comment.source, "[CDATA[x]]");
+        let (root, errors) = parse("<svg><![CDATA[abc");
+        assert_eq!(errors, ["eof", "other"]);
+        let svg = root.children[0].get_element().unwrap();
+        let text = cast!(&svg.children[0], AstNode::Text);
+        assert_eq!(text.full_text(), "abc");
+        assert_eq!(text.location.end.offset, 17);
+    }
+
+    #[test]
+    fn test_synthetic_tokens() {
+        let tag = |name| Tag {
+            name,
+            attributes: vec![],
+            self_closing: false,
+        };
+        // comment directly followed by an end tag, `<p><!--a--></p>`
+        let tokens = vec![
+            (Token::StartTag(tag("p")), 0, 3),
+            (Token::Comment("a"), 3, 11),
+            (Token::EndTag("p"), 11, 15),
+        ];
+        let (root, errors) = parse_tokens_with_errors(synthetic(tokens), ParseOption::default());
+        let p = root.children[0].get_element().unwrap();
+        assert_eq!(p.location.end.offset, 15);
+        let comment = cast!(&p.children[0], AstNode::Comment);
+        assert_eq!(comment.location.start.offset, 3);
+        assert_eq!(comment.location.end.offset, 11);
+        assert!(errors.is_empty());
+        // unclosed script comment is reported at the start of last token
+        let tokens = vec![
+            (Token::StartTag(tag("script")), 0, 8),
+            (Token::from("<!--"), 8, 12),
+            (Token::from("a"), 12, 13),
+        ];
+        let mut tokens = synthetic(tokens).with_flag_hint(true);
+        assert!(tokens.peek().is_some());
+        assert_eq!(tokens.current_position().offset, 0);
+        let (_, errors) = parse_tokens_with_errors(tokens, ParseOption::default());
+        let error = errors
+            .iter()
+            .find(|e| matches!(e.kind, ErrorKind::EofInScriptHtmlCommentLikeText))
+            .unwrap();
+        assert_eq!(error.location.start.offset, 12);
+        // interpolation in v-pre ignoring FlagVPre is kept as text
+        let attr = Attribute {
+            name: "v-pre",
+            value: None,
+            name_loc: Default::default(),
+            location: Default::default(),
+        };
+        let v_pre = Tag {
+            attributes: vec![attr],
+            ..tag("p")
+        };
+        let tokens = vec![
+            (Token::StartTag(v_pre), 0, 9),
+            (Token::from("a "), 9, 11),
+            (Token::Interpolation(" b "), 11, 18),
+            (Token::from(" c"), 18, 20),
+            (Token::Interpolation("d"), 20, 25),
+            (Token::EndTag("p"), 25, 29),
+        ];
+        let eh = std::rc::Rc::new(TestErrorHandler);
+        let root = Parser::new(ParseOption::default()).parse(synthetic(tokens), eh);
+        let p = root.children[0].get_element().unwrap();
+        let text = match &p.children[..] {
+            [AstNode::Text(t)] => t,
+            _ => panic!("v-pre children should be one text"),
+        };
+        assert_eq!(text.full_text(), "a {{ b }} c{{d}}");
+        assert_eq!(text.location.start.offset, 9);
+        assert_eq!(text.location.end.offset, 25);
+    }
+
+    #[cfg(feature = "bumpalo")]
+    #[test]
+    fn test_parse_in_arena() {
+        let src = r#"
+<div id="app" v-else>
+  <svg viewBox="0 0 10 10"><foreignObject><p>{{ a }}</p></foreignObject></svg>
+  <!-- comment -->
+  <comp v-for="item in items" #default="{ b }">text &amp; {{ b }}</comp>
+  <pre>
+  kept   </pre>
+  <span v-pre>{{ raw }}</span>
+</div>"#;
+        let option = || ParseOption {
+            get_namespace: preset::get_namespace,
+            ..Default::default()
+        };
+        let (root, errors) = parse_tokens_with_errors(base_scan(src), option());
+        let eh = std::rc::Rc::new(crate::error::VecErrorHandler::new());
+        let arena = bumpalo::Bump::new();
+        let in_arena = Parser::new(option()).parse_in(base_scan(src), eh.clone(), &arena);
+        assert_eq!(in_arena.to_owned_ast(), root.to_owned_ast());
+        assert_eq!(in_arena.node_count(), root.node_count());
+        let codes = |errors: &[CompilationError]| -> Vec<_> {
+            errors
+                .iter()
+                .map(|e| (e.code(), e.location.start.offset))
+                .collect()
+        };
+        assert_eq!(codes(&eh.errors()), codes(&errors));
+        assert_eq!(
+            serde_json::to_value(&in_arena).unwrap(),
+            serde_json::to_value(&root).unwrap()
+        );
+        let svg = &in_arena.single_root().unwrap().children[0];
+        let svg = svg.get_element().unwrap();
+        assert!(svg.namespace == Namespace::Svg);
+        let p = svg.children[0].get_element().unwrap().children[0].get_element();
+        assert!(p.unwrap().namespace == Namespace::Html);
+        let arena = bumpalo::Bump::new();
+        let in_arena = base_parse_in(src, &arena);
+        assert_eq!(in_arena.to_owned_ast(), base_parse(src).to_owned_ast());
+    }
+
+    pub fn base_parse(s: &str) -> AstRoot {
+        let tokens = base_scan(s);
+        let parser = Parser::new(ParseOption {
+            is_native_element: (|s: &str| s != "comp").into(),
+            ..Default::default()
+        });
+        let eh = std::rc::Rc::new(TestErrorHandler);
+        parser.parse(tokens, eh)
+    }
+
+    #[cfg(feature = "bumpalo")]
+    pub fn base_parse_in<'a>(s: &'a str, arena: &'a bumpalo::Bump) -> AstRoot<'a, Arena<'a>> {
+        let tokens = base_scan(s);
+        let parser = Parser::new(ParseOption {
+            is_native_element: (|s: &str| s != "comp").into(),
+            ..Default::default()
+        });
+        let eh = std::rc::Rc::new(TestErrorHandler);
+        parser.parse_in(tokens, eh, arena)
+    }
+
+    pub fn mock_element(s: &str) -> Element {
+        let mut m = base_parse(s).children;
+        m.pop().unwrap().into_element()
+    }
+
+    // errors reported by parser only, scanner errors are dropped by the token source
+    pub fn parse_tokens_with_errors<'a, Ts: TokenSource<'a>>(
+        tokens: Ts,
+        option: ParseOption,
+    ) -> (AstRoot<'a>, Vec<CompilationError>) {
+        let eh = std::rc::Rc::new(crate::error::VecErrorHandler::new());
+        let root = Parser::new(option).parse(tokens, eh.clone());
+        let errors = std::mem::take(&mut *eh.error_mut());
+        (root, errors)
+    }
+
+    // errors from both scanner and parser in reporting order
+    pub fn scan_parse_with_errors(
+        s: &str,
+        scan: ScanOption,
+        option: ParseOption,
+    ) -> (AstRoot<'_>, Vec<CompilationError>) {
+        let eh = std::rc::Rc::new(crate::error::VecErrorHandler::new());
+        let tokens = Scanner::new(scan).scan(s, eh.clone());
+        let root = Parser::new(option).parse(tokens, eh.clone());
+        let errors = std::mem::take(&mut *eh.error_mut());
+        (root, errors)
+    }
+
+    // errors paired with the source they point at
+    pub fn parse_with_errors(
+        s: &str,
+        option: ParseOption,
+    ) -> (AstRoot<'_>, Vec<(ErrorKind, &str)>) {
+        let (root, errors) = parse_tokens_with_errors(base_scan(s), option);
+        let errors = errors
+            .into_iter()
+            .map(|e| {
+                let loc = &e.location;
+                (e.kind, &s[loc.start.offset..loc.end.offset])
+            })
+            .collect();
+        (root, errors)
+    }
+
+    pub fn parse_errors(s: &str, option: ParseOption) -> Vec<(ErrorKind, &str)> {
+        parse_with_errors(s, option).1
     }
 }
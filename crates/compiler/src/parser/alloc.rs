@@ -0,0 +1,121 @@
+// Allocation of AST containers, i.e. children of root and elements.
+// Heap is the default and uses std Vec, so AST types without an explicit
+// allocator are the same as before. Arena, with the bumpalo feature, puts the
+// containers in a bump arena: fewer heap allocations and the whole tree is freed
+// with the arena. Properties stay in std Vec since they are built from scanner
+// attributes. Consumers like converters only accept Heap AST.
+
+use super::Element;
+use std::ops::{Deref, DerefMut};
+
+#[cfg(feature = "serde")]
+use serde::{Serialize, Serializer};
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// Chooses where AST children are allocated.
+/// Sealed: the parser only builds AST with Heap or Arena.
+pub trait AstAlloc<'a>: Copy + 'a + sealed::Sealed {
+    type Vec<T: 'a>: AstVec<T>;
+    fn new_vec<T: 'a>(self) -> Self::Vec<T>;
+    /// Lends the element as Heap AST for ParseOption hooks, which take `&Element`.
+    /// Arena lends a copy without children.
+    #[doc(hidden)]
+    fn with_heap_element<R>(e: &mut Element<'a, Self>, f: impl FnOnce(&Element<'a>) -> R) -> R;
+}
+
+/// Vec operations the parser needs on AST containers.
+pub trait AstVec<T>: Deref<Target = [T]> + DerefMut + IntoIterator<Item = T> {
+    fn push(&mut self, value: T);
+    fn pop(&mut self) -> Option<T>;
+    fn truncate(&mut self, len: usize);
+}
+
+/// Global allocator. AST types use it by default.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Heap;
+
+impl sealed::Sealed for Heap {}
+impl<'a> AstAlloc<'a> for Heap {
+    type Vec<T: 'a> = Vec<T>;
+    fn new_vec<T: 'a>(self) -> Vec<T> {
+        Vec::new()
+    }
+    fn with_heap_element<R>(e: &mut Element<'a>, f: impl FnOnce(&Element<'a>) -> R) -> R {
+        f(e)
+    }
+}
+
+impl<T> AstVec<T> for Vec<T> {
+    fn push(&mut self, value: T) {
+        Vec::push(self, value)
+    }
+    fn pop(&mut self) -> Option<T> {
+        Vec::pop(self)
+    }
+    fn truncate(&mut self, len: usize) {
+        Vec::truncate(self, len)
+    }
+}
+
+#[cfg(feature = "bumpalo")]
+pub use arena::Arena;
+
+#[cfg(feature = "bumpalo")]
+mod arena {
+    use super::{sealed, AstAlloc, AstVec, Element};
+    use bumpalo::{collections::Vec as BumpVec, Bump};
+    use std::mem;
+
+    /// Bump arena for Parser::parse_in.
+    #[derive(Clone, Copy)]
+    pub struct Arena<'a>(pub &'a Bump);
+
+    impl<'a> sealed::Sealed for Arena<'a> {}
+    impl<'a> AstAlloc<'a> for Arena<'a> {
+        type Vec<T: 'a> = BumpVec<'a, T>;
+        fn new_vec<T: 'a>(self) -> BumpVec<'a, T> {
+            BumpVec::new_in(self.0)
+        }
+        fn with_heap_element<R>(e: &mut Element<'a, Self>, f: impl FnOnce(&Element<'a>) -> R) -> R {
+            // properties are moved out and back, so no allocation
+            let shell = Element {
+                tag_name: e.tag_name,
+                tag_type: e.tag_type,
+                namespace: e.namespace,
+                properties: mem::take(&mut e.properties),
+                children: vec![],
+                location: e.location.clone(),
+                id: e.id,
+            };
+            let ret = f(&shell);
+            e.properties = shell.properties;
+            ret
+        }
+    }
+
+    impl<'a, T: 'a> AstVec<T> for BumpVec<'a, T> {
+        fn push(&mut self, value: T) {
+            BumpVec::push(self, value)
+        }
+        fn pop(&mut self) -> Option<T> {
+            BumpVec::pop(self)
+        }
+        fn truncate(&mut self, len: usize) {
+            BumpVec::truncate(self, len)
+        }
+    }
+}
+
+// serializes any container as a sequence, the same as Vec
+#[cfg(feature = "serde")]
+pub(super) fn serialize_slice<V, T, S>(v: &V, serializer: S) -> Result<S::Ok, S::Error>
+where
+    V: Deref<Target = [T]>,
+    T: Serialize,
+    S: Serializer,
+{
+    serializer.collect_seq(v.iter())
+}
@@ -0,0 +1,187 @@
+// Chainable construction of ParseOption. Predicate hooks are closures so
+// they can capture configuration data like a set of custom element names.
+
+use super::{Element, ParseOption, WhitespaceStrategy};
+use crate::{
+    flags::RuntimeHelper,
+    scanner::{Tag, TextMode},
+    Namespace,
+};
+use rustc_hash::FxHashSet;
+use std::{fmt, ops::Deref, sync::Arc};
+
+/// A string predicate hook in ParseOption, e.g. `is_void_tag`.
+/// It is `Send + Sync` so Parser can still be shared across threads.
+/// Function pointers and closures convert to it by `into()`.
+#[derive(Clone)]
+pub struct StrPredicate(Arc<dyn Fn(&str) -> bool + Send + Sync>);
+
+impl<F> From<F> for StrPredicate
+where
+    F: Fn(&str) -> bool + Send + Sync + 'static,
+{
+    fn from(f: F) -> Self {
+        Self(Arc::new(f))
+    }
+}
+
+/// Makes the predicate callable like a fn pointer, e.g. `(option.is_void_tag)(tag)`.
+impl Deref for StrPredicate {
+    type Target = dyn Fn(&str) -> bool + Send + Sync;
+    fn deref(&self) -> &Self::Target {
+        &*self.0
+    }
+}
+
+impl fmt::Debug for StrPredicate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("StrPredicate")
+    }
+}
+
+/// Builds ParseOption from hooks and data. Fields not covered here,
+/// e.g. limits, can be set by struct update on the built option.
+#[derive(Clone, Default)]
+pub struct ParseOptionBuilder {
+    option: ParseOption,
+}
+
+impl ParseOptionBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn whitespace(mut self, whitespace: WhitespaceStrategy) -> Self {
+        self.option.whitespace = whitespace;
+        self
+    }
+    pub fn comment_filter(mut self, f: impl Fn(&str) -> bool + Send + Sync + 'static) -> Self {
+        self.option.comment_filter = f.into();
+        self
+    }
+    pub fn is_void_tag(mut self, f: impl Fn(&str) -> bool + Send + Sync + 'static) -> Self {
+        self.option.is_void_tag = f.into();
+        self
+    }
+    pub fn is_pre_tag(mut self, f: impl Fn(&str) -> bool + Send + Sync + 'static) -> Self {
+        self.option.is_pre_tag = f.into();
+        self
+    }
+    pub fn is_inline_tag(mut self, f: impl Fn(&str) -> bool + Send + Sync + 'static) -> Self {
+        self.option.is_inline_tag = f.into();
+        self
+    }
+    pub fn is_custom_element(mut self, f: impl Fn(&str) -> bool + Send + Sync + 'static) -> Self {
+        self.option.is_custom_element = f.into();
+        self
+    }
+    pub fn is_native_element(mut self, f: impl Fn(&str) -> bool + Send + Sync + 'static) -> Self {
+        self.option.is_native_element = f.into();
+        self
+    }
+    pub fn is_known_directive(mut self, f: impl Fn(&str) -> bool + Send + Sync + 'static) -> Self {
+        self.option.is_known_directive = Some(f.into());
+        self
+    }
+    pub fn get_namespace(mut self, f: fn(&str, Option<&Element<'_>>) -> Namespace) -> Self {
+        self.option.get_namespace = f;
+        self
+    }
+    /// Should be the same as ScanOption::get_text_mode.
+    pub fn get_text_mode(mut self, f: fn(&Tag<'_>) -> TextMode) -> Self {
+        self.option.get_text_mode = f;
+        self
+    }
+    pub fn get_builtin_component(mut self, f: fn(&str) -> Option<RuntimeHelper>) -> Self {
+        self.option.get_builtin_component = f;
+        self
+    }
+    /// Treats exactly the given tags as void tags.
+    pub fn void_tags<I, S>(self, tags: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let tags = collect_set(tags);
+        self.is_void_tag(move |tag| tags.contains(tag))
+    }
+    /// Treats exactly the given tags as custom elements.
+    pub fn custom_elements<I, S>(self, tags: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let tags = collect_set(tags);
+        self.is_custom_element(move |tag| tags.contains(tag))
+    }
+    pub fn build(self) -> ParseOption {
+        self.option
+    }
+}
+
+fn collect_set<I, S>(items: I) -> FxHashSet<String>
+where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+{
+    items.into_iter().map(Into::into).collect()
+}
+
+impl ParseOption {
+    pub fn builder() -> ParseOptionBuilder {
+        ParseOptionBuilder::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::error::test::TestErrorHandler;
+    use crate::parser::{AstNode, ElementType, Parser};
+    use crate::scanner::test::base_scan;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_builder_from_data() {
+        let option = ParseOption::builder()
+            .void_tags(["br", "img"])
+            .custom_elements(vec![String::from("my-widget")])
+            .is_native_element(|tag| tag != "comp")
+            .build();
+        assert!((option.is_void_tag)("img"));
+        assert!(!(option.is_void_tag)("div"));
+        let parser = Parser::new(option);
+        let case = "<div><img><my-widget/><comp/></div>";
+        let root = parser.parse(base_scan(case), Rc::new(TestErrorHandler));
+        let div = root.children[0].get_element().unwrap();
+        let types: Vec<_> = div
+            .children
+            .iter()
+            .map(|n| match n {
+                AstNode::Element(e) => e.tag_type,
+                _ => panic!("expect element"),
+            })
+            .collect();
+        assert_eq!(
+            types,
+            [
+                ElementType::Plain,
+                ElementType::Plain,
+                ElementType::Component
+            ]
+        );
+        assert!(div.children[0].get_element().unwrap().children.is_empty());
+    }
+
+    #[test]
+    fn test_fn_pointer_into() {
+        fn is_br(tag: &str) -> bool {
+            tag == "br"
+        }
+        let f: fn(&str) -> bool = is_br;
+        let option = ParseOption {
+            is_void_tag: f.into(),
+            ..ParseOption::builder().is_void_tag(crate::util::no).build()
+        };
+        assert!((option.is_void_tag)("br"));
+    }
+}
@@ -0,0 +1,168 @@
+// Parse option presets for HTML platforms. compiler-dom reuses these hooks
+// so that a template parsed by core alone matches the dom compiler.
+
+use super::{Element, ParseOption};
+use crate::{
+    flags::RuntimeHelper as RH,
+    scanner::{Tag, TextMode},
+    Namespace,
+};
+use phf::{phf_set, Set};
+
+// Tag lists are the same as vue/shared's HTML_TAGS, SVG_TAGS and MATH_TAGS.
+const HTML_TAGS: Set<&str> = phf_set! {
+    "html","body","base","head","link","meta","style","title","address","article","aside",
+    "footer","header","hgroup","h1","h2","h3","h4","h5","h6","nav","section","div","dd","dl",
+    "dt","figcaption", "figure","picture","hr","img","li","main","ol","p","pre","ul","a","b",
+    "abbr","bdi","bdo","br","cite","code","data","dfn","em","i","kbd","mark","q","rp","rt",
+    "ruby","s","samp","small","span","strong","sub","sup","time","u","var","wbr","area",
+    "audio","map","track","video","embed","object","param","source","canvas","script",
+    "noscript","del","ins","caption","col","colgroup","table","thead","tbody","td","th",
+    "tr","button","datalist","fieldset","form","input","label","legend","meter","optgroup",
+    "option","output","progress","select","textarea","details","dialog","menu","summary",
+    "template","blockquote","iframe","tfoot",
+};
+
+const SVG_TAGS: Set<&str> = phf_set! {
+    "svg","animate","animateMotion","animateTransform","circle","clipPath","color-profile",
+    "defs","desc","discard","ellipse","feBlend","feColorMatrix","feComponentTransfer",
+    "feComposite","feConvolveMatrix","feDiffuseLighting","feDisplacementMap",
+    "feDistantLight","feDropShadow","feFlood","feFuncA","feFuncB","feFuncG","feFuncR",
+    "feGaussianBlur","feImage","feMerge","feMergeNode","feMorphology","feOffset",
+    "fePointLight","feSpecularLighting","feSpotLight","feTile","feTurbulence","filter",
+    "foreignObject","g","hatch","hatchpath","image","line","linearGradient","marker","mask",
+    "mesh","meshgradient","meshpatch","meshrow","metadata","mpath","path","pattern",
+    "polygon","polyline","radialGradient","rect","set","solidcolor","stop","switch","symbol",
+    "text","textPath","title","tspan","unknown","use","view",
+};
+
+const MATHML_TAGS: Set<&str> = phf_set! {
+    "annotation","annotation-xml","maction","maligngroup","malignmark","math","menclose",
+    "merror","mfenced","mfrac","mfraction","mglyph","mi","mlabeledtr","mlongdiv",
+    "mmultiscripts","mn","mo","mover","mpadded","mphantom","mprescripts","mroot","mrow","ms",
+    "mscarries","mscarry","msgroup","msline","mspace","msqrt","msrow","mstack","mstyle","msub",
+    "msubsup","msup","mtable","mtd","mtext","mtr","munder","munderover","none","semantics",
+};
+
+pub fn is_html_tag(tag: &str) -> bool {
+    HTML_TAGS.contains(tag)
+}
+
+pub fn is_svg_tag(tag: &str) -> bool {
+    SVG_TAGS.contains(tag)
+}
+
+pub fn is_mathml_tag(tag: &str) -> bool {
+    MATHML_TAGS.contains(tag)
+}
+
+/// For ParseOption::is_native_element. Matching is case sensitive like
+/// vue/shared, so `<clipPath>` is native while `<clippath>` is not.
+pub fn dom_is_native_element(tag: &str) -> bool {
+    is_html_tag(tag) || is_svg_tag(tag) || is_mathml_tag(tag)
+}
+
+// textarea also trims its leading newline, and keeps whitespace as RCDATA
+pub fn is_pre_tag(tag: &str) -> bool {
+    tag.eq_ignore_ascii_case("pre") || tag == "textarea"
+}
+
+const VOID_TAGS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "track", "wbr",
+];
+pub fn is_void_tag(tag: &str) -> bool {
+    VOID_TAGS.contains(&tag)
+}
+
+pub fn get_dom_builtin_component(tag: &str) -> Option<RH> {
+    match tag {
+        "transition" | "Transition" => Some(RH::TRANSITION),
+        "TransitionGroup" | "transition-group" => Some(RH::TRANSITION_GROUP),
+        _ => None,
+    }
+}
+
+/// Transition has no DOM hooks to run on server so it is the
+/// runtime-core BaseTransition. TransitionGroup still renders its tag.
+pub fn get_ssr_builtin_component(tag: &str) -> Option<RH> {
+    match tag {
+        "transition" | "Transition" => Some(RH::BASE_TRANSITION),
+        "TransitionGroup" | "transition-group" => Some(RH::TRANSITION_GROUP),
+        _ => None,
+    }
+}
+
+/// Text mode of HTML elements like compiler-dom: `textarea` and `title`
+/// decode entities and tokenize interpolation, raw text elements do neither.
+pub fn dom_get_text_mode(tag: &Tag) -> TextMode {
+    match tag.name {
+        "style" | "script" | "iframe" | "noscript" => TextMode::RawText,
+        "textarea" | "title" => TextMode::RcData,
+        _ => TextMode::Data,
+    }
+}
+
+#[deprecated(note = "use `dom_get_text_mode` instead")]
+pub fn get_text_mode(tag: &Tag) -> TextMode {
+    dom_get_text_mode(tag)
+}
+
+// https://html.spec.whatwg.org/multipage/parsing.html#tree-construction-dispatcher
+pub fn get_namespace(tag: &str, parent: Option<&Element>) -> Namespace {
+    if let Some(p) = parent {
+        if p.namespace == Namespace::MathMl {
+            if p.tag_name == "annotation-xml" {
+                if tag == "svg" {
+                    return Namespace::Svg;
+                } else {
+                    return Namespace::Html;
+                }
+            } else if ["mi", "mo", "mn", "ms", "mtext"].contains(&p.tag_name) {
+                if tag == "mglyph" || tag == "malignmark" {
+                    return Namespace::MathMl;
+                } else {
+                    return Namespace::Html;
+                }
+            } else {
+                return Namespace::MathMl;
+            }
+        }
+        if p.namespace == Namespace::Svg {
+            if ["foreignObject", "desc", "title"].contains(&p.tag_name) {
+                return Namespace::Html;
+            } else {
+                return Namespace::Svg;
+            }
+        }
+    }
+    if tag == "svg" {
+        Namespace::Svg
+    } else if tag == "math" {
+        Namespace::MathMl
+    } else {
+        Namespace::Html
+    }
+}
+
+impl ParseOption {
+    /// Parses templates like compiler-dom. Scanner should use the same get_text_mode.
+    pub fn dom() -> Self {
+        Self {
+            get_namespace,
+            get_text_mode: dom_get_text_mode,
+            is_void_tag: is_void_tag.into(),
+            is_pre_tag: is_pre_tag.into(),
+            is_native_element: dom_is_native_element.into(),
+            get_builtin_component: get_dom_builtin_component,
+            ..Default::default()
+        }
+    }
+    /// Same as `dom()` except builtin components rendered on server.
+    pub fn ssr() -> Self {
+        Self {
+            get_builtin_component: get_ssr_builtin_component,
+            ..Self::dom()
+        }
+    }
+}
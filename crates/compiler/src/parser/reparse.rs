@@ -0,0 +1,564 @@
+// Incremental reparse for editor tooling.
+// The innermost element strictly containing the edit is re-scanned and re-parsed
+// from its own start position. Other nodes are copied into the new source with
+// their locations shifted by the edit. Since AST borrows from source, the copy
+// cannot share nodes with the previous tree but it avoids scanning and parsing.
+// Whole template is parsed again when the element cannot be parsed in isolation:
+// * the edit touches no element or crosses element boundaries.
+// * the element is inside `<pre>`, `v-pre` or non-HTML namespace.
+// * the element is at or beyond ParseLimits::max_nesting_depth.
+// * checks of the element look at its parent or siblings, e.g. v-else or key.
+// * the re-parsed slice has errors or is not a single element anymore.
+
+use super::{
+    assign_node_ids, is_v_pre_boundary, AstNode, AstRoot, Directive, DirectiveArg, ElemProp,
+    Element, ElementType, Parser, SourceNode, TextNode,
+};
+use crate::{
+    error::{RcErrHandle, Severity, VecErrorHandler},
+    scanner::{Attribute, AttributeValue, Scanner},
+    util::{dir_finder, find_prop, VStr},
+    Namespace, Position, SourceLocation,
+};
+use std::rc::Rc;
+
+/// A single text replacement in byte offsets, like tree-sitter's InputEdit.
+/// `old_src[start..old_end]` is replaced by `new_src[start..new_end]`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TextEdit {
+    pub start: usize,
+    pub old_end: usize,
+    pub new_end: usize,
+}
+
+impl Parser {
+    /// Parses `new_src`, reusing `prev` parsed from `old_src` where possible.
+    /// The result is the same as a full parse. Diagnostics are only
+    /// reported for the re-parsed part of the template.
+    pub fn reparse<'a, 'b>(
+        &self,
+        scanner: &Scanner,
+        prev: &AstRoot<'a>,
+        old_src: &'a str,
+        new_src: &'b str,
+        edit: &TextEdit,
+        err_handle: RcErrHandle,
+    ) -> AstRoot<'b> {
+        if let Some(root) = self.reparse_subtree(scanner, prev, old_src, new_src, edit, &err_handle)
+        {
+            return root;
+        }
+        let tokens = scanner.scan(new_src, err_handle.clone());
+        self.parse(tokens, err_handle)
+    }
+
+    fn reparse_subtree<'a, 'b>(
+        &self,
+        scanner: &Scanner,
+        prev: &AstRoot<'a>,
+        old_src: &'a str,
+        new_src: &'b str,
+        edit: &TextEdit,
+        err_handle: &RcErrHandle,
+    ) -> Option<AstRoot<'b>> {
+        let TextEdit {
+            start,
+            old_end,
+            new_end,
+        } = *edit;
+        let valid = start <= old_end
+            && start <= new_end
+            && old_end <= old_src.len()
+            && new_end <= new_src.len()
+            && new_src.len() - new_end == old_src.len() - old_end;
+        if !valid {
+            return None;
+        }
+        let (path, ancestors, target) = find_innermost(prev, edit)?;
+        let parent = ancestors.last().copied();
+        if !self.is_isolated(&ancestors) || depends_on_context(target, parent) {
+            return None;
+        }
+        let elem_start = target.location.start.clone();
+        let slice_end = target.location.end.offset - old_end + new_end;
+        let slice = new_src.get(elem_start.offset..slice_end)?;
+        // parse errors may be caused by context, e.g. stray end tag of an ancestor
+        let collector = Rc::new(VecErrorHandler::new());
+        let eh: RcErrHandle = collector.clone();
+        let tokens = scanner.scan_from(slice, elem_start.clone(), eh.clone());
+        // the slice starts at depth 0, so ancestors count against the limit
+        let mut option = self.option.clone();
+        if let Some(max) = &mut option.limits.max_nesting_depth {
+            *max -= ancestors.len();
+        }
+        let mut sub = Parser::new(option).parse(tokens, eh);
+        let has_error = collector
+            .errors()
+            .iter()
+            .any(|e| e.severity() == Severity::Error);
+        if has_error || sub.children.len() != 1 {
+            return None;
+        }
+        let elem = match sub.children.pop()? {
+            AstNode::Element(e) if e.location.end.offset == slice_end => e,
+            _ => return None,
+        };
+        if depends_on_context(&elem, parent) {
+            return None;
+        }
+        if (self.option.get_namespace)(elem.tag_name, parent) != elem.namespace {
+            return None;
+        }
+        let shifter = Shifter {
+            old_src,
+            new_src,
+            edit,
+            old_end_pos: advance(&elem_start, &old_src[elem_start.offset..old_end]),
+            new_end_pos: advance(&elem_start, &new_src[elem_start.offset..new_end]),
+        };
+        let mut replacement = Some(elem);
+        let mut children = shifter.children(&prev.children, &path, &mut replacement)?;
+        // the re-parsed element may have a different number of descendants
+        assign_node_ids(&mut children);
+        for error in collector.error_mut().drain(..) {
+            err_handle.on_error(error);
+        }
+        let frontmatter = match &prev.frontmatter {
+            Some(f) => Some(shifter.source_node(f)?),
+            None => None,
+        };
+        Some(AstRoot {
+            children,
+            location: shifter.location(&prev.location),
+            frontmatter,
+        })
+    }
+
+    fn is_isolated(&self, ancestors: &[&Element]) -> bool {
+        (self.option.limits.max_nesting_depth).map_or(true, |max| ancestors.len() < max)
+            && ancestors.iter().all(|e| {
+                e.namespace == Namespace::Html
+                    && !(self.option.is_pre_tag)(e.tag_name)
+                    && !is_v_pre_boundary(e)
+            })
+    }
+}
+
+// parser checks these against the parent or siblings, e.g. dangling v-else
+// or keys on children of `<template v-for>`. A slice cannot re-run them.
+fn depends_on_context(e: &Element, parent: Option<&Element>) -> bool {
+    let in_template_for = parent.map_or(false, |p| {
+        p.tag_type == ElementType::Template && p.dirs().any(|d| d.name == "for")
+    });
+    in_template_for
+        || dir_finder(e, ["if", "else-if", "else", "for", "slot"])
+            .allow_empty()
+            .find()
+            .is_some()
+        || find_prop(e, "key").is_some()
+}
+
+// returns child indexes, ancestors and the innermost element containing the edit
+fn find_innermost<'r, 'a>(
+    root: &'r AstRoot<'a>,
+    edit: &TextEdit,
+) -> Option<(Vec<usize>, Vec<&'r Element<'a>>, &'r Element<'a>)> {
+    let contains = |e: &Element| {
+        let loc = &e.location;
+        loc.start.offset < edit.start && edit.old_end < loc.end.offset
+    };
+    let find = |children: &'r [AstNode<'a>]| {
+        children.iter().enumerate().find_map(|(i, n)| match n {
+            AstNode::Element(e) if contains(e) => Some((i, e)),
+            _ => None,
+        })
+    };
+    let (i, mut target) = find(&root.children)?;
+    let mut path = vec![i];
+    let mut ancestors = vec![];
+    while let Some((i, e)) = find(&target.children) {
+        path.push(i);
+        ancestors.push(target);
+        target = e;
+    }
+    Some((path, ancestors, target))
+}
+
+fn advance(start: &Position, text: &str) -> Position {
+    let mut pos = start.clone();
+    pos.advance(text);
+    pos
+}
+
+// copies nodes outside the edit into new source
+struct Shifter<'e, 'a, 'b> {
+    old_src: &'a str,
+    new_src: &'b str,
+    edit: &'e TextEdit,
+    old_end_pos: Position,
+    new_end_pos: Position,
+}
+
+impl<'e, 'a, 'b> Shifter<'e, 'a, 'b> {
+    fn str(&self, s: &'a str) -> Option<&'b str> {
+        if s.is_empty() {
+            return Some("");
+        }
+        let base = self.old_src.as_ptr() as usize;
+        let ptr = s.as_ptr() as usize;
+        if ptr < base || ptr + s.len() > base + self.old_src.len() {
+            // names not from source, see DirectiveParser
+            return match s {
+                "bind" => Some("bind"),
+                "on" => Some("on"),
+                "slot" => Some("slot"),
+                "prop" => Some("prop"),
+                _ => None,
+            };
+        }
+        let offset = ptr - base;
+        let end = offset + s.len();
+        if end <= self.edit.start {
+            Some(&self.new_src[offset..end])
+        } else if offset >= self.edit.old_end {
+            let new_offset = offset - self.edit.old_end + self.edit.new_end;
+            Some(&self.new_src[new_offset..new_offset + s.len()])
+        } else {
+            None
+        }
+    }
+    fn vstr(&self, s: &VStr<'a>) -> Option<VStr<'b>> {
+        Some(VStr {
+            raw: self.str(s.raw)?,
+            ops: s.ops,
+        })
+    }
+    fn position(&self, pos: &Position) -> Position {
+        if pos.offset < self.edit.old_end {
+            debug_assert!(pos.offset <= self.edit.start);
+            return pos.clone();
+        }
+        let (old, new) = (&self.old_end_pos, &self.new_end_pos);
+        let column = if pos.line == old.line {
+            pos.column - old.column + new.column
+        } else {
+            pos.column
+        };
+        Position {
+            offset: pos.offset - old.offset + new.offset,
+            line: pos.line - old.line + new.line,
+            column,
+        }
+    }
+    fn location(&self, loc: &SourceLocation) -> SourceLocation {
+        SourceLocation {
+            start: self.position(&loc.start),
+            end: self.position(&loc.end),
+        }
+    }
+
+    // path leads to the node to be replaced
+    fn children(
+        &self,
+        nodes: &[AstNode<'a>],
+        path: &[usize],
+        replacement: &mut Option<Element<'b>>,
+    ) -> Option<Vec<AstNode<'b>>> {
+        let (target, rest) = match path.split_first() {
+            Some((&i, rest)) => (Some(i), rest),
+            None => (None, path),
+        };
+        nodes
+            .iter()
+            .enumerate()
+            .map(|(i, node)| {
+                if target != Some(i) {
+                    return self.node(node);
+                }
+                let e = node.get_element()?;
+                if rest.is_empty() {
+                    replacement.take().map(AstNode::Element)
+                } else {
+                    let children = self.children(&e.children, rest, replacement)?;
+                    self.element(e, children).map(AstNode::Element)
+                }
+            })
+            .collect()
+    }
+
+    fn node(&self, node: &AstNode<'a>) -> Option<AstNode<'b>> {
+        Some(match node {
+            AstNode::Element(e) => {
+                let children = self.children(&e.children, &[], &mut None)?;
+                AstNode::Element(self.element(e, children)?)
+            }
+            AstNode::Text(t) => AstNode::Text(TextNode {
+                text: t.text.iter().map(|s| self.vstr(s)).collect::<Option<_>>()?,
+                location: self.location(&t.location),
+                id: Default::default(),
+                is_cdata: t.is_cdata,
+            }),
+            AstNode::Interpolation(i) => AstNode::Interpolation(self.source_node(i)?),
+            AstNode::Comment(c) => AstNode::Comment(self.source_node(c)?),
+            AstNode::ProcessingInstruction(p) => {
+                AstNode::ProcessingInstruction(self.source_node(p)?)
+            }
+        })
+    }
+
+    fn source_node(&self, node: &SourceNode<'a>) -> Option<SourceNode<'b>> {
+        Some(SourceNode {
+            source: self.str(node.source)?,
+            location: self.location(&node.location),
+            id: Default::default(),
+        })
+    }
+
+    fn element(&self, e: &Element<'a>, children: Vec<AstNode<'b>>) -> Option<Element<'b>> {
+        let properties = e
+            .properties
+            .iter()
+            .map(|p| match p {
+                ElemProp::Attr(a) => self.attribute(a).map(ElemProp::Attr),
+                ElemProp::Dir(d) => self.directive(d).map(ElemProp::Dir),
+            })
+            .collect::<Option<_>>()?;
+        Some(Element {
+            tag_name: self.str(e.tag_name)?,
+            tag_type: e.tag_type,
+            namespace: e.namespace,
+            properties,
+            children,
+            location: self.location(&e.location),
+            id: Default::default(),
+        })
+    }
+
+    fn attribute(&self, attr: &Attribute<'a>) -> Option<Attribute<'b>> {
+        Some(Attribute {
+            name: self.str(attr.name)?,
+            value: self.attr_value(&attr.value)?,
+            name_loc: self.location(&attr.name_loc),
+            location: self.location(&attr.location),
+        })
+    }
+
+    // outer None means failure, inner None means no value
+    fn attr_value(&self, val: &Option<AttributeValue<'a>>) -> Option<Option<AttributeValue<'b>>> {
+        let val = match val {
+            Some(v) => v,
+            None => return Some(None),
+        };
+        Some(Some(AttributeValue {
+            content: self.vstr(&val.content)?,
+            location: self.location(&val.location),
+            quote: val.quote,
+        }))
+    }
+
+    fn directive(&self, dir: &Directive<'a>) -> Option<Directive<'b>> {
+        let argument = match &dir.argument {
+            Some(DirectiveArg::Static(a)) => Some(DirectiveArg::Static(self.str(a)?)),
+            Some(DirectiveArg::Dynamic(a)) => Some(DirectiveArg::Dynamic(self.str(a)?)),
+            None => None,
+        };
+        let modifiers = dir
+            .modifiers
+            .iter()
+            .map(|m| self.str(m))
+            .collect::<Option<_>>()?;
+        Some(Directive {
+            name: self.str(dir.name)?,
+            argument,
+            modifiers,
+            expression: self.attr_value(&dir.expression)?,
+            head_loc: self.location(&dir.head_loc),
+            location: self.location(&dir.location),
+            arg_loc: dir.arg_loc.as_ref().map(|l| self.location(l)),
+            modifier_locs: dir.modifier_locs.iter().map(|l| self.location(l)).collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{error::test::TestErrorHandler, parser::ParseOption, scanner::ScanOption};
+
+    fn parser() -> Parser {
+        Parser::new(ParseOption {
+            is_native_element: (|s: &str| s != "comp").into(),
+            ..Default::default()
+        })
+    }
+
+    fn parse(src: &str) -> AstRoot<'_> {
+        parse_with(&parser(), src)
+    }
+
+    fn parse_with<'a>(parser: &Parser, src: &'a str) -> AstRoot<'a> {
+        let scanner = Scanner::new(ScanOption::default());
+        parser.parse(
+            scanner.scan(src, Rc::new(TestErrorHandler)),
+            Rc::new(TestErrorHandler),
+        )
+    }
+
+    // replaces the first `old` after `after` by `new`
+    fn edit(src: &str, after: &str, old: &str, new: &str) -> (String, TextEdit) {
+        let start = src.find(after).unwrap() + after.len();
+        let start = start + src[start..].find(old).unwrap();
+        let old_end = start + old.len();
+        let new_src = format!("{}{}{}", &src[..start], new, &src[old_end..]);
+        let edit = TextEdit {
+            start,
+            old_end,
+            new_end: start + new.len(),
+        };
+        (new_src, edit)
+    }
+
+    // returns if the edit is parsed incrementally
+    fn check(src: &str, after: &str, old: &str, new: &str) -> bool {
+        check_with(&parser(), src, after, old, new)
+    }
+
+    fn check_with(parser: &Parser, src: &str, after: &str, old: &str, new: &str) -> bool {
+        let scanner = Scanner::new(ScanOption::default());
+        let prev = parse_with(parser, src);
+        let (new_src, edit) = edit(src, after, old, new);
+        let eh: RcErrHandle = Rc::new(TestErrorHandler);
+        let partial = parser.reparse_subtree(&scanner, &prev, src, &new_src, &edit, &eh);
+        let incremental = partial.is_some();
+        let reparse_eh = Rc::new(VecErrorHandler::new());
+        let reparsed = parser.reparse(&scanner, &prev, src, &new_src, &edit, reparse_eh.clone());
+        let full_eh = Rc::new(VecErrorHandler::new());
+        let tokens = scanner.scan(&new_src, full_eh.clone());
+        let full = parser.parse(tokens, full_eh.clone());
+        assert_eq!(reparsed.to_owned_ast(), full.to_owned_ast(), "{}", new_src);
+        let errors = |eh: &VecErrorHandler| {
+            let errors = eh.errors();
+            let mut errors: Vec<_> = errors
+                .iter()
+                .map(|e| (e.code(), e.location.start.offset, e.location.end.offset))
+                .collect();
+            errors.sort_unstable();
+            errors
+        };
+        assert_eq!(errors(&reparse_eh), errors(&full_eh), "{}", new_src);
+        incremental
+    }
+
+    const SRC: &str = r#"<div id="app">
+  <ul :class="list">
+    <li v-for="item in items"><b @click="pick(item)">{{ item }} text</b></li>
+    <li>
+      last
+    </li>
+  </ul>
+  <comp v-slot="{ a }"><i :title="a">tail &amp; more</i></comp>
+</div>
+<p>after</p>"#;
+
+    #[test]
+    fn test_reparse_attribute() {
+        assert!(check(SRC, "<ul ", "list", "cls"));
+        assert!(check(SRC, "<li v-for", "pick(item)", "pick(item, $event)"));
+        assert!(check(SRC, "<i ", "a", "a +\n b"));
+        // new attribute
+        assert!(check(SRC, "<ul", " :class", " title=\"ok\"\n  :class"));
+    }
+
+    #[test]
+    fn test_reparse_text() {
+        assert!(check(SRC, "{{ item }}", " text", " longer\ntext"));
+        assert!(check(SRC, "<li>", "last", "la"));
+        assert!(check(SRC, "<comp", "&amp;", "&lt;"));
+        assert!(check(SRC, "<li v-for", "{{ item }}", "{{ item.name }}"));
+    }
+
+    #[test]
+    fn test_reparse_children() {
+        assert!(check(SRC, "<li>", "last", "<b>bold</b>\n<i/>"));
+        // remove the first li
+        let removed = &SRC[SRC.find("<li v-for").unwrap()..SRC.find("<li>").unwrap()];
+        assert!(check(SRC, "<ul", removed, ""));
+        assert!(check(SRC, "</ul>", "\n", "\n  <hr/>\n"));
+        // merge two li
+        assert!(check(
+            SRC,
+            "{{ item }}",
+            " text</b></li>\n    <li>",
+            " </b>"
+        ));
+    }
+
+    #[test]
+    fn test_reparse_fallback() {
+        // crosses element boundary
+        assert!(!check(SRC, "</ul>", "</div>\n<p>", "<p>"));
+        // unclosed element
+        assert!(!check(SRC, "<li>", "last", "<b>last"));
+        // stray end tag closing an ancestor
+        assert!(!check("<ul><li>a</li></ul>", "<li>", "a", "</ul>"));
+        // checks against parent or siblings
+        assert!(!check(SRC, "<li v-for", "items", "list"));
+        assert!(!check(SRC, "<comp ", "{ a }", "{ a,\n b }"));
+        assert!(!check(SRC, "<ul", " :class", " v-if=\"ok\"\n  :class"));
+        let src = r#"<template v-for="i in l"><p>{{ i }}</p></template>"#;
+        assert!(!check(src, "<p", ">", r#" :key="i">"#));
+        let src = r#"<div><p v-if="a">x</p><p v-else>y</p></div>"#;
+        assert!(!check(src, "<p", r#" v-if="a""#, ""));
+        let src = "<div><p>x</p><i>y</i></div>";
+        assert!(!check(src, "<i", ">", " v-else>"));
+        // edit outside of any element
+        assert!(!check(SRC, "</div>", "\n", "\ntext\n"));
+        // pre and v-pre depend on ancestors
+        let src = "<pre><b>\n a</b></pre><div v-pre><p>{{ a }}</p></div>";
+        assert!(!check(src, "<b>", "a", "b"));
+        assert!(!check(src, "<p>", "a", "b"));
+    }
+
+    #[test]
+    fn test_reparse_nesting_limit() {
+        let mut option = ParseOption::default();
+        option.limits.max_nesting_depth = Some(3);
+        let parser = Parser::new(option);
+        let src = "<a><b><c>text</c></b></a>";
+        assert!(check_with(&parser, src, "<b>", "<c>", "<c id=x>"));
+        // nesting within the slice exceeds the limit of the whole template
+        assert!(!check_with(&parser, src, "<c>", "text", "<d>text</d>"));
+        // element beyond the limit is parsed as self-closing
+        let src = "<a><b><c><d>text</d></c></b></a>";
+        assert!(!check_with(&parser, src, "<c>", "<d>", "<d id=x>"));
+    }
+
+    #[test]
+    fn test_reparse_invalid_edit() {
+        let prev = parse(SRC);
+        let scanner = Scanner::new(ScanOption::default());
+        let eh: RcErrHandle = Rc::new(TestErrorHandler);
+        let (short, long) = (&SRC[..9], format!("{}{}", SRC, "x".repeat(100)));
+        let edits = [
+            (short, 9, 9, 100),
+            (SRC, 9, 5, 9),
+            (SRC, 9, SRC.len() + 1, 9),
+        ];
+        for (new_src, start, old_end, new_end) in edits {
+            let edit = TextEdit {
+                start,
+                old_end,
+                new_end,
+            };
+            let partial = parser().reparse_subtree(&scanner, &prev, SRC, new_src, &edit, &eh);
+            assert!(partial.is_none());
+        }
+        let edit = TextEdit {
+            start: 9,
+            old_end: 9,
+            new_end: long.len() + 1,
+        };
+        let reparsed = parser().reparse(&scanner, &prev, SRC, &long, &edit, eh);
+        assert_eq!(reparsed.to_owned_ast(), parse(&long).to_owned_ast());
+    }
+}
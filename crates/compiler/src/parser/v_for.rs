@@ -0,0 +1,246 @@
+// Splits v-for expression like `(item, key, index) in source` into parts.
+// Only top-level `in`/`of` and commas split, so those inside brackets,
+// destructuring patterns or string literals are kept in their part.
+
+use super::Directive;
+use crate::{
+    error::{CompilationError, CompilationErrorKind as ErrorKind},
+    Position, SourceLocation,
+};
+
+/// A slice of directive expression with its own location in template source.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExprSlice<'a> {
+    pub content: &'a str,
+    pub location: SourceLocation,
+}
+
+/// Parts of v-for expression. Absent key/index is None while an empty
+/// one is Some, e.g. `(a,) in b`. Value is empty in ` in b`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VForParts<'a> {
+    pub value: ExprSlice<'a>,
+    pub key: Option<ExprSlice<'a>>,
+    pub index: Option<ExprSlice<'a>>,
+    pub source: ExprSlice<'a>,
+}
+
+impl<'a> Directive<'a> {
+    /// Returns VForNoExpression for empty expression and VForMalformedExpression
+    /// if the expression has no top-level `in`/`of` or no source.
+    pub fn parse_v_for(&self) -> Result<VForParts<'a>, CompilationError> {
+        if let Some(error) = self.check_empty_expr(ErrorKind::VForNoExpression) {
+            return Err(error);
+        }
+        let expr = self
+            .expression
+            .as_ref()
+            .expect("v-for must have expression");
+        split_v_for(expr.content.raw, &expr.location.start).ok_or_else(|| {
+            CompilationError::new(ErrorKind::VForMalformedExpression)
+                .with_location(expr.location.clone())
+        })
+    }
+}
+
+/// `start` is the position of `raw` in template source.
+pub(crate) fn split_v_for<'a>(raw: &'a str, start: &Position) -> Option<VForParts<'a>> {
+    let bytes = raw.as_bytes();
+    let is_space = |i: usize| matches!(bytes.get(i), Some(b) if b.is_ascii_whitespace());
+    let split = find_top_level(raw, 0..raw.len(), |i| {
+        // match bytes since i may be inside a multi-byte char, e.g. `项 in list`
+        let keyword = &bytes[i..];
+        (keyword.starts_with(b"in") || keyword.starts_with(b"of"))
+            && i > 0
+            && is_space(i - 1)
+            && is_space(i + 2)
+    })?;
+    let slice = |(s, e): (usize, usize)| ExprSlice {
+        content: &raw[s..e],
+        location: SourceLocation {
+            start: advance(start, &raw[..s]),
+            end: advance(start, &raw[..e]),
+        },
+    };
+    let source = trim(raw, split + 2, raw.len());
+    if source.0 == source.1 {
+        return None;
+    }
+    let (mut s, mut e) = trim(raw, 0, split);
+    if raw[s..e].starts_with('(') && raw[s..e].ends_with(')') {
+        let inner = trim(raw, s + 1, e - 1);
+        s = inner.0;
+        e = inner.1;
+    }
+    // at most two commas from right split key and index. Others belong to value.
+    let mut commas = vec![];
+    let mut from = s;
+    while let Some(i) = find_top_level(raw, from..e, |i| bytes[i] == b',') {
+        commas.push(i);
+        from = i + 1;
+    }
+    let mut rest = commas.into_iter().rev().take(2);
+    let (key, index) = match (rest.next(), rest.next()) {
+        (Some(last), Some(second)) => (Some((second + 1, last)), Some((last + 1, e))),
+        (Some(last), None) => (Some((last + 1, e)), None),
+        _ => (None, None),
+    };
+    let value_end = key.map_or(e, |k| k.0 - 1);
+    let part = |(s, e)| slice(trim(raw, s, e));
+    Some(VForParts {
+        value: part((s, value_end)),
+        key: key.map(part),
+        index: index.map(part),
+        source: slice(source),
+    })
+}
+
+// Returns the first index in range, outside brackets and quotes, satisfying pred.
+fn find_top_level<F>(raw: &str, range: std::ops::Range<usize>, pred: F) -> Option<usize>
+where
+    F: Fn(usize) -> bool,
+{
+    let bytes = raw.as_bytes();
+    let mut depth = 0usize;
+    let mut quote = None;
+    let mut escaped = false;
+    for i in range {
+        let b = bytes[i];
+        if let Some(q) = quote {
+            if escaped {
+                escaped = false;
+            } else if b == b'\\' {
+                escaped = true;
+            } else if b == q {
+                quote = None;
+            }
+            continue;
+        }
+        match b {
+            b'\'' | b'"' | b'`' => quote = Some(b),
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' | b']' | b'}' => depth = depth.saturating_sub(1),
+            _ if depth == 0 && pred(i) => return Some(i),
+            _ => (),
+        }
+    }
+    None
+}
+
+fn trim(raw: &str, mut s: usize, mut e: usize) -> (usize, usize) {
+    let bytes = raw.as_bytes();
+    while s < e && bytes[s].is_ascii_whitespace() {
+        s += 1;
+    }
+    while e > s && bytes[e - 1].is_ascii_whitespace() {
+        e -= 1;
+    }
+    (s, e)
+}
+
+fn advance(start: &Position, text: &str) -> Position {
+    let mut pos = start.clone();
+    pos.advance(text);
+    pos
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::test::base_parse;
+
+    fn parts(src: &str) -> Option<(&str, &str, Option<&str>, Option<&str>)> {
+        let parts = split_v_for(src, &Position::default())?;
+        Some((
+            parts.source.content,
+            parts.value.content,
+            parts.key.map(|k| k.content),
+            parts.index.map(|i| i.content),
+        ))
+    }
+
+    #[test]
+    fn test_alias_shapes() {
+        for &(src, expect) in &[
+            ("item in list", ("list", "item", None, None)),
+            ("item of list", ("list", "item", None, None)),
+            ("(item, i) in list", ("list", "item", Some("i"), None)),
+            ("(v, k, i) of obj", ("obj", "v", Some("k"), Some("i"))),
+            ("v, k, i in obj", ("obj", "v", Some("k"), Some("i"))),
+            (
+                "({ a, b }, i) in list",
+                ("list", "{ a, b }", Some("i"), None),
+            ),
+            ("[a, b] in list", ("list", "[a, b]", None, None)),
+            ("(item,) in list", ("list", "item", Some(""), None)),
+            ("   in list", ("list", "", None, None)),
+            ("index in indices", ("indices", "index", None, None)),
+            ("item\n  in\n  list", ("list", "item", None, None)),
+            (
+                "item in list.filter(x => 'in' in x)",
+                ("list.filter(x => 'in' in x)", "item", None, None),
+            ),
+            (
+                "{ a } of items.filter(x => x in y)",
+                ("items.filter(x => x in y)", "{ a }", None, None),
+            ),
+        ] {
+            assert_eq!(parts(src), Some(expect), "{:?}", src);
+        }
+        for src in &["", "item", "item in ", "item inlist", "('a in b')"] {
+            assert_eq!(parts(src), None, "{:?}", src);
+        }
+    }
+
+    #[test]
+    fn test_non_ascii_alias() {
+        for &(src, expect) in &[
+            ("项 in list", ("list", "项", None, None)),
+            ("(值, 键) of 对象", ("对象", "值", Some("键"), None)),
+            ("é in ñ", ("ñ", "é", None, None)),
+        ] {
+            assert_eq!(parts(src), Some(expect), "{:?}", src);
+        }
+        let case = "<p v-for=\"项 in list\">{{ 项 }}</p>";
+        let root = base_parse(case);
+        let p = root.children[0].get_element().unwrap();
+        let parts = p.dirs().next().unwrap().parse_v_for().ok().unwrap();
+        let location = &parts.value.location;
+        assert_eq!(&case[location.start.offset..location.end.offset], "项");
+    }
+
+    #[test]
+    fn test_part_locations() {
+        let case = "<p v-for=\"({ a }, i) in\n list\"/>";
+        let root = base_parse(case);
+        let p = root.children[0].get_element().unwrap();
+        let parts = match p.dirs().next().unwrap().parse_v_for() {
+            Ok(parts) => parts,
+            Err(e) => panic!("{}", e),
+        };
+        let source = |l: &SourceLocation| &case[l.start.offset..l.end.offset];
+        assert_eq!(source(&parts.value.location), "{ a }");
+        assert_eq!(source(&parts.key.unwrap().location), "i");
+        assert!(parts.index.is_none());
+        let location = &parts.source.location;
+        assert_eq!(source(location), "list");
+        assert_eq!((location.start.line, location.start.column), (2, 2));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let kind = |case| {
+            let root = base_parse(case);
+            let p = root.children[0].get_element().unwrap();
+            let error = p.dirs().next().unwrap().parse_v_for().unwrap_err();
+            error.kind
+        };
+        assert!(matches!(kind("<p v-for/>"), ErrorKind::VForNoExpression));
+        assert!(matches!(
+            kind("<p v-for=' '/>"),
+            ErrorKind::VForNoExpression
+        ));
+        let malformed = kind("<p v-for='a in'/>");
+        assert!(matches!(malformed, ErrorKind::VForMalformedExpression));
+    }
+}
@@ -0,0 +1,390 @@
+/*!
+Print AST back to template source.
+
+Printing is structural: `parse(print(ast))` yields the same tree as `ast`,
+but source formatting not recorded in AST is normalized.
+* childless void elements, components and SVG/MathML elements are printed as
+  self-closing tags. Other elements always get an end tag, as `<div/>` is not
+  self-closing in HTML.
+* directives use shorthands `:`, `@` and `#` when they have arguments.
+* attribute values keep single or double quotes if the value does not contain
+  the same quote. Otherwise, and for unquoted values, they are double quoted
+  unless the value contains double quotes only.
+* whitespaces condensed by the parser stay condensed.
+
+PrintOption must agree with the options the AST was parsed with, e.g.
+interpolation delimiters and pre tags whose leading newline was trimmed.
+
+Text and attribute values are printed as raw source so HTML entities are kept.
+Modified nodes should hold raw source, e.g. `&lt;` instead of `<`.
+*/
+
+use crate::{
+    parser::{
+        preset::is_void_tag, AstNode, AstRoot, Directive, DirectiveArg, ElemProp, Element,
+        ElementType, StrPredicate, TextNode,
+    },
+    Namespace,
+    scanner::{Attribute, AttributeValue, QuoteKind},
+    util::{StrOps, VStr},
+};
+use std::fmt::{Result, Write};
+
+#[derive(Clone)]
+pub struct PrintOption {
+    /// Same as ScanOption::delimiters.
+    pub delimiters: (String, String),
+    /// Same as ParseOption::is_pre_tag. Their trimmed leading newline is restored.
+    pub is_pre_tag: StrPredicate,
+}
+
+impl Default for PrintOption {
+    fn default() -> Self {
+        Self {
+            delimiters: ("{{".into(), "}}".into()),
+            is_pre_tag: (|s: &str| s == "pre").into(),
+        }
+    }
+}
+
+pub fn print_root(root: &AstRoot, option: &PrintOption) -> String {
+    let mut s = String::new();
+    if let Some(f) = &root.frontmatter {
+        s.push_str("---\n");
+        s.push_str(f.source);
+        s.push_str("\n---\n");
+    }
+    write_children(&root.children, option, &mut s).expect("writing to string never fails");
+    s
+}
+
+pub fn print_node(node: &AstNode, option: &PrintOption) -> String {
+    let mut s = String::new();
+    write_node(node, option, &mut s).expect("writing to string never fails");
+    s
+}
+
+fn write_children<W: Write>(children: &[AstNode], opt: &PrintOption, w: &mut W) -> Result {
+    children.iter().try_for_each(|n| write_node(n, opt, w))
+}
+
+fn write_node<W: Write>(node: &AstNode, opt: &PrintOption, w: &mut W) -> Result {
+    match node {
+        AstNode::Element(e) => write_element(e, opt, w),
+        AstNode::Text(t) => write_text(t, w),
+        AstNode::Interpolation(i) => {
+            let (open, close) = &opt.delimiters;
+            write!(w, "{}{}{}", open, i.source, close)
+        }
+        AstNode::Comment(c) => write!(w, "<!--{}-->", c.source),
+        AstNode::ProcessingInstruction(p) => write!(w, "<?{}>", p.source),
+    }
+}
+
+fn write_element<W: Write>(e: &Element, opt: &PrintOption, w: &mut W) -> Result {
+    write!(w, "<{}", e.tag_name)?;
+    for prop in &e.properties {
+        w.write_char(' ')?;
+        match prop {
+            ElemProp::Attr(attr) => write_attr(attr, w)?,
+            ElemProp::Dir(dir) => write_dir(dir, w)?,
+        }
+    }
+    if e.children.is_empty() && can_self_close(e) {
+        return w.write_str("/>");
+    }
+    w.write_char('>')?;
+    if (opt.is_pre_tag)(e.tag_name) {
+        write_pre_newline(e, w)?;
+    }
+    write_children(&e.children, opt, w)?;
+    write!(w, "</{}>", e.tag_name)
+}
+
+fn can_self_close(e: &Element) -> bool {
+    e.tag_type == ElementType::Component
+        || e.namespace != Namespace::Html
+        || is_void_tag(e.tag_name)
+}
+
+/// Parser trims the leading newline in pre tags. Restore it if it was trimmed,
+/// or if text starts with another newline that should not be trimmed.
+fn write_pre_newline<W: Write>(e: &Element, w: &mut W) -> Result {
+    let Some(AstNode::Text(t)) = e.children.first() else {
+        return Ok(());
+    };
+    let raw_len: usize = t.text.iter().map(|s| s.raw.len()).sum();
+    let span = t.location.end.offset - t.location.start.offset;
+    let trimmed = span.checked_sub(raw_len).filter(|n| *n == 1 || *n == 2);
+    // a trimmed `\r` does not start a new line in location
+    let newlines: usize = t.text.iter().map(|s| s.raw.matches('\n').count()).sum();
+    let lines = (t.location.end.line - t.location.start.line) as usize;
+    let starts_with_newline = t
+        .text
+        .first()
+        .is_some_and(|s| s.starts_with(&['\n', '\r'][..]));
+    match trimmed {
+        Some(2) => w.write_str("\r\n"),
+        Some(_) if lines > newlines => w.write_char('\n'),
+        Some(_) => w.write_char('\r'),
+        None if starts_with_newline => w.write_char('\n'),
+        None => Ok(()),
+    }
+}
+
+fn write_attr<W: Write>(attr: &Attribute, w: &mut W) -> Result {
+    w.write_str(attr.name)?;
+    match &attr.value {
+        Some(v) => write_value(v, w),
+        None => Ok(()),
+    }
+}
+
+fn write_dir<W: Write>(dir: &Directive, w: &mut W) -> Result {
+    let shorthand = match dir.name {
+        "bind" => ":",
+        "on" => "@",
+        "slot" => "#",
+        _ => "",
+    };
+    match &dir.argument {
+        Some(arg) if !shorthand.is_empty() => {
+            w.write_str(shorthand)?;
+            write_dir_arg(arg, w)?;
+        }
+        Some(arg) => {
+            write!(w, "v-{}:", dir.name)?;
+            write_dir_arg(arg, w)?;
+        }
+        None => write!(w, "v-{}", dir.name)?,
+    }
+    for m in &dir.modifiers {
+        write!(w, ".{}", m)?;
+    }
+    match &dir.expression {
+        Some(_) if dir.is_same_name_shorthand() => Ok(()),
+        Some(v) => write_value(v, w),
+        None => Ok(()),
+    }
+}
+
+fn write_dir_arg<W: Write>(arg: &DirectiveArg, w: &mut W) -> Result {
+    match arg {
+        DirectiveArg::Static(a) => w.write_str(a),
+        DirectiveArg::Dynamic(a) => write!(w, "[{}]", a),
+    }
+}
+
+fn write_value<W: Write>(value: &AttributeValue, w: &mut W) -> Result {
+    let raw = value.content.raw;
+    match value.quote {
+        QuoteKind::Single if !raw.contains('\'') => return write!(w, "='{}'", raw),
+        QuoteKind::Double if !raw.contains('"') => return write!(w, "=\"{}\"", raw),
+        _ => (),
+    }
+    if raw.contains('"') && !raw.contains('\'') {
+        return write!(w, "='{}'", raw);
+    }
+    w.write_str("=\"")?;
+    let mut parts = raw.split('"');
+    if let Some(first) = parts.next() {
+        w.write_str(first)?;
+    }
+    for part in parts {
+        w.write_str("&quot;")?;
+        w.write_str(part)?;
+    }
+    w.write_char('"')
+}
+
+fn write_text<W: Write>(t: &TextNode, w: &mut W) -> Result {
+    if t.is_cdata {
+        w.write_str("<![CDATA[")?;
+        t.text.iter().try_for_each(|s| w.write_str(s.raw))?;
+        return w.write_str("]]>");
+    }
+    for s in &t.text {
+        // keep entities undecoded but honor whitespace compression
+        let printed = VStr {
+            raw: s.raw,
+            ops: s.ops & StrOps::COMPRESS_WHITESPACE,
+        };
+        printed.write_to(&mut *w)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::test::base_parse;
+
+    // location-free structure for comparison
+    fn shape(nodes: &[AstNode]) -> String {
+        let mut s = String::new();
+        for n in nodes {
+            match n {
+                AstNode::Element(e) => {
+                    s.push_str(&format!("<{} {:?}", e.tag_name, e.tag_type));
+                    for p in &e.properties {
+                        match p {
+                            ElemProp::Attr(a) => {
+                                let v = a.value.as_ref().map(|v| v.content.into_string());
+                                s.push_str(&format!(" attr({}, {:?})", a.name, v));
+                            }
+                            ElemProp::Dir(d) => {
+                                let arg = d.argument.as_ref().map(|a| match a {
+                                    DirectiveArg::Static(a) => format!("static {}", a),
+                                    DirectiveArg::Dynamic(a) => format!("dynamic {}", a),
+                                });
+                                let v = d.expression.as_ref().map(|v| v.content.into_string());
+                                s.push_str(&format!(
+                                    " dir({}, {:?}, {:?}, {:?})",
+                                    d.name, arg, d.modifiers, v
+                                ));
+                            }
+                        }
+                    }
+                    s.push_str(&format!(">{}</>", shape(&e.children)));
+                }
+                AstNode::Text(t) => {
+                    let text: String = t.text.iter().map(|s| s.into_string()).collect();
+                    s.push_str(&format!("text({:?})", text));
+                }
+                AstNode::Interpolation(i) => s.push_str(&format!("interp({:?})", i.source)),
+                AstNode::Comment(c) => s.push_str(&format!("comment({:?})", c.source)),
+                AstNode::ProcessingInstruction(p) => s.push_str(&format!("pi({:?})", p.source)),
+            }
+        }
+        s
+    }
+
+    fn base_print(root: &AstRoot) -> String {
+        print_root(root, &PrintOption::default())
+    }
+
+    fn dom_parse(s: &str) -> AstRoot<'_> {
+        use crate::parser::{ParseOption, Parser};
+        use crate::scanner::test::base_scan;
+        let eh = std::rc::Rc::new(crate::error::test::TestErrorHandler);
+        Parser::new(ParseOption::dom()).parse(base_scan(s), eh)
+    }
+
+    fn dom_print(root: &AstRoot) -> String {
+        let option = PrintOption {
+            is_pre_tag: crate::parser::preset::is_pre_tag.into(),
+            ..Default::default()
+        };
+        print_root(root, &option)
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let cases = [
+            r#"<div id="a" :class="b" @click.stop="c()" v-if="d">text {{ e }}</div>"#,
+            r#"<comp #default="{ item }" v-bind="props" @[evt]="h" v-custom:arg.mod="x"/>"#,
+            r#"<p .prop-a="b" v-on:click="c" v-bind:d="e" v-slot:f="g">&amp; &lt;</p>"#,
+            "<div>\n  <p>a   b</p>\n  <!-- comment -->\n  <span/>\n</div>",
+            r#"<p title='say "hi"' alt="it's &quot;x&quot;" disabled/>"#,
+            r#"<div v-pre><p :a="b">{{ raw }}</p></div><textarea>{{ a }} &lt;</textarea>"#,
+            "<pre>\n\n  a\n   b</pre><pre>x</pre><pre>\n<b>c</b></pre>",
+            r#"<template v-for="(a, i) in b" :key="i"><img src="a.png"/></template>"#,
+        ];
+        for case in cases {
+            let ast = base_parse(case);
+            let printed = base_print(&ast);
+            let reparsed = base_parse(&printed);
+            assert_eq!(shape(&ast.children), shape(&reparsed.children), "{}", case);
+            assert_eq!(base_print(&reparsed), printed);
+        }
+        // CDATA needs namespace info to be recognized
+        let case = "<svg><![CDATA[ a<b>c</b> ]]></svg>";
+        let ast = dom_parse(case);
+        let printed = dom_print(&ast);
+        assert_eq!(printed, case);
+        assert_eq!(shape(&ast.children), shape(&dom_parse(&printed).children));
+    }
+
+    #[test]
+    fn test_byte_for_byte() {
+        let cases = [
+            r#"<img src="a.png"/><br/>"#,
+            "<pre>\n  a\n\n   b  </pre>",
+            "<pre>\r\n\n a</pre>",
+            "<pre>\r\r a</pre>",
+            "<pre>\n</pre><pre>\r\n</pre>",
+            r#"<div :a="b" @c="d" #e="f">{{ g }}<!--h--></div>"#,
+            r#"<p a='b' :c='"d"' e="f"></p>"#,
+            r#"<p :user-name :id.camel :a-b="aB"></p>"#,
+            "<p :class></p><div></div><comp/><template></template>",
+            "<pre></pre><textarea></textarea>",
+        ];
+        for case in cases {
+            assert_eq!(base_print(&base_parse(case)), case);
+        }
+    }
+
+    #[test]
+    fn test_self_closing() {
+        let case = "<svg><path/></svg><math><mi/></math><img/><slot></slot><Comp/>";
+        assert_eq!(dom_print(&dom_parse(case)), case);
+    }
+
+    #[test]
+    fn test_pre_like_newline() {
+        let cases = [
+            "<textarea>\n</textarea>",
+            "<textarea>\r\n a</textarea>",
+            "<pre><textarea>\n{{ a }}</textarea></pre>",
+        ];
+        for case in cases {
+            assert_eq!(dom_print(&dom_parse(case)), case);
+        }
+    }
+
+    #[test]
+    fn test_processing_instruction() {
+        use crate::parser::{ParseOption, Parser};
+        use crate::scanner::test::base_scan;
+        let option = ParseOption {
+            keep_processing_instructions: true,
+            ..Default::default()
+        };
+        for case in [r#"<?xml version="1.0"?><p></p>"#, "<?php echo 1 ><p>a</p>"] {
+            let eh = std::rc::Rc::new(crate::error::test::TestErrorHandler);
+            let ast = Parser::new(option.clone()).parse(base_scan(case), eh);
+            assert!(matches!(ast.children[0], AstNode::ProcessingInstruction(_)));
+            assert_eq!(base_print(&ast), case);
+        }
+    }
+
+    #[test]
+    fn test_custom_delimiters() {
+        use crate::parser::{ParseOption, Parser};
+        use crate::scanner::{ScanOption, Scanner};
+        let delimiters = ("${".to_string(), "}".to_string());
+        let scan = ScanOption {
+            delimiters: delimiters.clone(),
+            ..Default::default()
+        };
+        let case = "<p>${ a }{{ b }}</p>";
+        let eh = std::rc::Rc::new(crate::error::test::TestErrorHandler);
+        let tokens = Scanner::new(scan).scan(case, eh.clone());
+        let ast = Parser::new(ParseOption::default()).parse(tokens, eh);
+        let option = PrintOption {
+            delimiters,
+            ..Default::default()
+        };
+        assert_eq!(print_root(&ast, &option), case);
+    }
+
+    #[test]
+    fn test_print_modified() {
+        let mut ast = base_parse(r#"<p :a="b" c="d">x</p>"#);
+        let p = ast.children[0].get_element_mut().unwrap();
+        p.remove_prop("a");
+        p.children.clear();
+        let printed = print_node(&ast.children[0], &PrintOption::default());
+        assert_eq!(printed, r#"<p c="d"></p>"#);
+    }
+}
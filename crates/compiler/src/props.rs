@@ -0,0 +1,4 @@
+//! Helpers for props object shared by conversion, transform passes and plugins.
+
+pub mod merge;
+pub use merge::{merge_props, PropsBuilder};
@@ -0,0 +1,195 @@
+// Merging semantics of duplicate props, in line with Vue's dedupeProperties:
+// * class/style values are concatenated into an array in source order.
+// * onXxx handlers are collected into an array and all of them are invoked.
+// * other duplicates are conflicts and the first prop wins. Identical attribute
+//   names are already reported by parser, so the callback sees conflicts like
+//   `id` with `:id`, or props produced by plugins and directives.
+// Props with non-literal keys, e.g. `:[key]="val"`, are never merged.
+
+use crate::{
+    flags::StaticLevel,
+    ir::{JsExpr as Js, Prop},
+    util::{is_mergeable_prop, VStr},
+};
+use rustc_hash::FxHashMap;
+use std::mem;
+
+type OnConflict<'a, 'b> = Box<dyn FnMut(&Prop<'a>, &Prop<'a>) + 'b>;
+
+/// Collects props in insertion order while merging duplicate keys.
+#[derive(Default)]
+pub struct PropsBuilder<'a, 'b> {
+    props: Vec<Prop<'a>>,
+    /// if the entry of the same index is hoistable
+    statics: Vec<bool>,
+    known_props: FxHashMap<VStr<'a>, usize>,
+    on_conflict: Option<OnConflict<'a, 'b>>,
+}
+
+impl<'a, 'b> PropsBuilder<'a, 'b> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Called with (existing, incoming) when a non-mergeable prop is duplicate.
+    /// The incoming prop is dropped afterwards.
+    #[must_use]
+    pub fn on_conflict<F>(mut self, f: F) -> Self
+    where
+        F: FnMut(&Prop<'a>, &Prop<'a>) + 'b,
+    {
+        self.on_conflict = Some(Box::new(f));
+        self
+    }
+    pub fn len(&self) -> usize {
+        self.props.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.props.is_empty()
+    }
+    pub fn props(&self) -> &[Prop<'a>] {
+        &self.props
+    }
+    /// Returns if both key and value of the nth prop can be hoisted.
+    pub fn is_static(&self, i: usize) -> bool {
+        self.statics[i]
+    }
+    pub fn static_props(&self) -> impl Iterator<Item = &Prop<'a>> {
+        self.entries().filter(|e| e.1).map(|e| e.0)
+    }
+    pub fn dynamic_props(&self) -> impl Iterator<Item = &Prop<'a>> {
+        self.entries().filter(|e| !e.1).map(|e| e.0)
+    }
+    pub fn into_props(self) -> Vec<Prop<'a>> {
+        self.props
+    }
+    fn entries(&self) -> impl Iterator<Item = (&Prop<'a>, bool)> {
+        self.props.iter().zip(self.statics.iter().copied())
+    }
+}
+
+impl<'a, 'b> Extend<Prop<'a>> for PropsBuilder<'a, 'b> {
+    fn extend<T: IntoIterator<Item = Prop<'a>>>(&mut self, iter: T) {
+        for prop in iter {
+            merge_props(self, prop);
+        }
+    }
+}
+
+pub fn merge_props<'a>(existing: &mut PropsBuilder<'a, '_>, incoming: Prop<'a>) {
+    let is_static = is_static_prop(&incoming);
+    let name = match &incoming.0 {
+        Js::StrLit(name) => *name,
+        _ => return push_prop(existing, incoming, is_static),
+    };
+    let i = match existing.known_props.get(&name) {
+        Some(&i) => i,
+        None => {
+            existing.known_props.insert(name, existing.props.len());
+            return push_prop(existing, incoming, is_static);
+        }
+    };
+    // v-on keys are handler ops on the raw event name, e.g. click
+    if is_mergeable_prop(&name) || VStr::is_handler(&name) {
+        merge_as_array(&mut existing.props[i], incoming.1);
+        existing.statics[i] &= is_static;
+    } else if let Some(on_conflict) = &mut existing.on_conflict {
+        on_conflict(&existing.props[i], &incoming);
+    }
+}
+
+fn push_prop<'a>(existing: &mut PropsBuilder<'a, '_>, prop: Prop<'a>, is_static: bool) {
+    existing.props.push(prop);
+    existing.statics.push(is_static);
+}
+
+fn is_static_prop(prop: &Prop) -> bool {
+    let level = prop.0.static_level().min(prop.1.static_level());
+    level >= StaticLevel::CanHoist
+}
+
+fn merge_as_array<'a>(existing: &mut Prop<'a>, incoming: Js<'a>) {
+    let val = &mut existing.1;
+    if let Js::Array(arr) = val {
+        arr.push(incoming);
+    } else {
+        let v = mem::take(val);
+        *val = Js::Array(vec![v, incoming]);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::cell::RefCell;
+
+    fn lit_of<'a>(js: &Js<'a>) -> &'a str {
+        match js {
+            Js::StrLit(v) => v.raw,
+            Js::Simple(v, _) => v.raw,
+            Js::FuncSimple { src, .. } => src.raw,
+            _ => panic!("unexpected js"),
+        }
+    }
+
+    #[test]
+    fn test_class_concat() {
+        let mut builder = PropsBuilder::new();
+        builder.extend(vec![
+            (Js::str_lit("class"), Js::str_lit("a")),
+            (Js::str_lit("id"), Js::str_lit("b")),
+            (Js::str_lit("class"), Js::simple("c")),
+            (Js::str_lit("class"), Js::str_lit("d")),
+        ]);
+        assert_eq!(builder.len(), 2);
+        assert!(!builder.is_static(0));
+        assert!(builder.is_static(1));
+        let props = builder.into_props();
+        match &props[0].1 {
+            Js::Array(arr) => {
+                let vals: Vec<_> = arr.iter().map(lit_of).collect();
+                assert_eq!(vals, ["a", "c", "d"]);
+            }
+            _ => panic!("class should be array"),
+        }
+    }
+
+    #[test]
+    fn test_handler_array() {
+        let mut builder = PropsBuilder::new();
+        builder.extend(vec![
+            (Js::str_lit("onClick"), Js::func("a")),
+            (Js::simple("dyn"), Js::func("b")),
+            (Js::str_lit("onClick"), Js::func("c")),
+        ]);
+        let dynamic: Vec<_> = builder.dynamic_props().map(|p| lit_of(&p.0)).collect();
+        assert_eq!(dynamic, ["onClick", "dyn"]);
+        let props = builder.into_props();
+        assert_eq!(props.len(), 2);
+        match &props[0].1 {
+            Js::Array(arr) => assert_eq!(arr.iter().map(lit_of).collect::<Vec<_>>(), ["a", "c"]),
+            _ => panic!("handler should be array"),
+        }
+    }
+
+    #[test]
+    fn test_conflict() {
+        let conflicts = RefCell::new(vec![]);
+        let mut builder = PropsBuilder::new().on_conflict(|existing, incoming| {
+            let name = lit_of(&existing.0).to_string();
+            conflicts
+                .borrow_mut()
+                .push((name, lit_of(&incoming.1).to_string()));
+        });
+        builder.extend(vec![
+            (Js::str_lit("key"), Js::simple("a")),
+            (Js::str_lit("key"), Js::simple("b")),
+            (Js::str_lit("style"), Js::str_lit("c")),
+            (Js::str_lit("style"), Js::str_lit("d")),
+        ]);
+        assert!(builder.is_static(1));
+        assert_eq!(builder.static_props().count(), 1);
+        let props = builder.into_props();
+        assert_eq!(lit_of(&props[0].1), "a");
+        assert_eq!(*conflicts.borrow(), [("key".to_string(), "b".to_string())]);
+    }
+}
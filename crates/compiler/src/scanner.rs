@@ -4,20 +4,29 @@
 
 use super::{
     error::{CompilationError, CompilationErrorKind as ErrorKind, RcErrHandle},
-    util::{non_whitespace, VStr},
+    util::{char_ref_errors, non_whitespace, CharRefError, StrOps, VStr},
     Name, Position, SourceLocation,
 };
 use rustc_hash::FxHashSet;
-use std::{iter::FusedIterator, str::Bytes};
+use std::{collections::VecDeque, iter::FusedIterator, str::Bytes};
 
 #[cfg(feature = "serde")]
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
+/// An attribute of a tag. Malformed attributes are recovered as browsers do:
+/// * `a="1"b` starts a new attribute `b` after the closing quote.
+/// * `=a` is an attribute named `=a`, a leading `=` is part of the name.
+/// * `"`, `'` and `<` are kept in the name, e.g. `a"b`.
+/// * `a=b c` is attribute `a` with value `b` followed by attribute `c`.
+///
+/// Errors are reported at the offending char, except for the last case,
+/// which is valid HTML. Duplicate attributes are dropped from the tag.
 #[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct Attribute<'a> {
     pub name: Name<'a>,
     pub value: Option<AttributeValue<'a>>,
     pub name_loc: SourceLocation,
+    /// From the name to the value's closing quote. Whitespace around is excluded.
     pub location: SourceLocation,
 }
 
@@ -29,10 +38,38 @@ impl<'a> Attribute<'a> {
     }
 }
 
+/// How an attribute value is quoted in source.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum QuoteKind {
+    Double,
+    Single,
+    Unquoted,
+}
+
 #[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct AttributeValue<'a> {
     pub content: VStr<'a>,
+    /// Location of the content, excluding quotes.
     pub location: SourceLocation,
+    pub quote: QuoteKind,
+}
+
+impl<'a> AttributeValue<'a> {
+    /// Source text without quotes. Directive expressions are also trimmed.
+    pub fn raw(&self) -> &'a str {
+        self.content.raw
+    }
+    /// If content is decoded per ParseOption::decode_entities.
+    pub fn is_decoded(&self) -> bool {
+        self.content.ops.contains(StrOps::DECODE_ATTR)
+    }
+    /// Text with HTML entities decoded, regardless of parse option.
+    pub fn decoded(&self) -> String {
+        let mut content = self.content;
+        content.decode(true);
+        content.into_string()
+    }
 }
 
 /// Tag is used only for start tag since end tag is bare
@@ -54,7 +91,14 @@ pub enum Token<'a> {
     // 3. parser/IRConverter does not read text content
     Text(VStr<'a>), // merges chars to one str
     Comment(&'a str),
+    // <?xml ?> is a bogus comment per spec. Keep the same text as Comment,
+    // e.g. `?xml version="1.0"?`, so it can fall back to comment.
+    ProcessingInstruction(&'a str),
     Interpolation(&'a str), // Vue specific token
+    // CDATA section content in foreign content. It is never decoded.
+    CData(&'a str),
+    // content between leading `---` fences, only if FrontmatterMode is not None
+    Frontmatter(&'a str),
 }
 
 // NB: Token::from only takes decoded str
@@ -64,11 +108,29 @@ impl<'a> From<&'a str> for Token<'a> {
     }
 }
 
+/// How to handle a `---` fenced frontmatter block at the start of template,
+/// which static-site generators prepend to markup.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FrontmatterMode {
+    /// Frontmatter is parsed as template.
+    None,
+    /// Frontmatter is skipped.
+    Strip,
+    /// Frontmatter is kept in AstRoot.
+    Capture,
+}
+
 /// ScanOption defined a list of methods used in scanning
 #[derive(Clone)]
 pub struct ScanOption {
     pub delimiters: (String, String),
-    pub get_text_mode: fn(&str) -> TextMode,
+    /// Decides how children of a start tag are scanned. Attributes are
+    /// also available, e.g. `<template lang="pug">` can be scanned as raw text.
+    pub get_text_mode: fn(&Tag<'_>) -> TextMode,
+    pub frontmatter: FrontmatterMode,
+    /// Reports malformed character references in text and attribute values,
+    /// e.g. `&foo;` or `&#0;`. Should be the same as ParseOption::decode_entities.
+    pub decode_entities: bool,
 }
 
 impl Default for ScanOption {
@@ -99,6 +161,12 @@ pub trait FlagCDataNs {
     fn need_flag_hint(&self) -> bool;
 }
 
+/// Parser tells scanner if it is inside v-pre, for the same reason as FlagCDataNs.
+/// Interpolation delimiters in v-pre are scanned as plain text.
+pub trait FlagVPre {
+    fn set_is_in_v_pre(&mut self, flag: bool);
+}
+
 /// This trait produces a compiler's current position and selects a range.
 pub trait Locatable {
     /// Returns the scanner's current position in the source.
@@ -110,7 +178,7 @@ pub trait Locatable {
 
 /// TextMode represents different text scanning strategy.
 /// e.g. Scanning in script/textarea/div are different.
-#[derive(PartialEq, Eq)]
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
 pub enum TextMode {
     //         | Elements | Entities | End sign              | Inside of
     // DATA    | ✔        | ✔        | End tags of ancestors |
@@ -141,16 +209,30 @@ impl Scanner {
         }
     }
     pub fn scan<'a>(&self, source: &'a str, err_handle: RcErrHandle) -> Tokens<'a> {
+        self.scan_from(source, Position::default(), err_handle)
+    }
+    /// Scans a slice of a larger template. `start` is where the slice begins
+    /// so that tokens are located in the whole template.
+    pub fn scan_from<'a>(
+        &self,
+        source: &'a str,
+        start: Position,
+        err_handle: RcErrHandle,
+    ) -> Tokens<'a> {
         Tokens {
             source,
             err_handle,
-            position: Default::default(),
-            last_pos: Default::default(),
+            position: start.clone(),
+            last_pos: start.clone(),
             mode: TextMode::Data,
             option: self.option.clone(),
             last_start_tag_name: None,
             is_in_html_namespace: true,
+            is_in_v_pre: false,
             delimiter_first_char: self.delimiter_first_char,
+            maybe_frontmatter: start.offset == 0
+                && self.option.frontmatter != FrontmatterMode::None,
+            peeked: None,
         }
     }
 }
@@ -169,7 +251,13 @@ pub struct Tokens<'a> {
     last_start_tag_name: Option<&'a str>,
     // this flag is for handling CDATA in non HTML namespace.
     is_in_html_namespace: bool,
+    // interpolation is not scanned in v-pre
+    is_in_v_pre: bool,
     delimiter_first_char: char,
+    // frontmatter is only checked before the first token of a template
+    maybe_frontmatter: bool,
+    // peeked token with last_pos and position before peeking
+    peeked: Option<(Option<Token<'a>>, Position, Position)>,
 }
 
 // scanning methods
@@ -178,15 +266,62 @@ pub struct Tokens<'a> {
 // e.g. `let src = self.source` causes a stale src after [`move_by`].
 // while `let src= &self.source` forbids any src usage after a mut call.
 impl<'a> Tokens<'a> {
+    // frontmatter must be fenced by `---` lines before any non-whitespace.
+    // Returns None if there is no frontmatter or it is not closed.
+    fn scan_frontmatter(&mut self) -> Option<Token<'a>> {
+        let ws = self.source.find(non_whitespace)?;
+        let rest = &self.source[ws..];
+        let content_start = ["---\n", "---\r\n"]
+            .iter()
+            .find(|fence| rest.starts_with(*fence))?
+            .len();
+        let mut line_start = content_start;
+        let (content_end, fence_end) = loop {
+            let line_end = rest[line_start..].find('\n').map(|i| line_start + i);
+            let line = &rest[line_start..line_end.unwrap_or(rest.len())];
+            if line.trim_end_matches('\r') == "---" {
+                break (line_start, line_end.map_or(rest.len(), |i| i + 1));
+            }
+            match line_end {
+                Some(i) => line_start = i + 1,
+                None => {
+                    // report on the opening fence and scan it as text
+                    let mut start = self.current_position();
+                    start.advance(&self.source[..ws]);
+                    let mut end = start.clone();
+                    end.advance(&rest[..content_start]);
+                    let loc = SourceLocation { start, end };
+                    let err =
+                        CompilationError::new(ErrorKind::MissingFrontmatterEnd).with_location(loc);
+                    self.err_handle.on_error(err);
+                    return None;
+                }
+            }
+        };
+        self.skip_whitespace();
+        self.last_pos = self.current_position();
+        let fenced = self.move_by(fence_end);
+        // exclude the line break before closing fence
+        let content = &fenced[content_start..content_end];
+        let content = content.strip_suffix('\n').unwrap_or(content);
+        let content = content.strip_suffix('\r').unwrap_or(content);
+        Some(Token::Frontmatter(content))
+    }
+
     // https://html.spec.whatwg.org/multipage/parsing.html#data-state
     // NB: & is not handled here but instead in `decode_entities`
     fn scan_data(&mut self) -> Token<'a> {
         debug_assert!(self.mode == TextMode::Data);
         debug_assert!(!self.source.is_empty());
-        let d = self.delimiter_first_char;
+        let d = if self.is_in_v_pre {
+            '<'
+        } else {
+            self.delimiter_first_char
+        };
         let mut offset = 0;
         // process html entity & later
         while let Some(i) = self.source[offset..].find(&['<', d][..]) {
+            let i = offset + i;
             if i != 0 {
                 // found non empty text
                 return self.scan_text(i);
@@ -195,7 +330,7 @@ impl<'a> Tokens<'a> {
             } else if self.source.starts_with(&self.option.delimiters.0) {
                 return self.scan_interpolation();
             } else {
-                offset = i + 1;
+                offset = d.len_utf8();
             }
         }
         // return text if no tag or interpolation found
@@ -206,20 +341,24 @@ impl<'a> Tokens<'a> {
     fn scan_text(&mut self, size: usize) -> Token<'a> {
         debug_assert!(matches!(self.mode, TextMode::Data | TextMode::RcData));
         debug_assert_ne!(size, 0);
+        let start = self.current_position();
         let src = self.move_by(size);
+        self.report_char_ref_errors(src, &start, false);
         Token::Text(self.decode_text(src))
     }
 
     fn scan_interpolation(&mut self) -> Token<'a> {
         let delimiters = &self.option.delimiters;
         debug_assert!(self.source.starts_with(&delimiters.0));
-        let index = self.source.find(&delimiters.1);
+        let open_len = delimiters.0.len();
+        let rest = &self.source[open_len..];
+        let index = find_interpolation_end(rest, delimiters);
         if index.is_none() {
             let src = self.move_by(self.source.len());
             self.emit_error(ErrorKind::MissingInterpolationEnd);
-            return Token::Interpolation(&src[2..]);
+            return Token::Interpolation(&src[open_len..]);
         }
-        let src = &self.move_by(index.unwrap())[2..];
+        let src = &self.move_by(open_len + index.unwrap())[open_len..];
         self.move_by(self.option.delimiters.1.len());
         Token::Interpolation(src)
     }
@@ -234,8 +373,11 @@ impl<'a> Tokens<'a> {
         } else if source.starts_with("<!") {
             self.scan_comment_and_like()
         } else if source.starts_with("<?") {
-            self.emit_error(ErrorKind::UnexpectedQuestionMarkInsteadOfTagName);
-            self.scan_bogus_comment()
+            self.emit_error_at(ErrorKind::UnexpectedQuestionMarkInsteadOfTagName, 1);
+            match self.scan_bogus_comment() {
+                Token::Comment(c) => Token::ProcessingInstruction(c),
+                _ => unreachable!("bogus comment must be comment"),
+            }
         } else if source.len() == 1 {
             self.move_by(1);
             self.emit_error(ErrorKind::EofBeforeTagName);
@@ -259,7 +401,7 @@ impl<'a> Tokens<'a> {
         // https://html.spec.whatwg.org/multipage/parsing.html#parsing-elements-that-contain-only-text
         // Parsing algorithms are always invoked in response to a start tag token.
         let parsing_algorithm = self.option.get_text_mode;
-        self.mode = parsing_algorithm(tag.name);
+        self.mode = parsing_algorithm(&tag);
         if self.mode != TextMode::Data {
             self.last_start_tag_name.replace(tag.name);
         }
@@ -287,6 +429,8 @@ impl<'a> Tokens<'a> {
     // return attributes and if the tag is self closing
     // https://html.spec.whatwg.org/multipage/parsing.html#before-attribute-name-state
     fn scan_attributes(&mut self) -> Vec<Attribute<'a>> {
+        // linear search is cheaper than hashing for a few attributes.
+        // the set is only built for elements with many attributes.
         let mut attrs = vec![]; // TODO: size hint?
         let mut set = FxHashSet::default();
         loop {
@@ -299,13 +443,20 @@ impl<'a> Tokens<'a> {
                 continue;
             }
             let attr = self.scan_attribute();
-            if set.contains(attr.name) {
+            if attrs.len() == LINEAR_ATTR_SEARCH {
+                set.extend(attrs.iter().map(|a: &Attribute| a.name));
+            }
+            let is_duplicate = if attrs.len() < LINEAR_ATTR_SEARCH {
+                attrs.iter().any(|a| a.name == attr.name)
+            } else {
+                !set.insert(attr.name)
+            };
+            if is_duplicate {
                 // new attribute must be removed from the token.
                 // NB: original vue compiler does not remove it.
                 self.emit_error(ErrorKind::DuplicateAttribute);
                 continue;
             }
-            set.insert(attr.name);
             attrs.push(attr);
         }
     }
@@ -321,17 +472,21 @@ impl<'a> Tokens<'a> {
             || self.did_skip_slash_in_tag()
             || !self.source.starts_with('=')
         {
-            let location = self.get_location_from(start);
+            // whitespace and slash after name is not part of the attribute
             return Attribute {
                 name,
-                location,
+                location: name_loc.clone(),
                 name_loc,
                 value: None,
             };
         }
         self.move_by(1); // equal sign
-        let value = self.scan_attr_value();
-        let location = self.get_location_from(start);
+
+        // attribute ends with its value, if any, to exclude trailing whitespace
+        let (value, location) = match self.scan_attr_value() {
+            Some((v, end)) => (Some(v), SourceLocation { start, end }),
+            None => (None, self.get_location_from(start)),
+        };
         Attribute {
             name,
             value,
@@ -367,54 +522,73 @@ impl<'a> Tokens<'a> {
             .bytes()
             .take_while(|&c| semi_valid_attr_name(c))
             .count();
-        let src = self.move_by(count + offset);
-        if src.contains(&['<', '"', '\''][..]) {
-            self.emit_error(ErrorKind::UnexpectedCharacterInAttributeName);
+        let len = count + offset;
+        // quotes and < stay in the name, report the first one only
+        if let Some(i) = self.source[..len].find(&['<', '"', '\''][..]) {
+            self.emit_error_at(ErrorKind::UnexpectedCharacterInAttributeName, i);
         }
-        src
+        self.move_by(len)
     }
     // https://html.spec.whatwg.org/multipage/parsing.html#before-attribute-value-state
-    fn scan_attr_value(&mut self) -> Option<AttributeValue<'a>> {
+    // Returns the value and its end position including the closing quote.
+    fn scan_attr_value(&mut self) -> Option<(AttributeValue<'a>, Position)> {
         self.skip_whitespace();
         let source = &self.source;
         if source.starts_with('>') {
             self.emit_error(ErrorKind::MissingAttributeValue);
             return None;
         }
-        let start = self.current_position();
-        let content = if self.source.starts_with(&['"', '\''][..]) {
-            let c = self.source.chars().next().unwrap();
-            self.scan_quoted_attr_value(c)?
+        let quote = match self.source.chars().next() {
+            Some('"') => QuoteKind::Double,
+            Some('\'') => QuoteKind::Single,
+            _ => QuoteKind::Unquoted,
+        };
+        let (content, location) = if quote == QuoteKind::Unquoted {
+            let start = self.current_position();
+            let content = self.scan_unquoted_attr_value()?;
+            (content, self.get_location_from(start))
         } else {
-            self.scan_unquoted_attr_value()?
+            self.scan_quoted_attr_value()?
         };
-        Some(AttributeValue {
+        self.report_char_ref_errors(content, &location.start, true);
+        let end = self.current_position();
+        if quote != QuoteKind::Unquoted {
+            self.scan_after_quoted_attr_value();
+        }
+        let value = AttributeValue {
             content: VStr::raw(content),
-            location: self.get_location_from(start),
-        })
+            location,
+            quote,
+        };
+        Some((value, end))
     }
     // https://html.spec.whatwg.org/multipage/parsing.html#attribute-value-(double-quoted)-state
     // https://html.spec.whatwg.org/multipage/parsing.html#attribute-value-(single-quoted)-state
-    fn scan_quoted_attr_value(&mut self, quote: char) -> Option<&'a str> {
-        debug_assert!(self.source.starts_with(quote));
+    // Returns content and its location without quotes.
+    fn scan_quoted_attr_value(&mut self) -> Option<(&'a str, SourceLocation)> {
+        let quote = self.source.chars().next()?;
+        debug_assert!(quote == '"' || quote == '\'');
         self.move_by(1);
-        let src = if let Some(i) = self.source.find(quote) {
-            let val = if i == 0 { "" } else { self.move_by(i) };
-            self.move_by(1); // consume quote char
-            val
-        } else if !self.source.is_empty() {
-            self.move_by(self.source.len())
-        } else {
-            return None;
+        let start = self.current_position();
+        let (src, closed) = match self.source.find(quote) {
+            Some(i) => (if i == 0 { "" } else { self.move_by(i) }, true),
+            None if !self.source.is_empty() => (self.move_by(self.source.len()), false),
+            None => return None,
         };
-        // https://html.spec.whatwg.org/multipage/parsing.html#after-attribute-value-(quoted)-state
+        let location = self.get_location_from(start);
+        if closed {
+            self.move_by(1); // consume quote char
+        }
+        Some((src, location))
+    }
+    // https://html.spec.whatwg.org/multipage/parsing.html#after-attribute-value-(quoted)-state
+    fn scan_after_quoted_attr_value(&mut self) {
         if !self.is_about_to_close_tag()
             && !self.did_skip_slash_in_tag()
             && self.skip_whitespace() == 0
         {
             self.emit_error(ErrorKind::MissingWhitespaceBetweenAttributes);
         }
-        Some(src)
     }
     // https://html.spec.whatwg.org/multipage/parsing.html#attribute-value-(unquoted)-state
     fn scan_unquoted_attr_value(&mut self) -> Option<&'a str> {
@@ -430,11 +604,11 @@ impl<'a> Tokens<'a> {
             debug_assert!(self.source.is_empty());
             return None;
         }
-        let src = self.move_by(val_len);
-        if src.contains(&['"', '\'', '<', '=', '`'][..]) {
-            self.emit_error(ErrorKind::UnexpectedCharacterInUnquotedAttributeValue);
+        let src = &self.source[..val_len];
+        if let Some(i) = src.find(&['"', '\'', '<', '=', '`'][..]) {
+            self.emit_error_at(ErrorKind::UnexpectedCharacterInUnquotedAttributeValue, i);
         }
-        Some(src)
+        Some(self.move_by(val_len))
     }
 
     fn scan_close_start_tag(&mut self) -> bool {
@@ -453,14 +627,15 @@ impl<'a> Tokens<'a> {
         debug_assert!(self.source.starts_with("</"));
         let source = &self.source;
         if source.len() == 2 {
-            self.emit_error(ErrorKind::EofBeforeTagName);
+            self.emit_error_at(ErrorKind::EofBeforeTagName, 2);
             Token::from(self.move_by(2))
         } else if source.starts_with("</>") {
-            self.emit_error(ErrorKind::MissingEndTagName);
+            // `</>` is ignored entirely, neither text nor comment
+            self.emit_error_at(ErrorKind::MissingEndTagName, 2);
             self.move_by(3);
             Token::from("")
         } else if !self.source[2..].starts_with(ascii_alpha) {
-            self.emit_error(ErrorKind::InvalidFirstCharacterOfTagName);
+            self.emit_error_at(ErrorKind::InvalidFirstCharacterOfTagName, 2);
             self.scan_bogus_comment()
         } else {
             self.scan_end_tag()
@@ -491,12 +666,13 @@ impl<'a> Tokens<'a> {
         let s = &self.source;
         if s.starts_with("<!--") {
             self.scan_comment()
-        } else if s.starts_with("<!DOCTYPE") {
+        } else if matches!(s.get(..9), Some(d) if d.eq_ignore_ascii_case("<!DOCTYPE")) {
+            // DOCTYPE is meaningless in template, dropped like comment without error
             self.scan_bogus_comment()
         } else if s.starts_with("<![CDATA[") {
             if self.is_in_html_namespace {
                 self.emit_error(ErrorKind::CDataInHtmlContent);
-                self.scan_bogus_comment()
+                self.scan_html_cdata()
             } else {
                 self.scan_cdata()
             }
@@ -522,18 +698,24 @@ impl<'a> Tokens<'a> {
     }
     fn scan_comment_text(&mut self) -> &'a str {
         debug_assert!(self.source.starts_with("<!--"));
-        let comment_end = self.source.find("-->").or_else(|| self.source.find("--!>"));
+        // the earlier one of --> and --!> closes comment. Dashes in <!-- can be
+        // shared by --> as in <!-->, or by --!> only as in <!---!>
+        let close = self.source[2..].find("-->").map(|i| i + 2);
+        // only search --!> before --> so that scanning stays linear
+        let bang_end = close.map_or(self.source.len(), |i| i + 3);
+        let bang_close = self.source[3..bang_end].find("--!>").map(|i| i + 3);
+        let comment_end = bang_close.or(close);
         // NB: we take &str here since we will call move_by later
         let text = if let Some(end) = comment_end {
-            debug_assert!(end >= 2, "first two chars must be <!");
             // <!---> or <!-->
-            if end <= 3 {
+            if end <= 3 && self.source[end..].starts_with("-->") {
                 self.emit_error(ErrorKind::AbruptClosingOfEmptyComment);
                 self.move_by(end);
                 return "";
             }
-            self.move_by(4); // skip <!--
-            &self.source[..end - 4] // must be exclusive
+            let start = end.min(4); // skip <!--, or <!- in <!---!>
+            self.move_by(start);
+            &self.source[..end - start] // must be exclusive
         } else {
             // no closing comment
             self.move_by(4);
@@ -590,13 +772,29 @@ impl<'a> Tokens<'a> {
         };
         Token::Comment(text)
     }
+    // Spec ends the bogus comment at the first `>`. Swallow to `]]>` instead
+    // so content like `a > b` does not leak into text after one error.
+    #[cold]
+    #[inline(never)]
+    fn scan_html_cdata(&mut self) -> Token<'a> {
+        debug_assert!(self.source.starts_with("<![CDATA["));
+        if let Some(i) = self.source.find("]]>") {
+            // keep the text of bogus comment: `[CDATA[...]]`
+            let text = &self.source[2..i + 2];
+            self.move_by(i + 3);
+            Token::Comment(text)
+        } else {
+            let len = self.source.len();
+            Token::Comment(&self.move_by(len)[2..])
+        }
+    }
     #[cold]
     #[inline(never)]
     fn scan_cdata(&mut self) -> Token<'a> {
         debug_assert!(self.source.starts_with("<![CDATA["));
         self.move_by(9);
         let i = self.source.find("]]>").unwrap_or(self.source.len());
-        let text = self.move_by(i); // can be zero
+        let text = if i == 0 { "" } else { self.move_by(i) };
         if self.source.is_empty() {
             self.emit_error(ErrorKind::EofInCdata);
         } else {
@@ -604,7 +802,7 @@ impl<'a> Tokens<'a> {
             self.move_by(3);
         }
         // don't call scan_text since CDATA decodes nothing
-        Token::from(text)
+        Token::CData(text)
     }
 
     // https://html.spec.whatwg.org/multipage/parsing.html#rawtext-state
@@ -626,11 +824,15 @@ impl<'a> Tokens<'a> {
         debug_assert!(self.mode == TextMode::RcData);
         debug_assert!(!self.source.is_empty());
         let delimiter = &self.option.delimiters.0;
-        if self.source.starts_with(delimiter) {
+        if !self.is_in_v_pre && self.source.starts_with(delimiter) {
             return self.scan_interpolation();
         }
         let end = self.find_appropriate_end();
-        let interpolation_start = self.source.find(delimiter).unwrap_or(end);
+        let interpolation_start = if self.is_in_v_pre {
+            end
+        } else {
+            self.source.find(delimiter).unwrap_or(end)
+        };
         if interpolation_start < end {
             debug_assert_ne!(interpolation_start, 0);
             return self.scan_text(interpolation_start);
@@ -660,7 +862,9 @@ impl<'a> Tokens<'a> {
                 break;
             }
             // https://html.spec.whatwg.org/multipage/parsing.html#rawtext-end-tag-name-state
-            let is_appropriate_end = source[i + 2..e].eq_ignore_ascii_case(tag_name);
+            // compare bytes since e may not be on char boundary, e.g. </scripé
+            let name = &source.as_bytes()[i + 2..e];
+            let is_appropriate_end = name.eq_ignore_ascii_case(tag_name.as_bytes());
             // equivalent to source[e..] does not start with valid_name_char
             let terminated = !is_valid_name_char(source.as_bytes()[e]);
             if is_appropriate_end && terminated {
@@ -680,6 +884,40 @@ impl<'a> Tokens<'a> {
         let err = CompilationError::new(error_kind).with_location(loc);
         self.err_handle.on_error(err);
     }
+    // report at the offending char `offset` bytes ahead, like Vue's emitError
+    fn emit_error_at(&self, error_kind: ErrorKind, offset: usize) {
+        let mut pos = self.current_position();
+        pos.advance(&self.source[..offset]);
+        let loc = SourceLocation {
+            start: pos.clone(),
+            end: pos,
+        };
+        let err = CompilationError::new(error_kind).with_location(loc);
+        self.err_handle.on_error(err);
+    }
+
+    fn report_char_ref_errors(&self, src: &str, start: &Position, as_attr: bool) {
+        if !self.option.decode_entities || !src.contains('&') {
+            return;
+        }
+        for (range, error) in char_ref_errors(src, as_attr) {
+            use CharRefError as E;
+            let kind = match error {
+                E::UnknownNamed => ErrorKind::UnknownNamedCharacterReference,
+                E::MissingSemicolon => ErrorKind::MissingSemicolonAfterCharacterReference,
+                E::AbsenceOfDigits => ErrorKind::AbsenceOfDigitsInNumericCharacterReference,
+                E::NullCharacter => ErrorKind::NullCharacterReference,
+                E::OutsideUnicodeRange => ErrorKind::CharacterReferenceOutsideUnicodeRange,
+                E::Surrogate => ErrorKind::SurrogateCharacterReference,
+            };
+            let mut start = start.clone();
+            start.advance(&src[..range.start]);
+            let mut end = start.clone();
+            end.advance(&src[range]);
+            let err = CompilationError::new(kind).with_location(SourceLocation { start, end });
+            self.err_handle.on_error(err);
+        }
+    }
 
     fn decode_text(&self, src: &'a str) -> VStr<'a> {
         *VStr::raw(src).decode(false)
@@ -691,28 +929,10 @@ impl<'a> Tokens<'a> {
     /// `advance_to` is a better name but it collides with iter
     fn move_by(&mut self, size: usize) -> &'a str {
         debug_assert!(size > 0, "scanner must move forward");
-        let mut lines = 0;
-        let mut last_new_line_pos = -1;
-        for (i, c) in self.source[..size].bytes().enumerate() {
-            if c == b'\n' {
-                lines += 1;
-                last_new_line_pos = i as i32;
-            }
-        }
         let old_source = self.source;
         self.source = &self.source[size..];
         let ret = &old_source[..size];
-        // NB: pos is counted in char not u8
-        let pos = &mut self.position;
-        let offset = ret.chars().count();
-        pos.offset += offset;
-        pos.line += lines;
-        pos.column = if last_new_line_pos == -1 {
-            pos.column + offset as u32
-        } else {
-            ret[last_new_line_pos as usize..].chars().count() as u32
-            // size as u32 - last_new_line_pos as u32
-        };
+        self.position.advance(ret);
         ret
     }
 
@@ -726,6 +946,8 @@ impl<'a> Tokens<'a> {
     }
 }
 
+const LINEAR_ATTR_SEARCH: usize = 16;
+
 #[inline]
 fn ascii_alpha(c: char) -> bool {
     c.is_ascii_alphabetic()
@@ -766,10 +988,23 @@ impl<'a> Iterator for Tokens<'a> {
     type Item = Token<'a>;
     // https://html.spec.whatwg.org/multipage/parsing.html#concept-frag-parse-context
     fn next(&mut self) -> Option<Self::Item> {
+        if let Some((token, _, _)) = self.peeked.take() {
+            return token;
+        }
         if self.source.is_empty() {
             return None;
         }
         self.last_pos = self.current_position();
+        if self.maybe_frontmatter {
+            self.maybe_frontmatter = false;
+            if let Some(token) = self.scan_frontmatter() {
+                return Some(token);
+            }
+            if self.source.is_empty() {
+                return None;
+            }
+            self.last_pos = self.current_position();
+        }
         Some(match self.mode {
             TextMode::Data => self.scan_data(),
             TextMode::RcData => self.scan_rcdata(),
@@ -790,11 +1025,23 @@ impl<'a> FlagCDataNs for Tokens<'a> {
     }
 }
 
+impl<'a> FlagVPre for Tokens<'a> {
+    fn set_is_in_v_pre(&mut self, in_v_pre: bool) {
+        self.is_in_v_pre = in_v_pre;
+    }
+}
+
 impl<'a> Locatable for Tokens<'a> {
     fn current_position(&self) -> Position {
-        self.position.clone()
+        match &self.peeked {
+            Some((_, _, current)) => current.clone(),
+            None => self.position.clone(),
+        }
     }
     fn last_position(&self) -> Position {
+        if let Some((_, last, _)) = &self.peeked {
+            return last.clone();
+        }
         debug_assert! {
             self.position.offset == 0 ||
             self.last_pos.offset < self.position.offset
@@ -807,8 +1054,219 @@ impl<'a> Locatable for Tokens<'a> {
     }
 }
 
-pub trait TokenSource<'a>: FusedIterator<Item = Token<'a>> + FlagCDataNs + Locatable {}
-impl<'a> TokenSource<'a> for Tokens<'a> {}
+pub trait TokenSource<'a>:
+    FusedIterator<Item = Token<'a>> + FlagCDataNs + FlagVPre + Locatable
+{
+    /// Returns the next token without consuming it.
+    /// Locatable methods still refer to the state before peeking, i.e.
+    /// current_position is the end of the last consumed token.
+    /// NB: flags set after peeking do not affect the peeked token.
+    /// Wrap a source in Peekable to get this from a one-token buffer.
+    fn peek(&mut self) -> Option<&Token<'a>>;
+}
+
+impl<'a> TokenSource<'a> for Tokens<'a> {
+    fn peek(&mut self) -> Option<&Token<'a>> {
+        if self.peeked.is_none() {
+            let (last, current) = (self.last_pos.clone(), self.position.clone());
+            let token = self.next();
+            self.peeked = Some((token, last, current));
+        }
+        self.peeked
+            .as_ref()
+            .and_then(|(token, _, _)| token.as_ref())
+    }
+}
+
+/// Makes a token iterator a TokenSource with a one-token buffer,
+/// e.g. a custom source that cannot look ahead by itself.
+pub struct Peekable<'a, Ts> {
+    tokens: Ts,
+    // peeked token with last_position and current_position before peeking
+    peeked: Option<(Option<Token<'a>>, Position, Position)>,
+}
+
+impl<'a, Ts> Peekable<'a, Ts> {
+    pub fn new(tokens: Ts) -> Self {
+        Self {
+            tokens,
+            peeked: None,
+        }
+    }
+}
+
+impl<'a, Ts> Iterator for Peekable<'a, Ts>
+where
+    Ts: Iterator<Item = Token<'a>>,
+{
+    type Item = Token<'a>;
+    fn next(&mut self) -> Option<Token<'a>> {
+        match self.peeked.take() {
+            Some((token, _, _)) => token,
+            None => self.tokens.next(),
+        }
+    }
+}
+
+impl<'a, Ts> FusedIterator for Peekable<'a, Ts> where Ts: FusedIterator<Item = Token<'a>> {}
+
+impl<'a, Ts: FlagCDataNs> FlagCDataNs for Peekable<'a, Ts> {
+    fn set_is_in_html(&mut self, in_html: bool) {
+        self.tokens.set_is_in_html(in_html)
+    }
+    fn need_flag_hint(&self) -> bool {
+        self.tokens.need_flag_hint()
+    }
+}
+
+impl<'a, Ts: FlagVPre> FlagVPre for Peekable<'a, Ts> {
+    fn set_is_in_v_pre(&mut self, in_v_pre: bool) {
+        self.tokens.set_is_in_v_pre(in_v_pre)
+    }
+}
+
+impl<'a, Ts: Locatable> Locatable for Peekable<'a, Ts> {
+    fn current_position(&self) -> Position {
+        match &self.peeked {
+            Some((_, _, current)) => current.clone(),
+            None => self.tokens.current_position(),
+        }
+    }
+    fn last_position(&self) -> Position {
+        match &self.peeked {
+            Some((_, last, _)) => last.clone(),
+            None => self.tokens.last_position(),
+        }
+    }
+    fn get_location_from(&self, start: Position) -> SourceLocation {
+        let end = self.current_position();
+        SourceLocation { start, end }
+    }
+}
+
+impl<'a, Ts> TokenSource<'a> for Peekable<'a, Ts>
+where
+    Ts: FusedIterator<Item = Token<'a>> + FlagCDataNs + FlagVPre + Locatable,
+{
+    fn peek(&mut self) -> Option<&Token<'a>> {
+        if self.peeked.is_none() {
+            let last = self.tokens.last_position();
+            let current = self.tokens.current_position();
+            self.peeked = Some((self.tokens.next(), last, current));
+        }
+        self.peeked
+            .as_ref()
+            .and_then(|(token, _, _)| token.as_ref())
+    }
+}
+
+/// A TokenSource over tokens with their locations. It tests or fuzzes
+/// the parser with token sequences the scanner cannot produce.
+/// Positions are the stored locations of the last consumed token.
+pub struct VecTokenSource<'a> {
+    tokens: VecDeque<(Token<'a>, SourceLocation)>,
+    last: SourceLocation,
+    need_flag: bool,
+    is_in_html: bool,
+    is_in_v_pre: bool,
+}
+
+impl<'a> VecTokenSource<'a> {
+    pub fn new(tokens: Vec<(Token<'a>, SourceLocation)>) -> Self {
+        Self {
+            tokens: tokens.into(),
+            last: SourceLocation::default(),
+            need_flag: false,
+            is_in_html: true,
+            is_in_v_pre: false,
+        }
+    }
+    /// Sets the result of need_flag_hint, false by default.
+    pub fn with_flag_hint(mut self, need_flag: bool) -> Self {
+        self.need_flag = need_flag;
+        self
+    }
+    /// The namespace flag last set by the parser.
+    pub fn is_in_html(&self) -> bool {
+        self.is_in_html
+    }
+    /// The v-pre flag last set by the parser.
+    pub fn is_in_v_pre(&self) -> bool {
+        self.is_in_v_pre
+    }
+}
+
+impl<'a> Iterator for VecTokenSource<'a> {
+    type Item = Token<'a>;
+    fn next(&mut self) -> Option<Token<'a>> {
+        let (token, location) = self.tokens.pop_front()?;
+        self.last = location;
+        Some(token)
+    }
+}
+
+impl<'a> FusedIterator for VecTokenSource<'a> {}
+
+impl<'a> FlagCDataNs for VecTokenSource<'a> {
+    fn set_is_in_html(&mut self, in_html: bool) {
+        self.is_in_html = in_html;
+    }
+    fn need_flag_hint(&self) -> bool {
+        self.need_flag
+    }
+}
+
+impl<'a> FlagVPre for VecTokenSource<'a> {
+    fn set_is_in_v_pre(&mut self, in_v_pre: bool) {
+        self.is_in_v_pre = in_v_pre;
+    }
+}
+
+impl<'a> Locatable for VecTokenSource<'a> {
+    fn current_position(&self) -> Position {
+        self.last.end.clone()
+    }
+    fn last_position(&self) -> Position {
+        self.last.start.clone()
+    }
+    fn get_location_from(&self, start: Position) -> SourceLocation {
+        let end = self.current_position();
+        SourceLocation { start, end }
+    }
+}
+
+impl<'a> TokenSource<'a> for VecTokenSource<'a> {
+    fn peek(&mut self) -> Option<&Token<'a>> {
+        self.tokens.front().map(|(token, _)| token)
+    }
+}
+
+/// Finds the close delimiter outside of JS string literals, e.g. `{{ '}}' }}`.
+/// `${}` in template literal is not tracked, its content is still in the string.
+/// Unbalanced quote, e.g. `{{ it's }}`, falls back to the first close delimiter.
+/// Quotes are unbalanced if the scan reaches EOF or an unquoted open delimiter,
+/// which cannot appear in an expression, e.g. `{{ it's }} don't {{ x }}`.
+fn find_interpolation_end(src: &str, delimiters: &(String, String)) -> Option<usize> {
+    let (open, close) = delimiters;
+    let bytes = src.as_bytes();
+    let mut quote = None;
+    let mut i = 0;
+    while i < bytes.len() {
+        let b = bytes[i];
+        match quote {
+            Some(_) if b == b'\\' => i += 1,
+            Some(q) if b == q => quote = None,
+            Some(_) => (),
+            // a match of valid UTF-8 bytes is always on char boundary
+            None if bytes[i..].starts_with(close.as_bytes()) => return Some(i),
+            None if bytes[i..].starts_with(open.as_bytes()) => break,
+            None if matches!(b, b'\'' | b'"' | b'`') => quote = Some(b),
+            None => (),
+        }
+        i += 1;
+    }
+    src.find(close.as_str())
+}
 
 #[cfg(test)]
 pub mod test {
@@ -827,6 +1285,503 @@ pub mod test {
         ));
     }
 
+    #[test]
+    fn test_custom_delimiter() {
+        let opt = ScanOption {
+            delimiters: ("${".into(), "}".into()),
+            ..Default::default()
+        };
+        let a: Vec<_> = scan_with_opt("{<p>${ a }${ b", opt).collect();
+        assert_eq!(a.len(), 4);
+        assert!(matches!(a[0], Token::Text(VStr { raw: "{", .. })));
+        assert!(matches!(a[2], Token::Interpolation(" a ")));
+        // unterminated interpolation keeps the expression
+        assert!(matches!(a[3], Token::Interpolation(" b")));
+    }
+
+    #[test]
+    fn test_frontmatter() {
+        let opt = ScanOption {
+            frontmatter: FrontmatterMode::Strip,
+            ..Default::default()
+        };
+        let src = "\n---\r\ntitle: <a> {{ b }}\n---\r\n\n<p/>";
+        let a: Vec<_> = scan_with_opt(src, opt.clone()).collect();
+        assert_eq!(a.len(), 3);
+        assert!(matches!(a[0], Token::Frontmatter("title: <a> {{ b }}")));
+        let a: Vec<_> = scan_with_opt("---\n---", opt.clone()).collect();
+        assert!(matches!(a[..], [Token::Frontmatter("")]));
+        // not at start
+        let a: Vec<_> = scan_with_opt("a\n---\nb\n---\n", opt.clone()).collect();
+        assert!(matches!(a[..], [Token::Text(_)]));
+        let a: Vec<_> = base_scan(src).collect();
+        assert!(a.len() > 3);
+    }
+
+    #[test]
+    fn test_processing_instruction() {
+        let a: Vec<_> = base_scan(r#"<?xml version="1.0"?><p/>"#).collect();
+        assert_eq!(a.len(), 2);
+        assert!(matches!(
+            a[0],
+            Token::ProcessingInstruction(r#"?xml version="1.0"?"#)
+        ));
+    }
+
+    #[test]
+    fn test_doctype_and_html_cdata() {
+        let a: Vec<_> = base_scan("<!doctype html><p/>").collect();
+        assert!(matches!(
+            a[..],
+            [Token::Comment("doctype html"), Token::StartTag(_)]
+        ));
+        let a: Vec<_> = base_scan("<![CDATA[a > b]]>c").collect();
+        assert!(matches!(a[0], Token::Comment("[CDATA[a > b]]")));
+        assert!(matches!(a[1], Token::Text(VStr { raw: "c", .. })));
+        let a: Vec<_> = base_scan("<![CDATA[a").collect();
+        assert!(matches!(a[..], [Token::Comment("[CDATA[a")]));
+    }
+
+    #[test]
+    fn test_bogus_comment() {
+        let case = "<?php echo 1; ?><!WEIRD></ x>a</>b</";
+        let (a, errors) = scan_with_errors(case, ScanOption::default());
+        assert!(matches!(
+            a[..3],
+            [
+                Token::ProcessingInstruction("?php echo 1; ?"),
+                Token::Comment("WEIRD"),
+                Token::Comment(" x"),
+            ]
+        ));
+        let text: String = a[3..]
+            .iter()
+            .map(|t| match t {
+                Token::Text(s) => s.into_string(),
+                _ => panic!("expect text"),
+            })
+            .collect();
+        assert_eq!(text, "ab</");
+        let errors: Vec<_> = errors
+            .iter()
+            .map(|e| {
+                let kind = match e.kind {
+                    ErrorKind::UnexpectedQuestionMarkInsteadOfTagName => "question",
+                    ErrorKind::IncorrectlyOpenedComment => "opened",
+                    ErrorKind::InvalidFirstCharacterOfTagName => "first",
+                    ErrorKind::MissingEndTagName => "missing",
+                    ErrorKind::EofBeforeTagName => "eof",
+                    _ => "other",
+                };
+                (kind, e.location.start.offset)
+            })
+            .collect();
+        assert_eq!(
+            errors,
+            [
+                ("question", 1),
+                ("opened", 16),
+                ("first", 26),
+                ("missing", 32),
+                ("eof", 36)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_malformed_attributes() {
+        type Attr = (String, Option<String>, usize);
+        let scan = |case: &str| -> (Vec<Attr>, Vec<(&str, usize)>) {
+            let (mut tokens, errors) = scan_with_errors(case, ScanOption::default());
+            let tag = match tokens.remove(0) {
+                Token::StartTag(tag) => tag,
+                _ => panic!("expect start tag"),
+            };
+            let attrs = tag
+                .attributes
+                .iter()
+                .map(|a| {
+                    let value = a.value.as_ref().map(|v| v.raw().to_string());
+                    let name = &case[a.name_loc.start.offset..a.name_loc.end.offset];
+                    assert_eq!(name, a.name);
+                    (a.name.to_string(), value, a.name_loc.start.offset)
+                })
+                .collect();
+            let errors = errors
+                .iter()
+                .map(|e| {
+                    let kind = match e.kind {
+                        ErrorKind::MissingWhitespaceBetweenAttributes => "whitespace",
+                        ErrorKind::UnexpectedEqualsSignBeforeAttributeName => "equals",
+                        ErrorKind::UnexpectedCharacterInAttributeName => "name char",
+                        ErrorKind::UnexpectedCharacterInUnquotedAttributeValue => "value char",
+                        _ => "other",
+                    };
+                    (kind, e.location.start.offset)
+                })
+                .collect();
+            (attrs, errors)
+        };
+        let attr = |name: &str, value: Option<&str>, offset| {
+            (name.to_string(), value.map(String::from), offset)
+        };
+        let cases = [
+            (
+                "<div class=foo bar>",
+                vec![attr("class", Some("foo"), 5), attr("bar", None, 15)],
+                vec![],
+            ),
+            (
+                r#"<div class="a"id="b">"#,
+                vec![attr("class", Some("a"), 5), attr("id", Some("b"), 14)],
+                vec![("whitespace", 14)],
+            ),
+            ("<div =x>", vec![attr("=x", None, 5)], vec![("equals", 5)]),
+            (
+                r#"<div ="x">"#,
+                vec![attr(r#"="x""#, None, 5)],
+                vec![("equals", 5), ("name char", 6)],
+            ),
+            (
+                "<div a<b c>",
+                vec![attr("a<b", None, 5), attr("c", None, 9)],
+                vec![("name char", 6)],
+            ),
+            (
+                "<div a=b`c d>",
+                vec![attr("a", Some("b`c"), 5), attr("d", None, 11)],
+                vec![("value char", 8)],
+            ),
+        ];
+        for (case, attrs, errors) in cases.iter() {
+            let (a, e) = scan(case);
+            assert_eq!(&a, attrs, "{}", case);
+            assert_eq!(&e, errors, "{}", case);
+        }
+    }
+
+    #[test]
+    fn test_peek_keeps_position() {
+        check_peek_position(base_scan("ab<p>"));
+        check_peek_position(Peekable::new(base_scan("ab<p>")));
+        let loc = |start, end| SourceLocation {
+            start: Position {
+                offset: start,
+                ..Default::default()
+            },
+            end: Position {
+                offset: end,
+                ..Default::default()
+            },
+        };
+        let tag = Tag {
+            name: "p",
+            attributes: vec![],
+            self_closing: false,
+        };
+        let tokens = vec![
+            (Token::from("ab"), loc(0, 2)),
+            (Token::StartTag(tag), loc(2, 5)),
+        ];
+        check_peek_position(VecTokenSource::new(tokens));
+    }
+
+    fn check_peek_position<'a>(mut tokens: impl TokenSource<'a>) {
+        assert!(matches!(tokens.next(), Some(Token::Text(_))));
+        let (last, current) = (tokens.last_position(), tokens.current_position());
+        assert_eq!((last.offset, current.offset), (0, 2));
+        assert!(matches!(tokens.peek(), Some(Token::StartTag(_))));
+        assert!(matches!(tokens.peek(), Some(Token::StartTag(_))));
+        assert_eq!(tokens.last_position(), last);
+        assert_eq!(tokens.current_position(), current);
+        assert_eq!(tokens.get_location_from(last).end, current);
+        assert!(matches!(tokens.next(), Some(Token::StartTag(_))));
+        assert_eq!(tokens.last_position().offset, 2);
+        assert_eq!(tokens.current_position().offset, 5);
+        assert!(tokens.peek().is_none());
+        assert_eq!(tokens.current_position().offset, 5);
+        assert!(tokens.next().is_none());
+    }
+
+    #[test]
+    fn test_rawtext_end_tag() {
+        fn scan(case: &str) -> (Vec<Token<'_>>, Vec<&'static str>) {
+            let opt = ScanOption {
+                get_text_mode: crate::parser::preset::dom_get_text_mode,
+                ..Default::default()
+            };
+            let (tokens, errors) = scan_with_errors(case, opt);
+            let errors: Vec<_> = errors
+                .iter()
+                .map(|e| match e.kind {
+                    ErrorKind::EndTagWithAttributes => "attributes",
+                    ErrorKind::EndTagWithTrailingSolidus => "solidus",
+                    _ => "other",
+                })
+                .collect();
+            (tokens, errors)
+        }
+        let closed = [
+            ("<script>a</script >b", vec![]),
+            ("<script>a</SCRIPT>b", vec![]),
+            ("<style>a</style foo>b", vec!["attributes"]),
+            (r#"<script>a</script foo="bar">b"#, vec!["attributes"]),
+            ("<script>a</script/>b", vec!["solidus"]),
+        ];
+        for (case, expected) in closed.iter() {
+            let (tokens, errors) = scan(case);
+            assert!(
+                matches!(
+                    tokens[1..],
+                    [
+                        Token::Text(VStr { raw: "a", .. }),
+                        Token::EndTag(_),
+                        Token::Text(VStr { raw: "b", .. })
+                    ]
+                ),
+                "{}",
+                case
+            );
+            assert_eq!(&errors, expected, "{}", case);
+        }
+        let (tokens, errors) = scan("<script>a</scriptx>b");
+        assert!(matches!(
+            tokens[1..],
+            [Token::Text(VStr {
+                raw: "a</scriptx>b",
+                ..
+            })]
+        ));
+        assert!(errors.is_empty());
+        // non-ASCII char right after a partial tag name
+        let (tokens, _) = scan("<script>a</scrip\u{e9}></script>");
+        assert!(matches!(
+            tokens[1..],
+            [
+                Token::Text(VStr {
+                    raw: "a</scrip\u{e9}>",
+                    ..
+                }),
+                Token::EndTag("script")
+            ]
+        ));
+    }
+
+    #[test]
+    fn test_attribute_locations() {
+        // slices of name_loc, location and value location by source
+        let cases = [
+            ("<input disabled>", "disabled", "disabled", None),
+            ("<input disabled >", "disabled", "disabled", None),
+            ("<input disabled/>", "disabled", "disabled", None),
+            ("<input\n  disabled\n>", "disabled", "disabled", None),
+            (
+                r#"<input value="">"#,
+                "value",
+                r#"value="""#,
+                Some(("", 14)),
+            ),
+            ("<input value=''>", "value", "value=''", Some(("", 14))),
+            ("<input a=b >", "a", "a=b", Some(("b", 9))),
+            (r#"<input a = "x" >"#, "a", r#"a = "x""#, Some(("x", 12))),
+        ];
+        for &(case, name, location, value) in cases.iter() {
+            let tag = match base_scan(case).next() {
+                Some(Token::StartTag(tag)) => tag,
+                _ => panic!("expect start tag"),
+            };
+            let slice = |l: &SourceLocation| &case[l.start.offset..l.end.offset];
+            let attr = &tag.attributes[0];
+            assert_eq!(slice(&attr.name_loc), name, "{}", case);
+            assert_eq!(slice(&attr.location), location, "{}", case);
+            let actual = attr
+                .value
+                .as_ref()
+                .map(|v| (slice(&v.location), v.location.start.offset));
+            assert_eq!(actual, value, "{}", case);
+        }
+    }
+
+    #[test]
+    fn test_foreign_cdata() {
+        let mut tokens = base_scan("<![CDATA[a & <b>]]>c");
+        tokens.set_is_in_html(false);
+        let a: Vec<_> = tokens.collect();
+        assert!(matches!(a[0], Token::CData("a & <b>")));
+        assert!(matches!(a[1], Token::Text(VStr { raw: "c", .. })));
+        let mut tokens = base_scan("<![CDATA[]]>");
+        tokens.set_is_in_html(false);
+        let a: Vec<_> = tokens.collect();
+        assert!(matches!(a[..], [Token::CData("")]));
+    }
+
+    #[test]
+    fn test_interpolation_string_literal() {
+        let interpolation = |case| {
+            let a: Vec<_> = base_scan(case).collect();
+            assert_eq!(a.len(), 1, "{}", case);
+            match a[0] {
+                Token::Interpolation(s) => s,
+                _ => panic!("expect interpolation"),
+            }
+        };
+        assert_eq!(interpolation(r#"{{ "}}" }}"#), r#" "}}" "#);
+        assert_eq!(interpolation("{{ a + '}}' }}"), " a + '}}' ");
+        assert_eq!(interpolation("{{ `}}${b}` }}"), " `}}${b}` ");
+        assert_eq!(interpolation(r#"{{ "\"}}" }}"#), r#" "\"}}" "#);
+        assert_eq!(interpolation(r"{{ '\\' }}"), r" '\\' ");
+        assert_eq!(
+            interpolation("{{ s === '}}' || a < b }}"),
+            " s === '}}' || a < b "
+        );
+        // unbalanced quote ends at the first delimiter
+        let a: Vec<_> = base_scan("{{ it's }} ok").collect();
+        assert!(matches!(a[0], Token::Interpolation(" it's ")));
+        assert_eq!(a.len(), 2);
+        // so is a string running to EOF or another interpolation
+        for case in ["{{ it's }}<p>don't</p>{{ x }}", "{{ it's }}<p>don't</p>"] {
+            let a: Vec<_> = base_scan(case).collect();
+            assert!(matches!(a[0], Token::Interpolation(" it's ")), "{}", case);
+            assert!(matches!(a[1], Token::StartTag(_)), "{}", case);
+        }
+        let a: Vec<_> = base_scan("{{ it's }} don't {{ x }}").collect();
+        assert!(matches!(
+            a[..],
+            [
+                Token::Interpolation(" it's "),
+                _,
+                Token::Interpolation(" x ")
+            ]
+        ));
+        let a: Vec<_> = base_scan("{{ '}}'").collect();
+        assert!(matches!(
+            a[..],
+            [Token::Interpolation(" '"), Token::Text(_)]
+        ));
+        // unterminated
+        let (a, errors) = scan_with_errors("{{ 'a'", ScanOption::default());
+        assert!(matches!(a[..], [Token::Interpolation(" 'a'")]));
+        assert!(
+            matches!(errors[..], [ref e] if matches!(e.kind, ErrorKind::MissingInterpolationEnd))
+        );
+    }
+
+    #[test]
+    fn test_unquoted_attr_value_error() {
+        let errors = |case: &str| scan_errors(case, ScanOption::default()).len();
+        assert_eq!(errors("<p a=b/c.d?e&f>"), 0);
+        for c in ['"', '\'', '<', '=', '`'] {
+            assert_eq!(errors(&format!("<p a=b{}c>", c)), 1);
+        }
+    }
+
+    #[test]
+    fn test_duplicate_attribute() {
+        let scan = |case: &str| {
+            let (mut tokens, errors) = scan_with_errors(case, ScanOption::default());
+            let tag = cast!(tokens.remove(0), Token::StartTag);
+            let names: Vec<_> = tag.attributes.iter().map(|a| a.name.to_string()).collect();
+            (names, errors.len())
+        };
+        let (scanned, errors) = scan("<p a b a c b/>");
+        assert_eq!(scanned, ["a", "b", "c"]);
+        assert_eq!(errors, 2);
+        // many attributes switch to hash set
+        let names: Vec<_> = (0..20).map(|i| format!("a{}", i)).collect();
+        let case = format!("<p {} a3 a19 a20 a19/>", names.join(" "));
+        let (scanned, errors) = scan(&case);
+        assert_eq!(scanned.len(), 21);
+        assert_eq!(scanned.last().unwrap(), "a20");
+        assert_eq!(errors, 3);
+    }
+
+    #[test]
+    fn test_char_ref_errors() {
+        let scan = |case: &str, decode_entities| {
+            let opt = ScanOption {
+                decode_entities,
+                get_text_mode: crate::parser::preset::dom_get_text_mode,
+                ..Default::default()
+            };
+            let (tokens, errors) = scan_with_errors(case, opt);
+            let text = tokens.iter().find_map(|t| match t {
+                Token::Text(s) => Some(s.into_string()),
+                _ => None,
+            });
+            let errors: Vec<_> = errors
+                .iter()
+                .map(|e| {
+                    let kind = match e.kind {
+                        ErrorKind::UnknownNamedCharacterReference => "unknown",
+                        ErrorKind::MissingSemicolonAfterCharacterReference => "semicolon",
+                        ErrorKind::SurrogateCharacterReference => "surrogate",
+                        _ => "other",
+                    };
+                    let src = &case[e.location.start.offset..e.location.end.offset];
+                    (kind, src.to_string())
+                })
+                .collect();
+            (text, errors)
+        };
+        let case = "<p title=\"&foo; &#x41\" alt=&xx;>\n&bar; &notit; &amp &#12;&#xD800;</p>";
+        let (text, errors) = scan(case, true);
+        assert_eq!(text.unwrap(), "\n&bar; ¬it; & \u{c}\u{fffd}");
+        let expected = [
+            ("unknown", "&foo;"),
+            ("semicolon", "&#x41"),
+            ("unknown", "&xx;"),
+            ("unknown", "&bar;"),
+            ("semicolon", "&not"),
+            ("semicolon", "&amp"),
+            ("surrogate", "&#xD800;"),
+        ];
+        let expected: Vec<_> = expected.iter().map(|(k, s)| (*k, s.to_string())).collect();
+        assert_eq!(errors, expected);
+        let (_, errors) = scan(case, false);
+        assert!(errors.is_empty());
+        // raw text is not decoded
+        let (_, errors) = scan("<script>a &foo; b</script>", true);
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn test_comment_errors() {
+        use crate::error::ErrorKind as _;
+        let scan = |case: &'static str| {
+            let (tokens, errors) = scan_with_errors(case, ScanOption::default());
+            let text = match &tokens[0] {
+                Token::Comment(c) => *c,
+                _ => panic!("expect comment in {}", case),
+            };
+            let errors: Vec<_> = errors
+                .iter()
+                .map(|e| (e.kind.code(), e.location.start.offset))
+                .collect();
+            (text, errors)
+        };
+        let abrupt = ErrorKind::AbruptClosingOfEmptyComment.code();
+        let bang = ErrorKind::IncorrectlyClosedComment.code();
+        let nested = ErrorKind::NestedComment.code();
+        let eof = ErrorKind::EofInComment.code();
+        let cases = [
+            ("<!-->", "", vec![(abrupt, 0)]),
+            ("<!--->", "", vec![(abrupt, 0)]),
+            ("<!---->", "", vec![]),
+            ("<!--!>-->", "!>", vec![]),
+            ("<!---!>", "", vec![(bang, 3)]),
+            ("<!-- a --!>", " a ", vec![(bang, 7)]),
+            ("<!-- a --!> b -->", " a ", vec![(bang, 7)]),
+            ("<!-- a --> b --!>", " a ", vec![]),
+            ("<!-- <!-- b -->", " <!-- b ", vec![(nested, 9)]),
+            ("<!-- <!-->", " <!", vec![]),
+            ("<!-- a", " a", vec![(eof, 6)]),
+            ("<!-- <!--", " <!--", vec![(eof, 9)]),
+        ];
+        for (case, text, errors) in cases {
+            assert_eq!(scan(case), (text, errors), "{}", case);
+        }
+    }
+
     #[test]
     fn test_no_decode_attr() {
         let mut a: Vec<_> = base_scan("<p v='&amp;'/>").collect();
@@ -835,6 +1790,34 @@ pub mod test {
         assert_eq!(val.content.into_string(), "&amp;");
     }
 
+    #[test]
+    fn test_attr_value_quote() {
+        let case = r#"<p a="x" b='y' c=z d e="w"#;
+        let mut a: Vec<_> = base_scan(case).collect();
+        let tag = cast!(a.remove(0), Token::StartTag);
+        let source = |l: &SourceLocation| &case[l.start.offset..l.end.offset];
+        let values: Vec<_> = tag
+            .attributes
+            .iter()
+            .map(|attr| {
+                let v = attr.value.as_ref()?;
+                Some((source(&attr.location), source(&v.location), v.quote))
+            })
+            .collect();
+        use QuoteKind::*;
+        assert_eq!(
+            values,
+            [
+                Some((r#"a="x""#, "x", Double)),
+                Some(("b='y'", "y", Single)),
+                Some(("c=z", "z", Unquoted)),
+                None,
+                // unclosed quote
+                Some((r#"e="w"#, "w", Double)),
+            ]
+        );
+    }
+
     #[test]
     fn test_simple_text_with_invalid_end_tag() {
         let a: Vec<_> = base_scan("some text</div>").collect();
@@ -852,6 +1835,18 @@ pub mod test {
     pub fn base_scan(s: &str) -> impl TokenSource {
         scan_with_opt(s, ScanOption::default())
     }
+
+    // all tokens and the errors reported while scanning them
+    pub fn scan_with_errors(s: &str, opt: ScanOption) -> (Vec<Token<'_>>, Vec<CompilationError>) {
+        let eh = std::rc::Rc::new(crate::error::VecErrorHandler::new());
+        let tokens = Scanner::new(opt).scan(s, eh.clone()).collect();
+        let errors = std::mem::take(&mut *eh.error_mut());
+        (tokens, errors)
+    }
+
+    pub fn scan_errors(s: &str, opt: ScanOption) -> Vec<CompilationError> {
+        scan_with_errors(s, opt).1
+    }
     #[test]
     fn test_tokens_moveby_fun() {
         let mut test_moved_str = return_base_tokens("hello");
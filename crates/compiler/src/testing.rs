@@ -0,0 +1,209 @@
+/*!
+Invariants every parse result must hold regardless of input.
+
+Useful for fuzzing and corpus based regression tests, in this crate or in
+downstream compilers built on top of it. Only structural properties are
+checked so that any input, valid or not, can be replayed:
+* every location is well-formed: start ≤ end ≤ source length, on char boundary.
+* children and props are inside their element and siblings do not overlap.
+* text nodes have non-empty locations.
+* errors are in source order. Scanner and parser do not report errors in
+  source order, e.g. MissingEndTag is reported when its element is closed,
+  so collected errors should be ordered by `sort_errors` first.
+*/
+
+use crate::{
+    error::CompilationError,
+    parser::{AstNode, AstRoot, ElemProp, Element},
+    Position, SourceLocation,
+};
+use std::fmt;
+
+#[cfg(feature = "testing")]
+pub mod conformance;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct InvariantViolation {
+    pub message: String,
+    pub location: SourceLocation,
+}
+
+impl fmt::Display for InvariantViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let SourceLocation { start, end } = &self.location;
+        write!(f, "{} at {}..{}", self.message, start.offset, end.offset)
+    }
+}
+
+type Checked = Result<(), InvariantViolation>;
+
+pub fn check_invariants(
+    root: &AstRoot,
+    errors: &[CompilationError],
+    source: &str,
+) -> Result<(), InvariantViolation> {
+    let checker = Checker { source };
+    checker.check_location(&root.location, "root")?;
+    if let Some(frontmatter) = &root.frontmatter {
+        checker.check_location(&frontmatter.location, "frontmatter")?;
+    }
+    checker.check_tree(root)?;
+    checker.check_errors(errors)
+}
+
+/// Stable sort by start offset, so errors at the same place keep report order.
+pub fn sort_errors(errors: &mut [CompilationError]) {
+    errors.sort_by_key(|e| e.location.start.offset);
+}
+
+struct Checker<'s> {
+    source: &'s str,
+}
+
+impl<'s> Checker<'s> {
+    // iterative since input like a thousand unclosed tags is deeply nested
+    fn check_tree(&self, root: &AstRoot) -> Checked {
+        let mut stack = vec![(&root.children[..], &root.location)];
+        while let Some((children, parent)) = stack.pop() {
+            let mut prev_end = parent.start.offset;
+            for child in children {
+                let loc = child.get_location();
+                self.check_location(loc, "node")?;
+                self.check_inside(loc, parent, "node")?;
+                if loc.start.offset < prev_end {
+                    return violation("node overlaps previous sibling", loc);
+                }
+                prev_end = loc.end.offset;
+                if matches!(child, AstNode::Text(_)) && loc.start.offset == loc.end.offset {
+                    return violation("text node has empty location", loc);
+                }
+                if let AstNode::Element(e) = child {
+                    self.check_props(e)?;
+                    stack.push((&e.children, &e.location));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn check_props(&self, e: &Element) -> Checked {
+        let mut prev_end = e.location.start.offset;
+        for prop in &e.properties {
+            let (loc, head, value) = match prop {
+                ElemProp::Attr(a) => (&a.location, &a.name_loc, &a.value),
+                ElemProp::Dir(d) => (&d.location, &d.head_loc, &d.expression),
+            };
+            self.check_location(loc, "prop")?;
+            self.check_inside(loc, &e.location, "prop")?;
+            self.check_location(head, "prop name")?;
+            self.check_inside(head, loc, "prop name")?;
+            if let Some(v) = value {
+                self.check_location(&v.location, "prop value")?;
+                self.check_inside(&v.location, loc, "prop value")?;
+            }
+            if loc.start.offset < prev_end {
+                return violation("prop overlaps previous prop", loc);
+            }
+            prev_end = loc.end.offset;
+        }
+        Ok(())
+    }
+
+    fn check_errors(&self, errors: &[CompilationError]) -> Checked {
+        for error in errors {
+            self.check_location(&error.location, "error")?;
+        }
+        for pair in errors.windows(2) {
+            if pair[1].location.start.offset < pair[0].location.start.offset {
+                return violation("error is reported out of source order", &pair[1].location);
+            }
+        }
+        Ok(())
+    }
+
+    fn check_location(&self, loc: &SourceLocation, what: &str) -> Checked {
+        let SourceLocation { start, end } = loc;
+        if start.offset > end.offset {
+            return violation(&format!("{} starts after its end", what), loc);
+        }
+        if end.offset > self.source.len() {
+            return violation(&format!("{} ends after source", what), loc);
+        }
+        let on_boundary = |p: &Position| self.source.is_char_boundary(p.offset);
+        if !on_boundary(start) || !on_boundary(end) {
+            return violation(&format!("{} splits a char", what), loc);
+        }
+        if (start.line, start.column) > (end.line, end.column) {
+            return violation(&format!("{} has line/column after its end", what), loc);
+        }
+        Ok(())
+    }
+
+    fn check_inside(&self, loc: &SourceLocation, outer: &SourceLocation, what: &str) -> Checked {
+        if loc.start.offset < outer.start.offset || loc.end.offset > outer.end.offset {
+            return violation(&format!("{} is outside its parent", what), loc);
+        }
+        Ok(())
+    }
+}
+
+fn violation(message: &str, loc: &SourceLocation) -> Checked {
+    Err(InvariantViolation {
+        message: message.into(),
+        location: loc.clone(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{error::CompilationErrorKind as ErrorKind, parser::test::base_parse};
+
+    #[test]
+    fn test_valid_template() {
+        let source = "<div :a='b' c>d{{ e }}<!--f--></div>";
+        let root = base_parse(source);
+        assert_eq!(check_invariants(&root, &[], source), Ok(()));
+    }
+
+    #[test]
+    fn test_broken_location() {
+        let source = "<p>a</p>";
+        let mut root = base_parse(source);
+        root.children[0].get_element_mut().unwrap().children[0] =
+            base_parse("abcdefghij").children.pop().unwrap();
+        let err = check_invariants(&root, &[], source).unwrap_err();
+        assert_eq!(err.message, "node ends after source");
+        let root = base_parse(source);
+        let err = check_invariants(&root, &[], "<p>").unwrap_err();
+        assert_eq!(err.message, "root ends after source");
+    }
+
+    #[test]
+    fn test_unsorted_errors() {
+        let source = "<p>a</p>";
+        let root = base_parse(source);
+        let at = |offset| {
+            let pos = Position {
+                offset,
+                line: 1,
+                column: offset as u32 + 1,
+            };
+            let location = SourceLocation {
+                start: pos.clone(),
+                end: pos,
+            };
+            CompilationError::new(ErrorKind::InvalidEndTag).with_location(location)
+        };
+        let errors = [at(1), at(3), at(2)];
+        let err = check_invariants(&root, &errors, source).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "error is reported out of source order at 2..2"
+        );
+        assert!(check_invariants(&root, &errors[..2], source).is_ok());
+        let mut errors = errors;
+        sort_errors(&mut errors);
+        assert!(check_invariants(&root, &errors, source).is_ok());
+    }
+}
@@ -0,0 +1,480 @@
+/*!
+Compares parse results with ASTs exported from vue/compiler-core as JSON.
+
+A fixture is a JSON file with these fields:
+* `template`: the source to parse.
+* `options`: optional `whitespace` ("condense" or "preserve") and `comments`,
+  named as in the JS compiler options.
+* `ignoreLoc`: optional. Skips `loc` of every node when true.
+* `ast`: optional. `JSON.stringify(parse(template, options))` of compiler-dom,
+  i.e. compiler-core's baseParse with DOM parser options.
+* `errors`: optional. Names of compiler-core ErrorCodes reported by the parse,
+  e.g. `X_MISSING_END_TAG`. Order is ignored.
+* `knownDivergence`: optional. Why the Rust parser does not match yet. Such a
+  fixture is expected to fail, so a fix shows up as an unexpected pass.
+
+`tests/conformance/export.mjs` regenerates `ast` and `errors` from the JS compiler.
+
+Both sides are normalized to the JS shape before comparison. Only fields
+meaningful to both ASTs are kept, e.g. JS `codegenNode` or Rust node ids are
+dropped, and enums are named after their JS constants, e.g. `"tagType": "SLOT"`.
+Known differences are normalized away: v-pre directive is kept in Rust AST and
+interpolation source is not trimmed. Rust only diagnostics without an ErrorCodes
+counterpart are not compared.
+*/
+
+use crate::{
+    error::{CompilationErrorKind as ErrorKind, VecErrorHandler},
+    parser::{AstRoot, ParseOption, Parser, WhitespaceStrategy},
+    scanner::{ScanOption, Scanner},
+    util::no,
+};
+use serde_json::{json, Map, Value};
+use std::{fmt, fs, io, path::Path, rc::Rc};
+
+pub struct Fixture {
+    /// File name without extension.
+    pub name: String,
+    pub template: String,
+    pub whitespace: Option<WhitespaceStrategy>,
+    pub comments: Option<bool>,
+    pub ignore_loc: bool,
+    pub ast: Option<Value>,
+    pub errors: Option<Vec<String>>,
+    pub known_divergence: Option<String>,
+}
+
+fn invalid(name: &str, msg: &str) -> io::Error {
+    let msg = format!("fixture {}: {}", name, msg);
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+impl Fixture {
+    pub fn from_json(name: &str, json: &str) -> io::Result<Self> {
+        let value: Value = serde_json::from_str(json).map_err(|e| invalid(name, &e.to_string()))?;
+        let template = match value.get("template") {
+            Some(Value::String(s)) => s.clone(),
+            _ => return Err(invalid(name, "template must be a string")),
+        };
+        let options = value.get("options");
+        let whitespace = match options.and_then(|o| o.get("whitespace")) {
+            None => None,
+            Some(v) if v == "condense" => Some(WhitespaceStrategy::Condense),
+            Some(v) if v == "preserve" => Some(WhitespaceStrategy::Preserve),
+            Some(v) => return Err(invalid(name, &format!("unknown whitespace {}", v))),
+        };
+        let comments = options
+            .and_then(|o| o.get("comments"))
+            .and_then(Value::as_bool);
+        let ignore_loc = value.get("ignoreLoc").and_then(Value::as_bool);
+        let known_divergence = match value.get("knownDivergence") {
+            None => None,
+            Some(Value::String(s)) => Some(s.clone()),
+            Some(_) => return Err(invalid(name, "knownDivergence must be a string")),
+        };
+        let errors = match value.get("errors") {
+            None => None,
+            Some(Value::Array(errs)) => {
+                let names = errs.iter().map(|e| e.as_str().map(String::from));
+                let names = names.collect::<Option<Vec<_>>>();
+                Some(names.ok_or_else(|| invalid(name, "errors must be strings"))?)
+            }
+            Some(_) => return Err(invalid(name, "errors must be an array")),
+        };
+        Ok(Self {
+            name: name.to_string(),
+            template,
+            whitespace,
+            comments,
+            ignore_loc: ignore_loc.unwrap_or(false),
+            ast: value.get("ast").cloned(),
+            errors,
+            known_divergence,
+        })
+    }
+}
+
+/// Loads every `.json` file in dir, sorted by file name.
+pub fn load_fixtures(dir: &Path) -> io::Result<Vec<Fixture>> {
+    let mut paths = vec![];
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) == Some("json") {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    paths
+        .iter()
+        .map(|path| {
+            let name = path.file_stem().unwrap().to_string_lossy();
+            Fixture::from_json(&name, &fs::read_to_string(path)?)
+        })
+        .collect()
+}
+
+/// A difference found by `compare`. None means the field is missing.
+pub struct Mismatch {
+    /// e.g. `children[0].props[1].name`
+    pub path: String,
+    pub expected: Option<Value>,
+    pub actual: Option<Value>,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let show = |v: &Option<Value>| match v {
+            Some(v) => v.to_string(),
+            None => "<missing>".into(),
+        };
+        write!(
+            f,
+            "{}: expected {}, found {}",
+            self.path,
+            show(&self.expected),
+            show(&self.actual)
+        )
+    }
+}
+
+/// Structurally compares two normalized ASTs. Arrays of different lengths
+/// report the length and still compare their common prefix.
+pub fn compare(expected: &Value, actual: &Value) -> Vec<Mismatch> {
+    let mut mismatches = vec![];
+    compare_at(String::new(), expected, actual, &mut mismatches);
+    mismatches
+}
+
+fn compare_at(path: String, expected: &Value, actual: &Value, out: &mut Vec<Mismatch>) {
+    let join = |key: &str| {
+        if path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{}", path, key)
+        }
+    };
+    match (expected, actual) {
+        (Value::Object(e), Value::Object(a)) => {
+            for (key, ev) in e {
+                match a.get(key) {
+                    Some(av) => compare_at(join(key), ev, av, out),
+                    None => out.push(Mismatch {
+                        path: join(key),
+                        expected: Some(ev.clone()),
+                        actual: None,
+                    }),
+                }
+            }
+            let extra = a.iter().filter(|(k, _)| !e.contains_key(*k));
+            out.extend(extra.map(|(key, av)| Mismatch {
+                path: join(key),
+                expected: None,
+                actual: Some(av.clone()),
+            }));
+        }
+        (Value::Array(e), Value::Array(a)) => {
+            if e.len() != a.len() {
+                out.push(Mismatch {
+                    path: format!("{}.length", path),
+                    expected: Some(e.len().into()),
+                    actual: Some(a.len().into()),
+                });
+            }
+            for (i, (ev, av)) in e.iter().zip(a.iter()).enumerate() {
+                compare_at(format!("{}[{}]", path, i), ev, av, out);
+            }
+        }
+        (e, a) if e == a => (),
+        (e, a) => out.push(Mismatch {
+            path,
+            expected: Some(e.clone()),
+            actual: Some(a.clone()),
+        }),
+    }
+}
+
+/// Normalizes a root exported by `JSON.stringify(baseParse(..))`.
+pub fn normalize_js(root: &Value, ignore_loc: bool) -> Value {
+    JsNormalizer { ignore_loc }.node(root)
+}
+
+struct JsNormalizer {
+    ignore_loc: bool,
+}
+
+impl JsNormalizer {
+    fn node(&self, v: &Value) -> Value {
+        let mut out = Map::new();
+        let ty = v["type"].as_u64().unwrap_or(u64::MAX);
+        let name = match ty {
+            0 => "ROOT",
+            1 => "ELEMENT",
+            2 => "TEXT",
+            3 => "COMMENT",
+            5 => "INTERPOLATION",
+            6 => "ATTRIBUTE",
+            7 => "DIRECTIVE",
+            _ => "UNKNOWN",
+        };
+        out.insert("type".into(), name.into());
+        match ty {
+            0 => {
+                out.insert("children".into(), self.nodes(&v["children"]));
+            }
+            1 => {
+                let tag_type = ["ELEMENT", "COMPONENT", "SLOT", "TEMPLATE"];
+                let ns = ["HTML", "SVG", "MATH_ML"];
+                out.insert("tag".into(), v["tag"].clone());
+                out.insert("tagType".into(), js_enum(&v["tagType"], &tag_type));
+                out.insert("ns".into(), js_enum(&v["ns"], &ns));
+                out.insert("props".into(), self.nodes(&v["props"]));
+                out.insert("children".into(), self.nodes(&v["children"]));
+            }
+            2 | 3 => {
+                out.insert("content".into(), v["content"].clone());
+            }
+            5 => {
+                let content = v["content"]["content"].as_str().unwrap_or_default();
+                out.insert("content".into(), content.trim().into());
+            }
+            6 => {
+                out.insert("name".into(), v["name"].clone());
+                out.insert("value".into(), v["value"]["content"].clone());
+            }
+            7 => {
+                let arg = match &v["arg"] {
+                    Value::Object(arg) => json!({
+                        "content": arg["content"],
+                        "isStatic": arg["isStatic"],
+                    }),
+                    _ => Value::Null,
+                };
+                // modifiers are strings before Vue 3.5, expressions after it.
+                let modifiers = v["modifiers"].as_array().into_iter().flatten();
+                let modifiers = modifiers.map(|m| match m {
+                    Value::Object(m) => m["content"].clone(),
+                    m => m.clone(),
+                });
+                out.insert("name".into(), v["name"].clone());
+                out.insert("arg".into(), arg);
+                out.insert("exp".into(), v["exp"]["content"].clone());
+                out.insert("modifiers".into(), modifiers.collect());
+            }
+            _ => (),
+        }
+        if !self.ignore_loc && ty != 0 {
+            let pos = |p: &Value| json!({"offset": p["offset"], "line": p["line"], "column": p["column"]});
+            let loc = &v["loc"];
+            let loc = json!({"start": pos(&loc["start"]), "end": pos(&loc["end"])});
+            out.insert("loc".into(), loc);
+        }
+        Value::Object(out)
+    }
+    fn nodes(&self, v: &Value) -> Value {
+        let nodes = v.as_array().into_iter().flatten();
+        nodes.map(|n| self.node(n)).collect()
+    }
+}
+
+fn js_enum(v: &Value, names: &[&str]) -> Value {
+    let name = v.as_u64().and_then(|i| names.get(i as usize));
+    name.map_or(Value::Null, |&n| n.into())
+}
+
+/// Normalizes the serde output of AstRoot into the shape of `normalize_js`.
+pub fn normalize_rust(root: &AstRoot, ignore_loc: bool) -> Value {
+    let root = serde_json::to_value(root).expect("AstRoot must serialize");
+    let normalizer = RustNormalizer { ignore_loc };
+    json!({
+        "type": "ROOT",
+        "children": normalizer.nodes(&root["children"]),
+    })
+}
+
+struct RustNormalizer {
+    ignore_loc: bool,
+}
+
+impl RustNormalizer {
+    fn nodes(&self, v: &Value) -> Value {
+        let nodes = v.as_array().into_iter().flatten();
+        nodes.map(|n| self.node(n)).collect()
+    }
+    fn props(&self, v: &Value) -> Value {
+        let props = v.as_array().into_iter().flatten();
+        // JS parser drops v-pre itself from the element props.
+        let props = props.filter(|p| p["Dir"]["name"] != "pre");
+        props.map(|p| self.node(p)).collect()
+    }
+    fn node(&self, v: &Value) -> Value {
+        let (kind, v) = match v.as_object().and_then(|o| o.iter().next()) {
+            Some((kind, v)) => (kind.as_str(), v),
+            None => return json!({ "type": "UNKNOWN" }),
+        };
+        let mut out = Map::new();
+        match kind {
+            "Element" => {
+                let tag_type = match v["tag_type"].as_str() {
+                    Some("Plain") => "ELEMENT",
+                    Some("Component") => "COMPONENT",
+                    Some("SlotOutlet") => "SLOT",
+                    Some("Template") => "TEMPLATE",
+                    _ => "UNKNOWN",
+                };
+                let ns = match v["namespace"].as_str() {
+                    Some("Html") => "HTML",
+                    Some("Svg") => "SVG",
+                    Some("MathMl") => "MATH_ML",
+                    _ => "UNKNOWN",
+                };
+                out.insert("type".into(), "ELEMENT".into());
+                out.insert("tag".into(), v["tag_name"].clone());
+                out.insert("tagType".into(), tag_type.into());
+                out.insert("ns".into(), ns.into());
+                out.insert("props".into(), self.props(&v["properties"]));
+                out.insert("children".into(), self.nodes(&v["children"]));
+            }
+            "Text" => {
+                out.insert("type".into(), "TEXT".into());
+                out.insert("content".into(), v["text"].clone());
+            }
+            "Comment" => {
+                out.insert("type".into(), "COMMENT".into());
+                out.insert("content".into(), v["source"].clone());
+            }
+            "Interpolation" => {
+                let content = v["source"].as_str().unwrap_or_default();
+                out.insert("type".into(), "INTERPOLATION".into());
+                out.insert("content".into(), content.trim().into());
+            }
+            "Attr" => {
+                out.insert("type".into(), "ATTRIBUTE".into());
+                out.insert("name".into(), v["name"].clone());
+                out.insert("value".into(), v["value"]["content"].clone());
+            }
+            "Dir" => {
+                let arg = match &v["argument"] {
+                    Value::Object(arg) => match arg.iter().next() {
+                        Some((kind, content)) => json!({
+                            "content": content,
+                            "isStatic": kind == "Static",
+                        }),
+                        None => Value::Null,
+                    },
+                    _ => Value::Null,
+                };
+                out.insert("type".into(), "DIRECTIVE".into());
+                out.insert("name".into(), v["name"].clone());
+                out.insert("arg".into(), arg);
+                out.insert("exp".into(), v["expression"]["content"].clone());
+                out.insert("modifiers".into(), v["modifiers"].clone());
+            }
+            _ => {
+                out.insert("type".into(), kind.to_uppercase().into());
+            }
+        }
+        if !self.ignore_loc {
+            let loc = &v["location"];
+            let loc = json!({"start": position(&loc["start"]), "end": position(&loc["end"])});
+            out.insert("loc".into(), loc);
+        }
+        Value::Object(out)
+    }
+}
+
+// Position is serialized as "Pos: 0, Ln: 1, Col: 1".
+fn position(v: &Value) -> Value {
+    let mut nums = v.as_str().unwrap_or_default().split(", ").map(|part| {
+        let num = part.split(": ").nth(1).and_then(|n| n.parse::<u64>().ok());
+        num.map_or(Value::Null, Value::from)
+    });
+    let mut next = || nums.next().unwrap_or(Value::Null);
+    json!({"offset": next(), "line": next(), "column": next()})
+}
+
+/// Name of compiler-core ErrorCodes for parse errors. None if the error
+/// is Rust only, e.g. opt-in warnings.
+pub fn js_error_name(kind: &ErrorKind) -> Option<&'static str> {
+    use ErrorKind::*;
+    let name = match kind {
+        AbruptClosingOfEmptyComment => "ABRUPT_CLOSING_OF_EMPTY_COMMENT",
+        CDataInHtmlContent => "CDATA_IN_HTML_CONTENT",
+        DuplicateAttribute => "DUPLICATE_ATTRIBUTE",
+        EndTagWithAttributes => "END_TAG_WITH_ATTRIBUTES",
+        EndTagWithTrailingSolidus => "END_TAG_WITH_TRAILING_SOLIDUS",
+        EofBeforeTagName => "EOF_BEFORE_TAG_NAME",
+        EofInCdata => "EOF_IN_CDATA",
+        EofInComment => "EOF_IN_COMMENT",
+        EofInScriptHtmlCommentLikeText => "EOF_IN_SCRIPT_HTML_COMMENT_LIKE_TEXT",
+        EofInTag => "EOF_IN_TAG",
+        IncorrectlyClosedComment => "INCORRECTLY_CLOSED_COMMENT",
+        IncorrectlyOpenedComment => "INCORRECTLY_OPENED_COMMENT",
+        InvalidFirstCharacterOfTagName => "INVALID_FIRST_CHARACTER_OF_TAG_NAME",
+        MissingAttributeValue => "MISSING_ATTRIBUTE_VALUE",
+        MissingEndTagName => "MISSING_END_TAG_NAME",
+        MissingWhitespaceBetweenAttributes => "MISSING_WHITESPACE_BETWEEN_ATTRIBUTES",
+        NestedComment => "NESTED_COMMENT",
+        UnexpectedEqualsSignBeforeAttributeName => "UNEXPECTED_EQUALS_SIGN_BEFORE_ATTRIBUTE_NAME",
+        UnexpectedCharacterInAttributeName => "UNEXPECTED_CHARACTER_IN_ATTRIBUTE_NAME",
+        UnexpectedCharacterInUnquotedAttributeValue => {
+            "UNEXPECTED_CHARACTER_IN_UNQUOTED_ATTRIBUTE_VALUE"
+        }
+        UnexpectedNullCharacter => "UNEXPECTED_NULL_CHARACTER",
+        UnexpectedQuestionMarkInsteadOfTagName => "UNEXPECTED_QUESTION_MARK_INSTEAD_OF_TAG_NAME",
+        UnexpectedSolidusInTag => "UNEXPECTED_SOLIDUS_IN_TAG",
+        InvalidEndTag => "X_INVALID_END_TAG",
+        MissingEndTag => "X_MISSING_END_TAG",
+        MissingInterpolationEnd => "X_MISSING_INTERPOLATION_END",
+        MissingDynamicDirectiveArgumentEnd => "X_MISSING_DYNAMIC_DIRECTIVE_ARGUMENT_END",
+        _ => return None,
+    };
+    Some(name)
+}
+
+/// Parses the fixture with option, overridden by the fixture options, and
+/// compares the result with fixture expectations. Scanner uses the text mode
+/// of option so platform crates can pass their own presets.
+pub fn run_fixture(fixture: &Fixture, option: ParseOption) -> Result<(), Vec<Mismatch>> {
+    let mut option = option;
+    if let Some(whitespace) = fixture.whitespace.clone() {
+        option.whitespace = whitespace;
+    }
+    if fixture.comments == Some(false) {
+        option.comment_filter = no.into();
+    }
+    let scanner = Scanner::new(ScanOption {
+        get_text_mode: option.get_text_mode,
+        ..Default::default()
+    });
+    let eh = Rc::new(VecErrorHandler::new());
+    let tokens = scanner.scan(&fixture.template, eh.clone());
+    let root = Parser::new(option).parse(tokens, eh.clone());
+    let mut mismatches = vec![];
+    if let Some(ast) = &fixture.ast {
+        let expected = normalize_js(ast, fixture.ignore_loc);
+        let actual = normalize_rust(&root, fixture.ignore_loc);
+        mismatches.extend(compare(&expected, &actual));
+    }
+    if let Some(expected) = &fixture.errors {
+        let errors = eh.errors();
+        let mut actual: Vec<_> = errors
+            .iter()
+            .filter_map(|e| js_error_name(&e.kind))
+            .collect();
+        let mut expected: Vec<_> = expected.iter().map(String::as_str).collect();
+        actual.sort_unstable();
+        expected.sort_unstable();
+        if actual != expected {
+            mismatches.push(Mismatch {
+                path: "errors".into(),
+                expected: Some(expected.into()),
+                actual: Some(actual.into()),
+            });
+        }
+    }
+    if mismatches.is_empty() {
+        Ok(())
+    } else {
+        Err(mismatches)
+    }
+}
@@ -136,7 +136,7 @@ pub struct Scope<'a> {
 // we can optimize it by tracking how many IDs are introduced and skip unnecessary call
 // in practice it isn't a problem because stack overflow happens way faster :/
 impl<'a> Scope<'a> {
-    pub fn has_identifier(&self, id: Name<'a>) -> bool {
+    pub fn has_identifier(&self, id: &str) -> bool {
         self.identifiers.contains_key(id)
     }
     pub fn add_identifier(&mut self, id: Name<'a>) {
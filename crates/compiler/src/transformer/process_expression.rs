@@ -7,9 +7,19 @@ use crate::converter::v_on::get_handler_type;
 use crate::error::{CompilationError, CompilationErrorKind as ErrorKind, RcErrHandle};
 use crate::flags::{RuntimeHelper as RH, StaticLevel};
 use crate::ir::JsExpr as Js;
-use crate::util::{is_global_allow_listed, is_simple_identifier, rslint, VStr};
+use crate::util::{is_global_allow_listed, is_simple_identifier, rslint, StrOps, VStr};
 use crate::{cast, BindingTypes, SFCInfo, SourceLocation};
 use crate::error::NoopErrorHandler;
+use std::borrow::Cow;
+
+/// Name of identifier after string ops, e.g. camelized same-name v-bind shorthand.
+fn identifier_name<'a>(v: &VStr<'a>) -> Cow<'a, str> {
+    if v.ops.contains(StrOps::CAMEL_CASE) {
+        Cow::Owned(v.into_string())
+    } else {
+        Cow::Borrowed(v.raw)
+    }
+}
 
 pub struct ExpressionProcessor<'a, 'b> {
     pub prefix_identifier: bool,
@@ -143,7 +153,8 @@ impl<'a, 'b> ExpressionProcessor<'a, 'b> {
         if !is_simple_identifier(*v) {
             return false;
         }
-        let raw_exp = v.raw;
+        let name = identifier_name(v);
+        let raw_exp = &*name;
         let is_scope_reference = scope.has_identifier(raw_exp);
         let is_allowed_global = is_global_allow_listed(raw_exp);
         let is_literal = matches!(raw_exp, "true" | "false" | "null" | "this");
@@ -204,7 +215,7 @@ impl<'a, 'b> ExpressionProcessor<'a, 'b> {
         });
     }
     fn rewrite_identifier(&self, raw: VStr<'a>, level: StaticLevel, ctx: CtxType<'a>) -> Js<'a> {
-        let binding = self.sfc_info.binding_metadata.get(&raw.raw);
+        let binding = self.sfc_info.binding_metadata.get(&*identifier_name(&raw));
         if let Some(bind) = binding {
             if self.sfc_info.inline {
                 rewrite_inline_identifier(raw, level, bind, ctx)
@@ -462,6 +473,15 @@ mod test {
         assert_eq!(expr.into_string(), "_ctx.a");
     }
     #[test]
+    fn test_same_name_shorthand_prefix() {
+        let ir = transform("<p :user-name/>");
+        let vn = cast!(first_child(ir), IRNode::VNodeCall);
+        let props = vn.props.unwrap();
+        let props = cast!(props, Js::Props);
+        let expr = cast!(&props[0].1, Js::Simple);
+        assert_eq!(expr.into_string(), "_ctx.userName");
+    }
+    #[test]
     fn test_v_bind_prefix() {
         let ir = transform("<p v-bind='b'/>");
         let vn = cast!(&ir.body[0], IRNode::VNodeCall);
@@ -2,7 +2,7 @@ use super::{
     converter::BaseConvertInfo,
     flags::RuntimeHelper,
     ir::{JsExpr as Js, VNodeIR},
-    parser::{Directive, DirectiveArg, ElemProp, Element},
+    parser::{AstAlloc, Directive, DirectiveArg, ElemProp, Element, Heap},
     scanner::Attribute,
 };
 use std::{
@@ -24,11 +24,15 @@ macro_rules! cast {
 }
 
 mod decode_html;
+mod handler;
 mod json;
 mod named_chars;
 pub mod rslint;
 mod v_str;
-pub use v_str::VStr;
+pub(crate) use decode_html::{char_ref_errors, CharRefError};
+pub use handler::classify_handler;
+pub(crate) use handler::is_fn_exp;
+pub use v_str::{StrOps, VStr};
 
 pub fn non_whitespace(c: char) -> bool {
     !c.is_ascii_whitespace()
@@ -52,7 +56,7 @@ pub fn is_core_component(tag: &str) -> bool {
 fn is_event_prop(prop: &str) -> bool {
     let bytes = prop.as_bytes();
     // equivalent to /^on[^a-z]/
-    bytes.len() > 2 && bytes.starts_with(b"on") && !bytes[3].is_ascii_lowercase()
+    bytes.len() > 2 && bytes.starts_with(b"on") && !bytes[2].is_ascii_lowercase()
 }
 
 pub fn is_mergeable_prop(prop: &str) -> bool {
@@ -70,7 +74,15 @@ pub fn is_simple_identifier(s: VStr) -> bool {
     }
     let is_ident = |c| !not_js_identifier(c);
     let raw = s.raw;
-    raw.chars().all(is_ident) && !raw.starts_with(|c: char| c.is_ascii_digit())
+    if raw.starts_with(|c: char| c.is_ascii_digit()) {
+        return false;
+    }
+    // camelized kebab-case, e.g. same-name shorthand `:user-name`
+    if s.ops.contains(StrOps::CAMEL_CASE) {
+        let is_kebab = |c| c == '-' || is_ident(c);
+        return raw.chars().all(is_kebab) && !raw.ends_with('-') && !raw.contains("--");
+    }
+    raw.chars().all(is_ident)
 }
 
 macro_rules! make_list {
@@ -114,6 +126,17 @@ pub fn is_global_allow_listed(s: &str) -> bool {
     ALLOWED_GLOBALS.contains(&s)
 }
 
+// reserved words cannot be a bare identifier expression
+const JS_RESERVED_WORDS: &[&str] = make_list![
+    await, break, case, catch, class, const, continue, debugger, default, delete, do, else, enum,
+    export, extends, false, finally, for, function, if, implements, import, in, instanceof,
+    interface, let, new, null, package, private, protected, public, return, static, super, switch,
+    this, throw, true, try, typeof, var, void, while, with, yield
+];
+pub fn is_js_reserved_word(s: &str) -> bool {
+    JS_RESERVED_WORDS.contains(&s)
+}
+
 // https://github.com/vuejs/rfcs/blob/master/active-rfcs/0008-render-function-api-change.md#special-reserved-props
 const RESERVED: &[&str] = make_list![
     key,
@@ -131,6 +154,122 @@ pub fn is_reserved_prop(tag: &str) -> bool {
     RESERVED.contains(&tag)
 }
 
+// identifiers emitted by codegen, prefixed by `_`
+const GENERATED_NAMES: &[&str] = make_list![_ctx, _cache];
+const GENERATED_PREFIXES: &[&str] = make_list![_hoisted_, _component_, _directive_];
+
+/// Returns true if the name is `_` prefixed helper alias in generated code.
+/// e.g. `_ctx`, `_toDisplayString`, `_hoisted_1`.
+pub fn is_generated_alias(name: &str) -> bool {
+    let helper = match name.strip_prefix('_') {
+        Some(h) => h,
+        None => return false,
+    };
+    GENERATED_NAMES.contains(&name)
+        || GENERATED_PREFIXES.iter().any(|p| name.starts_with(p))
+        || RuntimeHelper::core_helpers().any(|rh| rh.helper_str(&[]) == helper)
+}
+
+/// Default check for binding names introduced by v-for/v-slot that
+/// may shadow runtime's `$` properties or compiler generated code.
+pub fn is_reserved_binding(name: &str) -> bool {
+    name.starts_with('$') || is_generated_alias(name)
+}
+
+/// Returns the truthiness of a JS literal source, if it is a literal.
+pub fn literal_truthiness(lit: &str) -> Option<bool> {
+    let lit = lit.trim();
+    match lit {
+        "true" => return Some(true),
+        "false" | "null" | "undefined" | "NaN" => return Some(false),
+        _ => (),
+    }
+    if let Some(truthy) = numeric_truthiness(lit) {
+        return Some(truthy);
+    }
+    string_truthiness(lit)
+}
+
+/// A string literal must not close before its last quote, e.g. `'a' + 'b'`.
+/// Template literals with substitutions are not constant.
+fn string_truthiness(lit: &str) -> Option<bool> {
+    let quote = lit
+        .chars()
+        .next()
+        .filter(|c| matches!(c, '\'' | '"' | '`'))?;
+    let body = lit[1..].strip_suffix(quote)?;
+    let mut chars = body.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next()?;
+            }
+            '$' if quote == '`' && chars.as_str().starts_with('{') => return None,
+            c if c == quote => return None,
+            _ => (),
+        }
+    }
+    Some(!body.is_empty())
+}
+
+/// Follows JS NumericLiteral grammar, optionally negated, so that
+/// spellings like `inf` or `NAN` that Rust accepts are not literals.
+fn numeric_truthiness(lit: &str) -> Option<bool> {
+    let num = lit.strip_prefix('-').unwrap_or(lit);
+    let (num, big_int) = match num.strip_suffix('n') {
+        Some(n) => (n, true),
+        None => (num, false),
+    };
+    let bytes = num.as_bytes();
+    if bytes.len() > 2 && bytes[0] == b'0' {
+        let radix = match bytes[1] {
+            b'x' | b'X' => Some(16),
+            b'o' | b'O' => Some(8),
+            b'b' | b'B' => Some(2),
+            _ => None,
+        };
+        if let Some(radix) = radix {
+            let digits = &num[2..];
+            return is_digits(digits, radix).then(|| has_nonzero(digits));
+        }
+    }
+    let (mantissa, exponent) = match num.find(['e', 'E']) {
+        Some(i) => (&num[..i], Some(&num[i + 1..])),
+        None => (num, None),
+    };
+    if let Some(exp) = exponent {
+        let exp = exp.strip_prefix(['+', '-']).unwrap_or(exp);
+        if big_int || !is_digits(exp, 10) {
+            return None;
+        }
+    }
+    let valid = match mantissa.split_once('.') {
+        Some(_) if big_int => false,
+        Some((int, frac)) => {
+            (int.is_empty() || is_digits(int, 10))
+                && (frac.is_empty() || is_digits(frac, 10))
+                && !(int.is_empty() && frac.is_empty())
+        }
+        None => is_digits(mantissa, 10),
+    };
+    valid.then(|| has_nonzero(mantissa))
+}
+
+/// Digits with `_` separators only between digits.
+fn is_digits(s: &str, radix: u32) -> bool {
+    !s.is_empty()
+        && !s.starts_with('_')
+        && !s.ends_with('_')
+        && !s.contains("__")
+        && s.chars().all(|c| c == '_' || c.is_digit(radix))
+}
+
+fn has_nonzero(digits: &str) -> bool {
+    digits
+        .chars()
+        .any(|c| c.is_ascii_alphanumeric() && c != '0')
+}
+
 pub fn is_component_tag(tag: &str) -> bool {
     tag == "component" || tag == "Component"
 }
@@ -266,19 +405,21 @@ impl<'a> PropMatcher<'a> for Directive<'a> {
     }
 }
 
-pub struct PropFound<'a, E, M>
+pub struct PropFound<'a, E, M, A = Heap>
 where
-    E: Borrow<Element<'a>>,
+    A: AstAlloc<'a>,
+    E: Borrow<Element<'a, A>>,
     M: PropMatcher<'a>,
 {
     elem: E,
     pos: usize,
-    m: PhantomData<&'a M>,
+    m: PhantomData<&'a (M, A)>,
 }
 
-impl<'a, E, M> PropFound<'a, E, M>
+impl<'a, E, M, A> PropFound<'a, E, M, A>
 where
-    E: Borrow<Element<'a>>,
+    A: AstAlloc<'a>,
+    E: Borrow<Element<'a, A>>,
     M: PropMatcher<'a>,
 {
     fn new(elem: E, pos: usize) -> Option<Self> {
@@ -293,9 +434,10 @@ where
     }
 }
 // take is only available when access is mutable
-impl<'a, E, M> PropFound<'a, E, M>
+impl<'a, E, M, A> PropFound<'a, E, M, A>
 where
-    E: BorrowMut<Element<'a>>,
+    A: AstAlloc<'a>,
+    E: BorrowMut<Element<'a, A>>,
     M: PropMatcher<'a>,
 {
     pub fn take(mut self) -> M {
@@ -304,37 +446,41 @@ where
     }
 }
 
-type DirFound<'a, E> = PropFound<'a, E, Directive<'a>>;
+type DirFound<'a, E, A> = PropFound<'a, E, Directive<'a>, A>;
 
 // sometimes mutable access to the element is not available so
 // Borrow is used to refine PropFound so `take` is optional
-pub fn dir_finder<'a, E, P>(elem: E, pat: P) -> PropFinder<'a, E, P, Directive<'a>>
+pub fn dir_finder<'a, E, P, A>(elem: E, pat: P) -> PropFinder<'a, E, P, Directive<'a>, A>
 where
-    E: Borrow<Element<'a>>,
+    A: AstAlloc<'a>,
+    E: Borrow<Element<'a, A>>,
     P: PropPattern,
 {
     PropFinder::new(elem, pat)
 }
 
-pub fn find_dir<'a, E, P>(elem: E, pat: P) -> Option<DirFound<'a, E>>
+pub fn find_dir<'a, E, P, A>(elem: E, pat: P) -> Option<DirFound<'a, E, A>>
 where
-    E: Borrow<Element<'a>>,
+    A: AstAlloc<'a>,
+    E: Borrow<Element<'a, A>>,
     P: PropPattern,
 {
     PropFinder::new(elem, pat).find()
 }
 
-pub fn find_dir_empty<'a, E, P>(elem: E, pat: P) -> Option<DirFound<'a, E>>
+pub fn find_dir_empty<'a, E, P, A>(elem: E, pat: P) -> Option<DirFound<'a, E, A>>
 where
-    E: Borrow<Element<'a>>,
+    A: AstAlloc<'a>,
+    E: Borrow<Element<'a, A>>,
     P: PropPattern,
 {
     PropFinder::new(elem, pat).allow_empty().find()
 }
 
-pub struct PropFinder<'a, E, P, M = ElemProp<'a>>
+pub struct PropFinder<'a, E, P, M = ElemProp<'a>, A = Heap>
 where
-    E: Borrow<Element<'a>>,
+    A: AstAlloc<'a>,
+    E: Borrow<Element<'a, A>>,
     P: PropPattern,
     M: PropMatcher<'a>,
 {
@@ -342,12 +488,13 @@ where
     pat: P,
     allow_empty: bool,
     filter: fn(&ElemProp<'a>) -> bool,
-    m: PhantomData<&'a M>,
+    m: PhantomData<&'a (M, A)>,
 }
 
-impl<'a, E, P, M> PropFinder<'a, E, P, M>
+impl<'a, E, P, M, A> PropFinder<'a, E, P, M, A>
 where
-    E: Borrow<Element<'a>>,
+    A: AstAlloc<'a>,
+    E: Borrow<Element<'a, A>>,
     P: PropPattern,
     M: PropMatcher<'a>,
 {
@@ -377,7 +524,7 @@ where
             ..self
         }
     }
-    pub fn find(self) -> Option<PropFound<'a, E, M>> {
+    pub fn find(self) -> Option<PropFound<'a, E, M, A>> {
         let pos = self
             .elem
             .borrow()
@@ -416,17 +563,19 @@ where
     }
 }
 
-pub fn find_prop<'a, E, P>(elem: E, pat: P) -> Option<PropFound<'a, E, ElemProp<'a>>>
+pub fn find_prop<'a, E, P, A>(elem: E, pat: P) -> Option<PropFound<'a, E, ElemProp<'a>, A>>
 where
-    E: Borrow<Element<'a>>,
+    A: AstAlloc<'a>,
+    E: Borrow<Element<'a, A>>,
     P: PropPattern,
 {
     PropFinder::new(elem, pat).find()
 }
 
-pub fn prop_finder<'a, E, P>(elem: E, pat: P) -> PropFinder<'a, E, P>
+pub fn prop_finder<'a, E, P, A>(elem: E, pat: P) -> PropFinder<'a, E, P, ElemProp<'a>, A>
 where
-    E: Borrow<Element<'a>>,
+    A: AstAlloc<'a>,
+    E: Borrow<Element<'a, A>>,
     P: PropPattern,
 {
     PropFinder::new(elem, pat)
@@ -552,4 +701,32 @@ mod test {
         assert_eq!(*l, 5050);
         assert_eq!(test, 1);
     }
+
+    #[test]
+    fn test_literal_truthiness() {
+        for lit in [
+            "inf", "Infinity", "-inf", "nan", "NAN", "1__0", "1_", "0x", "1.5n", "1e",
+        ] {
+            assert_eq!(literal_truthiness(lit), None, "{}", lit);
+        }
+        for lit in [
+            "'a' === 'b'",
+            "'a' + 'b'",
+            "'' || ''",
+            "'a\\'",
+            "`${a}`",
+            "'",
+            "\"a'",
+        ] {
+            assert_eq!(literal_truthiness(lit), None, "{}", lit);
+        }
+        for lit in [
+            "1", "1e3", ".5", "1.", "1_000", "0x1F", "0b10", "-1", "2n", "'a'", r"'\''", "`$a`",
+        ] {
+            assert_eq!(literal_truthiness(lit), Some(true), "{}", lit);
+        }
+        for lit in ["0", "0x0", "0.0e5", "-0", "0n", "''", "``", "NaN", "null"] {
+            assert_eq!(literal_truthiness(lit), Some(false), "{}", lit);
+        }
+    }
 }
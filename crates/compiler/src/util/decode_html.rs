@@ -1,5 +1,6 @@
 // sadly current html decode crate requires std::io::Write not fmt
 use std::fmt::{self, Write};
+use std::ops::Range;
 use super::named_chars::NAMED_CHAR_REF;
 use lazy_static::lazy_static;
 
@@ -7,30 +8,74 @@ lazy_static! {
     static ref MAX_CR_NAME_LEN: usize = NAMED_CHAR_REF.keys().copied().map(str::len).max().unwrap();
 }
 
+/// Parse errors of character references, named after the WHATWG spec.
+/// Malformed references are kept verbatim or replaced with U+FFFD.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CharRefError {
+    UnknownNamed,
+    MissingSemicolon,
+    AbsenceOfDigits,
+    NullCharacter,
+    OutsideUnicodeRange,
+    Surrogate,
+}
+
 type DecodeResult<'a> = Result<&'a str, fmt::Error>;
-pub fn decode_entities<W: Write>(s: &str, mut w: W, as_attr: bool) -> fmt::Result {
+type Report<'r> = dyn FnMut(usize, CharRefError) + 'r;
+
+pub fn decode_entities<W: Write>(s: &str, w: W, as_attr: bool) -> fmt::Result {
+    decode_with(s, w, as_attr, &mut |_, _| ())
+}
+
+/// Errors found by decode_entities in source order, with byte ranges in s.
+pub fn char_ref_errors(s: &str, as_attr: bool) -> Vec<(Range<usize>, CharRefError)> {
+    let mut errors = vec![];
+    let mut on_error = |range, err| errors.push((range, err));
+    decode_with(s, Discard, as_attr, &mut on_error).expect("Discard never fails");
+    errors
+}
+
+struct Discard;
+impl Write for Discard {
+    fn write_str(&mut self, _: &str) -> fmt::Result {
+        Ok(())
+    }
+}
+
+fn decode_with<W, F>(s: &str, mut w: W, as_attr: bool, on_error: &mut F) -> fmt::Result
+where
+    W: Write,
+    F: FnMut(Range<usize>, CharRefError),
+{
     let mut src = s;
     while let Some(idx) = src.find('&') {
         let (decoded, next) = src.split_at(idx);
         w.write_str(decoded)?;
-        src = next;
-        if src.starts_with("&#") {
-            src = decode_numeric_ref(src, &mut w)?;
+        let start = s.len() - next.len();
+        // reports error of the reference with its length
+        let mut report = |len: usize, err| on_error(start..start + len, err);
+        src = if next.starts_with("&#") {
+            decode_numeric_ref(next, &mut w, &mut report)?
         } else {
-            src = decode_named_ref(src, &mut w, as_attr)?;
-        }
+            decode_named_ref(next, &mut w, as_attr, &mut report)?
+        };
     }
     w.write_str(src)
 }
 
-fn decode_named_ref<W: Write>(s: &str, mut w: W, as_attr: bool) -> DecodeResult {
+// https://html.spec.whatwg.org/multipage/parsing.html#named-character-reference-state
+fn decode_named_ref<'a, W: Write>(
+    s: &'a str,
+    mut w: W,
+    as_attr: bool,
+    report: &mut Report,
+) -> DecodeResult<'a> {
     debug_assert!(s.starts_with('&'));
-    let mut src = &s[1..];
-    if !src.starts_with(|c: char| c.is_ascii_alphanumeric()) {
-        w.write_char('&')?;
-        return Ok(src);
-    }
-    let max_len = MAX_CR_NAME_LEN.min(src.len());
+    let src = &s[1..];
+    // names are ASCII alphanumerics with optional trailing semicolon
+    let name_len = src.bytes().take_while(u8::is_ascii_alphanumeric).count();
+    let has_semi = src[name_len..].starts_with(';');
+    let max_len = MAX_CR_NAME_LEN.min(name_len + has_semi as usize);
     let entry = (2..=max_len)
         .rev()
         .map(|i| &src[..i])
@@ -38,66 +83,73 @@ fn decode_named_ref<W: Write>(s: &str, mut w: W, as_attr: bool) -> DecodeResult
     let (key, val) = match entry {
         Some(entry) => entry,
         None => {
+            // https://html.spec.whatwg.org/multipage/parsing.html#ambiguous-ampersand-state
+            if name_len > 0 && has_semi {
+                report(name_len + 2, CharRefError::UnknownNamed);
+            }
             w.write_char('&')?;
             return Ok(src);
         }
     };
-    let semi = key.ends_with(';');
-    src = &src[key.len()..];
-    if as_attr && !semi && src.starts_with(|c: char| c == '=' || c.is_ascii_alphanumeric()) {
+    let rest = &src[key.len()..];
+    if key.ends_with(';') {
+        w.write_str(val)?;
+        return Ok(rest);
+    }
+    // legacy reference like &not in &notit; is kept in attribute value
+    if as_attr && rest.starts_with(|c: char| c == '=' || c.is_ascii_alphanumeric()) {
         w.write_char('&')?;
         w.write_str(key)?;
-        Ok(src)
-    } else {
-        w.write_str(val)?;
-        Ok(src)
+        return Ok(rest);
     }
+    report(key.len() + 1, CharRefError::MissingSemicolon);
+    w.write_str(val)?;
+    Ok(rest)
 }
-fn decode_numeric_ref<W: Write>(s: &str, mut w: W) -> DecodeResult {
+
+// https://html.spec.whatwg.org/multipage/parsing.html#numeric-character-reference-state
+fn decode_numeric_ref<'a, W: Write>(s: &'a str, mut w: W, report: &mut Report) -> DecodeResult<'a> {
     debug_assert!(s.starts_with("&#"));
-    let (num, next) = if let Some(src) = s.strip_prefix("&#x") {
-        // hex
-        let cnt = src.chars().take_while(|c| c.is_ascii_hexdigit()).count();
-        match u32::from_str_radix(&src[..cnt], 16) {
-            Ok(n) => {
-                if src[cnt..].starts_with(';') {
-                    (n, &src[cnt + 1..])
-                } else {
-                    (n, &src[cnt..])
-                }
-            }
-            Err(_) => return Ok(src),
+    let hex = s[2..].starts_with(&['x', 'X'][..]);
+    let (prefix_len, radix) = if hex { (3, 16) } else { (2, 10) };
+    let src = &s[prefix_len..];
+    let digits = src
+        .bytes()
+        .take_while(|b| (*b as char).is_digit(radix))
+        .count();
+    if digits == 0 {
+        report(prefix_len, CharRefError::AbsenceOfDigits);
+        w.write_str(&s[..prefix_len])?;
+        return Ok(src);
+    }
+    // saturates at u32::MAX, which is out of range anyway
+    let num = src[..digits].chars().fold(0u32, |n, c| {
+        let d = c.to_digit(radix).unwrap();
+        n.saturating_mul(radix).saturating_add(d)
+    });
+    let has_semi = src[digits..].starts_with(';');
+    let len = prefix_len + digits + has_semi as usize;
+    if !has_semi {
+        report(len, CharRefError::MissingSemicolon);
+    }
+    let num = match num {
+        0 => {
+            report(len, CharRefError::NullCharacter);
+            0xfffd
         }
-    } else {
-        // num
-        let src = &s[2..];
-        let cnt = src.chars().take_while(|c| c.is_numeric()).count();
-        match src[..cnt].parse() {
-            Ok(n) => {
-                if src[cnt..].starts_with(';') {
-                    (n, &src[cnt + 1..])
-                } else {
-                    (n, &src[cnt..])
-                }
-            }
-            Err(_) => return Ok(src),
+        n if n > 0x10ffff => {
+            report(len, CharRefError::OutsideUnicodeRange);
+            0xfffd
+        }
+        0xd800..=0xdfff => {
+            report(len, CharRefError::Surrogate);
+            0xfffd
         }
-    };
-    let num = match num {
-        0 => 0xfffd,
-        n if n > 0x10ffff => 0xfffd,
-        0xd800..=0xdfff => 0xfffd,
-        0xfdd0..=0xfdef => num,           // noop
-        n if (n & 0xfffe) == 0xfffe => n, // noop
         0x80..=0x9f => CCR_REPLACEMENTS[num as usize - 0x80],
-        num => num,
+        n => n,
     };
-    if let Some(c) = char::from_u32(num) {
-        w.write_char(c)?;
-        Ok(next)
-    } else {
-        Ok(next)
-    }
+    w.write_char(char::from_u32(num).expect("surrogates must be replaced"))?;
+    Ok(&s[len..])
 }
 
 // https://html.spec.whatwg.org/multipage/parsing.html#numeric-character-reference-end-state
@@ -155,4 +207,84 @@ mod test {
             assert_eq!(&actual, expected);
         }
     }
+    #[test]
+    fn test_named_ref_context() {
+        let decode = |s: &str, as_attr| {
+            let mut actual = String::new();
+            decode_entities(s, &mut actual, as_attr).unwrap();
+            actual
+        };
+        let both = [
+            ("&nbsp;&hellip;&copy;&rarr;", "\u{a0}…©→"),
+            ("&notin;", "∉"),
+            ("&not;in", "¬in"),
+            ("&foo; &amp", "&foo; &"),
+            ("&Aacute &zz", "Á &zz"),
+        ];
+        for &(input, expected) in both.iter() {
+            assert_eq!(decode(input, false), expected);
+            assert_eq!(decode(input, true), expected);
+        }
+        // only a legacy prefix matches, e.g. &not in &notit;
+        assert_eq!(decode("&notit;", false), "¬it;");
+        assert_eq!(decode("&notit;", true), "&notit;");
+        assert_eq!(decode("&not=1", false), "¬=1");
+        assert_eq!(decode("&not=1", true), "&not=1");
+        assert_eq!(decode("&not.", true), "¬.");
+    }
+    #[test]
+    fn test_char_ref_errors() {
+        use CharRefError::*;
+        let nul = '\u{fffd}';
+        // examples from the WHATWG spec, decoded in text
+        type Case = (
+            &'static str,
+            String,
+            &'static [(Range<usize>, CharRefError)],
+        );
+        let cases: &[Case] = &[
+            ("&#60;", "<".into(), &[]),
+            ("&#x1F600;", "😀".into(), &[]),
+            ("&#X3c;", "<".into(), &[]),
+            ("&#38", "&".into(), &[(0..4, MissingSemicolon)]),
+            ("&#;", "&#;".into(), &[(0..2, AbsenceOfDigits)]),
+            ("&#x;", "&#x;".into(), &[(0..3, AbsenceOfDigits)]),
+            ("&#xZZ;", "&#xZZ;".into(), &[(0..3, AbsenceOfDigits)]),
+            ("&#0;", nul.into(), &[(0..4, NullCharacter)]),
+            ("&#x110000;", nul.into(), &[(0..10, OutsideUnicodeRange)]),
+            (
+                "&#99999999999;",
+                nul.into(),
+                &[(0..14, OutsideUnicodeRange)],
+            ),
+            ("&#xD800;", nul.into(), &[(0..8, Surrogate)]),
+            ("&#x80;&#x81;", "€\u{81}".into(), &[]),
+            (
+                "a&#x0",
+                format!("a{}", nul),
+                &[(1..5, MissingSemicolon), (1..5, NullCharacter)],
+            ),
+            (
+                "I'm &notit; I tell you",
+                "I'm ¬it; I tell you".into(),
+                &[(4..8, MissingSemicolon)],
+            ),
+            ("I'm &notin; I tell you", "I'm ∉ I tell you".into(), &[]),
+            (
+                "&foo; &amp",
+                "&foo; &".into(),
+                &[(0..5, UnknownNamed), (6..10, MissingSemicolon)],
+            ),
+            ("&€;&a€", "&€;&a€".into(), &[]),
+        ];
+        for (input, expected, errors) in cases {
+            let mut actual = String::new();
+            decode_entities(input, &mut actual, false).unwrap();
+            assert_eq!(&actual, expected, "{}", input);
+            assert_eq!(char_ref_errors(input, false), *errors, "{}", input);
+        }
+        // kept without error in attribute value
+        assert!(char_ref_errors("&notit;", true).is_empty());
+        assert_eq!(char_ref_errors("&not.", true), [(0..4, MissingSemicolon)]);
+    }
 }
@@ -0,0 +1,218 @@
+// Classifies v-on expression by scanning it, without parsing it as JavaScript.
+use super::not_js_identifier;
+use crate::ir::HandlerType;
+
+/// Returns how a v-on handler is written. No allocation is needed so tools can
+/// call it on every directive. Expression is trimmed before classification.
+/// NB: converter checks member expression by a full JS parse, so the two may
+/// disagree on invalid JS like `a.` or `a[`.
+pub fn classify_handler(expr: &str) -> HandlerType {
+    let expr = expr.trim();
+    if is_member_path(expr) {
+        HandlerType::MemberExpr
+    } else if is_fn_exp(expr) {
+        HandlerType::FuncExpr
+    } else {
+        HandlerType::InlineStmt
+    }
+}
+
+fn is_js_identifier(c: char) -> bool {
+    !not_js_identifier(c)
+}
+
+// equivalent to this JS regexp
+// /^\s*([\w$_]+|(async\s*)?\([^)]*?\))\s*=>|^\s*(async\s+)?function(?:\s+[\w$]+)?\s*\(/
+pub(crate) fn is_fn_exp(raw: &str) -> bool {
+    // 0. strip whitespace
+    let mut raw = raw.trim_start();
+    // 1. strip potential async
+    raw = raw.trim_start_matches("async ").trim_start();
+    // 2.a  async => 123
+    if raw.starts_with("=>") {
+        return true;
+    }
+    // 2.b function keyword, e.g. `function (e) {}` or `function(e) {}`
+    if let Some(rest) = raw.strip_prefix("function") {
+        if rest.starts_with(|c: char| c.is_whitespace() || c == '(') {
+            // 3. trim function name
+            raw = rest
+                .trim_start()
+                .trim_start_matches(is_js_identifier)
+                .trim_start();
+            return raw.starts_with('(');
+        }
+    }
+    // 2.c arrow func shorthand. e.g: argName => expr
+    if raw.starts_with(is_js_identifier) {
+        // 3. strip argName
+        raw = raw.trim_start_matches(is_js_identifier).trim_start();
+        return raw.starts_with("=>");
+    }
+    // 2.d arrow func full (arg, arg, ...arg) => expr
+    if let Some(params) = raw.strip_prefix('(') {
+        // malformed params without `)`, e.g. `(a`, is not a function
+        return match params.find(')') {
+            Some(i) => params[i + 1..].trim_start().starts_with("=>"),
+            None => false,
+        };
+    }
+    false
+}
+
+// identifier followed by any `.b`, `?.b`, `[c]` or `?.[c]`
+fn is_member_path(raw: &str) -> bool {
+    let mut rest = match strip_identifier(raw) {
+        Some(rest) => rest,
+        None => return false,
+    };
+    loop {
+        rest = rest.trim_start();
+        if rest.is_empty() {
+            return true;
+        }
+        let (optional, access) = match rest.strip_prefix("?.") {
+            Some(access) => (true, access.trim_start()),
+            None => (false, rest),
+        };
+        let next = if access.starts_with('[') {
+            strip_brackets(access)
+        } else if optional {
+            strip_identifier(access)
+        } else if let Some(prop) = access.strip_prefix('.') {
+            strip_identifier(prop.trim_start())
+        } else {
+            None
+        };
+        rest = match next {
+            Some(next) => next,
+            None => return false,
+        };
+    }
+}
+
+fn strip_identifier(raw: &str) -> Option<&str> {
+    if raw.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    let rest = raw.trim_start_matches(is_js_identifier);
+    if rest.len() == raw.len() {
+        None
+    } else {
+        Some(rest)
+    }
+}
+
+// raw starts with `[`. Returns the rest after the matching `]`.
+fn strip_brackets(raw: &str) -> Option<&str> {
+    let mut depth = 0usize;
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in raw.char_indices() {
+        if let Some(q) = quote {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == q {
+                quote = None;
+            }
+            continue;
+        }
+        match c {
+            '\'' | '"' | '`' => quote = Some(c),
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return if c == ']' { Some(&raw[i + 1..]) } else { None };
+                }
+            }
+            _ => (),
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_is_fn_expr() {
+        let positive_cases = [
+            "() => 123",
+            "([a,b,c]) => 123",
+            "(arg) => 123",
+            "async => 123",
+            "async arg => 123",
+            "async (arg) => 123",
+            "function (arg) {}",
+            "function (arg) {}",
+            "async    function   (  arg)  {}",
+            "    function   (  arg)  {}",
+            "function(arg) {}",
+        ];
+        for case in positive_cases {
+            assert!(is_fn_exp(case), "{}", case);
+        }
+        let negative_cases = [
+            "a",
+            "a.b.c",
+            "call()",
+            "functional(a)",
+            "(a",
+            "(",
+            "(a => b",
+        ];
+        for case in negative_cases {
+            assert!(!is_fn_exp(case), "{}", case);
+        }
+    }
+
+    #[test]
+    fn test_classify_handler() {
+        use HandlerType::*;
+        for (expr, expected) in [
+            ("foo", MemberExpr),
+            ("  foo  ", MemberExpr),
+            ("$event", MemberExpr),
+            ("foo.bar", MemberExpr),
+            ("foo\n  .bar", MemberExpr),
+            ("foo['bar']", MemberExpr),
+            ("foo[bar].baz", MemberExpr),
+            ("foo[']'].baz", MemberExpr),
+            ("foo[bar[0]]", MemberExpr),
+            ("a?.b.c", MemberExpr),
+            ("a?.['b']", MemberExpr),
+            ("() => foo()", FuncExpr),
+            ("e => foo(e)", FuncExpr),
+            ("async () => {}", FuncExpr),
+            ("function($event) {}", FuncExpr),
+            ("function named (e) { foo(e) }", FuncExpr),
+            ("count++", InlineStmt),
+            ("foo($event)", InlineStmt),
+            ("foo.bar()", InlineStmt),
+            ("foo(); bar()", InlineStmt),
+            ("a = b", InlineStmt),
+            ("a ? b : c", InlineStmt),
+            ("a?.5:1", InlineStmt),
+            ("a[0", InlineStmt),
+            ("a(]", InlineStmt),
+            ("1a", InlineStmt),
+            ("a.", InlineStmt),
+            ("", InlineStmt),
+        ] {
+            assert_eq!(classify_handler(expr), expected, "{:?}", expr);
+        }
+    }
+
+    #[test]
+    fn test_converter_agrees_on_identifier_start() {
+        use crate::converter::v_on::get_handler_type;
+        use crate::util::VStr;
+        for expr in ["$event", "_foo", "$refs.a", "_a['b']", "_a()", "$emit('x')"] {
+            let ty = get_handler_type(VStr::raw(expr));
+            assert_eq!(ty, classify_handler(expr), "{:?}", expr);
+        }
+    }
+}
@@ -298,6 +298,14 @@ where
     })
 }
 
+pub fn is_call_expression(text: &str) -> bool {
+    let mut expr = parse_js_expr(text);
+    while let Some(Expr::GroupingExpr(e)) = expr {
+        expr = e.inner();
+    }
+    matches!(expr, Some(Expr::CallExpr(_)))
+}
+
 pub fn is_member_expression(text: &str) -> bool {
     is_member_expr_impl(parse_js_expr(text))
 }
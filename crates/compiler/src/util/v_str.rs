@@ -25,8 +25,9 @@ bitflags! {
         const VALID_DIR           = 1 << 2;
         const VALID_COMP          = 1 << 3;
         const V_DIR_PREFIX        = 1 << 4;
-        const COMPRESS_WHITESPACE = 1 << 5;
-        const DECODE_ENTITY       = 1 << 6;
+        // decode first so that whitespace like &#32; is also condensed
+        const DECODE_ENTITY       = 1 << 5;
+        const COMPRESS_WHITESPACE = 1 << 6;
         const CAMEL_CASE          = 1 << 7;
         const CAPITALIZED         = 1 << 8;
         const JS_STRING           = 1 << 9;
@@ -0,0 +1,311 @@
+/*!
+Visitor and walker over AST.
+
+Visitor methods recurse by default via the `walk_*` functions.
+Overriding `visit_element` can stop or customize recursion,
+in which case `walk_element` should be called to visit children.
+
+Ancestors of a node are the elements enclosing it, from root to parent.
+For directives, the element owning the directive is the parent.
+
+`walk_with_path` is a closure based alternative for rules that only need
+to look up, e.g. the nearest component ancestor. WalkPath keeps child indexes
+instead of references, and resolves ancestors from root on demand.
+Unlike node_path::NodePath, it borrows the tree and owns no tag names.
+*/
+
+use crate::{
+    parser::{AstNode, AstRoot, Directive, ElemProp, Element, SourceNode, TextNode},
+    Name,
+};
+use std::ops::Deref;
+
+/// Elements enclosing the visited node, root first.
+pub struct Ancestors<'b, 'a>(Vec<&'b Element<'a>>);
+
+impl<'b, 'a> Ancestors<'b, 'a> {
+    pub fn parent(&self) -> Option<&'b Element<'a>> {
+        self.0.last().copied()
+    }
+}
+
+impl<'b, 'a> Deref for Ancestors<'b, 'a> {
+    type Target = [&'b Element<'a>];
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// 'b is the borrow of AST and 'a is the lifetime of template source.
+pub trait Visitor<'b, 'a: 'b> {
+    fn visit_element(&mut self, e: &'b Element<'a>, ancestors: &mut Ancestors<'b, 'a>) {
+        walk_element(self, e, ancestors);
+    }
+    fn visit_directive(&mut self, _: &'b Directive<'a>, _: &Ancestors<'b, 'a>) {}
+    fn visit_text(&mut self, _: &'b TextNode<'a>, _: &Ancestors<'b, 'a>) {}
+    fn visit_interpolation(&mut self, _: &'b SourceNode<'a>, _: &Ancestors<'b, 'a>) {}
+    fn visit_comment(&mut self, _: &'b SourceNode<'a>, _: &Ancestors<'b, 'a>) {}
+    fn visit_processing_instruction(&mut self, _: &'b SourceNode<'a>, _: &Ancestors<'b, 'a>) {}
+}
+
+pub fn walk_root<'b, 'a, V>(root: &'b AstRoot<'a>, v: &mut V)
+where
+    V: Visitor<'b, 'a> + ?Sized,
+{
+    let mut ancestors = Ancestors(vec![]);
+    walk_children(v, &root.children, &mut ancestors);
+}
+
+pub fn walk_element<'b, 'a, V>(v: &mut V, e: &'b Element<'a>, ancestors: &mut Ancestors<'b, 'a>)
+where
+    V: Visitor<'b, 'a> + ?Sized,
+{
+    ancestors.0.push(e);
+    for dir in e.dirs() {
+        v.visit_directive(dir, ancestors);
+    }
+    walk_children(v, &e.children, ancestors);
+    ancestors.0.pop();
+}
+
+fn walk_children<'b, 'a, V>(
+    v: &mut V,
+    children: &'b [AstNode<'a>],
+    ancestors: &mut Ancestors<'b, 'a>,
+) where
+    V: Visitor<'b, 'a> + ?Sized,
+{
+    for child in children {
+        match child {
+            AstNode::Element(e) => v.visit_element(e, ancestors),
+            AstNode::Text(t) => v.visit_text(t, ancestors),
+            AstNode::Interpolation(i) => v.visit_interpolation(i, ancestors),
+            AstNode::Comment(c) => v.visit_comment(c, ancestors),
+            AstNode::ProcessingInstruction(p) => v.visit_processing_instruction(p, ancestors),
+        }
+    }
+}
+
+/// Position of the node visited by walk_with_path.
+pub struct WalkPath<'b, 'a> {
+    root: &'b [AstNode<'a>],
+    // index in siblings at each depth, root children first
+    indexes: Vec<usize>,
+}
+
+impl<'b, 'a> WalkPath<'b, 'a> {
+    /// Elements enclosing the node, root first.
+    pub fn ancestors(&self) -> impl Iterator<Item = &'b Element<'a>> + '_ {
+        let mut nodes = self.root;
+        let owners = &self.indexes[..self.indexes.len() - 1];
+        owners.iter().map(move |&i| {
+            let e = nodes[i].get_element().expect("ancestor must be element");
+            nodes = &e.children;
+            e
+        })
+    }
+    pub fn parent(&self) -> Option<&'b Element<'a>> {
+        self.ancestors().last()
+    }
+    pub fn index_in_parent(&self) -> usize {
+        *self.indexes.last().unwrap()
+    }
+    /// 0 for nodes directly under root.
+    pub fn depth(&self) -> usize {
+        self.indexes.len() - 1
+    }
+    fn siblings(&self) -> &'b [AstNode<'a>] {
+        self.parent().map_or(self.root, |p| &p.children)
+    }
+}
+
+/// Calls `f` on every node in document order with its position.
+pub fn walk_with_path<'b, 'a, F>(root: &'b AstRoot<'a>, mut f: F)
+where
+    F: FnMut(&'b AstNode<'a>, &WalkPath<'b, 'a>),
+{
+    let mut path = WalkPath {
+        root: &root.children,
+        indexes: vec![0],
+    };
+    loop {
+        let siblings = path.siblings();
+        let i = path.index_in_parent();
+        if let Some(node) = siblings.get(i) {
+            f(node, &path);
+            match node {
+                AstNode::Element(e) if !e.children.is_empty() => path.indexes.push(0),
+                _ => *path.indexes.last_mut().unwrap() += 1,
+            }
+            continue;
+        }
+        // all siblings are visited, go to the next sibling of parent
+        path.indexes.pop();
+        match path.indexes.last_mut() {
+            Some(i) => *i += 1,
+            None => return,
+        }
+    }
+}
+
+/// Mutable visitor can edit properties and children in place.
+/// Since ancestors are mutably borrowed, only their tag names are available.
+pub trait VisitorMut<'a> {
+    fn visit_element(&mut self, e: &mut Element<'a>, ancestors: &mut Vec<Name<'a>>) {
+        walk_element_mut(self, e, ancestors);
+    }
+    fn visit_directive(&mut self, _: &mut Directive<'a>, _: &[Name<'a>]) {}
+    fn visit_text(&mut self, _: &mut TextNode<'a>, _: &[Name<'a>]) {}
+    fn visit_interpolation(&mut self, _: &mut SourceNode<'a>, _: &[Name<'a>]) {}
+    fn visit_comment(&mut self, _: &mut SourceNode<'a>, _: &[Name<'a>]) {}
+    fn visit_processing_instruction(&mut self, _: &mut SourceNode<'a>, _: &[Name<'a>]) {}
+}
+
+pub fn walk_root_mut<'a, V>(root: &mut AstRoot<'a>, v: &mut V)
+where
+    V: VisitorMut<'a> + ?Sized,
+{
+    let mut ancestors = vec![];
+    walk_children_mut(v, &mut root.children, &mut ancestors);
+}
+
+pub fn walk_element_mut<'a, V>(v: &mut V, e: &mut Element<'a>, ancestors: &mut Vec<Name<'a>>)
+where
+    V: VisitorMut<'a> + ?Sized,
+{
+    ancestors.push(e.tag_name);
+    for prop in e.properties.iter_mut() {
+        if let ElemProp::Dir(dir) = prop {
+            v.visit_directive(dir, ancestors);
+        }
+    }
+    walk_children_mut(v, &mut e.children, ancestors);
+    ancestors.pop();
+}
+
+fn walk_children_mut<'a, V>(v: &mut V, children: &mut [AstNode<'a>], ancestors: &mut Vec<Name<'a>>)
+where
+    V: VisitorMut<'a> + ?Sized,
+{
+    for child in children {
+        match child {
+            AstNode::Element(e) => v.visit_element(e, ancestors),
+            AstNode::Text(t) => v.visit_text(t, ancestors),
+            AstNode::Interpolation(i) => v.visit_interpolation(i, ancestors),
+            AstNode::Comment(c) => v.visit_comment(c, ancestors),
+            AstNode::ProcessingInstruction(p) => v.visit_processing_instruction(p, ancestors),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::test::base_parse;
+    use crate::util::find_dir;
+
+    #[derive(Default)]
+    struct ForCollector<'a> {
+        fors: Vec<(&'a str, Option<Name<'a>>)>,
+    }
+    impl<'b, 'a: 'b> Visitor<'b, 'a> for ForCollector<'a> {
+        fn visit_directive(&mut self, dir: &'b Directive<'a>, ancestors: &Ancestors<'b, 'a>) {
+            if dir.name != "for" {
+                return;
+            }
+            let expr = dir.expression.as_ref().unwrap().content.raw;
+            // skip the owner element
+            let owner = ancestors.len() - 1;
+            let comp = ancestors[..owner]
+                .iter()
+                .rev()
+                .find(|e| e.is_component())
+                .map(|e| e.tag_name);
+            self.fors.push((expr, comp));
+        }
+    }
+
+    #[test]
+    fn test_visitor() {
+        let root = base_parse(
+            r#"<comp><div><p v-for="a in as"/></div></comp><p v-for="b in bs"><comp v-for="c in cs"/></p>"#,
+        );
+        let mut collector = ForCollector::default();
+        walk_root(&root, &mut collector);
+        assert_eq!(
+            collector.fors,
+            [
+                ("a in as", Some("comp")),
+                ("b in bs", None),
+                ("c in cs", None)
+            ]
+        );
+    }
+
+    struct Stripper;
+    impl<'a> VisitorMut<'a> for Stripper {
+        fn visit_element(&mut self, e: &mut Element<'a>, ancestors: &mut Vec<Name<'a>>) {
+            e.remove_prop("data-test");
+            e.children.retain(|n| !matches!(n, AstNode::Comment(_)));
+            walk_element_mut(self, e, ancestors);
+        }
+        fn visit_directive(&mut self, dir: &mut Directive<'a>, ancestors: &[Name<'a>]) {
+            if ancestors.contains(&"ul") {
+                dir.modifiers.push("stop");
+            }
+        }
+    }
+
+    #[test]
+    fn test_visitor_mut() {
+        let mut root = base_parse(r#"<ul data-test><li data-test @click="a"><!--c--></li></ul>"#);
+        walk_root_mut(&mut root, &mut Stripper);
+        let ul = root.children[0].get_element().unwrap();
+        assert!(ul.properties.is_empty());
+        let li = ul.children[0].get_element().unwrap();
+        assert!(li.children.is_empty());
+        assert_eq!(li.properties.len(), 1);
+        assert_eq!(li.dirs().next().unwrap().modifiers, ["stop"]);
+    }
+
+    #[test]
+    fn test_walk_with_path() {
+        let root = base_parse(
+            r#"<transition-group><li v-show="a"/><p><span v-show="b"/></p></transition-group><i v-show="c"/>"#,
+        );
+        // example rule: v-show inside transition-group
+        let mut found = vec![];
+        walk_with_path(&root, |node, path| {
+            let e = match node {
+                AstNode::Element(e) => e,
+                _ => return,
+            };
+            let show = match find_dir(e, "show") {
+                Some(dir) => dir,
+                None => return,
+            };
+            if path.ancestors().any(|a| a.tag_name == "transition-group") {
+                let expr = show.get_ref().expression.as_ref().unwrap().content.raw;
+                let parent = path.parent().unwrap().tag_name;
+                found.push((expr, parent, path.index_in_parent(), path.depth()));
+            }
+        });
+        assert_eq!(found, [("a", "transition-group", 0, 1), ("b", "p", 0, 2)]);
+        let mut tags = vec![];
+        walk_with_path(&root, |node, path| {
+            if let AstNode::Element(e) = node {
+                tags.push((e.tag_name, path.depth()));
+            }
+        });
+        assert_eq!(
+            tags,
+            [
+                ("transition-group", 0),
+                ("li", 1),
+                ("p", 1),
+                ("span", 2),
+                ("i", 0)
+            ]
+        );
+    }
+}
@@ -0,0 +1,177 @@
+/*!
+Impact of switching WhitespaceStrategy between Preserve and Condense.
+
+A template is compiled under both strategies and the generated code is compared.
+Text nodes responsible for the difference are found by matching text nodes of
+both ASTs by start offset: a text node is responsible if Condense removes it or
+changes its text.
+*/
+
+use crate::{
+    compiler::{get_base_passes, BaseCompiler, CompileOption, TemplateCompiler},
+    parser::{TextNode, WhitespaceStrategy},
+    visit::{walk_root, Ancestors, Visitor},
+    SFCInfo, SourceLocation,
+};
+use std::{collections::BTreeMap, fmt, fs, io, path::Path};
+
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct ImpactReport {
+    /// If the generated code differs between strategies.
+    pub differs: bool,
+    /// Text nodes removed or condensed by Condense, in source order.
+    pub text_nodes: Vec<SourceLocation>,
+}
+
+impl ImpactReport {
+    pub fn impact(&self) -> usize {
+        self.text_nodes.len()
+    }
+}
+
+pub fn whitespace_impact(source: &str) -> ImpactReport {
+    let (preserved_code, preserved) = compile_with(source, WhitespaceStrategy::Preserve);
+    let (condensed_code, condensed) = compile_with(source, WhitespaceStrategy::Condense);
+    let text_nodes = preserved
+        .into_iter()
+        .filter(|(offset, (_, text))| condensed.get(offset).map(|c| &c.1) != Some(text))
+        .map(|(_, (location, _))| location)
+        .collect();
+    ImpactReport {
+        differs: preserved_code != condensed_code,
+        text_nodes,
+    }
+}
+
+/// Text nodes keyed by start offset.
+type Texts = BTreeMap<usize, (SourceLocation, String)>;
+
+fn compile_with(source: &str, whitespace: WhitespaceStrategy) -> (Vec<u8>, Texts) {
+    let sfc_info = SFCInfo::default();
+    let option = CompileOption {
+        whitespace,
+        ..Default::default()
+    };
+    let compiler = BaseCompiler::new(Vec::new, get_base_passes, option);
+    let ast = compiler.parse(compiler.scan(source));
+    let mut collector = TextCollector::default();
+    walk_root(&ast, &mut collector);
+    let mut ir = compiler.convert(ast, &sfc_info);
+    compiler.transform(&mut ir, &sfc_info);
+    let code = compiler.generate(ir, &sfc_info);
+    (code.expect("writing to Vec cannot fail"), collector.0)
+}
+
+#[derive(Default)]
+struct TextCollector(Texts);
+
+impl<'b, 'a: 'b> Visitor<'b, 'a> for TextCollector {
+    fn visit_text(&mut self, t: &'b TextNode<'a>, _: &Ancestors<'b, 'a>) {
+        let text = t.text.iter().map(|s| s.into_string()).collect();
+        let location = t.location.clone();
+        self.0.insert(location.start.offset, (location, text));
+    }
+}
+
+/// Impact reports of many templates.
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct ImpactSummary {
+    pub reports: Vec<(String, ImpactReport)>,
+}
+
+impl ImpactSummary {
+    pub fn add(&mut self, name: &str, source: &str) {
+        self.reports
+            .push((name.to_string(), whitespace_impact(source)));
+    }
+    /// Adds every file with the extension under dir recursively.
+    /// Files must be plain templates, e.g. `html`, not SFC.
+    pub fn add_dir(&mut self, dir: &Path, extension: &str) -> io::Result<()> {
+        let mut entries = fs::read_dir(dir)?
+            .map(|e| e.map(|e| e.path()))
+            .collect::<io::Result<Vec<_>>>()?;
+        entries.sort();
+        for path in entries {
+            if path.is_dir() {
+                self.add_dir(&path, extension)?;
+            } else if path.extension() == Some(extension.as_ref()) {
+                let source = fs::read_to_string(&path)?;
+                self.add(&path.to_string_lossy(), &source);
+            }
+        }
+        Ok(())
+    }
+    /// Reports with the most responsible text nodes first, then by name.
+    pub fn sorted(&self) -> Vec<&(String, ImpactReport)> {
+        let mut sorted: Vec<_> = self.reports.iter().collect();
+        sorted.sort_by(|a, b| b.1.impact().cmp(&a.1.impact()).then_with(|| a.0.cmp(&b.0)));
+        sorted
+    }
+}
+
+/// Renders affected templates sorted by impact, e.g.
+/// `App.html: 3 text node(s) at 2:5, 4:1, 7:9`.
+impl fmt::Display for ImpactSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let affected: Vec<_> = self.sorted().into_iter().filter(|r| r.1.differs).collect();
+        for (name, report) in &affected {
+            let locations: Vec<_> = report
+                .text_nodes
+                .iter()
+                .map(|l| format!("{}:{}", l.start.line, l.start.column))
+                .collect();
+            writeln!(
+                f,
+                "{}: {} text node(s) at {}",
+                name,
+                report.impact(),
+                locations.join(", ")
+            )?;
+        }
+        writeln!(
+            f,
+            "{} of {} template(s) affected",
+            affected.len(),
+            self.reports.len()
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_invariant_template() {
+        let report = whitespace_impact("<div><p>a b</p></div>");
+        assert!(!report.differs);
+        assert!(report.text_nodes.is_empty());
+    }
+
+    #[test]
+    fn test_affected_template() {
+        let source = "<div>\n  <p>a   b</p>\n  <p/>\n</div>";
+        let report = whitespace_impact(source);
+        assert!(report.differs);
+        let offsets: Vec<_> = report.text_nodes.iter().map(|l| l.start.offset).collect();
+        assert_eq!(offsets, [11, 20]);
+    }
+
+    #[test]
+    fn test_summary() {
+        let mut summary = ImpactSummary::default();
+        summary.add("a.html", "<p>a</p>");
+        summary.add("b.html", "<p> a  </p>\n<p/>");
+        summary.add("c.html", "<p>a  b</p>");
+        let names: Vec<_> = summary.sorted().iter().map(|r| r.0.as_str()).collect();
+        assert_eq!(names, ["b.html", "c.html", "a.html"]);
+        let text = summary.to_string();
+        assert!(text.starts_with("b.html: 2 text node(s) at 1:4, 1:12\n"));
+        assert!(text.ends_with("2 of 3 template(s) affected\n"));
+    }
+}
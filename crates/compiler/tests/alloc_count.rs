@@ -0,0 +1,88 @@
+// Counts heap allocations made by parsing a large template, with Parser::parse
+// and with Parser::parse_in, which allocates AST children in a bump arena.
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use vue_compiler_core as compiler;
+
+use compiler::compiler::CompileOption;
+use compiler::error::VecErrorHandler;
+use compiler::parser::{AstAlloc, AstNode, Parser};
+use compiler::scanner::Scanner;
+
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+fn count_nodes<'a, A: AstAlloc<'a>>(nodes: &[AstNode<'a, A>]) -> usize {
+    nodes
+        .iter()
+        .map(|n| match n {
+            AstNode::Element(e) => 1 + count_nodes(&e.children),
+            _ => 1,
+        })
+        .sum()
+}
+
+fn allocations<R>(f: impl FnOnce() -> R) -> (R, usize) {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let ret = f();
+    (ret, ALLOCATIONS.load(Ordering::Relaxed) - before)
+}
+
+#[test]
+fn test_parse_allocation_count() {
+    let row = r#"
+    <tr :key="row.id" class="row" @click="select(row)">
+      <td>{{ row.id }}</td>
+      <td><a :href="row.url" v-if="row.url">{{ row.label }}</a></td>
+      <td><span class="icon" v-for="tag in row.tags">{{ tag }}</span></td>
+    </tr>"#;
+    let source = format!("<table><tbody>{}</tbody></table>", row.repeat(5_000));
+    let option = CompileOption::default();
+    let eh = Rc::new(VecErrorHandler::new());
+    let scanner = Scanner::new(option.scanning());
+    let parser = Parser::new(option.parsing());
+    let (root, heap) = allocations(|| parser.parse(scanner.scan(&source, eh.clone()), eh.clone()));
+    let nodes = count_nodes(&root.children);
+    // About 1.1 allocations per node at the time of writing (65k for 60k nodes):
+    // children vectors growing, plus attribute and props vectors per element.
+    assert!(
+        heap < nodes * 6 / 5,
+        "{} allocations for {} nodes",
+        heap,
+        nodes
+    );
+    // bumpalo allocates a few chunks for the arena itself
+    let arena = bumpalo::Bump::new();
+    let (in_arena, arena_mode) = allocations(|| {
+        let tokens = scanner.scan(&source, eh.clone());
+        parser.parse_in(tokens, eh.clone(), &arena)
+    });
+    assert_eq!(count_nodes(&in_arena.children), nodes);
+    // About 0.5 allocations per node at the time of writing (30k for 60k nodes):
+    // children vectors are in the arena, attribute and props vectors are not.
+    assert!(
+        arena_mode < heap * 2 / 3,
+        "{} allocations in arena mode, {} in heap mode",
+        arena_mode,
+        heap
+    );
+}
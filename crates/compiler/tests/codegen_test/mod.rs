@@ -30,3 +30,14 @@ fn test_text_codegen() {
         "<comp>Hello {{world}}</comp>",
     ]];
 }
+
+#[test]
+fn test_entity_in_binding() {
+    use super::common::get_errors;
+    // attribute values are decoded, but directive expressions are JS source
+    let case = r#"<p title="a &amp; b" :alt="'&lt;' + c"/>"#;
+    assert!(get_errors(case).is_empty());
+    let code = assert_codegen(case);
+    assert!(code.contains(r#"title: "a & b""#), "{}", code);
+    assert!(code.contains("alt: '&lt;' + c"), "{}", code);
+}
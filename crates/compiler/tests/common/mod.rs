@@ -5,7 +5,7 @@ use compiler::transformer::CorePass;
 use compiler::compiler::TemplateCompiler;
 pub use compiler::{Position, SourceLocation};
 use compiler::compiler::{BaseCompiler, CompileOption, get_base_passes};
-use compiler::scanner::TextMode;
+use compiler::scanner::{Tag, TextMode};
 use serde::Serialize;
 use std::rc::Rc;
 use vue_compiler_core as compiler;
@@ -19,15 +19,15 @@ pub fn serialize_yaml<T: Serialize>(t: T) -> String {
     serialize_value(&t, SerializationFormat::Yaml, SnapshotLocation::File)
 }
 
-fn get_text_mode(s: &str) -> TextMode {
-    match s {
+fn get_text_mode(tag: &Tag) -> TextMode {
+    match tag.name {
         "style" | "script" | "iframe" | "noscript" => TextMode::RawText,
         "textarea" | "title" => TextMode::RcData,
         _ => TextMode::Data,
     }
 }
 
-fn get_compile_option() -> CompileOption {
+pub fn get_compile_option() -> CompileOption {
     CompileOption {
         get_text_mode,
         is_native_tag: |s| s != "comp",
@@ -0,0 +1,35 @@
+// Regenerates `ast` and `errors` of every fixture in this directory from the
+// JS compiler. `template`, `options`, `ignoreLoc` and `knownDivergence` are kept.
+//
+//   npm install --no-save @vue/compiler-dom
+//   node tests/conformance/export.mjs
+//
+// compiler-dom's parse is compiler-core's baseParse with DOM parser options,
+// matching ParseOption::dom() used by conformance_test.rs.
+import { readFileSync, readdirSync, writeFileSync } from 'node:fs'
+import { dirname, join } from 'node:path'
+import { fileURLToPath } from 'node:url'
+import { parse, ErrorCodes } from '@vue/compiler-dom'
+
+const dir = dirname(fileURLToPath(import.meta.url))
+
+for (const file of readdirSync(dir).filter(f => f.endsWith('.json')).sort()) {
+  const path = join(dir, file)
+  const fixture = JSON.parse(readFileSync(path, 'utf8'))
+  const errors = []
+  const options = fixture.options || {}
+  const ast = parse(fixture.template, {
+    whitespace: options.whitespace || 'condense',
+    comments: options.comments ?? true,
+    onError: e => errors.push(ErrorCodes[e.code]),
+    onWarn: () => {},
+  })
+  if ('ast' in fixture) {
+    // drop codegen fields and circular-free cruft by a JSON round trip
+    fixture.ast = JSON.parse(JSON.stringify(ast, (key, v) => (key === 'codegenNode' ? undefined : v)))
+  }
+  if ('errors' in fixture || errors.length) {
+    fixture.errors = errors
+  }
+  writeFileSync(path, JSON.stringify(fixture, null, 2) + '\n')
+}
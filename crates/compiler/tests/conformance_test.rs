@@ -0,0 +1,50 @@
+// Runs fixtures in tests/conformance, whose expected ASTs follow the JSON shape
+// of vue/compiler-dom. See testing::conformance for the fixture format.
+use std::path::PathBuf;
+use vue_compiler_core as compiler;
+
+use compiler::parser::ParseOption;
+use compiler::testing::conformance::{compare, load_fixtures, run_fixture};
+use serde_json::json;
+
+#[test]
+fn test_conformance_fixtures() {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/conformance");
+    let fixtures = load_fixtures(&dir).unwrap();
+    assert!(!fixtures.is_empty());
+    let failures: Vec<_> = fixtures
+        .iter()
+        .filter_map(|fixture| {
+            let result = run_fixture(fixture, ParseOption::dom());
+            match (result, &fixture.known_divergence) {
+                (Ok(()), None) | (Err(_), Some(_)) => None,
+                (Ok(()), Some(_)) => {
+                    Some(format!("{}: passes, remove knownDivergence", fixture.name))
+                }
+                (Err(mismatches), None) => {
+                    let lines: Vec<_> = mismatches.iter().map(|m| format!("  {}", m)).collect();
+                    Some(format!("{}:\n{}", fixture.name, lines.join("\n")))
+                }
+            }
+        })
+        .collect();
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
+
+#[test]
+fn test_compare_report() {
+    let expected = json!({"tag": "div", "props": [{"name": "a"}], "ns": "HTML"});
+    let actual = json!({"tag": "p", "props": [], "isStatic": true});
+    let report: Vec<_> = compare(&expected, &actual)
+        .iter()
+        .map(|m| m.to_string())
+        .collect();
+    // serde_json::Map is sorted by key.
+    let expected = [
+        r#"ns: expected "HTML", found <missing>"#,
+        "props.length: expected 1, found 0",
+        r#"tag: expected "div", found "p""#,
+        "isStatic: expected <missing>, found true",
+    ];
+    assert_eq!(report, expected);
+}
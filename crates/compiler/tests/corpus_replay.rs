@@ -0,0 +1,80 @@
+// Replays every template in tests/corpus. Inputs are chosen to stress error
+// recovery so only invariants are asserted, not the exact output.
+use std::rc::Rc;
+use vue_compiler_core as compiler;
+
+#[path = "common/corpus.rs"]
+mod corpus;
+use corpus::corpus;
+
+use compiler::compiler::{get_base_passes, BaseCompiler, CompileOption, TemplateCompiler};
+use compiler::error::VecErrorHandler;
+use compiler::parser::{ParseLimits, Parser};
+use compiler::scanner::Scanner;
+use compiler::testing::{check_invariants, sort_errors};
+
+#[test]
+fn test_parse_invariants() {
+    let corpus = corpus();
+    assert!(!corpus.is_empty());
+    for (name, source) in &corpus {
+        let option = CompileOption::default();
+        let eh = Rc::new(VecErrorHandler::new());
+        let tokens = Scanner::new(option.scanning()).scan(source, eh.clone());
+        let root = Parser::new(option.parsing()).parse(tokens, eh.clone());
+        let mut errors = std::mem::take(&mut *eh.error_mut());
+        sort_errors(&mut errors);
+        if let Err(violation) = check_invariants(&root, &errors, source) {
+            panic!("{}: {}", name, violation);
+        }
+    }
+}
+
+// conversion and code generation recurse per nesting level, so deeply
+// nested input like unclosed_tags.vue needs a larger stack than test threads.
+const COMPILE_STACK_SIZE: usize = 64 << 20;
+
+#[test]
+fn test_compile_no_panic() {
+    let handle = std::thread::Builder::new()
+        .stack_size(COMPILE_STACK_SIZE)
+        .spawn(|| {
+            for (name, source) in corpus() {
+                let eh = Rc::new(VecErrorHandler::new());
+                let option = CompileOption {
+                    error_handler: eh,
+                    ..Default::default()
+                };
+                let sfc_info = Default::default();
+                let compiler = BaseCompiler::new(Vec::new, get_base_passes, option);
+                let output = compiler.compile(&source, &sfc_info);
+                assert!(output.is_ok(), "{}", name);
+            }
+        })
+        .unwrap();
+    handle.join().unwrap();
+}
+
+// debug build takes about 12KiB stack per nesting level to compile
+const DEEP_STACK_SIZE: usize = 64 << 20;
+
+#[test]
+fn test_compile_at_nesting_limit() {
+    let handle = std::thread::Builder::new()
+        .stack_size(DEEP_STACK_SIZE)
+        .spawn(|| {
+            let depth = ParseLimits::default().max_nesting_depth.unwrap() + 1;
+            let source = r#"<div :a="b">"#.repeat(depth) + "{{ c }}" + &"</div>".repeat(depth);
+            let eh = Rc::new(VecErrorHandler::new());
+            let option = CompileOption {
+                error_handler: eh.clone(),
+                ..Default::default()
+            };
+            let sfc_info = Default::default();
+            let compiler = BaseCompiler::new(Vec::new, get_base_passes, option);
+            assert!(compiler.compile(&source, &sfc_info).is_ok());
+            assert_eq!(eh.errors().len(), 1);
+        })
+        .unwrap();
+    handle.join().unwrap();
+}
@@ -41,3 +41,32 @@ fn test_abrupt_closing_of_comment() {
         r#"<template><!----></template>"#,
     ]];
 }
+
+#[test]
+fn test_reserved_binding() {
+    assert_error![[
+        r#"<p v-for="$event in xs"/>"#,
+        r#"<p v-for="(item, _cache) in xs"/>"#,
+        r#"<comp v-slot="{ _ctx, a = $attrs }">{{ a }}</comp>"#,
+        r#"<comp><template #named="{ $slots }"/></comp>"#,
+        r#"<p @click="$event => foo($event)"/>"#,
+    ]];
+}
+
+#[test]
+fn test_stray_end_tag() {
+    assert_error![[
+        r#"<div><span :a="a"></div></span>"#,
+        r#"<div><p :a="a"><span :b="b"></div></span></p>"#,
+        r#"<div></span></div>"#,
+    ]];
+}
+
+#[test]
+fn test_invalid_dynamic_arg() {
+    assert_error![[
+        r#"<p :[foo bar]="x"/>"#,
+        r#"<p :['foo']="x"/>"#,
+        r#"<p :[foo]="x"/>"#,
+    ]];
+}
@@ -0,0 +1,86 @@
+// Runs the examples over tests/corpus, so the public API they use keeps
+// compiling and the invariants a downstream tool relies on keep holding.
+use vue_compiler_core as compiler;
+
+#[path = "common/corpus.rs"]
+mod corpus;
+use corpus::corpus;
+
+#[allow(dead_code)]
+#[path = "../examples/compile.rs"]
+mod compile;
+#[allow(dead_code)]
+#[path = "../examples/format.rs"]
+mod format;
+#[allow(dead_code)]
+#[path = "../examples/lint.rs"]
+mod lint;
+
+use compiler::error::Severity;
+use compiler::testing::check_invariants;
+
+// see corpus_replay.rs, deeply nested input needs a larger stack
+const STACK_SIZE: usize = 64 << 20;
+
+fn with_large_stack(f: fn()) {
+    std::thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(f)
+        .unwrap()
+        .join()
+        .unwrap();
+}
+
+#[test]
+fn test_lint_diagnostics_sorted() {
+    for (name, source) in corpus() {
+        let (root, errors) = lint::lint(&source);
+        let offsets: Vec<_> = errors.iter().map(|e| e.location.start.offset).collect();
+        assert!(offsets.windows(2).all(|w| w[0] <= w[1]), "{}", name);
+        if let Err(violation) = check_invariants(&root, &errors, &source) {
+            panic!("{}: {}", name, violation);
+        }
+    }
+}
+
+#[test]
+fn test_lint_custom_rule() {
+    let (_, errors) = lint::lint(r#"<img src="a"/><img alt="" src="b"/><img/>"#);
+    let offsets: Vec<_> = errors.iter().map(|e| e.location.start.offset).collect();
+    assert_eq!(offsets, [0, 35]);
+    assert!(errors.iter().all(|e| e.is_warning()));
+}
+
+#[test]
+fn test_format_round_trip() {
+    let mut formatted_count = 0;
+    for (name, source) in corpus() {
+        let formatted = match format::format(&source) {
+            Some(formatted) => formatted,
+            None => continue,
+        };
+        formatted_count += 1;
+        let again = format::format(&formatted);
+        assert_eq!(again.as_ref(), Some(&formatted), "{}", name);
+    }
+    assert!(formatted_count > 0);
+    assert_eq!(format::format("<p>a</p><"), None);
+}
+
+#[test]
+fn test_compile_output_parses() {
+    with_large_stack(|| {
+        let mut checked = 0;
+        for (name, source) in corpus() {
+            let (code, errors) = compile::compile(&source);
+            if errors.iter().any(|e| e.severity() == Severity::Error) {
+                continue;
+            }
+            checked += 1;
+            let wrapped = format!("function render_wrapper() {{\n{}\n}}", code);
+            let parse = rslint_parser::parse_text(&wrapped, 0);
+            assert!(parse.errors().is_empty(), "{}:\n{}", name, code);
+        }
+        assert!(checked > 0);
+    });
+}
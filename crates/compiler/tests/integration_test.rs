@@ -2,6 +2,7 @@ mod codegen_test;
 mod common;
 mod converter_test;
 mod error_test;
+mod ir_document_test;
 mod parser_test;
 mod scanner_test;
 mod transformer_test;
@@ -0,0 +1,63 @@
+use super::common::{get_compile_option, serialize_yaml};
+use crate::meta_macro;
+use compiler::compiler::compile_to_ir;
+use compiler::ir::{IrDocument, IR_VERSION};
+use vue_compiler_core as compiler;
+
+fn assert_ir_doc(case: &str) -> String {
+    let doc = compile_to_ir(case, &get_compile_option());
+    serialize_yaml(doc)
+}
+
+meta_macro!(assert_ir_doc);
+
+#[test]
+fn test_text() {
+    assert_ir_doc![["hello {{ world }}!", "<!--comment-->"]];
+}
+
+#[test]
+fn test_element() {
+    assert_ir_doc![[
+        r#"<div id="a" :class="b" class="c" @click="d++"><p v-show="e"/></div>"#,
+        r#"<input v-model="f" v-bind="g" v-custom:h.i="j"/>"#,
+    ]];
+}
+
+#[test]
+fn test_component() {
+    assert_ir_doc![[
+        "<comp>default</comp>",
+        r#"<comp><template #a="{ b }">{{ b }}</template><template v-if="c" #d/></comp>"#,
+        r#"<component :is="e"/>"#,
+        "<keep-alive><comp/></keep-alive>",
+    ]];
+}
+
+#[test]
+fn test_control_flow() {
+    assert_ir_doc![[
+        r#"<p v-if="a"/><template v-else-if="b">b</template><p v-else/>"#,
+        r#"<p v-for="(item, key, i) in list" :key="key">{{ item }}</p>"#,
+        r#"<p v-once/><p v-memo="[a]"/>"#,
+    ]];
+}
+
+#[test]
+fn test_slot_outlet() {
+    assert_ir_doc![[
+        r#"<slot name="a" :b="c">fallback</slot>"#,
+        r#"<slot :name="d"/>"#,
+    ]];
+}
+
+// documents serialized by previous IR versions must stay readable
+#[test]
+fn test_deserialize_v1() {
+    let case = r#"<div :a="b"><span v-for="e in f">{{ e }}</span></div>"#;
+    let fixture = include_str!("fixtures/v1.json");
+    let doc: IrDocument = serde_json::from_str(fixture).unwrap();
+    assert_eq!(doc.version, 1);
+    assert!(doc.version <= IR_VERSION);
+    assert_eq!(doc, compile_to_ir(case, &get_compile_option()));
+}
@@ -66,6 +66,15 @@ fn test_slot_dir() {
     ]];
 }
 #[test]
+fn test_svg_namespaced_dir() {
+    assert_dir![[
+        r#"<svg :xlink:href="url"/>"#,       // bind, xlink:href
+        r#"<svg v-bind:xlink:href="url"/>"#, // bind, xlink:href
+        r#"<svg :xml:space.camel="url"/>"#,  // bind, xml:space, camel
+        r##"<svg xlink:href="#id"/>"##,      // static attribute
+    ]];
+}
+#[test]
 fn test_dir_parse_error() {
     assert_dir![[
         r#"<p v-="tt"/>"#,       // ERROR,
@@ -1,9 +1,13 @@
 use vue_compiler_core as compiler;
 mod dir;
-use super::common::{serialize_yaml, get_compiler};
+use super::common::{serialize_yaml, get_compiler, get_compile_option, TestErrorHandler};
 use compiler::compiler::TemplateCompiler;
-use compiler::parser::AstRoot;
+use compiler::flags::RuntimeHelper;
+use compiler::parser::{preset, AstNode, AstRoot, ElemProp, ElementType, ParseOption, Parser};
+use compiler::scanner::{QuoteKind, ScanOption, Scanner};
+use compiler::{Namespace, SourceLocation, SourceMapper};
 use crate::meta_macro;
+use std::{fs, rc::Rc, sync::Arc, thread};
 
 fn assert_parse(case: &str) -> String {
     let root = base_parse(case);
@@ -25,8 +29,267 @@ fn test_script() {
     ]];
 }
 
+fn parse_with_delimiters(s: &str, delimiters: (&str, &str)) -> String {
+    let option = get_compile_option();
+    let mut scan_option = option.scanning();
+    scan_option.delimiters = (delimiters.0.into(), delimiters.1.into());
+    let scanner = Scanner::new(scan_option);
+    let parser = Parser::new(option.parsing());
+    parse_with(&scanner, &parser, s)
+}
+
+#[test]
+fn test_custom_delimiters() {
+    let default = "<p title='{{a}}'>{{ b }}<i v-pre>{{ c }}</i></p>{{d";
+    let custom = "<p title='[[a]]'>[[ b ]]<i v-pre>[[ c ]]</i></p>[[d";
+    let expected = parse_with_delimiters(default, ("{{", "}}"));
+    let actual = parse_with_delimiters(custom, ("[[", "]]"));
+    assert_eq!(expected.replace("{{", "[[").replace("}}", "]]"), actual);
+    let dollar = parse_with_delimiters("${ a }{{ b }}<p v-pre>${ c }</p>", ("${", "}"));
+    insta::assert_snapshot!(dollar);
+}
+
+#[test]
+fn test_location_slice() {
+    let case = "<div title='いろは'>\r\n  <!-- 注释 -->\r\n  {{ 你好 }}<p>🖖</p>\r\n</div>";
+    let root = base_parse(case);
+    let mapper = SourceMapper::new(case);
+    let div = root.children[0].get_element().unwrap();
+    assert_eq!(mapper.slice(&div.location), case);
+    let texts: Vec<_> = div
+        .children
+        .iter()
+        .map(|n| mapper.slice(n.get_location()))
+        .collect();
+    assert_eq!(
+        texts,
+        ["<!-- 注释 -->", "\r\n  ", "{{ 你好 }}", "<p>🖖</p>"]
+    );
+    let p = div.children[3].get_location();
+    assert_eq!(mapper.position_of(p.start.offset), p.start);
+    assert_eq!(mapper.position_of(p.end.offset), p.end);
+}
+
+fn fixture_corpus() -> Vec<String> {
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/../../benches/fixtures");
+    let mut paths: Vec<_> = fs::read_dir(dir)
+        .expect("fixture dir should exist")
+        .map(|e| e.unwrap().path())
+        .filter(|p| p.extension().is_some_and(|e| e == "vue"))
+        .collect();
+    paths.sort();
+    paths
+        .into_iter()
+        .map(|p| fs::read_to_string(p).unwrap())
+        .collect()
+}
+
+fn parse_with(scanner: &Scanner, parser: &Parser, s: &str) -> String {
+    let eh = Rc::new(TestErrorHandler);
+    let tokens = scanner.scan(s, eh.clone());
+    serialize_yaml(parser.parse(tokens, eh))
+}
+
+#[test]
+fn test_shared_parser_across_threads() {
+    let option = get_compile_option();
+    let scanner = Arc::new(Scanner::new(option.scanning()));
+    let parser = Arc::new(Parser::new(option.parsing()));
+    let corpus = Arc::new(fixture_corpus());
+    assert!(!corpus.is_empty());
+    let expected: Vec<_> = corpus
+        .iter()
+        .map(|s| parse_with(&scanner, &parser, s))
+        .collect();
+    let handles: Vec<_> = (0..4)
+        .map(|_| {
+            let scanner = scanner.clone();
+            let parser = parser.clone();
+            let corpus = corpus.clone();
+            thread::spawn(move || {
+                corpus
+                    .iter()
+                    .map(|s| parse_with(&scanner, &parser, s))
+                    .collect::<Vec<_>>()
+            })
+        })
+        .collect();
+    for handle in handles {
+        assert_eq!(handle.join().unwrap(), expected);
+    }
+}
+
+fn check_prop_locations(src: &str, nodes: &[AstNode]) {
+    let slice = |loc: &SourceLocation| &src[loc.start.offset..loc.end.offset];
+    for e in nodes.iter().filter_map(|n| n.get_element()) {
+        let start_tag = e.start_tag_source(src);
+        let tag_start = e.location.start.offset;
+        let tag_end = tag_start + start_tag.len();
+        assert!(start_tag.starts_with(&format!("<{}", e.tag_name)));
+        for prop in &e.properties {
+            let raw = prop.raw_source(src);
+            let loc = prop.get_location();
+            assert!(tag_start < loc.start.offset && loc.end.offset <= tag_end);
+            let (name, value) = match prop {
+                ElemProp::Attr(a) => {
+                    assert_eq!(slice(&a.name_loc), a.name);
+                    (slice(&a.name_loc), a.value.as_ref())
+                }
+                ElemProp::Dir(d) => (slice(&d.head_loc), d.expression.as_ref()),
+            };
+            assert!(raw.starts_with(name), "{}", raw);
+            let value = match value {
+                Some(v) => v,
+                None => {
+                    assert_eq!(raw, name);
+                    continue;
+                }
+            };
+            // values are located without quotes, directive values after trimming
+            let content = slice(&value.location);
+            if matches!(prop, ElemProp::Dir(_)) && value.content.raw.is_empty() {
+                assert_eq!(content.trim(), "", "{}", raw);
+            } else {
+                assert_eq!(content, value.content.raw, "{}", raw);
+            }
+            let quote = match value.quote {
+                QuoteKind::Double => "\"",
+                QuoteKind::Single => "'",
+                QuoteKind::Unquoted => "",
+            };
+            let value_start = value.location.start.offset - loc.start.offset;
+            let value_end = value.location.end.offset - loc.start.offset;
+            assert_eq!(raw[value_end..].trim_start(), quote, "{}", raw);
+            let between = raw[name.len()..value_start].trim_end().strip_suffix(quote);
+            assert_eq!(between.map(str::trim), Some("="), "{}", raw);
+        }
+        check_prop_locations(src, &e.children);
+    }
+}
+
+#[test]
+fn test_prop_locations_over_corpus() {
+    let mut corpus = fixture_corpus();
+    corpus.push("<p a = 'b'\n\tc\n:d=e @f.g=\" h \" / i/><a x=\"\"/>".into());
+    for src in &corpus {
+        let root = base_parse(src);
+        check_prop_locations(src, &root.children);
+    }
+}
+
 pub fn base_parse(s: &str) -> AstRoot {
     let compiler = get_compiler();
     let tokens = compiler.scan(s);
     compiler.parse(tokens)
 }
+
+#[test]
+fn test_v_pre() {
+    assert_parse![[
+        r#"<div v-pre><span v-pre :a="b">{{ x }}</span></div>"#,
+        r#"<p v-pre :a="b">{{ x }}</p><p v-pre>{{ y }}</p><p :c="d">{{ z }}</p>"#,
+        r#"<p v-pre/><p :a="b">{{ x }}</p>"#,
+        r#"<div v-pre><p v-pre/>{{ x }}</div>{{ y }}"#,
+    ]];
+}
+
+fn flatten_elements<'a>(nodes: &[AstNode<'a>], out: &mut Vec<(&'a str, ElementType, Namespace)>) {
+    for node in nodes {
+        if let AstNode::Element(e) = node {
+            out.push((e.tag_name, e.tag_type, e.namespace));
+            flatten_elements(&e.children, out);
+        }
+    }
+}
+
+#[test]
+fn test_dom_preset() {
+    let case = r#"
+<form @submit.prevent="save">
+  <!-- search box -->
+  <label>
+    <svg class="icon" viewBox="0 0 16 16"><title>Search</title><path d="M0 0h16"/></svg>
+    <input v-model="query">
+  </label>
+  <textarea><b>not bold</b></textarea>
+  <transition><my-hint v-if="show"/></transition>
+  <br>
+</form>"#;
+    let option = ParseOption::dom();
+    let scanner = Scanner::new(ScanOption {
+        get_text_mode: option.get_text_mode,
+        ..Default::default()
+    });
+    let eh = Rc::new(TestErrorHandler);
+    let root = Parser::new(option).parse(scanner.scan(case, eh.clone()), eh);
+    let mut elems = vec![];
+    flatten_elements(&root.children, &mut elems);
+    use ElementType::{Component as C, Plain as P};
+    use Namespace::{Html as H, Svg as S};
+    let expected = [
+        ("form", P, H),
+        ("label", P, H),
+        ("svg", P, S),
+        ("title", P, S),
+        ("path", P, S),
+        ("input", P, H),
+        ("textarea", P, H),
+        ("transition", C, H),
+        ("my-hint", C, H),
+        ("br", P, H),
+    ];
+    assert_eq!(elems.len(), expected.len());
+    for (actual, expected) in elems.iter().zip(expected.iter()) {
+        assert_eq!(actual.0, expected.0);
+        assert_eq!(actual.1, expected.1, "{}", actual.0);
+        assert!(actual.2 == expected.2, "{}", actual.0);
+    }
+    let children = &root.children[0].get_element().unwrap().children;
+    let comments = children.iter().filter(|n| matches!(n, AstNode::Comment(_)));
+    assert_eq!(comments.count(), 1);
+    let mut elements = children.iter().filter_map(|n| n.get_element());
+    let textarea = elements.find(|e| e.tag_name == "textarea").unwrap();
+    assert!(matches!(&textarea.children[..], [AstNode::Text(_)]));
+}
+
+#[test]
+fn test_ssr_preset() {
+    let dom = ParseOption::dom();
+    let ssr = ParseOption::ssr();
+    assert!((dom.get_builtin_component)("Transition") == Some(RuntimeHelper::TRANSITION));
+    assert!((ssr.get_builtin_component)("Transition") == Some(RuntimeHelper::BASE_TRANSITION));
+    assert!((ssr.is_void_tag)("br") && (ssr.is_native_element)("svg"));
+}
+
+#[test]
+fn test_dom_native_element() {
+    assert!(preset::is_html_tag("hgroup") && !preset::is_html_tag("clipPath"));
+    assert!(preset::is_svg_tag("clipPath") && preset::is_svg_tag("title"));
+    assert!(preset::is_mathml_tag("mglyph") && !preset::is_mathml_tag("svg"));
+    assert!(!preset::dom_is_native_element("clippath"));
+    let case = "<svg><clipPath/></svg><clip-path-thing/><math><mi/></math>";
+    let option = ParseOption {
+        is_custom_element: (|s: &str| s == "my-element").into(),
+        ..ParseOption::dom()
+    };
+    let scanner = Scanner::new(ScanOption::default());
+    let eh = Rc::new(TestErrorHandler);
+    let root = Parser::new(option).parse(scanner.scan(case, eh.clone()), eh);
+    let mut elems = vec![];
+    flatten_elements(&root.children, &mut elems);
+    use ElementType::{Component as C, Plain as P};
+    use Namespace::{Html as H, MathMl as M, Svg as S};
+    let expected = [
+        ("svg", P, S),
+        ("clipPath", P, S),
+        ("clip-path-thing", C, H),
+        ("math", P, M),
+        ("mi", P, M),
+    ];
+    assert_eq!(elems.len(), expected.len());
+    for (actual, expected) in elems.iter().zip(expected.iter()) {
+        assert_eq!(actual.0, expected.0);
+        assert_eq!(actual.1, expected.1, "{}", actual.0);
+        assert!(actual.2 == expected.2, "{}", actual.0);
+    }
+}
@@ -6,7 +6,7 @@ use compiler::parser::{ElemProp, DirectiveArg};
 use compiler::util::find_prop;
 use super::DirectiveConvertResult;
 use crate::extension::{dom_helper as dh, DomError};
-use crate::options::is_native_tag;
+use crate::options::dom_is_native_element;
 
 use super::{CoreDirConvRet, Directive, DirectiveConverter, Element, ErrorHandler};
 pub fn convert_v_model<'a>(
@@ -63,7 +63,7 @@ type RuntimeResult = Result<RuntimeHelper, CompilationError>;
 fn compute_v_model_runtime(e: &Element, dir: &Directive) -> RuntimeResult {
     let tag = e.tag_name;
     // tag is not component nor native, so it must be custom
-    let is_custom_element = !is_native_tag(tag);
+    let is_custom_element = !dom_is_native_element(tag);
     if !["input", "select", "textarea"].contains(&tag) && !is_custom_element {
         let error = CompilationError::extended(DomError::VModelOnInvalidElement)
             .with_location(dir.location.clone());
@@ -1,4 +1,4 @@
-use compiler::error::ErrorKind;
+use compiler::error::{ErrorKind, EXTEND_POINT_CODE};
 
 pub mod dom_helper {
     use compiler::flags::RuntimeHelper as RH;
@@ -10,8 +10,9 @@ pub mod dom_helper {
     pub const V_ON_WITH_MODIFIERS: RH = RH(RH::INTERNAL_MAX + 5);
     pub const V_ON_WITH_KEYS: RH = RH(RH::INTERNAL_MAX + 6);
     pub const V_SHOW: RH = RH(RH::INTERNAL_MAX + 7);
-    pub const TRANSITION: RH = RH(RH::INTERNAL_MAX + 8);
-    pub const TRANSITION_GROUP: RH = RH(RH::INTERNAL_MAX + 9);
+    // defined in core so that ParseOption::dom() can resolve them
+    pub const TRANSITION: RH = RH::TRANSITION;
+    pub const TRANSITION_GROUP: RH = RH::TRANSITION_GROUP;
 
     pub const DOM_HELPER_MAP: &[&str] = &[
         "vModelRadio",
@@ -22,8 +23,6 @@ pub mod dom_helper {
         "withModifiers",
         "withKeys",
         "vShow",
-        "Transition",
-        "TransitionGroup",
     ];
 }
 
@@ -58,4 +57,22 @@ impl ErrorKind for DomError {
           IgnoredSideEffectTag => "Tags with side effect (<script> and <style>) are ignored in client component templates."
         }
     }
+    /// Append-only like core codes, starting from EXTEND_POINT_CODE as Vue's DOMErrorCodes.
+    fn code(&self) -> u32 {
+        use DomError::*;
+        let offset = match self {
+            VHtmlNoExpression => 0,
+            VHtmlWithChildren => 1,
+            VTextNoExpression => 2,
+            VTextWithChildren => 3,
+            VModelOnInvalidElement => 4,
+            VModelArgOnElement => 5,
+            VModelOnFileInputElement => 6,
+            VModelUnnecessaryValue => 7,
+            VShowNoExpression => 8,
+            TransitionInvalidChildren => 9,
+            IgnoredSideEffectTag => 10,
+        };
+        EXTEND_POINT_CODE + offset
+    }
 }
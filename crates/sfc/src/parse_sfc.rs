@@ -2,7 +2,7 @@ use compiler::compiler::CompileOption;
 use compiler::util::prop_finder;
 use compiler::{
     SourceLocation, BindingMetadata,
-    scanner::{Scanner, TextMode},
+    scanner::{Attribute, Scanner, TextMode},
     parser::{Parser, AstNode, AstRoot, Element, ElemProp},
     error::{VecErrorHandler, CompilationError, RcErrHandle, ErrorKind},
 };
@@ -202,7 +202,11 @@ fn parse_ast(source: &str, err_handle: RcErrHandle) -> AstRoot {
         is_pre_tag: |_| true,
         is_native_tag: |_| true,
         get_text_mode: |tag| {
-            if tag == "template" {
+            // template with lang other than html, e.g. pug, is raw text
+            let is_html = |a: &Attribute| {
+                a.name != "lang" || a.value.as_ref().map_or(true, |v| v.raw() == "html")
+            };
+            if tag.name == "template" && tag.attributes.iter().all(is_html) {
                 TextMode::Data
             } else {
                 TextMode::RawText